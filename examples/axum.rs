@@ -0,0 +1,36 @@
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use tower_helmet::header::CrossOriginResourcePolicy;
+use tower_helmet::{CspNonce, HeaderOverride, HelmetLayer};
+
+async fn index(nonce: CspNonce) -> Html<String> {
+    Html(format!(
+        r#"<!doctype html><script nonce="{}">console.log("hello")</script>"#,
+        nonce
+    ))
+}
+
+async fn widget() -> (HeaderOverride, &'static str) {
+    // this route may be embedded by other sites
+    (
+        HeaderOverride::new().enable(CrossOriginResourcePolicy::CrossOrigin),
+        "widget",
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    let mut helmet = HelmetLayer::with_defaults_v2();
+    helmet.enable_nonce();
+
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/widget", get(widget))
+        .layer(helmet.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+        .await
+        .unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
@@ -0,0 +1,193 @@
+//! Integration with [actix-web](https://docs.rs/actix-web), enabled with the `actix-web` feature.
+//!
+//! [`Helmet`] is an actix-web middleware applying a [`crate::HelmetLayer`] configured as usual, so
+//! actix-web and tower services can share a single configuration. Nonces are added to the request
+//! extensions (for example extracted with `web::ReqData<CspNonce>`) and set as
+//! [`CspNonce::current`] while the handler runs. A [`HeaderOverride`] is applied when inserted into
+//! the extensions of the response.
+//!
+//! ```
+//! use actix_web::App;
+//! use tower_helmet::actix::Helmet;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let app = App::new().wrap(Helmet::from(HelmetLayer::with_defaults()));
+//! ```
+
+use std::borrow::Cow;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage};
+use futures::ready;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use pin_project_lite::pin_project;
+
+use crate::{nonce, preset, CspNonce, HeaderOverride};
+
+/// actix-web middleware applying a [`crate::HelmetLayer`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Helmet(Arc<crate::HelmetService<()>>);
+
+impl From<crate::HelmetLayer> for Helmet {
+    fn from(layer: crate::HelmetLayer) -> Self {
+        Helmet(Arc::new(layer.service(())))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Helmet
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = HelmetMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HelmetMiddleware {
+            service,
+            helmet: self.0.clone(),
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct HelmetMiddleware<S> {
+    service: S,
+    helmet: Arc<crate::HelmetService<()>>,
+}
+
+impl<S, B> Service<ServiceRequest> for HelmetMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let request_headers = to_http1(request.headers().iter());
+        let (headers, adjustments, failed) = crate::or_fallback(
+            self.helmet.prepare(
+                preset::is_grpc(&request_headers),
+                request.path(),
+                request_headers
+                    .get(http::header::USER_AGENT)
+                    .map(HeaderValue::as_bytes),
+                self.helmet.session_id(|| Cow::Borrowed(&request_headers)),
+                None,
+                None,
+            ),
+        );
+        let nonce = adjustments.nonce();
+        if let Some(nonce) = &nonce {
+            request.extensions_mut().insert(nonce.clone());
+        }
+
+        ResponseFuture {
+            // the request is answered without the service if it fails anyway
+            future: if failed {
+                None
+            } else {
+                Some(self.service.call(request))
+            },
+            headers,
+            nonce,
+            helmet: self.helmet.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`HelmetMiddleware`].
+    pub struct ResponseFuture<F> {
+        // `None` if the request fails with `500 Internal Server Error`
+        #[pin]
+        future: Option<F>,
+
+        headers: HeaderMap,
+        nonce: Option<CspNonce>,
+        helmet: Arc<crate::HelmetService<()>>,
+    }
+}
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<ServiceResponse<B>, Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let future = match this.future.as_pin_mut() {
+            Some(future) => future,
+            None => {
+                return Poll::Ready(Err(actix_web::error::ErrorInternalServerError(
+                    "the security headers couldn't be computed",
+                )))
+            }
+        };
+        let mut res = {
+            let _current = nonce::enter(this.nonce.as_ref());
+            ready!(future.poll(cx)?)
+        };
+        let header_override = res
+            .response_mut()
+            .extensions_mut()
+            .remove::<HeaderOverride>();
+
+        let mut headers = to_http1(res.headers().iter());
+        let status = StatusCode::from_u16(res.status().as_u16()).unwrap_or(StatusCode::OK);
+        this.helmet.respond(
+            status,
+            &mut headers,
+            this.headers,
+            this.nonce.as_ref(),
+            header_override,
+        );
+        if let Some(nonce) = this.nonce.take() {
+            res.response_mut().extensions_mut().insert(nonce);
+        }
+
+        let response_headers = res.headers_mut();
+        response_headers.clear();
+        for (name, value) in &headers {
+            // both `http` versions accept the same names and values
+            if let (Ok(name), Ok(value)) = (
+                actix_web::http::header::HeaderName::from_bytes(name.as_str().as_bytes()),
+                actix_web::http::header::HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                response_headers.append(name, value);
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+fn to_http1<'a>(
+    headers: impl Iterator<
+        Item = (
+            &'a actix_web::http::header::HeaderName,
+            &'a actix_web::http::header::HeaderValue,
+        ),
+    >,
+) -> HeaderMap {
+    let mut converted = HeaderMap::new();
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
@@ -0,0 +1,793 @@
+//! Lints a header set for common misconfigurations.
+//!
+//! [`audit`] (or [`crate::HelmetLayer::audit`]) returns machine-readable [`Finding`]s, which can be
+//! checked at startup or in tests to prevent the configuration from silently getting weaker.
+//!
+//! ```
+//! use tower_helmet::audit::Severity;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let findings = HelmetLayer::with_defaults().audit();
+//! assert!(findings.iter().all(|f| f.severity < Severity::Warning));
+//! ```
+//!
+//! A [`Strict`] standard enforces this mechanically: [`crate::HelmetBuilder::strict`] makes
+//! building a layer with findings fail, and [`crate::HelmetLayer::enforce`] checks any layer at
+//! startup.
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{
+    AsHeaderName, HeaderName, CACHE_CONTROL, CONTENT_SECURITY_POLICY,
+    CONTENT_SECURITY_POLICY_REPORT_ONLY, ETAG, STRICT_TRANSPORT_SECURITY,
+};
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::{
+    parse_directives, CROSS_ORIGIN_EMBEDDER_POLICY, CROSS_ORIGIN_OPENER_POLICY, NEL,
+};
+
+/// Six months in seconds. Shorter HSTS `max-age` values are considered weak.
+const HSTS_MIN_MAX_AGE: u64 = 15552000;
+
+/// One day in seconds. Shorter HSTS `max-age` values (other than `0`, which clears the policy)
+/// barely protect anything.
+const HSTS_ONE_DAY: u64 = 86400;
+
+/// One year in seconds, the minimum `max-age` for preloading.
+const HSTS_PRELOAD_MIN_MAX_AGE: u64 = 31536000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// The rule which produced a [`Finding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// Neither `Content-Security-Policy` nor `Content-Security-Policy-Report-Only` is set.
+    MissingContentSecurityPolicy,
+    /// `Content-Security-Policy-Report-Only` is set without `report-uri` or `report-to`, so
+    /// violations are not reported anywhere.
+    ReportOnlyWithoutEndpoint,
+    /// `script-src` (or `default-src` as its fallback) allows `'unsafe-inline'`.
+    UnsafeInlineScript,
+    /// `script-src` (or `default-src` as its fallback) allows `'unsafe-eval'`.
+    UnsafeEvalScript,
+    /// `Strict-Transport-Security` is not set.
+    MissingStrictTransportSecurity,
+    /// `Strict-Transport-Security` has no valid `max-age`, so browsers ignore the header.
+    InvalidStrictTransportSecurity,
+    /// `Strict-Transport-Security` has `max-age=0`, which deliberately clears the policy, see
+    /// [`crate::header::StrictTransportSecurity::clear`].
+    ClearedStrictTransportSecurity,
+    /// `Strict-Transport-Security` has a `max-age` below six months (but not `0`).
+    WeakStrictTransportSecurity,
+    /// `Strict-Transport-Security` has a `max-age` below one day (but not `0`, which deliberately
+    /// clears the policy).
+    VeryShortStrictTransportSecurity,
+    /// `Strict-Transport-Security` has `preload` without `includeSubDomains`.
+    PreloadWithoutIncludeSubDomains,
+    /// `Strict-Transport-Security` has `preload` with a `max-age` below one year.
+    PreloadWithShortMaxAge,
+    /// `Cross-Origin-Embedder-Policy` is enabled without `Cross-Origin-Opener-Policy: same-origin`.
+    EmbedderPolicyWithoutOpenerPolicy,
+    /// A redirect's `Location` points to an origin outside the allowlist, see
+    /// [`crate::redirect::RedirectAudit`].
+    OffsiteRedirect,
+    /// An HTML document loads a subresource over plain `http://`, see `MixedContentAudit` of the
+    /// `html` feature.
+    MixedContent,
+    /// A critical header was stripped after the [`crate::HelmetLayer`], see
+    /// [`crate::critical::CriticalHeadersLayer`] and [`crate::canary::Canary`].
+    MissingCriticalHeader,
+    /// A critical header was replaced with a different value after the [`crate::HelmetLayer`], see
+    /// [`crate::critical::CriticalHeadersLayer`] and [`crate::canary::Canary`].
+    ChangedCriticalHeader,
+    /// The [`crate::canary::Canary`] header didn't arrive, so the response didn't pass through the
+    /// expected configuration.
+    MissingCanary,
+    /// `Access-Control-Allow-Origin: *` is combined with `Access-Control-Allow-Credentials: true`,
+    /// see [`crate::cors::CorsAudit`].
+    WildcardCorsWithCredentials,
+    /// `Access-Control-Allow-Origin: *` is combined with a `Cross-Origin-Resource-Policy` of
+    /// `same-origin` or `same-site`, see [`crate::cors::CorsAudit`].
+    WildcardCorsWithRestrictiveCorp,
+    /// An explicitly allowed CORS origin is combined with `Cross-Origin-Resource-Policy:
+    /// same-origin`, see [`crate::cors`].
+    CorsWithSameOriginCorp,
+    /// A header required for cross-origin isolation is missing, see
+    /// [`audit_cross_origin_isolated`].
+    NotCrossOriginIsolated,
+    /// A `report-to` of the CSP, COOP, COEP or NEL names a reporting group which is neither
+    /// defined in `Reporting-Endpoints` nor in `Report-To`, so its reports are dropped.
+    UndefinedReportingGroup,
+    /// A response with a per-request nonce may be cached, see [`audit_nonce_caching`].
+    CacheableNonce,
+    /// A single header is larger than the [`HeaderBudget`] allows, see [`audit_header_size`].
+    OversizedHeader,
+    /// All headers together are larger than the [`HeaderBudget`] allows, see
+    /// [`audit_header_size`].
+    OversizedHeaders,
+}
+
+impl Rule {
+    /// A stable, kebab-case identifier of the rule.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rule::MissingContentSecurityPolicy => "missing-content-security-policy",
+            Rule::ReportOnlyWithoutEndpoint => "report-only-without-endpoint",
+            Rule::UnsafeInlineScript => "unsafe-inline-script",
+            Rule::UnsafeEvalScript => "unsafe-eval-script",
+            Rule::MissingStrictTransportSecurity => "missing-strict-transport-security",
+            Rule::InvalidStrictTransportSecurity => "invalid-strict-transport-security",
+            Rule::ClearedStrictTransportSecurity => "cleared-strict-transport-security",
+            Rule::WeakStrictTransportSecurity => "weak-strict-transport-security",
+            Rule::VeryShortStrictTransportSecurity => "very-short-strict-transport-security",
+            Rule::PreloadWithoutIncludeSubDomains => "preload-without-include-subdomains",
+            Rule::PreloadWithShortMaxAge => "preload-with-short-max-age",
+            Rule::EmbedderPolicyWithoutOpenerPolicy => "embedder-policy-without-opener-policy",
+            Rule::OffsiteRedirect => "offsite-redirect",
+            Rule::MixedContent => "mixed-content",
+            Rule::MissingCriticalHeader => "missing-critical-header",
+            Rule::ChangedCriticalHeader => "changed-critical-header",
+            Rule::MissingCanary => "missing-canary",
+            Rule::WildcardCorsWithCredentials => "wildcard-cors-with-credentials",
+            Rule::WildcardCorsWithRestrictiveCorp => "wildcard-cors-with-restrictive-corp",
+            Rule::CorsWithSameOriginCorp => "cors-with-same-origin-corp",
+            Rule::NotCrossOriginIsolated => "not-cross-origin-isolated",
+            Rule::UndefinedReportingGroup => "undefined-reporting-group",
+            Rule::CacheableNonce => "cacheable-nonce",
+            Rule::OversizedHeader => "oversized-header",
+            Rule::OversizedHeaders => "oversized-headers",
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A single problem found by [`audit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// The header the finding is about, if it is about a single one.
+    pub header: Option<HeaderName>,
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(
+        rule: Rule,
+        severity: Severity,
+        header: Option<HeaderName>,
+        message: impl Into<String>,
+    ) -> Self {
+        Finding {
+            rule,
+            severity,
+            header,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:?}] {}: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// A minimum standard for the headers: no findings of at least a severity, other than those of
+/// explicitly allowed rules.
+///
+/// ```
+/// use tower_helmet::audit::{Rule, Severity, Strict};
+/// use tower_helmet::header::StrictTransportSecurity;
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut strict = Strict::new();
+/// strict.allow(Rule::MissingContentSecurityPolicy);
+///
+/// let layer = HelmetLayer::builder()
+///     .hsts(StrictTransportSecurity::default())
+///     .strict(strict.clone())
+///     .try_build();
+/// assert!(layer.is_ok());
+///
+/// let error = HelmetLayer::builder()
+///     .strict(strict)
+///     .try_build()
+///     .unwrap_err();
+/// assert_eq!(error.findings()[0].rule, Rule::MissingStrictTransportSecurity);
+/// assert_eq!(error.findings()[0].severity, Severity::Warning);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strict {
+    severity: Severity,
+    allowed: Vec<Rule>,
+}
+
+impl Default for Strict {
+    fn default() -> Self {
+        Strict {
+            severity: Severity::Warning,
+            allowed: Vec::new(),
+        }
+    }
+}
+
+impl Strict {
+    /// Rejects all warnings and errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects findings of at least `severity`, [`Severity::Warning`] by default.
+    pub fn severity(&mut self, severity: Severity) -> &mut Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Accepts findings of `rule`, for deliberate exceptions such as a report-only CSP during
+    /// its rollout.
+    pub fn allow(&mut self, rule: Rule) -> &mut Self {
+        if !self.allowed.contains(&rule) {
+            self.allowed.push(rule);
+        }
+        self
+    }
+
+    /// Audits `headers` like [`audit`], failing with the findings which violate the standard.
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), StrictError> {
+        let findings: Vec<Finding> = audit(headers)
+            .into_iter()
+            .filter(|f| f.severity >= self.severity && !self.allowed.contains(&f.rule))
+            .collect();
+        if findings.is_empty() {
+            Ok(())
+        } else {
+            Err(StrictError(findings))
+        }
+    }
+}
+
+/// Returned if headers violate a [`Strict`] standard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrictError(Vec<Finding>);
+
+impl StrictError {
+    /// The findings violating the standard, never empty.
+    pub fn findings(&self) -> &[Finding] {
+        &self.0
+    }
+}
+
+impl Display for StrictError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the headers violate the strict standard:")?;
+        for finding in &self.0 {
+            write!(f, "\n  {}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StrictError {}
+
+/// Audits the given response headers.
+pub fn audit(headers: &HeaderMap) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    audit_content_security_policy(headers, &mut findings);
+    audit_strict_transport_security(headers, &mut findings);
+    audit_cross_origin_isolation(headers, &mut findings);
+    audit_reporting(headers, &mut findings);
+    findings.extend(crate::cors::check(headers));
+    findings.extend(audit_header_size(headers, &HeaderBudget::default()));
+
+    findings
+}
+
+fn header_str<K: AsHeaderName>(headers: &HeaderMap, name: K) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn audit_content_security_policy(headers: &HeaderMap, findings: &mut Vec<Finding>) {
+    let enforced = header_str(headers, CONTENT_SECURITY_POLICY);
+    let report_only = header_str(headers, CONTENT_SECURITY_POLICY_REPORT_ONLY);
+
+    if enforced.is_none() && report_only.is_none() {
+        findings.push(Finding::new(
+            Rule::MissingContentSecurityPolicy,
+            Severity::Warning,
+            Some(CONTENT_SECURITY_POLICY),
+            "no Content-Security-Policy is set",
+        ));
+    }
+
+    if let Some(value) = report_only {
+        let directives = parse_directives(value);
+        if !directives
+            .iter()
+            .any(|(name, _)| *name == "report-uri" || *name == "report-to")
+        {
+            findings.push(Finding::new(
+                Rule::ReportOnlyWithoutEndpoint,
+                Severity::Warning,
+                Some(CONTENT_SECURITY_POLICY_REPORT_ONLY),
+                "the report-only policy has neither report-uri nor report-to, violations are not reported anywhere",
+            ));
+        }
+    }
+
+    if let Some(value) = enforced {
+        let directives = parse_directives(value);
+        let script_src = directives
+            .iter()
+            .find(|(name, _)| *name == "script-src")
+            .or_else(|| directives.iter().find(|(name, _)| *name == "default-src"));
+
+        if let Some((name, sources)) = script_src {
+            if sources.contains(&"'unsafe-inline'") {
+                findings.push(Finding::new(
+                    Rule::UnsafeInlineScript,
+                    Severity::Warning,
+                    Some(CONTENT_SECURITY_POLICY),
+                    format!("{} allows 'unsafe-inline'", name),
+                ));
+            }
+            if sources.contains(&"'unsafe-eval'") {
+                findings.push(Finding::new(
+                    Rule::UnsafeEvalScript,
+                    Severity::Warning,
+                    Some(CONTENT_SECURITY_POLICY),
+                    format!("{} allows 'unsafe-eval'", name),
+                ));
+            }
+        }
+    }
+}
+
+fn audit_strict_transport_security(headers: &HeaderMap, findings: &mut Vec<Finding>) {
+    let value = match header_str(headers, STRICT_TRANSPORT_SECURITY) {
+        Some(value) => value,
+        None => {
+            findings.push(Finding::new(
+                Rule::MissingStrictTransportSecurity,
+                Severity::Warning,
+                Some(STRICT_TRANSPORT_SECURITY),
+                "no Strict-Transport-Security is set",
+            ));
+            return;
+        }
+    };
+
+    let directives: Vec<String> = value
+        .split(';')
+        .map(|d| d.trim().to_ascii_lowercase())
+        .collect();
+    let max_age = match directives
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age="))
+        .and_then(|v| v.trim_matches('"').parse::<u64>().ok())
+    {
+        Some(max_age) => max_age,
+        None => {
+            findings.push(Finding::new(
+                Rule::InvalidStrictTransportSecurity,
+                Severity::Warning,
+                Some(STRICT_TRANSPORT_SECURITY),
+                "Strict-Transport-Security has no valid max-age and is ignored by browsers",
+            ));
+            return;
+        }
+    };
+    let include_subdomains = directives.iter().any(|d| d == "includesubdomains");
+    let preload = directives.iter().any(|d| d == "preload");
+
+    if max_age == 0 {
+        findings.push(Finding::new(
+            Rule::ClearedStrictTransportSecurity,
+            Severity::Info,
+            Some(STRICT_TRANSPORT_SECURITY),
+            "max-age=0 clears the policy in browsers",
+        ));
+    } else if max_age < HSTS_ONE_DAY {
+        findings.push(Finding::new(
+            Rule::VeryShortStrictTransportSecurity,
+            Severity::Error,
+            Some(STRICT_TRANSPORT_SECURITY),
+            format!("max-age={} is less than a day", max_age),
+        ));
+    } else if max_age < HSTS_MIN_MAX_AGE {
+        findings.push(Finding::new(
+            Rule::WeakStrictTransportSecurity,
+            Severity::Warning,
+            Some(STRICT_TRANSPORT_SECURITY),
+            format!("max-age={} is less than six months", max_age),
+        ));
+    }
+
+    if preload && !include_subdomains {
+        findings.push(Finding::new(
+            Rule::PreloadWithoutIncludeSubDomains,
+            Severity::Warning,
+            Some(STRICT_TRANSPORT_SECURITY),
+            "preload is set without includeSubDomains, which the preload list requires",
+        ));
+    }
+    if preload && max_age < HSTS_PRELOAD_MIN_MAX_AGE {
+        findings.push(Finding::new(
+            Rule::PreloadWithShortMaxAge,
+            Severity::Warning,
+            Some(STRICT_TRANSPORT_SECURITY),
+            format!(
+                "preload is set with max-age={}, the preload list requires at least one year",
+                max_age
+            ),
+        ));
+    }
+}
+
+/// Whether a finding is about a suspicious `Strict-Transport-Security` configuration.
+#[cfg(feature = "tracing")]
+pub(crate) fn is_hsts_sanity(rule: Rule) -> bool {
+    matches!(
+        rule,
+        Rule::InvalidStrictTransportSecurity
+            | Rule::VeryShortStrictTransportSecurity
+            | Rule::PreloadWithoutIncludeSubDomains
+            | Rule::PreloadWithShortMaxAge
+    )
+}
+
+/// Audits headers which have to enable [cross-origin isolation](https://web.dev/articles/coop-coep),
+/// for pages relying on `crossOriginIsolated` features such as `SharedArrayBuffer`. Both
+/// `Cross-Origin-Embedder-Policy` (`require-corp` or `credentialless`) and
+/// `Cross-Origin-Opener-Policy: same-origin` are needed, with only one of them browsers silently
+/// keep the page unisolated.
+///
+/// ```
+/// use tower_helmet::audit::{audit_cross_origin_isolated, Rule};
+/// use tower_helmet::header::CrossOriginOpenerPolicy;
+/// use tower_helmet::{HelmetLayer, IntoHeaders};
+///
+/// let mut layer = HelmetLayer::blank();
+/// layer.enable(CrossOriginOpenerPolicy::SameOrigin);
+///
+/// let findings = audit_cross_origin_isolated(&layer.header_map().unwrap());
+/// assert_eq!(findings[0].rule, Rule::NotCrossOriginIsolated);
+/// ```
+pub fn audit_cross_origin_isolated(headers: &HeaderMap) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if !isolating_embedder_policy(headers) {
+        findings.push(Finding::new(
+            Rule::NotCrossOriginIsolated,
+            Severity::Error,
+            Some(CROSS_ORIGIN_EMBEDDER_POLICY),
+            "cross-origin isolation requires Cross-Origin-Embedder-Policy: require-corp or credentialless",
+        ));
+    }
+    if !isolating_opener_policy(headers) {
+        findings.push(Finding::new(
+            Rule::NotCrossOriginIsolated,
+            Severity::Error,
+            Some(CROSS_ORIGIN_OPENER_POLICY),
+            "cross-origin isolation requires Cross-Origin-Opener-Policy: same-origin",
+        ));
+    }
+
+    findings
+}
+
+/// Audits the headers of a response carrying a per-request nonce (see
+/// [`crate::HelmetLayer::enable_nonce`]) for caching. A cached page is served with the nonce of
+/// the request which filled the cache, so attackers can learn the nonce and inject scripts using it.
+/// Responses which may be stored by a shared cache (`public`, `max-age` or `s-maxage`) or
+/// revalidated with an `ETag` are flagged, unless they have `no-store`.
+///
+/// With nonces enabled, the layer checks every response and logs the finding as warning with the
+/// `tracing` feature.
+///
+/// ```
+/// use http::{HeaderMap, HeaderValue};
+/// use tower_helmet::audit::{audit_nonce_caching, Rule};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert("cache-control", HeaderValue::from_static("public, max-age=600"));
+/// assert_eq!(audit_nonce_caching(&headers).unwrap().rule, Rule::CacheableNonce);
+///
+/// headers.insert("cache-control", HeaderValue::from_static("no-store"));
+/// assert!(audit_nonce_caching(&headers).is_none());
+/// ```
+pub fn audit_nonce_caching(headers: &HeaderMap) -> Option<Finding> {
+    let cache_control = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let directives: Vec<String> = cache_control
+        .split(',')
+        .map(|d| d.trim().to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    if directives.iter().any(|d| d == "no-store") {
+        return None;
+    }
+
+    let max_age = |name: &str| {
+        directives
+            .iter()
+            .filter_map(|d| d.strip_prefix(name)?.strip_prefix('='))
+            .any(|v| v.trim_matches('"').parse::<u64>().is_ok_and(|v| v > 0))
+    };
+    let (header, message) = if directives.iter().any(|d| d == "public")
+        || max_age("max-age")
+        || max_age("s-maxage")
+    {
+        (
+            CACHE_CONTROL,
+            format!(
+                "a response with a per-request nonce is cacheable (cache-control: {}), the cached nonce is reused",
+                cache_control
+            ),
+        )
+    } else if headers.contains_key(ETAG) {
+        (
+            ETAG,
+            "a response with a per-request nonce has an etag, revalidated copies reuse the cached nonce"
+                .to_owned(),
+        )
+    } else {
+        return None;
+    };
+
+    Some(Finding::new(
+        Rule::CacheableNonce,
+        Severity::Warning,
+        Some(header),
+        message,
+    ))
+}
+
+/// The value of a structured header without its parameters, such as `report-to`.
+fn token<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    header_str(headers, name)
+        .and_then(|v| v.split(';').next())
+        .map(str::trim)
+}
+
+fn isolating_embedder_policy(headers: &HeaderMap) -> bool {
+    matches!(
+        token(headers, "cross-origin-embedder-policy"),
+        Some("require-corp") | Some("credentialless")
+    )
+}
+
+fn isolating_opener_policy(headers: &HeaderMap) -> bool {
+    token(headers, "cross-origin-opener-policy") == Some("same-origin")
+}
+
+fn audit_cross_origin_isolation(headers: &HeaderMap, findings: &mut Vec<Finding>) {
+    if isolating_embedder_policy(headers) && !isolating_opener_policy(headers) {
+        findings.push(Finding::new(
+            Rule::EmbedderPolicyWithoutOpenerPolicy,
+            Severity::Warning,
+            Some(CROSS_ORIGIN_OPENER_POLICY),
+            "Cross-Origin-Embedder-Policy is enabled without Cross-Origin-Opener-Policy: same-origin",
+        ));
+    }
+}
+
+/// Headers whose `report-to` parameter names a reporting group.
+const REPORT_TO_PARAMETER: [&str; 4] = [
+    "cross-origin-embedder-policy",
+    "cross-origin-embedder-policy-report-only",
+    "cross-origin-opener-policy",
+    "cross-origin-opener-policy-report-only",
+];
+
+fn audit_reporting(headers: &HeaderMap, findings: &mut Vec<Finding>) {
+    let mut groups: Vec<(HeaderName, String)> = Vec::new();
+
+    for name in [CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY] {
+        if let Some(value) = header_str(headers, &name) {
+            let group = parse_directives(value)
+                .into_iter()
+                .find(|(directive, _)| *directive == "report-to")
+                .and_then(|(_, values)| values.first().map(|v| v.to_string()));
+            groups.extend(group.map(|group| (name.clone(), group)));
+        }
+    }
+
+    for name in REPORT_TO_PARAMETER {
+        let group = header_str(headers, name).and_then(|value| {
+            value.split(';').skip(1).find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                (key.trim() == "report-to").then(|| value.trim().trim_matches('"').to_owned())
+            })
+        });
+        groups.extend(group.map(|group| (HeaderName::from_static(name), group)));
+    }
+
+    if let Some(value) = header_str(headers, "nel") {
+        let group = json_string(value, "report_to");
+        groups.extend(group.map(|group| (NEL, group)));
+    }
+
+    if groups.is_empty() {
+        return;
+    }
+
+    let mut defined: Vec<String> = Vec::new();
+    if let Some(value) = header_str(headers, "reporting-endpoints") {
+        defined.extend(
+            value
+                .split(',')
+                .filter_map(|endpoint| endpoint.split_once('='))
+                .map(|(name, _)| name.trim().to_owned()),
+        );
+    }
+    if let Some(value) = header_str(headers, "report-to") {
+        defined
+            .extend(json_objects(value).map(|object| {
+                json_string(object, "group").unwrap_or_else(|| "default".to_owned())
+            }));
+    }
+
+    for (header, group) in groups {
+        if !defined.contains(&group) {
+            findings.push(Finding::new(
+                Rule::UndefinedReportingGroup,
+                Severity::Warning,
+                Some(header.clone()),
+                format!(
+                    "{} reports to `{}`, which is not defined in Reporting-Endpoints or Report-To",
+                    header, group
+                ),
+            ));
+        }
+    }
+}
+
+/// The top-level objects of a comma-separated list of JSON objects, as used by `Report-To`.
+fn json_objects(value: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut objects = Vec::new();
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            '}' if !in_string && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&value[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    objects.into_iter()
+}
+
+/// The string value of the first `"key"` in a JSON document.
+fn json_string(json: &str, key: &str) -> Option<String> {
+    let quoted = format!("\"{}\"", key);
+    let rest = &json[json.find(&quoted)? + quoted.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_owned())
+}
+
+/// Size limits for response headers, see [`audit_header_size`].
+///
+/// The defaults of 8 KiB for a single header and 16 KiB for all headers are below the limits of
+/// common proxies and CDNs (nginx' default buffers, for example, are 4 or 8 KiB per header line),
+/// which reject or truncate larger responses, usually with a 502.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeaderBudget {
+    /// The largest size of a single header line in bytes.
+    pub single: usize,
+    /// The largest size of all header lines together in bytes.
+    pub total: usize,
+    /// Reports exceeded budgets as [`Severity::Error`] instead of [`Severity::Warning`].
+    pub strict: bool,
+}
+
+impl Default for HeaderBudget {
+    fn default() -> Self {
+        HeaderBudget {
+            single: 8 * 1024,
+            total: 16 * 1024,
+            strict: false,
+        }
+    }
+}
+
+/// Checks that the headers fit into `budget`, counting each header as it is sent over HTTP/1.1
+/// (`name: value` followed by CRLF). Large headers are mostly a `Content-Security-Policy` with
+/// many sources or hashes, which works locally and breaks once a proxy with smaller buffers is
+/// in front of the application.
+///
+/// [`audit`] runs this with the default budget. The headers of the application and of other
+/// middleware count towards the total as well, so leave room for them or audit whole responses.
+///
+/// ```
+/// use http::{HeaderMap, HeaderValue};
+/// use tower_helmet::audit::{audit_header_size, HeaderBudget, Rule, Severity};
+///
+/// let mut headers = HeaderMap::new();
+/// let policy = format!("script-src {}", "'sha256-abc' ".repeat(1000));
+/// headers.insert("content-security-policy", HeaderValue::from_str(&policy).unwrap());
+///
+/// let budget = HeaderBudget { strict: true, ..HeaderBudget::default() };
+/// let findings = audit_header_size(&headers, &budget);
+/// assert_eq!(findings[0].rule, Rule::OversizedHeader);
+/// assert_eq!(findings[0].severity, Severity::Error);
+/// ```
+pub fn audit_header_size(headers: &HeaderMap, budget: &HeaderBudget) -> Vec<Finding> {
+    let severity = if budget.strict {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let mut findings = Vec::new();
+    let mut total = 0;
+
+    for (name, value) in headers {
+        let size = header_line_size(name, value);
+        total += size;
+        if size > budget.single {
+            findings.push(Finding::new(
+                Rule::OversizedHeader,
+                severity,
+                Some(name.clone()),
+                format!(
+                    "{} is {} bytes, more than the budget of {} bytes for a single header",
+                    name, size, budget.single
+                ),
+            ));
+        }
+    }
+
+    if total > budget.total {
+        findings.push(Finding::new(
+            Rule::OversizedHeaders,
+            severity,
+            None,
+            format!(
+                "the headers are {} bytes, more than the budget of {} bytes for all headers",
+                total, budget.total
+            ),
+        ));
+    }
+
+    findings
+}
+
+/// The size of a header line: the name, `": "`, the value and CRLF.
+fn header_line_size(name: &HeaderName, value: &HeaderValue) -> usize {
+    name.as_str().len() + value.len() + 4
+}
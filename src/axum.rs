@@ -0,0 +1,194 @@
+//! Integration with [axum](https://docs.rs/axum), enabled with the `axum` feature.
+//!
+//! - [`CspNonce`] is an extractor for the nonce of the current request (see
+//!   [`crate::HelmetLayer::enable_nonce`]). It is also available as `Option<CspNonce>`.
+//! - [`HeaderOverride`] can be returned as response parts to change the headers of a single route.
+//! - [`RouteHelmet`] changes the headers of all handlers of a route, for `Router::route_layer`.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum_core::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum_core::response::{IntoResponse, IntoResponseParts, Response, ResponseParts};
+use futures::ready;
+use http::header::{AsHeaderName, HeaderName};
+use http::request::Parts;
+use http::{Request, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{CspNonce, HeaderOverride, IntoHeaders};
+
+/// Rejection of the [`CspNonce`] extractor, used if the request didn't pass through a
+/// [`crate::HelmetLayer`] with nonces enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct MissingCspNonce;
+
+impl IntoResponse for MissingCspNonce {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Missing CSP nonce. Is the HelmetLayer added with nonces enabled?",
+        )
+            .into_response()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CspNonce {
+    type Rejection = MissingCspNonce;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CspNonce>()
+            .cloned()
+            .ok_or(MissingCspNonce)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for CspNonce {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<CspNonce>().cloned())
+    }
+}
+
+impl IntoResponseParts for HeaderOverride {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// Layer relaxing or tightening the headers of a route, to be added with `Router::route_layer`.
+///
+/// It adds its [`HeaderOverride`] to the responses of the route, so it takes precedence over the
+/// global [`crate::HelmetLayer`], which has to wrap the router. A [`HeaderOverride`] returned by a
+/// handler takes precedence over the route's one: the closer to the handler, the later a change is
+/// applied.
+///
+/// ```
+/// use axum::routing::get;
+/// use axum::Router;
+/// use tower_helmet::axum::RouteHelmet;
+/// use tower_helmet::header::CrossOriginResourcePolicy;
+/// use tower_helmet::HelmetLayer;
+///
+/// let app: Router = Router::new()
+///     .route("/widget", get(|| async { "widget" }))
+///     .route_layer(RouteHelmet::new().enable(CrossOriginResourcePolicy::CrossOrigin))
+///     .route("/", get(|| async { "index" }))
+///     .layer(HelmetLayer::with_defaults_v2());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RouteHelmet {
+    header_override: HeaderOverride,
+}
+
+impl RouteHelmet {
+    /// Doesn't change any header yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the given headers, replacing the global layer's values, see [`HeaderOverride::enable`].
+    pub fn enable(mut self, h: impl IntoHeaders) -> Self {
+        self.header_override = self.header_override.enable(h);
+        self
+    }
+
+    /// Doesn't send the given header, see [`HeaderOverride::remove`].
+    pub fn remove<K>(mut self, key: K) -> Self
+    where
+        K: AsHeaderName + Into<HeaderName>,
+    {
+        self.header_override = self.header_override.remove(key);
+        self
+    }
+}
+
+impl From<HeaderOverride> for RouteHelmet {
+    fn from(header_override: HeaderOverride) -> Self {
+        RouteHelmet { header_override }
+    }
+}
+
+impl<S> Layer<S> for RouteHelmet {
+    type Service = RouteHelmetService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RouteHelmetService {
+            inner: service,
+            header_override: self.header_override.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteHelmetService<S> {
+    inner: S,
+    header_override: HeaderOverride,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for RouteHelmetService<S>
+where
+    S: Service<Request<ReqBody>, Response = http::Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RouteFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        RouteFuture {
+            future: self.inner.call(req),
+            header_override: Some(self.header_override.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`RouteHelmetService`].
+    pub struct RouteFuture<F> {
+        #[pin]
+        future: F,
+
+        header_override: Option<HeaderOverride>,
+    }
+}
+
+impl<F, ResBody, E> Future for RouteFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.future.poll(cx)?);
+
+        let route = this
+            .header_override
+            .take()
+            .expect("polled after completion");
+        let header_override = match res.extensions_mut().remove::<HeaderOverride>() {
+            Some(handler) => route.then(handler),
+            None => route,
+        };
+        res.extensions_mut().insert(header_override);
+
+        Poll::Ready(Ok(res))
+    }
+}
@@ -0,0 +1,137 @@
+//! Renders and checks a helmet configuration, enabled with the `cli` feature.
+//!
+//! The configuration file contains one `Name: value` header per line, empty lines and lines
+//! starting with `#` are ignored. Its headers are applied on top of the `--base` configuration.
+//!
+//! ```text
+//! tower-helmet [--base blank|defaults|defaults-v2] [--format headers|nginx|caddy]
+//!              [--fail-on info|warning|error|never] [FILE]
+//! ```
+//!
+//! The resulting headers are printed to stdout, the audit findings and the grade to stderr. The
+//! exit code is `1` if there is a finding of at least the `--fail-on` severity (by default
+//! `warning`) and `2` for invalid arguments or configuration files.
+
+use std::process::ExitCode;
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use tower_helmet::audit::Severity;
+use tower_helmet::{HelmetLayer, IntoHeaders};
+
+const USAGE: &str = "usage: tower-helmet [--base blank|defaults|defaults-v2] \
+                     [--format headers|nginx|caddy] [--fail-on info|warning|error|never] [FILE]";
+
+struct Args {
+    base: HelmetLayer,
+    format: String,
+    fail_on: Option<Severity>,
+    file: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        base: HelmetLayer::blank(),
+        format: "headers".to_owned(),
+        fail_on: Some(Severity::Warning),
+        file: None,
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--base" => {
+                args.base = match value()?.as_str() {
+                    "blank" => HelmetLayer::blank(),
+                    "defaults" => HelmetLayer::with_defaults(),
+                    "defaults-v2" => HelmetLayer::with_defaults_v2(),
+                    base => return Err(format!("unknown base: {}", base)),
+                }
+            }
+            "--format" => match value()?.as_str() {
+                format @ ("headers" | "nginx" | "caddy") => args.format = format.to_owned(),
+                format => return Err(format!("unknown format: {}", format)),
+            },
+            "--fail-on" => {
+                args.fail_on = match value()?.as_str() {
+                    "info" => Some(Severity::Info),
+                    "warning" => Some(Severity::Warning),
+                    "error" => Some(Severity::Error),
+                    "never" => None,
+                    severity => return Err(format!("unknown severity: {}", severity)),
+                }
+            }
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ => args.file = Some(arg),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Parses `Name: value` lines.
+fn parse_config(config: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+
+    for (i, line) in config.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || format!("line {}: expected `Name: value`, got {:?}", i + 1, line);
+        let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid())?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|_| invalid())?;
+        headers.append(name, value);
+    }
+
+    Ok(headers)
+}
+
+fn run() -> Result<bool, String> {
+    let args = parse_args()?;
+
+    let mut headers = args.base.header_map().map_err(|e| e.to_string())?;
+    if let Some(file) = &args.file {
+        let config =
+            std::fs::read_to_string(file).map_err(|e| format!("can't read {}: {}", file, e))?;
+        for (name, value) in parse_config(&config)? {
+            if let Some(name) = name {
+                headers.insert(name, value);
+            }
+        }
+    }
+    let layer = HelmetLayer::from_header_map(&headers);
+
+    match args.format.as_str() {
+        "nginx" => print!("{}", layer.to_nginx()),
+        "caddy" => print!("{}", layer.to_caddy()),
+        _ => print!("{}", layer),
+    }
+
+    let findings = layer.audit();
+    for finding in &findings {
+        eprintln!("{}", finding);
+    }
+    eprintln!("grade: {}", layer.grade());
+
+    Ok(args
+        .fail_on
+        .is_some_and(|fail_on| findings.iter().any(|f| f.severity >= fail_on)))
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::from(2)
+        }
+    }
+}
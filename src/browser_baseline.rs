@@ -0,0 +1,45 @@
+use http::header::HeaderName;
+use http::HeaderMap;
+
+use crate::header::{XDownloadOptions, XXSSProtection, X_DOWNLOAD_OPTIONS};
+use crate::IntoHeader;
+
+/// `BrowserBaseline` describes the oldest browsers a service has to support. Passing it to
+/// [`crate::HelmetLayer::target`] tailors the configured headers to it. There is no default, as
+/// both baselines change which headers are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserBaseline {
+    /// Only current versions of evergreen browsers (Chrome, Edge, Firefox, Safari) are supported.
+    ///
+    /// Legacy headers which these browsers ignore (`X-XSS-Protection`, `X-Download-Options` and
+    /// `Feature-Policy`) are removed.
+    Evergreen,
+    /// Old browsers such as Internet Explorer 11 or old versions of Safari have to be supported.
+    ///
+    /// `X-XSS-Protection` and `X-Download-Options` are added if missing. Other values are left as
+    /// configured, e.g. `Cross-Origin-Embedder-Policy: credentialless` is kept, which these
+    /// browsers ignore like a missing header.
+    Legacy,
+}
+
+impl BrowserBaseline {
+    pub(crate) fn apply(self, headers: &mut HeaderMap) {
+        match self {
+            BrowserBaseline::Evergreen => {
+                headers.remove(http::header::X_XSS_PROTECTION);
+                headers.remove(X_DOWNLOAD_OPTIONS);
+                headers.remove(HeaderName::from_static("feature-policy"));
+            }
+            BrowserBaseline::Legacy => {
+                for h in [
+                    &XXSSProtection::Disabled as &dyn IntoHeader,
+                    &XDownloadOptions as &dyn IntoHeader,
+                ] {
+                    if !headers.contains_key(h.header_name()) {
+                        headers.insert(h.header_name(), h.header_value().unwrap());
+                    }
+                }
+            }
+        }
+    }
+}
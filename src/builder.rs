@@ -0,0 +1,280 @@
+use std::marker::PhantomData;
+
+use crate::audit::{Strict, StrictError};
+use crate::header::{
+    ContentSecurityPolicy, CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy,
+    CrossOriginResourcePolicy, ExpectCt, HeaderSet, OriginAgentCluster, ReferrerPolicy,
+    StrictTransportSecurity, XContentTypeOptions, XDnsPrefetchControl, XDownloadOptions,
+    XFrameOptions, XPermittedCrossDomainPolicies, XXSSProtection,
+};
+use crate::HelmetLayer;
+
+/// Builds a [`HelmetLayer`] with one method per header, created with [`HelmetLayer::builder`].
+///
+/// It starts without any headers, every method enables one. Calling a method again replaces the
+/// previous value. All methods take the builder by value, so they can be chained in any order.
+/// The headers are collected in a [`HeaderSet`] and only rendered when the layer is built.
+///
+/// ```
+/// use tower_helmet::header::{StrictTransportSecurity, XFrameOptions};
+/// use tower_helmet::HelmetLayer;
+///
+/// let layer = HelmetLayer::builder()
+///     .hsts(StrictTransportSecurity::default())
+///     .frame_options(XFrameOptions::Deny)
+///     .content_type_options()
+///     .build();
+///
+/// assert_eq!(
+///     layer.to_string(),
+///     "strict-transport-security: max-age=15552000; includeSubDomains\n\
+///      x-content-type-options: nosniff\n\
+///      x-frame-options: DENY\n"
+/// );
+/// ```
+///
+/// The builder of [`HelmetLayer::explicit_builder`] starts in the [`CspUndecided`] state, which
+/// can't be built until the CSP is either configured or explicitly left out.
+#[derive(Debug, Clone, Default)]
+pub struct HelmetBuilder<S = CspDecided> {
+    set: HeaderSet,
+    nonce: bool,
+    strict: Option<Strict>,
+    state: PhantomData<S>,
+}
+
+/// State of a [`HelmetBuilder`] which can't be built yet, because neither
+/// [`HelmetBuilder::content_security_policy`] nor [`HelmetBuilder::no_content_security_policy`]
+/// was called.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CspUndecided;
+
+/// State of a [`HelmetBuilder`] whose CSP was decided on, so it can be built.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CspDecided;
+
+impl HelmetBuilder<CspUndecided> {
+    /// Sets `Content-Security-Policy`, or `Content-Security-Policy-Report-Only` if the policy is
+    /// [report only](ContentSecurityPolicy::report_only), deciding on the CSP.
+    pub fn content_security_policy(mut self, csp: ContentSecurityPolicy<'_>) -> HelmetBuilder {
+        self.set.set_content_security_policy(csp);
+        self.decided()
+    }
+
+    /// Decides to send no CSP at all, for example for an API which only returns JSON.
+    pub fn no_content_security_policy(mut self) -> HelmetBuilder {
+        self.set.content_security_policy = None;
+        self.set.content_security_policy_report_only = None;
+        self.decided()
+    }
+
+    fn decided(self) -> HelmetBuilder {
+        HelmetBuilder {
+            set: self.set,
+            nonce: self.nonce,
+            strict: self.strict,
+            state: PhantomData,
+        }
+    }
+}
+
+impl HelmetBuilder {
+    /// Continues from the headers of `set`.
+    pub fn from_header_set(set: HeaderSet) -> Self {
+        HelmetBuilder {
+            set,
+            nonce: false,
+            strict: None,
+            state: PhantomData,
+        }
+    }
+
+    /// Sets `Content-Security-Policy`, or `Content-Security-Policy-Report-Only` if the policy is
+    /// [report only](ContentSecurityPolicy::report_only).
+    pub fn content_security_policy(mut self, csp: ContentSecurityPolicy<'_>) -> Self {
+        self.set.set_content_security_policy(csp);
+        self
+    }
+
+    /// Builds the layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header value is invalid, like [`HelmetLayer::enable`], or if the headers
+    /// violate the [strict](HelmetBuilder::strict) standard.
+    pub fn build(&self) -> HelmetLayer {
+        match self.try_build() {
+            Ok(layer) => layer,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Builds the layer, failing if the headers violate the [strict](HelmetBuilder::strict)
+    /// standard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header value is invalid, like [`HelmetLayer::enable`].
+    pub fn try_build(&self) -> Result<HelmetLayer, StrictError> {
+        let mut layer = HelmetLayer::from(self.set.clone());
+        if let Some(strict) = &self.strict {
+            layer.enforce(strict)?;
+        }
+        if self.nonce {
+            layer.enable_nonce();
+        }
+        Ok(layer)
+    }
+}
+
+impl<S> HelmetBuilder<S> {
+    /// Sets `Cross-Origin-Embedder-Policy`.
+    pub fn cross_origin_embedder_policy(mut self, policy: CrossOriginEmbedderPolicy) -> Self {
+        self.set.cross_origin_embedder_policy = Some(policy);
+        self
+    }
+
+    /// Sets `Cross-Origin-Opener-Policy`.
+    pub fn cross_origin_opener_policy(mut self, policy: CrossOriginOpenerPolicy) -> Self {
+        self.set.cross_origin_opener_policy = Some(policy);
+        self
+    }
+
+    /// Sets `Cross-Origin-Resource-Policy`.
+    pub fn cross_origin_resource_policy(mut self, policy: CrossOriginResourcePolicy) -> Self {
+        self.set.cross_origin_resource_policy = Some(policy);
+        self
+    }
+
+    /// Sets `Expect-CT`, which browsers ignore by now.
+    pub fn expect_ct(mut self, expect_ct: ExpectCt) -> Self {
+        self.set.expect_ct = Some(expect_ct);
+        self
+    }
+
+    /// Sets `Origin-Agent-Cluster: ?1`.
+    pub fn origin_agent_cluster(mut self) -> Self {
+        self.set.origin_agent_cluster = Some(OriginAgentCluster::new());
+        self
+    }
+
+    /// Sets `Referrer-Policy`.
+    pub fn referrer_policy(mut self, policy: ReferrerPolicy) -> Self {
+        self.set.referrer_policy = Some(policy);
+        self
+    }
+
+    /// Sets `Strict-Transport-Security`.
+    pub fn hsts(mut self, hsts: StrictTransportSecurity) -> Self {
+        self.set.strict_transport_security = Some(hsts);
+        self
+    }
+
+    /// Sets `X-Content-Type-Options: nosniff`.
+    pub fn content_type_options(mut self) -> Self {
+        self.set.x_content_type_options = Some(XContentTypeOptions::new());
+        self
+    }
+
+    /// Sets `X-DNS-Prefetch-Control` to `on` if `enabled`, otherwise to `off`.
+    pub fn dns_prefetch_control(mut self, enabled: bool) -> Self {
+        self.set.x_dns_prefetch_control = Some(XDnsPrefetchControl(enabled));
+        self
+    }
+
+    /// Sets `X-Download-Options: noopen`, only understood by Internet Explorer.
+    pub fn download_options(mut self) -> Self {
+        self.set.x_download_options = Some(XDownloadOptions::new());
+        self
+    }
+
+    /// Sets `X-Frame-Options`.
+    pub fn frame_options(mut self, options: XFrameOptions) -> Self {
+        self.set.x_frame_options = Some(options);
+        self
+    }
+
+    /// Sets `X-Permitted-Cross-Domain-Policies`.
+    pub fn permitted_cross_domain_policies(
+        mut self,
+        policies: XPermittedCrossDomainPolicies,
+    ) -> Self {
+        self.set.x_permitted_cross_domain_policies = Some(policies);
+        self
+    }
+
+    /// Sets `X-XSS-Protection: 0`, disabling the buggy XSS filter of old browsers.
+    pub fn xss_protection(mut self) -> Self {
+        self.set.x_xss_protection = Some(XXSSProtection::new());
+        self
+    }
+
+    /// Enables or disables the cross-origin isolation headers: `Cross-Origin-Embedder-Policy`,
+    /// `Cross-Origin-Opener-Policy`, `Cross-Origin-Resource-Policy` and `Origin-Agent-Cluster`.
+    /// Enabling keeps values set before and uses the defaults for the others.
+    ///
+    /// ```
+    /// use tower_helmet::header::CrossOriginResourcePolicy;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let layer = HelmetLayer::builder()
+    ///     .cross_origin_resource_policy(CrossOriginResourcePolicy::CrossOrigin)
+    ///     .isolation(true)
+    ///     .legacy(false)
+    ///     .build();
+    ///
+    /// let set = layer.header_set();
+    /// assert_eq!(set.cross_origin_resource_policy, Some(CrossOriginResourcePolicy::CrossOrigin));
+    /// assert!(set.cross_origin_opener_policy.is_some());
+    /// ```
+    pub fn isolation(mut self, enabled: bool) -> Self {
+        let set = &mut self.set;
+        toggle(&mut set.cross_origin_embedder_policy, enabled);
+        toggle(&mut set.cross_origin_opener_policy, enabled);
+        toggle(&mut set.cross_origin_resource_policy, enabled);
+        toggle(&mut set.origin_agent_cluster, enabled);
+        self
+    }
+
+    /// Enables or disables `Strict-Transport-Security`, keeping a value set before.
+    pub fn transport(mut self, enabled: bool) -> Self {
+        toggle(&mut self.set.strict_transport_security, enabled);
+        self
+    }
+
+    /// Enables or disables the headers only old browsers understand: `X-XSS-Protection`,
+    /// `X-Download-Options` and `Expect-CT`, keeping values set before.
+    pub fn legacy(mut self, enabled: bool) -> Self {
+        let set = &mut self.set;
+        toggle(&mut set.x_xss_protection, enabled);
+        toggle(&mut set.x_download_options, enabled);
+        toggle(&mut set.expect_ct, enabled);
+        self
+    }
+
+    /// Generates a nonce for every request, see [`HelmetLayer::enable_nonce`].
+    pub fn nonce(mut self) -> Self {
+        self.nonce = true;
+        self
+    }
+
+    /// Makes building fail if the headers violate `strict`, see [`crate::audit::Strict`].
+    pub fn strict(mut self, strict: Strict) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    /// The headers configured so far.
+    pub fn header_set(&self) -> &HeaderSet {
+        &self.set
+    }
+}
+
+/// Sets `header` to its default if `enabled` and it isn't set yet, otherwise removes it.
+fn toggle<T: Default>(header: &mut Option<T>, enabled: bool) {
+    if !enabled {
+        *header = None;
+    } else if header.is_none() {
+        *header = Some(T::default());
+    }
+}
@@ -0,0 +1,101 @@
+//! Detects headers stripped in transit.
+//!
+//! CDNs and proxies sometimes drop response headers, for example because they exceed a size
+//! limit, so the CSP never reaches users while everything looks fine in the application. A
+//! [`Canary`] is a harmless header enabled alongside the security headers: if it arrives but other
+//! configured headers don't, they were stripped on the way. [`Canary::check`] compares the headers
+//! received by a client (such as a synthetic monitoring probe or the `scan` feature) against the
+//! configuration.
+//!
+//! ```
+//! use tower_helmet::audit::Rule;
+//! use tower_helmet::canary::Canary;
+//! use tower_helmet::{HelmetLayer, IntoHeaders};
+//!
+//! let canary = Canary::new("2024-06-01");
+//! let mut layer = HelmetLayer::with_defaults();
+//! layer.enable(canary.clone());
+//!
+//! // headers received through the CDN, without the CSP
+//! let mut received = layer.header_map().unwrap();
+//! received.remove("content-security-policy");
+//!
+//! let findings = canary.check(&layer, &received);
+//! assert_eq!(findings[0].rule, Rule::MissingCriticalHeader);
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{HeaderName, InvalidHeaderValue};
+use http::{HeaderMap, HeaderValue};
+
+use crate::audit::{Finding, Rule, Severity};
+use crate::{HelmetLayer, IntoHeader};
+
+/// Sets a canary header, by default `X-Helmet-Canary`, to an arbitrary value, such as the deployed
+/// version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canary {
+    pub name: HeaderName,
+    pub value: String,
+}
+
+impl Canary {
+    pub fn new(value: impl Into<String>) -> Self {
+        Canary {
+            name: HeaderName::from_static("x-helmet-canary"),
+            value: value.into(),
+        }
+    }
+
+    /// Uses another header name, for example one the CDN is known to pass through.
+    pub fn with_name(name: HeaderName, value: impl Into<String>) -> Self {
+        Canary {
+            name,
+            value: value.into(),
+        }
+    }
+
+    /// Checks the headers a client received against the headers configured in `layer`.
+    ///
+    /// If the canary is missing or has another value, the response didn't come from this
+    /// configuration (or everything was stripped) and a [`Rule::MissingCanary`] finding is
+    /// returned. Otherwise every configured header which is missing or changed is reported, like
+    /// [`crate::critical::CriticalHeadersLayer`] does.
+    pub fn check(&self, layer: &HelmetLayer, received: &HeaderMap) -> Vec<Finding> {
+        let arrived = received
+            .get(&self.name)
+            .is_some_and(|value| value.as_bytes() == self.value.as_bytes());
+        if !arrived {
+            return vec![Finding::new(
+                Rule::MissingCanary,
+                Severity::Error,
+                Some(self.name.clone()),
+                format!(
+                    "{} `{}` is missing, the response didn't pass through this configuration",
+                    self.name, self.value
+                ),
+            )];
+        }
+
+        let mut expected = layer.headers();
+        expected.remove(&self.name);
+        crate::critical::check(&expected, received)
+    }
+}
+
+impl Display for Canary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl IntoHeader for Canary {
+    fn header_name(&self) -> HeaderName {
+        self.name.clone()
+    }
+
+    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        HeaderValue::from_str(&self.value)
+    }
+}
@@ -0,0 +1,22 @@
+//! The current time. On `wasm32-unknown-unknown` `std::time` panics, so with the `js` feature it
+//! is read from JavaScript instead.
+
+use std::time::SystemTime;
+
+#[cfg(not(feature = "js"))]
+pub(crate) use std::time::Instant;
+#[cfg(feature = "js")]
+pub(crate) use web_time::Instant;
+
+/// The current time as `std` type, so it can be compared with the times passed in.
+pub(crate) fn now() -> SystemTime {
+    #[cfg(feature = "js")]
+    {
+        let elapsed = web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .unwrap_or_default();
+        SystemTime::UNIX_EPOCH + elapsed
+    }
+    #[cfg(not(feature = "js"))]
+    SystemTime::now()
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use http::header::CONTENT_SECURITY_POLICY;
+use http::HeaderValue;
+
+use crate::header::{
+    CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy, CrossOriginResourcePolicy, ExpectCt,
+    OriginAgentCluster, ReferrerPolicy, StrictTransportSecurity, XContentTypeOptions,
+    XDnsPrefetchControl, XDownloadOptions, XFrameOptions, XPermittedCrossDomainPolicies,
+    XXSSProtection,
+};
+use crate::HelmetLayer;
+
+/// Major versions of [helmet.js](https://github.com/helmetjs/helmet) whose default headers can be
+/// reproduced with [`HelmetLayer::compat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HelmetJsVersion {
+    V4,
+    V5,
+    V6,
+    V7,
+}
+
+impl HelmetJsVersion {
+    /// The `Content-Security-Policy` helmet.js sends by default, byte for byte.
+    fn content_security_policy(self) -> &'static str {
+        match self {
+            HelmetJsVersion::V4 => "default-src 'self';base-uri 'self';block-all-mixed-content;font-src 'self' https: data:;frame-ancestors 'self';img-src 'self' data:;object-src 'none';script-src 'self';script-src-attr 'none';style-src 'self' https: 'unsafe-inline';upgrade-insecure-requests",
+            HelmetJsVersion::V5 => "default-src 'self';base-uri 'self';block-all-mixed-content;font-src 'self' https: data:;form-action 'self';frame-ancestors 'self';img-src 'self' data:;object-src 'none';script-src 'self';script-src-attr 'none';style-src 'self' https: 'unsafe-inline';upgrade-insecure-requests",
+            HelmetJsVersion::V6 | HelmetJsVersion::V7 => "default-src 'self';base-uri 'self';font-src 'self' https: data:;form-action 'self';frame-ancestors 'self';img-src 'self' data:;object-src 'none';script-src 'self';script-src-attr 'none';style-src 'self' https: 'unsafe-inline';upgrade-insecure-requests",
+        }
+    }
+}
+
+impl HelmetLayer {
+    /// Helmet with exactly the headers the given helmet.js major version sends by default, so
+    /// Node and Rust services can send identical headers and upgrade deliberately.
+    ///
+    /// - `V4` doesn't send the cross-origin isolation headers (COEP, COOP, CORP) and
+    ///   `Origin-Agent-Cluster`.
+    /// - `V5` adds them and `form-action 'self'` to the CSP.
+    /// - `V6` removes `Expect-CT`, `Cross-Origin-Embedder-Policy` and `block-all-mixed-content`.
+    /// - `V7` raises the HSTS `max-age` to 365 days.
+    ///
+    /// ```
+    /// use tower_helmet::{HelmetJsVersion, HelmetLayer};
+    ///
+    /// let layer = HelmetLayer::compat(HelmetJsVersion::V7);
+    /// ```
+    #[allow(clippy::default_constructed_unit_structs)]
+    pub fn compat(version: HelmetJsVersion) -> Self {
+        let mut layer = Self::blank();
+        layer.update(|headers| {
+            headers.insert(
+                CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static(version.content_security_policy()),
+            );
+        });
+
+        if version != HelmetJsVersion::V4 {
+            layer
+                .enable(CrossOriginOpenerPolicy::SameOrigin)
+                .enable(CrossOriginResourcePolicy::SameOrigin)
+                .enable(OriginAgentCluster::default());
+        }
+
+        if matches!(version, HelmetJsVersion::V4 | HelmetJsVersion::V5) {
+            layer.enable(ExpectCt::default());
+        }
+
+        if version == HelmetJsVersion::V5 {
+            layer.enable(CrossOriginEmbedderPolicy::RequireCorp);
+        }
+
+        let max_age = match version {
+            HelmetJsVersion::V4 | HelmetJsVersion::V5 | HelmetJsVersion::V6 => 15552000,
+            HelmetJsVersion::V7 => 31536000,
+        };
+
+        layer
+            .enable(ReferrerPolicy::default())
+            .enable(StrictTransportSecurity {
+                max_age: Duration::from_secs(max_age),
+                include_subdomains: true,
+                preload: false,
+            })
+            .enable(XContentTypeOptions::default())
+            .enable(XDnsPrefetchControl(false))
+            .enable(XDownloadOptions::default())
+            .enable(XFrameOptions::SameOrigin)
+            .enable(XPermittedCrossDomainPolicies::None)
+            .enable(XXSSProtection::default());
+
+        layer
+    }
+}
@@ -0,0 +1,413 @@
+//! Checklists mapping a header set to the requirements of compliance frameworks.
+//!
+//! Auditors ask for evidence that the browser-facing requirements of a framework are met. A
+//! [`Checklist`] lists these requirements together with whether the configured headers satisfy
+//! them and the header values proving it, and renders as plain text for the audit documentation.
+//! It only covers what response headers can show, never the whole requirement. Presets for
+//! regulated environments explain their headers with a [`Rationale`] each instead.
+//!
+//! ```
+//! use tower_helmet::HelmetLayer;
+//!
+//! let checklist = HelmetLayer::with_defaults_v2().pci_dss_checklist();
+//! for check in checklist.unsatisfied() {
+//!     println!("{}: {}", check.requirement, check.evidence);
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{
+    HeaderName, ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_SECURITY_POLICY,
+    CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+    X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use http::HeaderMap;
+
+use crate::header::parse_directives;
+
+/// One year in seconds.
+const ONE_YEAR: u64 = 31536000;
+
+/// Six months in seconds, the minimum HSTS `max-age` accepted for the ASVS, which gives
+/// `15724800` as an example.
+const SIX_MONTHS: u64 = 15552000;
+
+/// Referrer policies which don't send the path to other origins.
+const PRIVATE_REFERRER_POLICIES: [&str; 4] = [
+    "no-referrer",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+];
+
+/// Sources which allow loading scripts from anywhere (of a scheme).
+const UNRESTRICTED_SOURCES: [&str; 6] = [
+    "*",
+    "http:",
+    "https:",
+    "data:",
+    "'unsafe-inline'",
+    "'unsafe-eval'",
+];
+
+/// The result of checking a header set against a compliance framework.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Checklist {
+    /// The framework and its version, such as `PCI DSS 4.0`.
+    pub framework: &'static str,
+    pub checks: Vec<Check>,
+}
+
+/// A single requirement of a [`Checklist`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Check {
+    /// The number of the requirement in the framework, such as `6.4.3`.
+    pub requirement: &'static str,
+    pub description: &'static str,
+    pub satisfied: bool,
+    /// The header values the result is based on, or what is missing.
+    pub evidence: String,
+}
+
+impl Checklist {
+    /// Whether every requirement is satisfied.
+    pub fn is_satisfied(&self) -> bool {
+        self.checks.iter().all(|check| check.satisfied)
+    }
+
+    /// Only the requirements which are not satisfied.
+    pub fn unsatisfied(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| !check.satisfied)
+    }
+}
+
+/// Renders the checklist with one line per requirement, such as
+/// `[x] 6.2.4 Clickjacking protection: frame-ancestors 'none'`.
+impl Display for Checklist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.framework)?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "[{}] {} {}: {}",
+                if check.satisfied { "x" } else { " " },
+                check.requirement,
+                check.description,
+                check.evidence
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Why a preset sets a header, see for example [`crate::preset::HealthcarePortal::rationale`].
+///
+/// With the `serde` feature it can be serialized, for exporting it to other documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rationale {
+    /// The lowercase header name.
+    pub header: &'static str,
+    pub value: String,
+    pub reason: &'static str,
+}
+
+/// Renders the rationale as `name: value`, followed by the reason on an indented line.
+impl Display for Rationale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}\n  {}", self.header, self.value, self.reason)
+    }
+}
+
+/// Checks the headers against the browser-related requirements of PCI DSS 4.0, see
+/// [`crate::preset::PaymentPages`] for a preset satisfying them.
+///
+/// - 4.2.1: `Strict-Transport-Security` with a `max-age` of at least one year, so cardholder data
+///   is only sent over HTTPS.
+/// - 6.2.4: clickjacking protection through `frame-ancestors` or `X-Frame-Options`.
+/// - 6.2.4: `X-Content-Type-Options: nosniff` against MIME type confusion.
+/// - 6.4.3: an enforced CSP whose `script-src` is an allowlist of authorized scripts, without
+///   wildcards, schemes, `'unsafe-inline'` or `'unsafe-eval'`.
+/// - 11.6.1: CSP violations are reported, to get alerted about unauthorized changes to the scripts
+///   of payment pages.
+pub fn pci_dss(headers: &HeaderMap) -> Checklist {
+    let headers = Headers::new(headers);
+    let mut checks = Vec::new();
+
+    let (max_age, _) = headers.hsts();
+    let hsts = headers.header(STRICT_TRANSPORT_SECURITY);
+    checks.push(Check {
+        requirement: "4.2.1",
+        description: "Strong cryptography during transmission",
+        satisfied: max_age >= ONE_YEAR,
+        evidence: match hsts {
+            Some(hsts) if max_age >= ONE_YEAR => format!("strict-transport-security: {}", hsts),
+            Some(hsts) => format!("strict-transport-security: {} (less than a year)", hsts),
+            None => "strict-transport-security is not set".to_owned(),
+        },
+    });
+
+    let (satisfied, evidence) = headers.framing();
+    checks.push(Check {
+        requirement: "6.2.4",
+        description: "Clickjacking protection",
+        satisfied,
+        evidence,
+    });
+
+    let (satisfied, evidence) = headers.nosniff();
+    checks.push(Check {
+        requirement: "6.2.4",
+        description: "MIME type sniffing protection",
+        satisfied,
+        evidence,
+    });
+
+    let script_src = headers.script_src();
+    let allowlist = script_src
+        .is_some_and(|(_, sources)| !sources.iter().any(|s| UNRESTRICTED_SOURCES.contains(s)));
+    checks.push(Check {
+        requirement: "6.4.3",
+        description: "Payment page scripts are authorized",
+        satisfied: allowlist,
+        evidence: match script_src {
+            Some(directive) if allowlist => render(directive),
+            Some(directive) => format!("{} (not an allowlist)", render(directive)),
+            None => "no enforced script-src or default-src".to_owned(),
+        },
+    });
+
+    let report_endpoints: Vec<String> =
+        [CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY]
+            .iter()
+            .filter_map(|name| headers.header(name.clone()))
+            .flat_map(parse_directives)
+            .filter(|(name, _)| *name == "report-uri" || *name == "report-to")
+            .map(|directive| render(&directive))
+            .collect();
+    checks.push(Check {
+        requirement: "11.6.1",
+        description: "Unauthorized changes are detected",
+        satisfied: !report_endpoints.is_empty(),
+        evidence: if report_endpoints.is_empty() {
+            "csp violations are not reported".to_owned()
+        } else {
+            report_endpoints.join(", ")
+        },
+    });
+
+    Checklist {
+        framework: "PCI DSS 4.0",
+        checks,
+    }
+}
+
+/// Checks the headers against the controls of the OWASP ASVS 4.0.3 which response headers
+/// cover:
+///
+/// - 8.2.1: `Cache-Control: no-store`, so sensitive data isn't cached by browsers.
+/// - 14.4.3: an enforced CSP whose `script-src` allows neither `'unsafe-inline'` nor
+///   `'unsafe-eval'`.
+/// - 14.4.4: `X-Content-Type-Options: nosniff`.
+/// - 14.4.5: `Strict-Transport-Security` with `includeSubDomains` and a `max-age` of at least six
+///   months.
+/// - 14.4.6: a `Referrer-Policy` which doesn't leak URLs to other origins.
+/// - 14.4.7: content can't be embedded by third-party sites, through `frame-ancestors` or
+///   `X-Frame-Options`.
+/// - 14.5.3: `Access-Control-Allow-Origin` isn't `*`. It is satisfied if no CORS headers are set.
+///
+/// With the `serde` feature, the checklist can be serialized for machine-readable reports.
+///
+/// ```
+/// use tower_helmet::HelmetLayer;
+///
+/// let checklist = HelmetLayer::with_defaults_v2().asvs_checklist();
+/// let uncovered: Vec<_> = checklist.unsatisfied().map(|c| c.requirement).collect();
+/// assert_eq!(uncovered, ["8.2.1"]);
+/// ```
+pub fn asvs(headers: &HeaderMap) -> Checklist {
+    let headers = Headers::new(headers);
+    let mut checks = Vec::new();
+
+    let cache_control = headers.header(CACHE_CONTROL);
+    let no_store = cache_control.is_some_and(|value| {
+        value
+            .split(',')
+            .any(|d| d.trim().eq_ignore_ascii_case("no-store"))
+    });
+    checks.push(Check {
+        requirement: "8.2.1",
+        description: "Sensitive data is not cached",
+        satisfied: no_store,
+        evidence: match cache_control {
+            Some(value) => format!("cache-control: {}", value),
+            None => "cache-control is not set".to_owned(),
+        },
+    });
+
+    let script_src = headers.script_src();
+    let safe = script_src.is_some_and(|(_, sources)| {
+        !sources.contains(&"'unsafe-inline'") && !sources.contains(&"'unsafe-eval'")
+    });
+    checks.push(Check {
+        requirement: "14.4.3",
+        description: "Content-Security-Policy mitigates XSS",
+        satisfied: safe,
+        evidence: match script_src {
+            Some(directive) if safe => render(directive),
+            Some(directive) => format!("{} (allows unsafe scripts)", render(directive)),
+            None => "no enforced script-src or default-src".to_owned(),
+        },
+    });
+
+    let (satisfied, evidence) = headers.nosniff();
+    checks.push(Check {
+        requirement: "14.4.4",
+        description: "X-Content-Type-Options: nosniff",
+        satisfied,
+        evidence,
+    });
+
+    let (max_age, include_subdomains) = headers.hsts();
+    let satisfied = max_age >= SIX_MONTHS && include_subdomains;
+    checks.push(Check {
+        requirement: "14.4.5",
+        description: "Strict-Transport-Security on all responses and subdomains",
+        satisfied,
+        evidence: match headers.header(STRICT_TRANSPORT_SECURITY) {
+            Some(hsts) if satisfied => format!("strict-transport-security: {}", hsts),
+            Some(hsts) => format!(
+                "strict-transport-security: {} (needs includeSubDomains and max-age={})",
+                hsts, SIX_MONTHS
+            ),
+            None => "strict-transport-security is not set".to_owned(),
+        },
+    });
+
+    let referrer_policy = headers.header(REFERRER_POLICY);
+    // browsers use the last policy they understand
+    let private = referrer_policy.is_some_and(|value| {
+        let policy = value.rsplit(',').next().unwrap_or_default().trim();
+        PRIVATE_REFERRER_POLICIES.contains(&policy)
+    });
+    checks.push(Check {
+        requirement: "14.4.6",
+        description: "Referrer-Policy doesn't leak URLs",
+        satisfied: private,
+        evidence: match referrer_policy {
+            Some(value) => format!("referrer-policy: {}", value),
+            None => "referrer-policy is not set".to_owned(),
+        },
+    });
+
+    let (satisfied, evidence) = headers.framing();
+    checks.push(Check {
+        requirement: "14.4.7",
+        description: "Content can't be embedded by third-party sites",
+        satisfied,
+        evidence,
+    });
+
+    let allow_origin = headers.header(ACCESS_CONTROL_ALLOW_ORIGIN);
+    checks.push(Check {
+        requirement: "14.5.3",
+        description: "CORS origins are allowlisted",
+        satisfied: allow_origin != Some("*"),
+        evidence: match allow_origin {
+            Some(value) => format!("access-control-allow-origin: {}", value),
+            None => "access-control-allow-origin is not set".to_owned(),
+        },
+    });
+
+    Checklist {
+        framework: "OWASP ASVS 4.0.3",
+        checks,
+    }
+}
+
+/// Renders a CSP directive as in the header.
+fn render((name, sources): &(&str, Vec<&str>)) -> String {
+    format!("{} {}", name, sources.join(" "))
+}
+
+/// Response headers with the enforced CSP parsed, shared by the checklists.
+struct Headers<'a> {
+    headers: &'a HeaderMap,
+    csp: Option<Vec<(&'a str, Vec<&'a str>)>>,
+}
+
+impl<'a> Headers<'a> {
+    fn new(headers: &'a HeaderMap) -> Self {
+        let csp = headers
+            .get(CONTENT_SECURITY_POLICY)
+            .and_then(|v| v.to_str().ok())
+            .map(parse_directives);
+        Headers { headers, csp }
+    }
+
+    fn header(&self, name: HeaderName) -> Option<&'a str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    fn directive(&self, name: &str) -> Option<&(&'a str, Vec<&'a str>)> {
+        self.csp
+            .as_ref()
+            .and_then(|csp| csp.iter().find(|(n, _)| *n == name))
+    }
+
+    /// `script-src`, or `default-src` as its fallback.
+    fn script_src(&self) -> Option<&(&'a str, Vec<&'a str>)> {
+        self.directive("script-src")
+            .or_else(|| self.directive("default-src"))
+    }
+
+    /// The HSTS `max-age` (`0` if not set) and whether it has `includeSubDomains`.
+    fn hsts(&self) -> (u64, bool) {
+        let directives: Vec<String> = self
+            .header(STRICT_TRANSPORT_SECURITY)
+            .unwrap_or_default()
+            .split(';')
+            .map(|d| d.trim().to_ascii_lowercase())
+            .collect();
+        let max_age = directives
+            .iter()
+            .find_map(|d| d.strip_prefix("max-age="))
+            .and_then(|v| v.trim_matches('"').parse().ok())
+            .unwrap_or_default();
+
+        (max_age, directives.iter().any(|d| d == "includesubdomains"))
+    }
+
+    /// Whether framing by other sites is restricted, through `frame-ancestors` or
+    /// `X-Frame-Options`, with the evidence.
+    fn framing(&self) -> (bool, String) {
+        let frame_options = self.header(X_FRAME_OPTIONS).filter(|xfo| {
+            xfo.eq_ignore_ascii_case("deny") || xfo.eq_ignore_ascii_case("sameorigin")
+        });
+        match (self.directive("frame-ancestors"), frame_options) {
+            (Some(directive), _) if !directive.1.is_empty() && !directive.1.contains(&"*") => {
+                (true, render(directive))
+            }
+            (_, Some(xfo)) => (true, format!("x-frame-options: {}", xfo)),
+            (Some(directive), None) => (false, render(directive)),
+            (None, None) => (
+                false,
+                "neither frame-ancestors nor x-frame-options is set".to_owned(),
+            ),
+        }
+    }
+
+    /// Whether `X-Content-Type-Options: nosniff` is set, with the evidence.
+    fn nosniff(&self) -> (bool, String) {
+        match self.header(X_CONTENT_TYPE_OPTIONS) {
+            Some(value) if value.eq_ignore_ascii_case("nosniff") => {
+                (true, "x-content-type-options: nosniff".to_owned())
+            }
+            _ => (false, "x-content-type-options is not nosniff".to_owned()),
+        }
+    }
+}
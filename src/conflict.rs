@@ -0,0 +1,107 @@
+//! How headers already set by the application or a proxied backend are treated.
+//!
+//! By default the layer's headers replace the values of the response. Gateways in front of a fleet
+//! of backends often want something else: some backends send a carefully tuned CSP which should be
+//! kept, others send outdated values which should be replaced. [`ConflictStrategies`] passed to
+//! [`crate::HelmetLayer::conflicts`] choose a [`Conflict`] strategy per header, only applied when
+//! the response already has the header; headers it doesn't have are always set.
+//!
+//! A [`crate::HeaderOverride`] is applied afterwards and still takes precedence.
+//!
+//! ```
+//! use http::header::{CONTENT_SECURITY_POLICY, X_FRAME_OPTIONS};
+//! use tower_helmet::conflict::{Conflict, ConflictStrategies};
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut conflicts = ConflictStrategies::new();
+//! conflicts
+//!     .header(CONTENT_SECURITY_POLICY, Conflict::MergeValues)
+//!     .header(X_FRAME_OPTIONS, Conflict::KeepUpstream);
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.conflicts(conflicts);
+//! ```
+
+use http::header::{AsHeaderName, HeaderName};
+use http::HeaderMap;
+
+/// What to do with a header the response already has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Conflict {
+    /// Sends the layer's values instead.
+    #[default]
+    Replace,
+    /// Sends the values of the response, ignoring the layer's ones. With a nonce, the CSP of the
+    /// response doesn't get it.
+    KeepUpstream,
+    /// Sends the values of the response and the layer's ones which differ from them, as separate
+    /// header values. Only suitable for headers browsers combine: every `Content-Security-Policy`
+    /// is enforced, so the response is restricted by both policies. Browsers ignore most other
+    /// security headers with several values.
+    MergeValues,
+}
+
+/// The [`Conflict`] strategy of every header, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct ConflictStrategies {
+    otherwise: Conflict,
+    headers: Vec<(HeaderName, Conflict)>,
+}
+
+impl ConflictStrategies {
+    /// Replaces the values of every header, like the layer without strategies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `conflict` for `key`, replacing an earlier strategy for it.
+    pub fn header<K>(&mut self, key: K, conflict: Conflict) -> &mut Self
+    where
+        K: AsHeaderName + Into<HeaderName>,
+    {
+        let name = key.into();
+        self.headers.retain(|(header, _)| *header != name);
+        self.headers.push((name, conflict));
+        self
+    }
+
+    /// Uses `conflict` for the headers without a strategy of their own.
+    pub fn otherwise(&mut self, conflict: Conflict) -> &mut Self {
+        self.otherwise = conflict;
+        self
+    }
+
+    /// The strategy for the header `name`.
+    pub fn strategy(&self, name: &HeaderName) -> Conflict {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map_or(self.otherwise, |(_, conflict)| *conflict)
+    }
+
+    /// Sets the layer's `headers` on the response, resolving conflicts with its `response_headers`.
+    pub(crate) fn apply(&self, response_headers: &mut HeaderMap, headers: &HeaderMap) {
+        for name in headers.keys() {
+            let strategy = if response_headers.contains_key(name) {
+                self.strategy(name)
+            } else {
+                Conflict::Replace
+            };
+            match strategy {
+                Conflict::Replace => {
+                    for value in headers.get_all(name) {
+                        response_headers.insert(name, value.clone());
+                    }
+                }
+                Conflict::KeepUpstream => {}
+                Conflict::MergeValues => {
+                    for value in headers.get_all(name) {
+                        if !response_headers.get_all(name).iter().any(|v| v == value) {
+                            response_headers.append(name, value.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Hardening of the cookies set by the inner service.
+//!
+//! A [`CookiePolicy`] passed to [`crate::HelmetLayer::harden_cookies`] rewrites every outgoing
+//! `Set-Cookie` header to carry `Secure`, `HttpOnly` and the configured `SameSite` value, so a
+//! single handler forgetting them doesn't weaken the whole application.
+//!
+//! ```
+//! use tower_helmet::cookie::{CookiePolicy, SameSite};
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut policy = CookiePolicy::default();
+//! policy.same_site = Some(SameSite::Strict);
+//! // read by JavaScript, so it can't be `HttpOnly`
+//! policy.exempt("csrf_token");
+//!
+//! let mut layer = HelmetLayer::with_defaults();
+//! layer.harden_cookies(policy);
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::header::SET_COOKIE;
+use http::{HeaderMap, HeaderValue};
+
+/// Value of the `SameSite` cookie attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Sent with cross-site requests as well. Browsers require `Secure` with it.
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// Attributes enforced on outgoing cookies.
+#[derive(Debug, Clone)]
+pub struct CookiePolicy {
+    /// Adds `Secure`, so the cookie is only sent over HTTPS. Defaults to `true`.
+    pub secure: bool,
+    /// Adds `HttpOnly`, so the cookie can't be read by JavaScript. Defaults to `true`.
+    pub http_only: bool,
+    /// Replaces the `SameSite` attribute with this value, if set. Defaults to [`SameSite::Lax`].
+    pub same_site: Option<SameSite>,
+    /// Names of cookies which are left untouched.
+    pub exempt: Vec<String>,
+}
+
+impl Default for CookiePolicy {
+    fn default() -> Self {
+        CookiePolicy {
+            secure: true,
+            http_only: true,
+            same_site: Some(SameSite::Lax),
+            exempt: Vec::new(),
+        }
+    }
+}
+
+impl CookiePolicy {
+    /// Leaves the cookie with the given name untouched.
+    pub fn exempt(&mut self, name: impl Into<String>) -> &mut Self {
+        self.exempt.push(name.into());
+        self
+    }
+
+    /// Rewrites all `Set-Cookie` headers of a response.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        let cookies: Vec<HeaderValue> = match headers.entry(SET_COOKIE) {
+            http::header::Entry::Occupied(entry) => entry.remove_entry_mult().1.collect(),
+            http::header::Entry::Vacant(_) => return,
+        };
+
+        for cookie in cookies {
+            let cookie = match cookie.to_str() {
+                Ok(value) => self
+                    .harden(value)
+                    .and_then(|value| HeaderValue::from_str(&value).ok())
+                    .unwrap_or(cookie),
+                Err(_) => cookie,
+            };
+            headers.append(SET_COOKIE, cookie);
+        }
+    }
+
+    /// The hardened `Set-Cookie` value, or `None` if the cookie is exempt.
+    fn harden(&self, cookie: &str) -> Option<String> {
+        let mut parts = cookie.split(';').map(str::trim);
+        let pair = parts.next()?;
+        let name = pair.split('=').next()?.trim();
+        if self.exempt.iter().any(|exempt| exempt == name) {
+            return None;
+        }
+
+        let mut attributes: Vec<&str> = parts.filter(|a| !a.is_empty()).collect();
+        let same_site = self
+            .same_site
+            .map(|same_site| format!("SameSite={}", same_site));
+        if same_site.is_some() {
+            attributes.retain(|a| !is_attribute(a, "samesite"));
+        }
+
+        let secure = self.secure || self.same_site == Some(SameSite::None);
+        let add_secure = secure && !attributes.iter().any(|a| is_attribute(a, "secure"));
+        let add_http_only =
+            self.http_only && !attributes.iter().any(|a| is_attribute(a, "httponly"));
+
+        let mut hardened = vec![pair];
+        hardened.extend(attributes);
+        if add_secure {
+            hardened.push("Secure");
+        }
+        if add_http_only {
+            hardened.push("HttpOnly");
+        }
+        if let Some(same_site) = &same_site {
+            hardened.push(same_site);
+        }
+
+        Some(hardened.join("; "))
+    }
+}
+
+fn is_attribute(attribute: &str, name: &str) -> bool {
+    attribute
+        .split('=')
+        .next()
+        .is_some_and(|a| a.trim().eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,109 @@
+//! `Cross-Origin-Resource-Policy` chosen per response, by its content type.
+//!
+//! Services serving both documents and static resources need different values: fonts, images and
+//! media embedded by other sites need `cross-origin`, while documents and API responses should
+//! stay `same-origin`, so other sites can't load them into their process. A [`ContentTypeCorp`]
+//! passed to [`crate::HelmetLayer::corp_by_content_type`] maps content types to values and sets
+//! the one matching the `Content-Type` of each response, overriding the layer's static value.
+//! Responses without matching rule keep the layer's value.
+//!
+//! ```
+//! use tower_helmet::corp::ContentTypeCorp;
+//! use tower_helmet::header::CrossOriginResourcePolicy;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut corp = ContentTypeCorp::recommended();
+//! corp.content_type("application/wasm", CrossOriginResourcePolicy::CrossOrigin);
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.corp_by_content_type(corp);
+//! ```
+
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::CrossOriginResourcePolicy;
+use crate::IntoHeader;
+
+/// Maps content types to `Cross-Origin-Resource-Policy` values, see the [module docs](self).
+///
+/// A rule is either a full type such as `application/json` or a whole top-level type such as
+/// `image/*`. Types are compared case-insensitively and without parameters; a full type takes
+/// precedence over a top-level one.
+#[derive(Debug, Clone, Default)]
+pub struct ContentTypeCorp {
+    rules: Vec<(String, HeaderValue)>,
+}
+
+impl ContentTypeCorp {
+    /// Creates an empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `cross-origin` for fonts, images, audio and video, `same-origin` for HTML, JSON and
+    /// XML documents.
+    pub fn recommended() -> Self {
+        let mut corp = Self::new();
+        for content_type in ["font/*", "image/*", "audio/*", "video/*"] {
+            corp.content_type(content_type, CrossOriginResourcePolicy::CrossOrigin);
+        }
+        for content_type in [
+            "text/html",
+            "application/json",
+            "application/xhtml+xml",
+            "application/xml",
+            "text/xml",
+        ] {
+            corp.content_type(content_type, CrossOriginResourcePolicy::SameOrigin);
+        }
+        corp
+    }
+
+    /// Sets `policy` for responses of `content_type`, replacing an earlier rule for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` is an invalid [`CrossOriginResourcePolicy::Custom`] value.
+    pub fn content_type(
+        &mut self,
+        content_type: &str,
+        policy: CrossOriginResourcePolicy,
+    ) -> &mut Self {
+        let content_type = content_type.trim().to_ascii_lowercase();
+        let value = policy.header_value().unwrap();
+        self.rules.retain(|(rule, _)| *rule != content_type);
+        self.rules.push((content_type, value));
+        self
+    }
+
+    /// The value for a response with `content_type`, `None` if no rule matches.
+    pub fn policy_for(&self, content_type: &str) -> Option<&HeaderValue> {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let top_level = format!("{}/*", essence.split('/').next().unwrap_or_default());
+
+        [essence, top_level].iter().find_map(|wanted| {
+            self.rules
+                .iter()
+                .find(|(rule, _)| rule == wanted)
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// Sets the value matching the response's `Content-Type`, if any.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        let value = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|content_type| self.policy_for(content_type))
+            .cloned();
+        if let Some(value) = value {
+            headers.insert(CrossOriginResourcePolicy::SameOrigin.header_name(), value);
+        }
+    }
+}
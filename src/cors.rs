@@ -0,0 +1,144 @@
+//! Detection of wide-open or conflicting CORS headers in the response path.
+//!
+//! CORS headers are usually set by another layer or by handlers, so they can't be checked with the
+//! rest of the configuration. A [`CorsAudit`] passed to [`crate::HelmetLayer::audit_cors`] inspects
+//! every response after the security headers have been added and flags
+//! `Access-Control-Allow-Origin: *` combined with
+//!
+//! - `Access-Control-Allow-Credentials: true` ([`Rule::WildcardCorsWithCredentials`]): browsers
+//!   refuse such responses, which often leads to "fixing" it by reflecting any `Origin` instead,
+//! - a `Cross-Origin-Resource-Policy` of `same-origin` or `same-site`
+//!   ([`Rule::WildcardCorsWithRestrictiveCorp`]): the resource is shared with everyone and
+//!   isolated at the same time, so one of them is not what was intended.
+//!
+//! An explicitly allowed origin combined with `Cross-Origin-Resource-Policy: same-origin` is
+//! flagged as well ([`Rule::CorsWithSameOriginCorp`]): the resource can only be used cross-origin
+//! through CORS requests, while `<img>`, `<script>` and other no-cors loads from the allowed origin
+//! fail. These checks are part of [`crate::audit::audit`] as well, so they also apply to scanned
+//! responses and header sets audited ahead of time.
+//!
+//! Responses are not modified: the [`Finding`]s are passed to a callback and with the `tracing`
+//! feature logged as warning.
+//!
+//! ```
+//! use tower_helmet::cors::CorsAudit;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut audit = CorsAudit::new();
+//! audit.on_finding(|finding| eprintln!("{}", finding));
+//!
+//! let mut layer = HelmetLayer::with_defaults();
+//! layer.audit_cors(audit);
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use http::header::{ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN};
+use http::HeaderMap;
+
+use crate::audit::{Finding, Rule, Severity};
+use crate::header::CROSS_ORIGIN_RESOURCE_POLICY;
+
+type FindingCallback = Arc<dyn Fn(&Finding) + Send + Sync>;
+
+/// Flags responses with wide-open or conflicting CORS headers, see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct CorsAudit {
+    on_finding: Option<FindingCallback>,
+}
+
+impl Debug for CorsAudit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CorsAudit")
+            .field("on_finding", &self.on_finding.is_some())
+            .finish()
+    }
+}
+
+impl CorsAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` for every finding.
+    pub fn on_finding<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Finding) + Send + Sync + 'static,
+    {
+        self.on_finding = Some(Arc::new(callback));
+        self
+    }
+
+    /// Checks the headers of a response.
+    pub fn check(&self, headers: &HeaderMap) -> Vec<Finding> {
+        check(headers)
+    }
+
+    /// Checks a response and reports its findings.
+    pub(crate) fn report(&self, headers: &HeaderMap) {
+        for finding in check(headers) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(rule = %finding.rule, "{}", finding.message);
+
+            if let Some(callback) = &self.on_finding {
+                callback(&finding);
+            }
+        }
+    }
+}
+
+pub(crate) fn check(headers: &HeaderMap) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let origin = match headers.get(ACCESS_CONTROL_ALLOW_ORIGIN) {
+        Some(origin) => origin,
+        None => return findings,
+    };
+    let corp = headers
+        .get(&CROSS_ORIGIN_RESOURCE_POLICY)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase());
+
+    if origin.as_bytes() != b"*" {
+        if corp.as_deref() == Some("same-origin") && origin.as_bytes() != b"null" {
+            findings.push(Finding::new(
+                Rule::CorsWithSameOriginCorp,
+                Severity::Warning,
+                Some(CROSS_ORIGIN_RESOURCE_POLICY.clone()),
+                format!(
+                    "Cross-Origin-Resource-Policy: same-origin blocks no-cors loads from {}, which CORS allows",
+                    String::from_utf8_lossy(origin.as_bytes())
+                ),
+            ));
+        }
+        return findings;
+    }
+
+    let credentials = headers
+        .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"true"));
+    if credentials {
+        findings.push(Finding::new(
+            Rule::WildcardCorsWithCredentials,
+            Severity::Warning,
+            Some(ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            "Access-Control-Allow-Origin: * is combined with Access-Control-Allow-Credentials: true",
+        ));
+    }
+
+    let corp = corp.filter(|v| v == "same-origin" || v == "same-site");
+    if let Some(corp) = corp {
+        findings.push(Finding::new(
+            Rule::WildcardCorsWithRestrictiveCorp,
+            Severity::Warning,
+            Some(CROSS_ORIGIN_RESOURCE_POLICY.clone()),
+            format!(
+                "Access-Control-Allow-Origin: * is combined with Cross-Origin-Resource-Policy: {}",
+                corp
+            ),
+        ));
+    }
+
+    findings
+}
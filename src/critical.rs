@@ -0,0 +1,313 @@
+//! Verifies that critical headers actually leave the application.
+//!
+//! Layers added outside of [`HelmetLayer`] (or handlers overriding its headers) can strip or
+//! replace them, for example a proxy layer copying upstream headers or a compression layer
+//! rebuilding the response. [`CriticalHeadersLayer`] is added as outermost layer and checks every
+//! response against the values configured in the [`HelmetLayer`]. A missing header is reported as
+//! [`Rule::MissingCriticalHeader`], a different value as [`Rule::ChangedCriticalHeader`]. The
+//! [`Finding`]s are passed to a callback and with the `tracing` feature logged as error;
+//! [`CriticalHeadersLayer::fail_closed`] additionally replaces such responses with
+//! `500 Internal Server Error`, so the problem can't go unnoticed.
+//!
+//! Per-request additions to the CSP, the [`crate::CspNonce`] and hashes of inline blocks, are
+//! ignored, as well as the order of its directives.
+//!
+//! ```
+//! use http::header::{CONTENT_SECURITY_POLICY, STRICT_TRANSPORT_SECURITY};
+//! use tower_helmet::critical::CriticalHeadersLayer;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let helmet = HelmetLayer::with_defaults();
+//! let mut critical =
+//!     CriticalHeadersLayer::new(&helmet, [CONTENT_SECURITY_POLICY, STRICT_TRANSPORT_SECURITY]);
+//! critical.fail_closed(true);
+//!
+//! // let app = Router::new().layer(helmet).layer(CompressionLayer::new()).layer(critical);
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::ready;
+use http::header::{HeaderName, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY};
+use http::{HeaderMap, HeaderValue, Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::audit::{Finding, Rule, Severity};
+use crate::header::parse_directives;
+use crate::{preset, HelmetLayer};
+
+type FindingCallback = Arc<dyn Fn(&Finding) + Send + Sync>;
+
+/// Layer checking that critical headers are sent as configured, see the [module docs](self).
+#[derive(Clone)]
+pub struct CriticalHeadersLayer {
+    expected: HeaderMap,
+    /// The expected headers of gRPC responses, see [`HelmetLayer::grpc`].
+    grpc: Option<HeaderMap>,
+    exempt_paths: Vec<String>,
+    exempt_prefixes: Vec<String>,
+    fail_closed: bool,
+    on_finding: Option<FindingCallback>,
+}
+
+impl Debug for CriticalHeadersLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CriticalHeadersLayer")
+            .field("expected", &self.expected)
+            .field("grpc", &self.grpc)
+            .field("exempt_paths", &self.exempt_paths)
+            .field("exempt_prefixes", &self.exempt_prefixes)
+            .field("fail_closed", &self.fail_closed)
+            .field("on_finding", &self.on_finding.is_some())
+            .finish()
+    }
+}
+
+impl CriticalHeadersLayer {
+    /// Checks the given headers against the values configured in `helmet`. Headers it doesn't
+    /// set are ignored.
+    pub fn new<I>(helmet: &HelmetLayer, names: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+    {
+        let names: Vec<HeaderName> = names.into_iter().collect();
+        let select = |headers: &HeaderMap| {
+            let mut expected = HeaderMap::new();
+            for name in &names {
+                if let Some(value) = headers.get(name) {
+                    expected.insert(name.clone(), value.clone());
+                }
+            }
+            expected
+        };
+
+        CriticalHeadersLayer {
+            expected: select(&helmet.headers()),
+            grpc: helmet.grpc.as_ref().map(select),
+            exempt_paths: Vec::new(),
+            exempt_prefixes: Vec::new(),
+            fail_closed: false,
+            on_finding: None,
+        }
+    }
+
+    /// Doesn't check responses to exactly this path, for example because its handler overrides
+    /// headers on purpose.
+    pub fn exempt_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.exempt_paths.push(path.into());
+        self
+    }
+
+    /// Doesn't check responses to paths starting with `prefix`.
+    pub fn exempt_prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.exempt_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Replaces responses with missing or changed headers with an empty
+    /// `500 Internal Server Error`. Defaults to `false`.
+    pub fn fail_closed(&mut self, fail_closed: bool) -> &mut Self {
+        self.fail_closed = fail_closed;
+        self
+    }
+
+    /// Calls `callback` for every missing or changed header.
+    pub fn on_finding<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Finding) + Send + Sync + 'static,
+    {
+        self.on_finding = Some(Arc::new(callback));
+        self
+    }
+
+    /// Checks the headers of a response, returning a finding for every missing or changed one.
+    pub fn check(&self, headers: &HeaderMap) -> Vec<Finding> {
+        check(&self.expected, headers)
+    }
+
+    fn is_exempt<B>(&self, request: &Request<B>) -> bool {
+        let path = request.uri().path();
+        self.exempt_paths.iter().any(|p| p == path)
+            || self.exempt_prefixes.iter().any(|p| path.starts_with(p))
+    }
+
+    /// The expected headers of responses to gRPC or other requests.
+    fn expected(&self, is_grpc: bool) -> &HeaderMap {
+        match &self.grpc {
+            Some(grpc) if is_grpc => grpc,
+            _ => &self.expected,
+        }
+    }
+
+    fn report(&self, finding: &Finding) {
+        #[cfg(feature = "tracing")]
+        tracing::error!(rule = %finding.rule, "{}", finding.message);
+
+        if let Some(callback) = &self.on_finding {
+            callback(finding);
+        }
+    }
+}
+
+pub(crate) fn check(expected: &HeaderMap, headers: &HeaderMap) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for (name, value) in expected {
+        let finding = match headers.get(name) {
+            None => Finding::new(
+                Rule::MissingCriticalHeader,
+                Severity::Error,
+                Some(name.clone()),
+                format!("{} was stripped from the response", name),
+            ),
+            Some(actual) if !matches(name, value, actual) => Finding::new(
+                Rule::ChangedCriticalHeader,
+                Severity::Error,
+                Some(name.clone()),
+                format!(
+                    "{} was changed to `{}`",
+                    name,
+                    String::from_utf8_lossy(actual.as_bytes())
+                ),
+            ),
+            Some(_) => continue,
+        };
+        findings.push(finding);
+    }
+
+    findings
+}
+
+fn matches(name: &HeaderName, expected: &HeaderValue, actual: &HeaderValue) -> bool {
+    if name != CONTENT_SECURITY_POLICY && name != CONTENT_SECURITY_POLICY_REPORT_ONLY {
+        return expected == actual;
+    }
+
+    match (expected.to_str(), actual.to_str()) {
+        (Ok(expected), Ok(actual)) => normalize_policy(expected) == normalize_policy(actual),
+        _ => expected == actual,
+    }
+}
+
+/// The directives of a CSP without nonce and hash sources, sorted by name. Directives which only
+/// repeat `default-src` are dropped, as adding a nonce or hash to a missing directive creates it
+/// with the sources of `default-src`.
+fn normalize_policy(policy: &str) -> Vec<(String, Vec<String>)> {
+    let is_generated = |source: &str| {
+        ["'nonce-", "'sha256-", "'sha384-", "'sha512-"]
+            .iter()
+            .any(|prefix| source.starts_with(prefix))
+    };
+
+    let mut directives: Vec<(String, Vec<String>)> = parse_directives(policy)
+        .into_iter()
+        .map(|(name, sources)| {
+            let sources = sources
+                .into_iter()
+                .filter(|source| !is_generated(source))
+                .map(str::to_owned)
+                .collect();
+            (name.to_ascii_lowercase(), sources)
+        })
+        .collect();
+
+    let default_src = directives
+        .iter()
+        .find(|(name, _)| name == "default-src")
+        .map(|(_, sources)| sources.clone());
+    if let Some(default_src) = default_src {
+        directives.retain(|(name, sources)| name == "default-src" || *sources != default_src);
+    }
+
+    directives.sort();
+    directives
+}
+
+impl<S> Layer<S> for CriticalHeadersLayer {
+    type Service = CriticalHeadersService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        CriticalHeadersService {
+            inner: service,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CriticalHeadersService<S> {
+    inner: S,
+    layer: Arc<CriticalHeadersLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for CriticalHeadersService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let is_grpc =
+            Some(preset::is_grpc(request.headers())).filter(|_| !self.layer.is_exempt(&request));
+        ResponseFuture {
+            future: self.inner.call(request),
+            is_grpc,
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`CriticalHeadersService`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        future: F,
+
+        // whether the request is a gRPC one, `None` if it is exempted
+        is_grpc: Option<bool>,
+        layer: Arc<CriticalHeadersLayer>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: Default,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.future.poll(cx)?);
+
+        let is_grpc = match *this.is_grpc {
+            Some(is_grpc) => is_grpc,
+            None => return Poll::Ready(Ok(res)),
+        };
+        let findings = check(this.layer.expected(is_grpc), res.headers());
+        for finding in &findings {
+            this.layer.report(finding);
+        }
+
+        if this.layer.fail_closed && !findings.is_empty() {
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return Poll::Ready(Ok(response));
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
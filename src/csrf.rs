@@ -0,0 +1,195 @@
+//! Rejects cross-site requests which change state, based on their `Origin` (or `Referer`)
+//! header.
+//!
+//! [`CsrfLayer`] checks all requests except `GET`, `HEAD`, `OPTIONS` and `TRACE`, which must not
+//! change state, so custom methods such as WebDAV's are checked as well: browsers send the origin
+//! of the page that triggered them, which has to be one of the allowed origins. Requests without
+//! `Origin` are checked against the origin of their `Referer` instead. Default ports are ignored,
+//! so `https://example.com:443` is the same origin as `https://example.com`. This is a lightweight
+//! CSRF defense which doesn't need tokens, best combined with `SameSite` cookies.
+//!
+//! ```
+//! use http::StatusCode;
+//! use tower_helmet::csrf::CsrfLayer;
+//!
+//! let mut layer = CsrfLayer::new(["https://example.com", "https://admin.example.com"]);
+//! layer.failure_status(StatusCode::BAD_REQUEST).failure_body("cross-site request rejected");
+//!
+//! // let app = Router::new().layer(layer);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::{ORIGIN, REFERER};
+use http::{Method, Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::origin::{normalize, origin_of, without_default_port};
+
+/// Layer validating the origin of state-changing requests, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct CsrfLayer {
+    allowed_origins: Vec<String>,
+    allow_missing: bool,
+    failure_status: StatusCode,
+    failure_body: String,
+}
+
+impl CsrfLayer {
+    /// Allows state-changing requests from the given origins, such as `https://example.com`.
+    pub fn new<I, T>(allowed_origins: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        CsrfLayer {
+            allowed_origins: allowed_origins
+                .into_iter()
+                .map(|origin| without_default_port(normalize(&origin.into())))
+                .collect(),
+            allow_missing: false,
+            failure_status: StatusCode::FORBIDDEN,
+            failure_body: String::new(),
+        }
+    }
+
+    /// Whether requests with neither `Origin` nor `Referer` are allowed. Current browsers always
+    /// send `Origin` with state-changing requests, so these usually come from non-browser clients.
+    /// Defaults to `false`.
+    pub fn allow_missing(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing = allow;
+        self
+    }
+
+    /// Status of the response to rejected requests. Defaults to `403 Forbidden`.
+    pub fn failure_status(&mut self, status: StatusCode) -> &mut Self {
+        self.failure_status = status;
+        self
+    }
+
+    /// Body of the response to rejected requests. Empty by default.
+    pub fn failure_body(&mut self, body: impl Into<String>) -> &mut Self {
+        self.failure_body = body.into();
+        self
+    }
+
+    /// Whether a request passes the check.
+    ///
+    /// ```
+    /// use http::{Method, Request};
+    /// use tower_helmet::csrf::CsrfLayer;
+    ///
+    /// let layer = CsrfLayer::new(["https://example.com"]);
+    /// let request = |method: &str, origin: &str| {
+    ///     Request::builder()
+    ///         .method(Method::from_bytes(method.as_bytes()).unwrap())
+    ///         .header("origin", origin)
+    ///         .body(())
+    ///         .unwrap()
+    /// };
+    ///
+    /// assert!(layer.allows(&request("GET", "https://evil.example")));
+    /// assert!(!layer.allows(&request("PROPPATCH", "https://evil.example")));
+    /// assert!(layer.allows(&request("POST", "https://example.com:443")));
+    /// ```
+    pub fn allows<B>(&self, request: &Request<B>) -> bool {
+        let is_safe = matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        );
+        if is_safe {
+            return true;
+        }
+
+        let headers = request.headers();
+        let origin = match headers.get(ORIGIN) {
+            Some(origin) => origin.to_str().ok().map(normalize),
+            None => match headers.get(REFERER) {
+                Some(referer) => referer.to_str().ok().and_then(origin_of),
+                None => return self.allow_missing,
+            },
+        };
+
+        origin.is_some_and(|origin| self.allowed_origins.contains(&without_default_port(origin)))
+    }
+}
+
+impl<S> Layer<S> for CsrfLayer {
+    type Service = CsrfService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        CsrfService {
+            inner: service,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsrfService<S> {
+    inner: S,
+    layer: Arc<CsrfLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for CsrfService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: From<String>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        if !self.layer.allows(&request) {
+            let mut response = Response::new(ResBody::from(self.layer.failure_body.clone()));
+            *response.status_mut() = self.layer.failure_status;
+            return ResponseFuture::Rejected {
+                response: Some(response),
+            };
+        }
+
+        ResponseFuture::Inner {
+            future: self.inner.call(request),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`CsrfService`].
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Inner {
+            #[pin]
+            future: F,
+        },
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F, ResBody>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Rejected { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Compares a configuration against a recommended baseline or another layer.
+//!
+//! ```
+//! use tower_helmet::diff::Baseline;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let diff = HelmetLayer::with_defaults().diff(&Baseline::Owasp);
+//! for change in &diff.changed {
+//!     println!("{}: {:?} -> {:?}", change.name, change.baseline, change.current);
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{
+    InvalidHeaderValue, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY,
+};
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::header::{
+    parse_directives, CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy,
+    CrossOriginResourcePolicy, ReferrerPolicy, StrictTransportSecurity, XContentTypeOptions,
+    XFrameOptions, XPermittedCrossDomainPolicies,
+};
+use crate::{HelmetLayer, IntoHeaders};
+
+/// Recommended header sets to compare a configuration against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Baseline {
+    /// The recommendations of the [OWASP Secure Headers Project](https://owasp.org/www-project-secure-headers/).
+    Owasp,
+    /// The defaults of this crate, see [`HelmetLayer::with_defaults_v2`].
+    Helmet,
+}
+
+impl Baseline {
+    /// The headers of this baseline as a layer.
+    pub fn layer(&self) -> HelmetLayer {
+        match self {
+            Baseline::Owasp => {
+                let mut layer = HelmetLayer::blank();
+                layer
+                    .enable(StrictTransportSecurity {
+                        max_age: std::time::Duration::from_secs(31536000),
+                        include_subdomains: true,
+                        preload: false,
+                    })
+                    .enable(XFrameOptions::Deny)
+                    .enable(XContentTypeOptions)
+                    .enable(XPermittedCrossDomainPolicies::None)
+                    .enable(ReferrerPolicy::default())
+                    .enable(CrossOriginEmbedderPolicy::RequireCorp)
+                    .enable(CrossOriginOpenerPolicy::SameOrigin)
+                    .enable(CrossOriginResourcePolicy::SameOrigin);
+                layer.update(|headers| {
+                    headers.insert(
+                        CONTENT_SECURITY_POLICY,
+                        HeaderValue::from_static(
+                            "default-src 'self'; form-action 'self'; object-src 'none'; frame-ancestors 'none'; upgrade-insecure-requests; block-all-mixed-content",
+                        ),
+                    );
+                });
+                layer
+            }
+            Baseline::Helmet => HelmetLayer::with_defaults_v2(),
+        }
+    }
+}
+
+impl IntoHeaders for Baseline {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        Ok(self.layer().headers())
+    }
+}
+
+/// The differences between a configuration and a baseline, see [`HelmetLayer::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Headers which are only configured, not in the baseline.
+    pub added: Vec<(HeaderName, HeaderValue)>,
+    /// Headers which are only in the baseline, not configured.
+    pub removed: Vec<(HeaderName, HeaderValue)>,
+    /// Headers which are set to different values.
+    pub changed: Vec<Change>,
+}
+
+/// A header which is set to different values in the configuration and the baseline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub name: HeaderName,
+    pub baseline: HeaderValue,
+    pub current: HeaderValue,
+    /// For `Content-Security-Policy` (and its report-only variant), the directives which differ.
+    pub directives: Vec<DirectiveChange>,
+}
+
+/// A CSP directive which differs between the configuration and the baseline. `None` means the
+/// directive is not set on that side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveChange {
+    pub directive: String,
+    pub baseline: Option<Vec<String>>,
+    pub current: Option<Vec<String>>,
+}
+
+impl Diff {
+    /// Compares `current` against `baseline`.
+    pub fn new(current: &HeaderMap, baseline: &HeaderMap) -> Self {
+        let mut diff = Diff::default();
+
+        for (name, value) in crate::export::sorted(current) {
+            match baseline.get(name) {
+                None => diff.added.push((name.clone(), value.clone())),
+                Some(baseline) if baseline != value => diff.changed.push(Change {
+                    name: name.clone(),
+                    baseline: baseline.clone(),
+                    current: value.clone(),
+                    directives: directive_changes(name, baseline, value),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (name, value) in crate::export::sorted(baseline) {
+            if !current.contains_key(name) {
+                diff.removed.push((name.clone(), value.clone()));
+            }
+        }
+
+        diff
+    }
+
+    /// Whether the configuration matches the baseline exactly.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn directive_changes(
+    name: &HeaderName,
+    baseline: &HeaderValue,
+    current: &HeaderValue,
+) -> Vec<DirectiveChange> {
+    if name != CONTENT_SECURITY_POLICY && name != CONTENT_SECURITY_POLICY_REPORT_ONLY {
+        return Vec::new();
+    }
+
+    let (baseline, current) = match (baseline.to_str(), current.to_str()) {
+        (Ok(baseline), Ok(current)) => (parse_directives(baseline), parse_directives(current)),
+        _ => return Vec::new(),
+    };
+    let find = |directives: &[(&str, Vec<&str>)], name: &str| {
+        directives
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, sources)| {
+                let mut sources: Vec<String> = sources.iter().map(|s| s.to_string()).collect();
+                sources.sort();
+                sources
+            })
+    };
+
+    let mut names: Vec<&str> = baseline
+        .iter()
+        .chain(current.iter())
+        .map(|(name, _)| *name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let b = find(&baseline, name);
+            let c = find(&current, name);
+            (b != c).then(|| DirectiveChange {
+                directive: name.to_owned(),
+                baseline: b,
+                current: c,
+            })
+        })
+        .collect()
+}
+
+/// Prints the differences like a unified diff: `+` for added, `-` for removed and `~` for changed
+/// headers.
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lossy = |v: &HeaderValue| String::from_utf8_lossy(v.as_bytes()).into_owned();
+
+        for (name, value) in &self.added {
+            writeln!(f, "+ {}: {}", name, lossy(value))?;
+        }
+        for (name, value) in &self.removed {
+            writeln!(f, "- {}: {}", name, lossy(value))?;
+        }
+        for change in &self.changed {
+            writeln!(
+                f,
+                "~ {}: {} -> {}",
+                change.name,
+                lossy(&change.baseline),
+                lossy(&change.current)
+            )?;
+            for directive in &change.directives {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    directive.directive,
+                    directive
+                        .baseline
+                        .as_ref()
+                        .map_or("(unset)".to_owned(), |s| s.join(" ")),
+                    directive
+                        .current
+                        .as_ref()
+                        .map_or("(unset)".to_owned(), |s| s.join(" ")),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+//! Integration with [Dioxus](https://docs.rs/dioxus) fullstack rendering, enabled with the
+//! `dioxus` feature.
+//!
+//! [`csp_nonce`] returns the nonce of the current request (see
+//! [`crate::HelmetLayer::enable_nonce`]) from within components rendered on the server and server
+//! functions, so inline scripts can carry it:
+//!
+//! ```ignore
+//! #[component]
+//! fn Analytics() -> Element {
+//!     let nonce = tower_helmet::dioxus::csp_nonce().map(|nonce| nonce.to_string());
+//!     rsx! { script { nonce, "track()" } }
+//! }
+//! ```
+//!
+//! Unlike Leptos, Dioxus has no way to pass a nonce to the scripts its server renderer generates
+//! for hydration, so these don't carry one and have to be allowed by the CSP separately, for
+//! example by hash.
+
+use dioxus_fullstack_core::FullstackContext;
+
+use crate::CspNonce;
+
+/// The nonce of the request currently being rendered, `None` on the client and if the request
+/// didn't pass through a [`crate::HelmetLayer`] with nonces enabled.
+///
+/// The nonce is taken from the extensions of the request in Dioxus' `FullstackContext`, or from
+/// [`CspNonce::current`] outside of it.
+pub fn csp_nonce() -> Option<CspNonce> {
+    FullstackContext::current()
+        .and_then(|context| context.extension::<CspNonce>())
+        .or_else(CspNonce::current)
+}
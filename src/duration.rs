@@ -0,0 +1,50 @@
+//! (De)serializes `max_age` fields either from a number of seconds or from a human-friendly
+//! string such as `"180d"` or `"1y"` (see [humantime](https://docs.rs/humantime)).
+
+use std::convert::TryFrom;
+use std::fmt::Formatter;
+use std::time::Duration;
+
+use serde::de::{Error, Visitor};
+use serde::{Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    struct DurationVisitor;
+
+    impl<'de> Visitor<'de> for DurationVisitor {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "a number of seconds or a duration such as \"180d\"")
+        }
+
+        fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Duration::from_secs(v))
+        }
+
+        fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+            u64::try_from(v)
+                .map(Duration::from_secs)
+                .map_err(|_| E::custom("duration must not be negative"))
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+            humantime::parse_duration(v).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(DurationVisitor)
+}
+
+#[cfg(feature = "schemars")]
+pub fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({
+        "description": "A number of seconds or a duration such as \"180d\".",
+        "type": ["integer", "string"],
+        "minimum": 0
+    })
+}
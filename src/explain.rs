@@ -0,0 +1,257 @@
+//! Explaining how the headers of selected requests were decided.
+//!
+//! A CSP which breaks a page in production is hard to diagnose: the headers of a response depend
+//! on the matching route, the `User-Agent`, the nonce, the policy provider and overrides set by
+//! handlers. With [`crate::HelmetLayer::explain`], selected requests (those carrying a debug
+//! header, or every n-th one) get an [`Explanation`] of every change made to their headers: which
+//! headers were set, which were skipped for the user agent, the value after adding the nonce and
+//! which were overridden by a [`crate::HeaderOverride`].
+//!
+//! The explanation is added to the response extensions and, with the `tracing` feature, logged at
+//! the `info` level, one event per [`Decision`]. Only the layer for `http` 1.x services explains
+//! its decisions.
+//!
+//! ```
+//! use tower_helmet::explain::Explain;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut explain = Explain::new();
+//! explain.header("x-helmet-debug", "f3a9c2").every(1000);
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.explain(explain);
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use http::header::HeaderName;
+use http::{HeaderMap, HeaderValue};
+
+/// Selects the requests to explain, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Explain {
+    header: Option<(HeaderName, HeaderValue)>,
+    every: Option<u64>,
+    requests: Arc<AtomicU64>,
+}
+
+impl Explain {
+    /// Explains no request yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Explains requests with the header `name` set to `value`. The value works like a password,
+    /// so outsiders can't flood the logs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` or `value` is invalid.
+    pub fn header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.header = Some((
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        ));
+        self
+    }
+
+    /// Explains every `n`-th request, counted over all clones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn every(&mut self, n: u64) -> &mut Self {
+        assert!(n > 0, "at least every first request has to be explained");
+        self.every = Some(n);
+        self
+    }
+
+    /// Whether the request with `headers` is explained.
+    pub(crate) fn selects(&self, headers: &HeaderMap) -> bool {
+        let requested = self
+            .header
+            .as_ref()
+            .is_some_and(|(name, value)| headers.get_all(name).iter().any(|v| v == value));
+        let sampled = self.every.is_some_and(|n| {
+            self.requests
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(n)
+        });
+        requested || sampled
+    }
+}
+
+/// Where the headers of an explained response come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// The headers configured in the layer.
+    Layer,
+    /// The headers of the [route](crate::HelmetLayer::routes) matching the path.
+    Route,
+    /// The [gRPC preset](crate::HelmetLayer::grpc).
+    Grpc,
+    /// The headers of the [policy provider](crate::HelmetLayer::policy_provider).
+    Provider,
+    /// None, because the [fallback](crate::HelmetLayer::fallback) replaced them.
+    Fallback,
+}
+
+/// The step of the layer which made a [`Decision`], in the order they are taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// Headers added by feature flags enabled for the request, see [`crate::rollout`].
+    Rollout,
+    /// Headers adjusted or skipped for the `User-Agent`, see [`crate::user_agent`].
+    UserAgent,
+    /// The partners allowed to frame the response, see [`crate::frame_ancestors`].
+    FrameAncestors,
+    /// The nonce added to the CSP.
+    Nonce,
+    /// The headers of the policy provider, with the user agent adjustments and the nonce applied.
+    Provider,
+    /// The headers dropped by the fallback.
+    Fallback,
+    /// The headers set on the response, replacing values of the application.
+    Response,
+    /// The `Cross-Origin-Resource-Policy` chosen for the content type, see [`crate::corp`].
+    ContentType,
+    /// A [`crate::HeaderOverride`] set by the handler.
+    Override,
+    /// The request headers the response depends on, added to `Vary`.
+    Vary,
+    /// Cookies hardened by the [`crate::cookie::CookiePolicy`].
+    Cookies,
+    /// A response with a session nonce kept out of shared caches.
+    Private,
+}
+
+/// What a [`Decision`] did to a header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// The header wasn't set before.
+    Set,
+    /// The header had different values before.
+    Replaced,
+    /// The header was removed.
+    Removed,
+}
+
+/// A single change of a header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub step: Step,
+    pub header: HeaderName,
+    pub action: Action,
+    /// The values after the change, empty if the header was removed.
+    pub values: Vec<HeaderValue>,
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {:?} {}", self.step, self.action, self.header)?;
+        for value in &self.values {
+            write!(f, " {:?}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the headers of a response were decided, in the response extensions of explained requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// The path of the request.
+    pub path: String,
+    pub source: Source,
+    /// The changes in the order they were made.
+    pub decisions: Vec<Decision>,
+}
+
+impl Explanation {
+    pub(crate) fn new(path: &str) -> Self {
+        Explanation {
+            path: path.to_owned(),
+            source: Source::Layer,
+            decisions: Vec::new(),
+        }
+    }
+
+    /// Records the changes `step` made from `before` to `after`.
+    pub(crate) fn record(&mut self, step: Step, before: &HeaderMap, after: &HeaderMap) {
+        for name in after.keys() {
+            let values: Vec<HeaderValue> = after.get_all(name).iter().cloned().collect();
+            let action = if !before.contains_key(name) {
+                Action::Set
+            } else if !before.get_all(name).iter().eq(&values) {
+                Action::Replaced
+            } else {
+                continue;
+            };
+            self.decisions.push(Decision {
+                step,
+                header: name.clone(),
+                action,
+                values,
+            });
+        }
+        for name in before.keys().filter(|name| !after.contains_key(*name)) {
+            self.decisions.push(Decision {
+                step,
+                header: name.clone(),
+                action: Action::Removed,
+                values: Vec::new(),
+            });
+        }
+    }
+
+    /// Runs `change` on `headers`, recording what it did as `step` if explaining.
+    pub(crate) fn trace(
+        explanation: Option<&mut Self>,
+        step: Step,
+        headers: &mut HeaderMap,
+        change: impl FnOnce(&mut HeaderMap),
+    ) {
+        match explanation {
+            Some(explanation) => {
+                let before = headers.clone();
+                change(headers);
+                explanation.record(step, &before, headers);
+            }
+            None => change(headers),
+        }
+    }
+
+    /// Logs the explanation, with the `tracing` feature.
+    pub(crate) fn log(&self) {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::info!(
+                path = %self.path,
+                source = ?self.source,
+                decisions = self.decisions.len(),
+                "tower-helmet explanation"
+            );
+            for decision in &self.decisions {
+                tracing::info!(
+                    path = %self.path,
+                    step = ?decision.step,
+                    header = %decision.header,
+                    action = ?decision.action,
+                    values = ?decision.values,
+                    "tower-helmet decision"
+                );
+            }
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (headers of {:?})", self.path, self.source)?;
+        for decision in &self.decisions {
+            write!(f, "\n  {}", decision)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use std::fmt::Write;
+
+use http::HeaderMap;
+
+/// Renders the headers as a Caddyfile `header` block. See [`crate::HelmetLayer::to_caddy`].
+pub fn caddy(headers: &HeaderMap) -> String {
+    let mut out = String::from("header {\n");
+
+    for (name, value) in super::sorted(headers) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        writeln!(out, "\t{} {}", name, super::quote(&value)).unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,56 @@
+use http::header::{
+    CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY,
+    STRICT_TRANSPORT_SECURITY,
+};
+use http::{HeaderMap, HeaderName};
+use serde_json::{json, Map, Value};
+
+use crate::header::parse_directives;
+
+/// Builds a structured JSON document of the given headers. See
+/// [`crate::HelmetLayer::to_json_report`].
+pub fn json_report(headers: &HeaderMap) -> Value {
+    let headers: Vec<Value> = super::sorted(headers)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            json!({
+                "name": name.as_str(),
+                "value": value,
+                "directives": directives(name, &value),
+            })
+        })
+        .collect();
+
+    json!({ "headers": headers })
+}
+
+fn directives(name: &HeaderName, value: &str) -> Value {
+    if name == CONTENT_SECURITY_POLICY || name == CONTENT_SECURITY_POLICY_REPORT_ONLY {
+        let directives: Map<String, Value> = parse_directives(value)
+            .into_iter()
+            .map(|(name, sources)| (name.to_owned(), json!(sources)))
+            .collect();
+        Value::Object(directives)
+    } else if name == STRICT_TRANSPORT_SECURITY || name == "expect-ct" {
+        let separator = if name == STRICT_TRANSPORT_SECURITY {
+            ';'
+        } else {
+            ','
+        };
+        let directives: Map<String, Value> = value
+            .split(separator)
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(|d| match d.split_once('=') {
+                Some((key, value)) => (key.to_owned(), json!(value.trim_matches('"'))),
+                None => (d.to_owned(), Value::Bool(true)),
+            })
+            .collect();
+        Value::Object(directives)
+    } else if name == REFERRER_POLICY {
+        json!(value.split(',').map(str::trim).collect::<Vec<_>>())
+    } else {
+        Value::Null
+    }
+}
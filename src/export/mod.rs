@@ -0,0 +1,33 @@
+//! Renders a configured header set into other formats.
+
+mod caddy;
+#[cfg(feature = "json")]
+mod json;
+mod netlify;
+mod nginx;
+mod snapshot;
+#[cfg(feature = "json")]
+mod vercel;
+
+pub use self::caddy::caddy;
+#[cfg(feature = "json")]
+pub use self::json::json_report;
+pub use self::netlify::netlify;
+pub use self::nginx::nginx;
+pub use self::snapshot::snapshot;
+#[cfg(feature = "json")]
+pub use self::vercel::vercel;
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+/// Returns the headers sorted by name, so every export is stable.
+pub(crate) fn sorted(headers: &HeaderMap) -> Vec<(&HeaderName, &HeaderValue)> {
+    let mut headers: Vec<_> = headers.iter().collect();
+    headers.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    headers
+}
+
+/// Wraps the value in double quotes, escaping backslashes and double quotes.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
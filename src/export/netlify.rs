@@ -0,0 +1,15 @@
+use std::fmt::Write;
+
+use http::HeaderMap;
+
+/// Renders the headers as a Netlify `_headers` file. See [`crate::HelmetLayer::to_netlify`].
+pub fn netlify(headers: &HeaderMap, path: &str) -> String {
+    let mut out = format!("{}\n", path);
+
+    for (name, value) in super::sorted(headers) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        writeln!(out, "  {}: {}", name, value).unwrap();
+    }
+
+    out
+}
@@ -0,0 +1,15 @@
+use std::fmt::Write;
+
+use http::HeaderMap;
+
+/// Renders the headers as nginx `add_header` directives. See [`crate::HelmetLayer::to_nginx`].
+pub fn nginx(headers: &HeaderMap) -> String {
+    let mut out = String::new();
+
+    for (name, value) in super::sorted(headers) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        writeln!(out, "add_header {} {} always;", name, super::quote(&value)).unwrap();
+    }
+
+    out
+}
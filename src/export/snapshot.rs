@@ -0,0 +1,37 @@
+use std::fmt::Write;
+
+use http::header::{CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY};
+use http::HeaderMap;
+
+use crate::header::parse_directives;
+
+/// Renders the headers for snapshot testing. See [`crate::HelmetLayer::to_snapshot`].
+pub fn snapshot(headers: &HeaderMap) -> String {
+    let mut out = String::new();
+
+    for (name, value) in super::sorted(headers) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        if name != CONTENT_SECURITY_POLICY && name != CONTENT_SECURITY_POLICY_REPORT_ONLY {
+            writeln!(out, "{}: {}", name, value).unwrap();
+            continue;
+        }
+
+        let mut directives = parse_directives(&value);
+        directives.sort_by_key(|(directive, _)| *directive);
+
+        writeln!(out, "{}:", name).unwrap();
+        for (directive, sources) in directives {
+            write!(out, "  {}", directive).unwrap();
+            for source in sources {
+                if source.starts_with("'nonce-") {
+                    out.push_str(" 'nonce-[redacted]'");
+                } else {
+                    write!(out, " {}", source).unwrap();
+                }
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
@@ -0,0 +1,24 @@
+use http::HeaderMap;
+use serde_json::json;
+
+/// Renders the headers as a `vercel.json` document. See [`crate::HelmetLayer::to_vercel`].
+pub fn vercel(headers: &HeaderMap, source: &str) -> String {
+    let headers: Vec<_> = super::sorted(headers)
+        .into_iter()
+        .map(|(name, value)| {
+            json!({
+                "key": name.as_str(),
+                "value": String::from_utf8_lossy(value.as_bytes()),
+            })
+        })
+        .collect();
+
+    let config = json!({
+        "headers": [{
+            "source": source,
+            "headers": headers,
+        }]
+    });
+
+    serde_json::to_string_pretty(&config).unwrap()
+}
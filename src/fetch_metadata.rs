@@ -0,0 +1,335 @@
+//! Rejects cross-site requests based on [Fetch Metadata](https://web.dev/articles/fetch-metadata)
+//! request headers.
+//!
+//! [`FetchMetadataLayer`] implements the resource isolation policy: browsers send
+//! `Sec-Fetch-Site`, `Sec-Fetch-Mode` and `Sec-Fetch-Dest` with every request, so requests other
+//! sites trigger (through `<img>`, `<script>`, `fetch()`, form posts, ...) can be told apart from
+//! the application's own ones and rejected with `403 Forbidden` before they reach any handler.
+//! This mitigates CSRF, XSSI and cross-site leaks.
+//!
+//! A request is allowed if
+//!
+//! - it has no `Sec-Fetch-Site` header, because it was not sent by a browser supporting it,
+//! - it is `same-origin`, `same-site` or `none` (typed into the address bar, bookmarks, ...),
+//! - it is a simple top-level navigation: a `GET` with `Sec-Fetch-Mode: navigate` which is not
+//!   loading an `<object>` or `<embed>`, so links from other sites keep working,
+//! - or its path is exempted, for example for endpoints meant to be used cross-site.
+//!
+//! ```
+//! use tower_helmet::fetch_metadata::FetchMetadataLayer;
+//!
+//! let mut layer = FetchMetadataLayer::new();
+//! layer.exempt_path("/oauth/callback").exempt_prefix("/public/");
+//!
+//! // let app = Router::new().layer(layer);
+//! ```
+//!
+//! # Report-only mode
+//!
+//! Before enforcing the policy, [`FetchMetadataLayer::report_only`] allows observing which requests
+//! would be rejected, to build the list of exemptions. Every [`Violation`] is passed to the
+//! [`FetchMetadataLayer::on_violation`] callback (for example to record metrics) and, with the
+//! `tracing` feature, logged as warning.
+//!
+//! ```
+//! use tower_helmet::fetch_metadata::FetchMetadataLayer;
+//!
+//! let mut layer = FetchMetadataLayer::new();
+//! layer.report_only(true).on_violation(|violation| {
+//!     println!("would block {} {}", violation.method, violation.path);
+//! });
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::HeaderName;
+use http::{HeaderMap, Method, Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+static SEC_FETCH_SITE: HeaderName = HeaderName::from_static("sec-fetch-site");
+static SEC_FETCH_MODE: HeaderName = HeaderName::from_static("sec-fetch-mode");
+static SEC_FETCH_DEST: HeaderName = HeaderName::from_static("sec-fetch-dest");
+
+/// A request rejected by the policy (or which would have been in report-only mode).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub method: Method,
+    pub path: String,
+    /// The values of the `Sec-Fetch-Site`, `Sec-Fetch-Mode` and `Sec-Fetch-Dest` headers.
+    pub site: String,
+    pub mode: Option<String>,
+    pub dest: Option<String>,
+    /// Whether the request was passed on because of report-only mode.
+    pub report_only: bool,
+}
+
+type ViolationCallback = Arc<dyn Fn(&Violation) + Send + Sync>;
+
+/// Layer enforcing the resource isolation policy, see the [module docs](self).
+#[derive(Clone)]
+pub struct FetchMetadataLayer {
+    allow_same_site: bool,
+    allow_navigation: bool,
+    exempt_paths: Vec<String>,
+    exempt_prefixes: Vec<String>,
+    report_only: bool,
+    on_violation: Option<ViolationCallback>,
+}
+
+impl Debug for FetchMetadataLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FetchMetadataLayer")
+            .field("allow_same_site", &self.allow_same_site)
+            .field("allow_navigation", &self.allow_navigation)
+            .field("exempt_paths", &self.exempt_paths)
+            .field("exempt_prefixes", &self.exempt_prefixes)
+            .field("report_only", &self.report_only)
+            .field("on_violation", &self.on_violation.is_some())
+            .finish()
+    }
+}
+
+impl Default for FetchMetadataLayer {
+    fn default() -> Self {
+        FetchMetadataLayer {
+            allow_same_site: true,
+            allow_navigation: true,
+            exempt_paths: Vec::new(),
+            exempt_prefixes: Vec::new(),
+            report_only: false,
+            on_violation: None,
+        }
+    }
+}
+
+impl FetchMetadataLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether requests from other origins of the same site (such as other subdomains) are
+    /// allowed. Defaults to `true`.
+    pub fn allow_same_site(&mut self, allow: bool) -> &mut Self {
+        self.allow_same_site = allow;
+        self
+    }
+
+    /// Whether cross-site top-level navigations, such as following a link from another site, are
+    /// allowed. Defaults to `true`.
+    pub fn allow_navigation(&mut self, allow: bool) -> &mut Self {
+        self.allow_navigation = allow;
+        self
+    }
+
+    /// Allows all requests to exactly this path.
+    pub fn exempt_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.exempt_paths.push(path.into());
+        self
+    }
+
+    /// Allows all requests to paths starting with `prefix`.
+    pub fn exempt_prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.exempt_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Only reports violations instead of rejecting the requests. Defaults to `false`.
+    pub fn report_only(&mut self, report_only: bool) -> &mut Self {
+        self.report_only = report_only;
+        self
+    }
+
+    /// Calls `callback` for every request violating the policy, whether it is rejected or not.
+    pub fn on_violation<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Violation) + Send + Sync + 'static,
+    {
+        self.on_violation = Some(Arc::new(callback));
+        self
+    }
+
+    /// Whether the policy allows a request, regardless of report-only mode.
+    pub fn allows<B>(&self, request: &Request<B>) -> bool {
+        self.violation(request).is_none()
+    }
+
+    fn violation<B>(&self, request: &Request<B>) -> Option<Violation> {
+        let headers = request.headers();
+        let site = header_str(headers, &SEC_FETCH_SITE)?;
+
+        match site {
+            "same-origin" | "none" => return None,
+            "same-site" if self.allow_same_site => return None,
+            _ => {}
+        }
+
+        let mode = header_str(headers, &SEC_FETCH_MODE);
+        let dest = header_str(headers, &SEC_FETCH_DEST);
+        let is_navigation = request.method() == Method::GET
+            && mode == Some("navigate")
+            && !matches!(dest, Some("object") | Some("embed"));
+        if self.allow_navigation && is_navigation {
+            return None;
+        }
+
+        let path = request.uri().path();
+        if self.exempt_paths.iter().any(|p| p == path)
+            || self.exempt_prefixes.iter().any(|p| path.starts_with(p))
+        {
+            return None;
+        }
+
+        Some(Violation {
+            method: request.method().clone(),
+            path: path.to_owned(),
+            site: site.to_owned(),
+            mode: mode.map(str::to_owned),
+            dest: dest.map(str::to_owned),
+            report_only: self.report_only,
+        })
+    }
+
+    fn report(&self, violation: &Violation) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            method = %violation.method,
+            path = %violation.path,
+            sec_fetch_site = %violation.site,
+            sec_fetch_mode = ?violation.mode,
+            sec_fetch_dest = ?violation.dest,
+            report_only = violation.report_only,
+            "cross-site request violates the fetch metadata policy"
+        );
+
+        if let Some(callback) = &self.on_violation {
+            callback(violation);
+        }
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+impl<S> Layer<S> for FetchMetadataLayer {
+    type Service = FetchMetadataService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        FetchMetadataService {
+            inner: service,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+/// Service rejecting cross-site requests with an empty `403 Forbidden`, without calling the inner
+/// service.
+///
+/// ```
+/// use http::{Method, Request, StatusCode};
+/// use tower_helmet::fetch_metadata::FetchMetadataLayer;
+/// use tower_helmet::test_util::{call_service, CountingEcho};
+/// use tower_layer::Layer;
+///
+/// let request = |site: &str| {
+///     Request::builder()
+///         .method(Method::POST)
+///         .uri("/account")
+///         .header("sec-fetch-site", site)
+///         .header("sec-fetch-mode", "cors")
+///         .body("form".to_owned())
+///         .unwrap()
+/// };
+/// let echo = CountingEcho::new();
+/// let mut service = FetchMetadataLayer::new().layer(echo.clone());
+///
+/// let res = call_service(&mut service, request("cross-site"));
+/// assert_eq!(res.status(), StatusCode::FORBIDDEN);
+/// assert!(res.headers().is_empty());
+/// assert_eq!(res.body(), "");
+/// assert_eq!(echo.calls(), 0);
+///
+/// let res = call_service(&mut service, request("same-origin"));
+/// assert_eq!(res.body(), "form");
+/// assert_eq!(echo.calls(), 1);
+///
+/// let mut layer = FetchMetadataLayer::new();
+/// layer.report_only(true);
+/// let res = call_service(&mut layer.layer(echo.clone()), request("cross-site"));
+/// assert_eq!(res.status(), StatusCode::OK);
+/// assert_eq!(echo.calls(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FetchMetadataService<S> {
+    inner: S,
+    layer: Arc<FetchMetadataLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for FetchMetadataService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let violation = self.layer.violation(&request);
+        if let Some(violation) = &violation {
+            self.layer.report(violation);
+        }
+
+        if violation.is_some_and(|v| !v.report_only) {
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::FORBIDDEN;
+            return ResponseFuture::Rejected {
+                response: Some(response),
+            };
+        }
+
+        ResponseFuture::Inner {
+            future: self.inner.call(request),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`FetchMetadataService`].
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Inner {
+            #[pin]
+            future: F,
+        },
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F, ResBody>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Rejected { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Stable fingerprints of the effective policy, for telling which configuration produced a
+//! response.
+//!
+//! When a CSP breaks pages after a deploy, the first question is which policy the affected
+//! responses carried. A [`Fingerprint`] is a short hash of a set of headers which only changes if
+//! the headers do: header names are compared case-insensitively and in any order, and nonces are
+//! ignored, so every response of the same policy has the same fingerprint. It is stable across
+//! processes and versions of this crate, so fingerprints logged by different instances and
+//! releases can be compared.
+//!
+//! [`crate::HelmetLayer::fingerprint`] returns the fingerprint of the configured headers, which
+//! the `tracing` feature also logs when the layer is first used. With
+//! [`crate::HelmetLayer::fingerprints`], every response gets the fingerprint of the headers chosen
+//! for it (of its route, the policy provider, with enabled feature flags, ...) in its extensions.
+//! Adding a nonce or adjusting headers changes them, so it can differ from the fingerprint of the
+//! configured headers, but is the same for all responses with the same policy.
+//! With the `tracing` feature it is recorded in the `helmet.fingerprint` field of the current span,
+//! if the span declares it, and it can be sent in a response header for debugging, which is best
+//! left out in production. Only the layer for `http` 1.x services fingerprints responses.
+//!
+//! ```
+//! use tower_helmet::fingerprint::Fingerprints;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! println!("policy {}", layer.fingerprint());
+//!
+//! let mut fingerprints = Fingerprints::new();
+//! if cfg!(debug_assertions) {
+//!     fingerprints.header("x-helmet-policy");
+//! }
+//! layer.fingerprints(fingerprints);
+//! ```
+//!
+//! For the fingerprint to show up in the logs of a request, its span (for example created by
+//! tower-http's `TraceLayer`) declares the field as `helmet.fingerprint = tracing::field::Empty`.
+
+use std::fmt::{Display, Formatter};
+
+use http::header::HeaderName;
+use http::{HeaderMap, HeaderValue, Response};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A stable hash of a set of headers, displayed as 16 hexadecimal digits, see the
+/// [module docs](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// The fingerprint of `headers`, ignoring the values of `'nonce-...'` sources.
+    ///
+    /// ```
+    /// use http::{HeaderMap, HeaderValue};
+    /// use tower_helmet::fingerprint::Fingerprint;
+    ///
+    /// let mut a = HeaderMap::new();
+    /// a.insert("content-security-policy", HeaderValue::from_static("script-src 'nonce-a1'"));
+    /// let mut b = HeaderMap::new();
+    /// b.insert("content-security-policy", HeaderValue::from_static("script-src 'nonce-b2'"));
+    /// assert_eq!(Fingerprint::of(&a), Fingerprint::of(&b));
+    /// ```
+    pub fn of(headers: &HeaderMap) -> Self {
+        let mut names: Vec<&HeaderName> = headers.keys().collect();
+        names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+        for name in names {
+            write(name.as_str().as_bytes());
+            for value in headers.get_all(name) {
+                write(b"\n");
+                write(without_nonces(value).as_bytes());
+            }
+            write(b"\n\n");
+        }
+
+        Fingerprint(hash)
+    }
+
+    /// The hash as a number.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// `value` with every `'nonce-...'` source replaced by `'nonce'`.
+fn without_nonces(value: &HeaderValue) -> String {
+    let value = String::from_utf8_lossy(value.as_bytes());
+    let mut masked = String::with_capacity(value.len());
+    let mut rest = value.as_ref();
+    while let Some(start) = rest.find("'nonce-") {
+        masked.push_str(&rest[..start]);
+        masked.push_str("'nonce'");
+        let nonce = &rest[start + "'nonce-".len()..];
+        rest = match nonce.find('\'') {
+            Some(end) => &nonce[end + 1..],
+            None => "",
+        };
+    }
+    masked.push_str(rest);
+    masked
+}
+
+/// Fingerprints the headers of every response, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprints {
+    header: Option<HeaderName>,
+}
+
+impl Fingerprints {
+    /// Adds the fingerprint to the response extensions and, with the `tracing` feature, the
+    /// current span.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also sends the fingerprint in the response header `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is invalid.
+    pub fn header(&mut self, name: &str) -> &mut Self {
+        self.header = Some(HeaderName::from_bytes(name.as_bytes()).unwrap());
+        self
+    }
+
+    /// Adds the fingerprint of `headers`, the ones chosen for `res`, to the response.
+    pub(crate) fn apply<B>(&self, headers: &HeaderMap, res: &mut Response<B>) {
+        let fingerprint = Fingerprint::of(headers);
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("helmet.fingerprint", tracing::field::display(fingerprint));
+
+        if let Some(name) = &self.header {
+            let value = HeaderValue::from_str(&fingerprint.to_string()).unwrap();
+            res.headers_mut().insert(name, value);
+        }
+        res.extensions_mut().insert(fingerprint);
+    }
+}
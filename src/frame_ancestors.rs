@@ -0,0 +1,160 @@
+//! The partners allowed to embed the application, changed at runtime.
+//!
+//! SaaS applications embedded by partners onboard them without redeploying, so the
+//! `frame-ancestors` directive of the CSP can't be fixed at startup. A [`FrameAncestorsHandle`]
+//! passed to [`crate::HelmetLayer::frame_ancestors`] holds the allowlist; clones of it, kept by
+//! an admin endpoint for example, add and remove partners. Every origin is validated like
+//! [`crate::origin::sanitize_origin`] before it is added, and every change swaps the whole list
+//! at once, so a request either sees the list before or after a change.
+//!
+//! The allowlist replaces the `frame-ancestors` directive of the CSP of every response (the
+//! enforced one and the report-only one, if set), also of routes and of the policy provider.
+//! Browsers which support `frame-ancestors` ignore `X-Frame-Options`, so it can stay for older
+//! ones.
+//!
+//! ```
+//! use tower_helmet::frame_ancestors::FrameAncestorsHandle;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let partners = FrameAncestorsHandle::new();
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.frame_ancestors(partners.clone());
+//!
+//! // later, while the application is running
+//! partners.add_origin("https://partner.example").unwrap();
+//! assert!(partners.add_origin("https://evil.example; script-src *").is_err());
+//! assert_eq!(partners.directive(), "frame-ancestors 'self' https://partner.example");
+//!
+//! partners.remove_origin("https://partner.example");
+//! assert_eq!(partners.directive(), "frame-ancestors 'self'");
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use http::HeaderMap;
+
+use crate::header::parse_directives;
+use crate::nonce::{render, update};
+use crate::origin::{normalize, sanitize_origin, InvalidOriginError, OriginSetError, OriginSets};
+
+/// The allowlist of partners which may embed the application, shared between clones, see the
+/// [module docs](self).
+#[derive(Debug, Clone)]
+pub struct FrameAncestorsHandle {
+    allow_self: bool,
+    origins: Arc<RwLock<Arc<[String]>>>,
+}
+
+impl Default for FrameAncestorsHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameAncestorsHandle {
+    /// Allows the application itself (`'self'`) and no partners yet.
+    pub fn new() -> Self {
+        FrameAncestorsHandle {
+            allow_self: true,
+            origins: Arc::new(RwLock::new(Arc::from(Vec::new()))),
+        }
+    }
+
+    /// Allows only the partners, not the application itself. Without partners, framing is
+    /// forbidden (`'none'`).
+    pub fn partners_only() -> Self {
+        FrameAncestorsHandle {
+            allow_self: false,
+            ..Self::new()
+        }
+    }
+
+    /// Allows `origin` to embed the application, returning whether it wasn't allowed before.
+    /// Fails if `origin` is not a single well-formed origin, see
+    /// [`crate::origin::sanitize_origin`].
+    pub fn add_origin(&self, origin: &str) -> Result<bool, InvalidOriginError> {
+        let origin = sanitize_origin(origin)?;
+        let mut origins = self.origins.write().unwrap();
+        if origins.contains(&origin) {
+            return Ok(false);
+        }
+        let mut changed = origins.to_vec();
+        changed.push(origin);
+        *origins = changed.into();
+        Ok(true)
+    }
+
+    /// Allows all members of the set `name` of `sets` to embed the application at once, see
+    /// [`OriginSets`]. Returns how many of them weren't allowed before.
+    pub fn add_set(&self, sets: &OriginSets, name: &str) -> Result<usize, OriginSetError> {
+        let members = sets.origins(&[name])?;
+        let mut origins = self.origins.write().unwrap();
+        let mut changed = origins.to_vec();
+        let before = changed.len();
+        for member in members {
+            if !changed.contains(&member) {
+                changed.push(member);
+            }
+        }
+        let added = changed.len() - before;
+        *origins = changed.into();
+        Ok(added)
+    }
+
+    /// Stops allowing `origin`, returning whether it was allowed.
+    pub fn remove_origin(&self, origin: &str) -> bool {
+        let origin = normalize(origin);
+        let mut origins = self.origins.write().unwrap();
+        if !origins.contains(&origin) {
+            return false;
+        }
+        let changed: Vec<String> = origins.iter().filter(|o| **o != origin).cloned().collect();
+        *origins = changed.into();
+        true
+    }
+
+    /// The partners currently allowed, in the order they were added.
+    pub fn origins(&self) -> Vec<String> {
+        self.snapshot().to_vec()
+    }
+
+    /// The current `frame-ancestors` directive.
+    pub fn directive(&self) -> String {
+        render(&[("frame-ancestors", self.sources(&self.snapshot()))])
+    }
+
+    /// The partners at this moment, unaffected by later changes.
+    pub(crate) fn snapshot(&self) -> Arc<[String]> {
+        self.origins.read().unwrap().clone()
+    }
+
+    fn sources<'a>(&self, origins: &'a [String]) -> Vec<&'a str> {
+        let own = Some("'self'").filter(|_| self.allow_self);
+        let sources: Vec<&str> = own
+            .into_iter()
+            .chain(origins.iter().map(String::as_str))
+            .collect();
+        if sources.is_empty() {
+            vec!["'none'"]
+        } else {
+            sources
+        }
+    }
+
+    /// Replaces the `frame-ancestors` directive of the CSP headers in `headers` with `origins`, a
+    /// [snapshot](FrameAncestorsHandle::snapshot) taken for the request.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap, origins: &[String]) {
+        let sources = self.sources(origins);
+        update(headers, |policy| {
+            let mut directives: Vec<(&str, Vec<&str>)> = parse_directives(policy);
+            match directives
+                .iter_mut()
+                .find(|(name, _)| *name == "frame-ancestors")
+            {
+                Some((_, current)) => *current = sources.clone(),
+                None => directives.push(("frame-ancestors", sources.clone())),
+            }
+            render(&directives)
+        });
+    }
+}
@@ -0,0 +1,255 @@
+//! Estimates how a header set would be graded by the [Mozilla Observatory](https://observatory.mozilla.org/).
+//!
+//! The scoring follows the [Observatory's scoring methodology](https://github.com/mozilla/http-observatory/blob/main/httpobs/docs/scoring.md)
+//! for the tests that only depend on response headers. Tests which need the whole site (cookies,
+//! redirections, subresource integrity, ...) are not taken into account, so the result is an
+//! estimate and not a replacement for running the real scanner.
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{
+    CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+    X_FRAME_OPTIONS,
+};
+use http::HeaderMap;
+
+use crate::header::parse_directives;
+
+/// Six months in seconds, the minimum HSTS `max-age` the Observatory accepts.
+const HSTS_MIN_MAX_AGE: u64 = 15552000;
+
+/// The estimated result, see [`crate::HelmetLayer::grade`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grade {
+    /// The score, starting at `100`. Penalties are subtracted and, if the score is at least `90`,
+    /// bonuses are added. It never drops below `0`.
+    pub score: u32,
+    /// Every test which changed the score.
+    pub adjustments: Vec<Adjustment>,
+}
+
+/// A single test result which changed the score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjustment {
+    /// Name of the Observatory test, such as `content-security-policy`.
+    pub test: &'static str,
+    /// Points added (bonus) or subtracted (penalty).
+    pub points: i32,
+    pub description: &'static str,
+}
+
+impl Grade {
+    /// The letter grade for [`Grade::score`], such as `A+` or `C-`.
+    pub fn letter(&self) -> &'static str {
+        match self.score {
+            100.. => "A+",
+            90..=99 => "A",
+            85..=89 => "A-",
+            80..=84 => "B+",
+            70..=79 => "B",
+            65..=69 => "B-",
+            60..=64 => "C+",
+            50..=59 => "C",
+            45..=49 => "C-",
+            40..=44 => "D+",
+            30..=39 => "D",
+            25..=29 => "D-",
+            _ => "F",
+        }
+    }
+
+    /// Only the adjustments which cost points.
+    pub fn penalties(&self) -> impl Iterator<Item = &Adjustment> {
+        self.adjustments.iter().filter(|a| a.points < 0)
+    }
+}
+
+impl Display for Grade {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}/100)", self.letter(), self.score)
+    }
+}
+
+/// Grades the given response headers.
+pub fn grade(headers: &HeaderMap) -> Grade {
+    let mut adjustments = Vec::new();
+    let mut adjust = |test, points, description| {
+        adjustments.push(Adjustment {
+            test,
+            points,
+            description,
+        })
+    };
+
+    let csp = headers
+        .get(CONTENT_SECURITY_POLICY)
+        .and_then(|v| v.to_str().ok())
+        .map(parse_directives);
+
+    match &csp {
+        None => adjust(
+            "content-security-policy",
+            -25,
+            "Content Security Policy (CSP) header not implemented",
+        ),
+        Some(directives) => {
+            let sources = |name: &str| {
+                directives
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .or_else(|| directives.iter().find(|(n, _)| *n == "default-src"))
+                    .map(|(_, sources)| sources.clone())
+            };
+            let script_src = sources("script-src");
+            let style_src = sources("style-src");
+            let has = |sources: &Option<Vec<&str>>, token: &str| {
+                sources.as_ref().is_some_and(|s| s.contains(&token))
+            };
+
+            if script_src.is_none() {
+                adjust(
+                    "content-security-policy",
+                    -25,
+                    "Content Security Policy (CSP) implemented without script-src or default-src",
+                );
+            } else if has(&script_src, "'unsafe-inline'") {
+                adjust(
+                    "content-security-policy",
+                    -20,
+                    "Content Security Policy (CSP) implemented unsafely, script-src allows 'unsafe-inline'",
+                );
+            } else if has(&script_src, "'unsafe-eval'") {
+                adjust(
+                    "content-security-policy",
+                    -10,
+                    "Content Security Policy (CSP) implemented, but script-src allows 'unsafe-eval'",
+                );
+            } else if has(&style_src, "'unsafe-inline'") {
+                // allowed by the observatory without penalty, but no bonus either
+            } else if directives
+                .iter()
+                .any(|(n, s)| *n == "default-src" && s == &["'none'"])
+            {
+                adjust(
+                    "content-security-policy",
+                    10,
+                    "Content Security Policy (CSP) implemented with default-src 'none' and no 'unsafe'",
+                );
+            } else {
+                adjust(
+                    "content-security-policy",
+                    5,
+                    "Content Security Policy (CSP) implemented without 'unsafe-inline' or 'unsafe-eval'",
+                );
+            }
+        }
+    }
+
+    match headers
+        .get(STRICT_TRANSPORT_SECURITY)
+        .and_then(|v| v.to_str().ok())
+    {
+        None => adjust(
+            "strict-transport-security",
+            -20,
+            "HTTP Strict Transport Security (HSTS) header not implemented",
+        ),
+        Some(value) => {
+            let directives: Vec<String> = value
+                .split(';')
+                .map(|d| d.trim().to_ascii_lowercase())
+                .collect();
+            let max_age = directives
+                .iter()
+                .find_map(|d| d.strip_prefix("max-age="))
+                .and_then(|v| v.trim_matches('"').parse::<u64>().ok());
+
+            match max_age {
+                None => adjust(
+                    "strict-transport-security",
+                    -20,
+                    "HTTP Strict Transport Security (HSTS) header cannot be recognized",
+                ),
+                Some(max_age) if max_age < HSTS_MIN_MAX_AGE => adjust(
+                    "strict-transport-security",
+                    -10,
+                    "HTTP Strict Transport Security (HSTS) header set to a max-age less than six months",
+                ),
+                Some(_) if directives.iter().any(|d| d == "preload") => adjust(
+                    "strict-transport-security",
+                    5,
+                    "Preloaded via the HTTP Strict Transport Security (HSTS) preloading process",
+                ),
+                Some(_) => {}
+            }
+        }
+    }
+
+    if !headers
+        .get(X_CONTENT_TYPE_OPTIONS)
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"nosniff"))
+    {
+        adjust(
+            "x-content-type-options",
+            -5,
+            "X-Content-Type-Options header not implemented or invalid",
+        );
+    }
+
+    let frame_ancestors = csp
+        .as_ref()
+        .and_then(|d| d.iter().find(|(n, _)| *n == "frame-ancestors"));
+    if frame_ancestors.is_some() {
+        adjust(
+            "x-frame-options",
+            5,
+            "X-Frame-Options (XFO) implemented via the CSP frame-ancestors directive",
+        );
+    } else if headers.get(X_FRAME_OPTIONS).is_none_or(|v| {
+        !v.as_bytes().eq_ignore_ascii_case(b"deny")
+            && !v.as_bytes().eq_ignore_ascii_case(b"sameorigin")
+    }) {
+        adjust(
+            "x-frame-options",
+            -20,
+            "X-Frame-Options (XFO) header not implemented or invalid",
+        );
+    }
+
+    if let Some(value) = headers.get(REFERRER_POLICY).and_then(|v| v.to_str().ok()) {
+        // browsers use the last policy they understand
+        let policy = value.rsplit(',').next().unwrap_or_default().trim();
+        match policy {
+            "no-referrer" | "same-origin" | "strict-origin" | "strict-origin-when-cross-origin" => {
+                adjust(
+                    "referrer-policy",
+                    5,
+                    "Referrer-Policy header set to a private value",
+                )
+            }
+            "origin" | "origin-when-cross-origin" | "no-referrer-when-downgrade" | "unsafe-url" => {
+                adjust("referrer-policy", -5, "Referrer-Policy header set unsafely")
+            }
+            _ => {}
+        }
+    }
+
+    let penalties: i32 = adjustments
+        .iter()
+        .filter(|a| a.points < 0)
+        .map(|a| a.points)
+        .sum();
+    let mut score = (100 + penalties).max(0);
+    if score >= 90 {
+        score += adjustments
+            .iter()
+            .filter(|a| a.points > 0)
+            .map(|a| a.points)
+            .sum::<i32>();
+    }
+
+    Grade {
+        score: score as u32,
+        adjustments,
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::*;
+
+/// Characters of the values generated for `Custom` variants.
+const TOKEN_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-";
+
+/// Directives generated for [`ContentSecurityPolicy`].
+const DIRECTIVES: [&str; 16] = [
+    "base-uri",
+    "block-all-mixed-content",
+    "connect-src",
+    "default-src",
+    "font-src",
+    "form-action",
+    "frame-ancestors",
+    "frame-src",
+    "img-src",
+    "object-src",
+    "report-to",
+    "script-src",
+    "script-src-attr",
+    "style-src",
+    "upgrade-insecure-requests",
+    "worker-src",
+];
+
+/// Sources generated for [`ContentSecurityPolicy`].
+const SOURCES: [&str; 14] = [
+    "'self'",
+    "'none'",
+    "'unsafe-inline'",
+    "'unsafe-eval'",
+    "'strict-dynamic'",
+    "'nonce-cmFuZG9t'",
+    "'sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hXuFU='",
+    "*",
+    "data:",
+    "blob:",
+    "https:",
+    "https://example.com",
+    "https://*.example.com",
+    "wss://example.com:8443",
+];
+
+/// A lowercase token, such as a value newer than this crate.
+fn token(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(1..=16)?;
+    (0..len)
+        .map(|_| u.choose(TOKEN_CHARS).map(|c| *c as char))
+        .collect()
+}
+
+fn max_age(u: &mut Unstructured<'_>) -> Result<Duration> {
+    Ok(Duration::from_secs(u32::arbitrary(u)?.into()))
+}
+
+/// Implements [`Arbitrary`] for unit structs.
+macro_rules! arbitrary_unit {
+    ($($ty:ident,)*) => {
+        $(
+            impl<'a> Arbitrary<'a> for $ty {
+                fn arbitrary(_u: &mut Unstructured<'a>) -> Result<Self> {
+                    Ok($ty)
+                }
+
+                fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+                    (0, Some(0))
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_unit! {
+    OriginAgentCluster,
+    XContentTypeOptions,
+    XDownloadOptions,
+}
+
+/// Implements [`Arbitrary`] for enums with unit variants and a `Custom(String)` one.
+macro_rules! arbitrary_enum {
+    ($($ty:ident => [$($variant:ident),+],)*) => {
+        $(
+            impl<'a> Arbitrary<'a> for $ty {
+                fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                    let variants = [$($ty::$variant),+];
+                    match u.choose_index(variants.len() + 1)? {
+                        i if i < variants.len() => Ok(variants[i].clone()),
+                        _ => Ok($ty::Custom(token(u)?)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_enum! {
+    CrossOriginEmbedderPolicy => [UnsafeNone, RequireCorp, Credentialless],
+    CrossOriginOpenerPolicy => [UnsafeNone, SameOriginAllowPopups, SameOrigin],
+    CrossOriginResourcePolicy => [SameSite, SameOrigin, CrossOrigin],
+    ReferrerPolicyValue => [
+        NoReferrer,
+        NoReferrerWhenDowngrade,
+        Origin,
+        OriginWhenCrossOrigin,
+        SameOrigin,
+        StrictOrigin,
+        StrictOriginWhenCrossOrigin
+    ],
+    XFrameOptions => [Deny, SameOrigin],
+    XPermittedCrossDomainPolicies => [None, MasterOnly, ByContentType, All],
+}
+
+/// Generates one to four policies.
+impl<'a> Arbitrary<'a> for ReferrerPolicy {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(1..=4)?;
+        let values = (0..len)
+            .map(|_| ReferrerPolicyValue::arbitrary(u))
+            .collect::<Result<_>>()?;
+        Ok(ReferrerPolicy(values))
+    }
+}
+
+impl<'a> Arbitrary<'a> for XDnsPrefetchControl {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(XDnsPrefetchControl(bool::arbitrary(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for StrictTransportSecurity {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(StrictTransportSecurity {
+            max_age: max_age(u)?,
+            include_subdomains: bool::arbitrary(u)?,
+            preload: bool::arbitrary(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ExpectCt {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let report_uri = if bool::arbitrary(u)? {
+            let url = format!("https://{}.example/report", token(u)?);
+            Some(
+                url.parse()
+                    .map_err(|_| ::arbitrary::Error::IncorrectFormat)?,
+            )
+        } else {
+            None
+        };
+
+        Ok(ExpectCt {
+            max_age: max_age(u)?,
+            enforce: bool::arbitrary(u)?,
+            report_uri,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for XXSSProtection {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(4)? {
+            0 => XXSSProtection::Disabled,
+            1 => XXSSProtection::Enabled,
+            2 => XXSSProtection::EnabledBlock,
+            _ => {
+                let url = format!("https://{}.example/report", token(u)?);
+                XXSSProtection::EnabledReport(
+                    url.parse()
+                        .map_err(|_| ::arbitrary::Error::IncorrectFormat)?,
+                )
+            }
+        })
+    }
+}
+
+/// Generates up to eight directives out of common ones with common sources, so the policies are
+/// realistic rather than random strings.
+///
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use tower_helmet::header::ContentSecurityPolicy;
+/// use tower_helmet::IntoHeader;
+///
+/// let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+/// let mut u = Unstructured::new(&data);
+/// while !u.is_empty() {
+///     let csp = ContentSecurityPolicy::arbitrary(&mut u).unwrap();
+///     assert!(csp.header_value().is_ok());
+/// }
+/// ```
+impl<'a> Arbitrary<'a> for ContentSecurityPolicy<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(0..=8)?;
+        let mut directives = HashMap::new();
+        for _ in 0..len {
+            let name: &str = u.choose(&DIRECTIVES)?;
+            let sources = (0..u.int_in_range(0..=4)?)
+                .map(|_| u.choose(&SOURCES).copied())
+                .collect::<Result<_>>()?;
+            directives.insert(name, sources);
+        }
+
+        Ok(ContentSecurityPolicy {
+            use_defaults: bool::arbitrary(u)?,
+            directives,
+            report_only: bool::arbitrary(u)?,
+        })
+    }
+}
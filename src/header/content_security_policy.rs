@@ -1,9 +1,15 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 use lazy_static::lazy_static;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+};
 use crate::IntoHeader;
 
 lazy_static! {
@@ -43,7 +49,7 @@ lazy_static! {
 /// style-src 'self' https: 'unsafe-inline';
 /// upgrade-insecure-requests
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ContentSecurityPolicy<'a> {
     pub use_defaults: bool,
     /// Each key is the directive name in kebab case (such as `default-src`).
@@ -74,6 +80,36 @@ impl ContentSecurityPolicy<'static> {
     }
 }
 
+impl<'a> ContentSecurityPolicy<'a> {
+    /// Checks the directives for characters which would break the header or inject further
+    /// directives or policies: control characters in general, and `;` and `,` in sources. Values
+    /// which fail the check are also rejected by [`IntoHeader::header_value`], but without saying
+    /// why.
+    ///
+    /// ```
+    /// use tower_helmet::header::ContentSecurityPolicy;
+    ///
+    /// let mut csp = ContentSecurityPolicy::default();
+    /// csp.directives.insert("frame-ancestors", vec!["https://partner.example; script-src *"]);
+    /// assert!(csp.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        let header = if self.report_only {
+            "Content-Security-Policy-Report-Only"
+        } else {
+            "Content-Security-Policy"
+        };
+
+        for (name, values) in &self.directives {
+            validate_value_with(header, name, &[';', ',', ' '])?;
+            for value in values {
+                validate_value_with(header, value, &[';', ','])?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'a> Default for ContentSecurityPolicy<'a> {
     fn default() -> Self {
         ContentSecurityPolicy {
@@ -84,16 +120,129 @@ impl<'a> Default for ContentSecurityPolicy<'a> {
     }
 }
 
-impl<'a> IntoHeader for ContentSecurityPolicy<'a> {
-    fn header_name(&self) -> HeaderName {
-        if self.report_only {
-            http::header::CONTENT_SECURITY_POLICY_REPORT_ONLY
-        } else {
-            http::header::CONTENT_SECURITY_POLICY
+/// Parses a value such as `default-src 'self'; img-src 'self' data:`, borrowing the directives
+/// from it. `FromStr` can't be implemented for that reason.
+///
+/// The result has `use_defaults` disabled, so it renders the parsed directives only, and
+/// `report_only` disabled, as the value doesn't tell which header it came from. Like browsers do,
+/// only the first occurrence of a repeated directive is kept.
+///
+/// ```
+/// use std::convert::TryFrom;
+///
+/// use tower_helmet::header::ContentSecurityPolicy;
+///
+/// let csp = ContentSecurityPolicy::try_from("default-src 'self'; img-src 'self' data:").unwrap();
+/// assert_eq!(csp.directives["img-src"], ["'self'", "data:"]);
+/// ```
+impl<'a> TryFrom<&'a str> for ContentSecurityPolicy<'a> {
+    type Error = ParseHeaderError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let error = || ParseHeaderError::new("Content-Security-Policy", value);
+        let mut directives = HashMap::new();
+
+        for (name, values) in parse_directives(value) {
+            let is_valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !is_valid {
+                return Err(error());
+            }
+            directives.entry(name).or_insert(values);
+        }
+        if directives.is_empty() {
+            return Err(error());
         }
+
+        Ok(ContentSecurityPolicy {
+            use_defaults: false,
+            directives,
+            report_only: false,
+        })
     }
+}
 
-    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+impl<'a> TryFrom<&'a HeaderValue> for ContentSecurityPolicy<'a> {
+    type Error = ParseHeaderError;
+
+    fn try_from(value: &'a HeaderValue) -> Result<Self, Self::Error> {
+        let value = value.to_str().map_err(|_| {
+            ParseHeaderError::new(
+                "Content-Security-Policy",
+                &String::from_utf8_lossy(value.as_bytes()),
+            )
+        })?;
+        ContentSecurityPolicy::try_from(value)
+    }
+}
+
+/// A [`ContentSecurityPolicy`] owning its directives, as held by a
+/// [`HeaderSet`](crate::header::HeaderSet), so policies built from dynamic input or loaded from
+/// configuration files don't need to outlive it. It keeps the rendered policy and is converted
+/// from a `ContentSecurityPolicy` with `into()`, or parsed from a value such as
+/// `default-src 'self'`.
+///
+/// ```
+/// use tower_helmet::header::{ContentSecurityPolicy, OwnedContentSecurityPolicy};
+///
+/// let owned: OwnedContentSecurityPolicy = "default-src 'self'; img-src *".parse().unwrap();
+/// assert_eq!(owned.policy().directives["img-src"], ["*"]);
+///
+/// let owned = OwnedContentSecurityPolicy::from(ContentSecurityPolicy::default());
+/// assert!(owned.as_str().starts_with("base-uri 'self';"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedContentSecurityPolicy(String);
+
+impl OwnedContentSecurityPolicy {
+    /// The policy, borrowing the directives. It has `use_defaults` and `report_only` disabled.
+    pub fn policy(&self) -> ContentSecurityPolicy<'_> {
+        ContentSecurityPolicy::try_from(self.0.as_str()).unwrap_or(ContentSecurityPolicy {
+            use_defaults: false,
+            directives: HashMap::new(),
+            report_only: false,
+        })
+    }
+
+    /// The rendered policy.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.policy()
+            .validate()
+            .map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(&self.0)
+    }
+}
+
+impl<'a> From<ContentSecurityPolicy<'a>> for OwnedContentSecurityPolicy {
+    fn from(csp: ContentSecurityPolicy<'a>) -> Self {
+        OwnedContentSecurityPolicy(csp.to_string())
+    }
+}
+
+/// Parses the policy like [`ContentSecurityPolicy::try_from`], keeping the value as is.
+impl FromStr for OwnedContentSecurityPolicy {
+    type Err = ParseHeaderError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ContentSecurityPolicy::try_from(value)?;
+        Ok(OwnedContentSecurityPolicy(value.to_owned()))
+    }
+}
+
+impl Display for OwnedContentSecurityPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Renders the policy with its directives sorted by name. Unlike [`IntoHeader::header_value`], the
+/// directives are not validated, see [`ContentSecurityPolicy::validate`].
+impl<'a> Display for ContentSecurityPolicy<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let directives = if self.use_defaults {
             if self.directives.is_empty() {
                 DEFAULT_DIRECTIVES.clone()
@@ -107,12 +256,50 @@ impl<'a> IntoHeader for ContentSecurityPolicy<'a> {
             self.directives.clone()
         };
 
+        // sorted so the rendered policy is the same on every run
+        let mut directives: Vec<_> = directives.into_iter().collect();
+        directives.sort_unstable_by_key(|(key, _)| *key);
+
         let header = directives
             .iter()
-            .map(|(key, values)| format!("{} {}", key, values.join(" ")))
+            .map(|(key, values)| {
+                if values.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{} {}", key, values.join(" "))
+                }
+            })
             .collect::<Vec<String>>()
             .join("; ");
 
-        HeaderValue::from_str(header.trim())
+        write!(f, "{}", header.trim())
     }
 }
+
+impl<'a> IntoHeader for ContentSecurityPolicy<'a> {
+    fn header_name(&self) -> HeaderName {
+        if self.report_only {
+            http::header::CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            http::header::CONTENT_SECURITY_POLICY
+        }
+    }
+
+    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(self.to_string().as_str())
+    }
+}
+
+/// Splits a rendered `Content-Security-Policy` value into its directives, keeping their order.
+pub(crate) fn parse_directives(value: &str) -> Vec<(&str, Vec<&str>)> {
+    value
+        .split(';')
+        .filter_map(|directive| {
+            let mut tokens = directive.split_ascii_whitespace();
+            let name = tokens.next()?;
+            Some((name, tokens.collect()))
+        })
+        .collect()
+}
@@ -1,24 +1,79 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+    CROSS_ORIGIN_EMBEDDER_POLICY,
+};
 use crate::IntoHeader;
 
-/// `CrossOriginEmbedderPolicy` sets the `Cross-Origin-Embedder-Policy` header to `require-corp`.
-/// See [MDN's article on this header](https://developer.cdn.mozilla.net/en-US/docs/Web/HTTP/Headers/Cross-Origin-Embedder-Policy) for more.
-pub struct CrossOriginEmbedderPolicy;
+/// `CrossOriginEmbedderPolicy` sets the `Cross-Origin-Embedder-Policy` header, by default to
+/// `require-corp`. See [MDN's article on this header](https://developer.cdn.mozilla.net/en-US/docs/Web/HTTP/Headers/Cross-Origin-Embedder-Policy) for more.
+///
+/// `credentialless` is only understood by recent browsers, older ones ignore the header with it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum CrossOriginEmbedderPolicy {
+    UnsafeNone,
+    #[default]
+    RequireCorp,
+    Credentialless,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`CrossOriginEmbedderPolicy::validate`].
+    Custom(String),
+}
+
+impl Display for CrossOriginEmbedderPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            CrossOriginEmbedderPolicy::UnsafeNone => "unsafe-none",
+            CrossOriginEmbedderPolicy::RequireCorp => "require-corp",
+            CrossOriginEmbedderPolicy::Credentialless => "credentialless",
+            CrossOriginEmbedderPolicy::Custom(value) => value.as_str(),
+        };
+
+        write!(f, "{}", s)
+    }
+}
 
-impl Default for CrossOriginEmbedderPolicy {
-    fn default() -> Self {
-        CrossOriginEmbedderPolicy
+impl CrossOriginEmbedderPolicy {
+    /// Checks a [`CrossOriginEmbedderPolicy::Custom`] value for characters which would break the header or
+    /// inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        match self {
+            CrossOriginEmbedderPolicy::Custom(value) => {
+                validate_value_with("Cross-Origin-Embedder-Policy", value, &[','])
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for CrossOriginEmbedderPolicy {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unsafe-none" => Ok(CrossOriginEmbedderPolicy::UnsafeNone),
+            "require-corp" => Ok(CrossOriginEmbedderPolicy::RequireCorp),
+            "credentialless" => Ok(CrossOriginEmbedderPolicy::Credentialless),
+            _ => Err(ParseHeaderError::new("Cross-Origin-Embedder-Policy", s)),
+        }
     }
 }
 
 impl IntoHeader for CrossOriginEmbedderPolicy {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("cross-origin-embedder-policy")
+        CROSS_ORIGIN_EMBEDDER_POLICY
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str("require-corp")
+        self.validate().map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -1,18 +1,27 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+    CROSS_ORIGIN_OPENER_POLICY,
+};
 use crate::IntoHeader;
 
 /// `CrossOriginOpenerPolicy` sets the `Cross-Origin-Opener-Policy` header.
 /// For more, see [MDN's article on this header](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cross-Origin-Opener-Policy).
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum CrossOriginOpenerPolicy {
     UnsafeNone,
     SameOriginAllowPopups,
     #[default]
     SameOrigin,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`CrossOriginOpenerPolicy::validate`].
+    Custom(String),
 }
 
 impl Display for CrossOriginOpenerPolicy {
@@ -21,18 +30,58 @@ impl Display for CrossOriginOpenerPolicy {
             CrossOriginOpenerPolicy::UnsafeNone => "unsafe-none",
             CrossOriginOpenerPolicy::SameOriginAllowPopups => "same-origin-allow-popups",
             CrossOriginOpenerPolicy::SameOrigin => "same-origin",
+            CrossOriginOpenerPolicy::Custom(value) => value.as_str(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl CrossOriginOpenerPolicy {
+    /// Checks a [`CrossOriginOpenerPolicy::Custom`] value for characters which would break the header or
+    /// inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    ///
+    /// ```
+    /// use tower_helmet::header::CrossOriginOpenerPolicy;
+    ///
+    /// let coop = CrossOriginOpenerPolicy::Custom("noopener-allow-popups".to_owned());
+    /// assert!(coop.validate().is_ok());
+    ///
+    /// let coop = CrossOriginOpenerPolicy::Custom("same-origin\r\nSet-Cookie: a=b".to_owned());
+    /// assert!(coop.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        match self {
+            CrossOriginOpenerPolicy::Custom(value) => {
+                validate_value_with("Cross-Origin-Opener-Policy", value, &[','])
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for CrossOriginOpenerPolicy {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unsafe-none" => Ok(CrossOriginOpenerPolicy::UnsafeNone),
+            "same-origin-allow-popups" => Ok(CrossOriginOpenerPolicy::SameOriginAllowPopups),
+            "same-origin" => Ok(CrossOriginOpenerPolicy::SameOrigin),
+            _ => Err(ParseHeaderError::new("Cross-Origin-Opener-Policy", s)),
+        }
+    }
+}
+
 impl IntoHeader for CrossOriginOpenerPolicy {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("cross-origin-opener-policy")
+        CROSS_ORIGIN_OPENER_POLICY
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
         HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -1,18 +1,27 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+    CROSS_ORIGIN_RESOURCE_POLICY,
+};
 use crate::IntoHeader;
 
 /// `CrossOriginResourcePolicy` sets the `Cross-Origin-Resource-Policy` header.
 /// For more, see ["Consider deploying Cross-Origin Resource Policy](https://resourcepolicy.fyi/) and [MDN's article on this header](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cross-Origin-Resource-Policy).
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum CrossOriginResourcePolicy {
     SameSite,
     #[default]
     SameOrigin,
     CrossOrigin,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`CrossOriginResourcePolicy::validate`].
+    Custom(String),
 }
 
 impl Display for CrossOriginResourcePolicy {
@@ -21,18 +30,48 @@ impl Display for CrossOriginResourcePolicy {
             CrossOriginResourcePolicy::SameSite => "same-site",
             CrossOriginResourcePolicy::SameOrigin => "same-origin",
             CrossOriginResourcePolicy::CrossOrigin => "cross-origin",
+            CrossOriginResourcePolicy::Custom(value) => value.as_str(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl CrossOriginResourcePolicy {
+    /// Checks a [`CrossOriginResourcePolicy::Custom`] value for characters which would break the header or
+    /// inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        match self {
+            CrossOriginResourcePolicy::Custom(value) => {
+                validate_value_with("Cross-Origin-Resource-Policy", value, &[','])
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for CrossOriginResourcePolicy {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "same-site" => Ok(CrossOriginResourcePolicy::SameSite),
+            "same-origin" => Ok(CrossOriginResourcePolicy::SameOrigin),
+            "cross-origin" => Ok(CrossOriginResourcePolicy::CrossOrigin),
+            _ => Err(ParseHeaderError::new("Cross-Origin-Resource-Policy", s)),
+        }
+    }
+}
+
 impl IntoHeader for CrossOriginResourcePolicy {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("cross-origin-resource-policy")
+        CROSS_ORIGIN_RESOURCE_POLICY
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
         HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -1,39 +1,126 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::Duration;
 
 use http::header::{HeaderName, InvalidHeaderValue};
-use http::HeaderValue;
+use http::{HeaderValue, Uri};
 
+use crate::header::{
+    invalid_header_value, validate_report_uri, InvalidReportUriError, ParseHeaderError, EXPECT_CT,
+};
 use crate::IntoHeader;
 
 /// `ExpectCt` sets the `Expect-CT` header which helps mitigate misissued SSL certificates.
 /// See [MDN's article on Certificate Transparency](https://developer.mozilla.org/en-US/docs/Web/Security/Certificate_Transparency) and the [`Expect-CT` header](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Expect-CT) for more.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ExpectCt {
     /// `max_age` is the number of seconds to expect Certificate Transparency.
+    ///
+    /// With the `serde` feature it is deserialized from either a number of seconds or a
+    /// human-friendly duration such as `"180d"` or `"1y"`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::duration"))]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::duration::json_schema")
+    )]
     pub max_age: Duration,
     /// If `true`, the user agent (usually a browser) should refuse future connections that violate
     /// its Certificate Transparency policy.
     pub enforce: bool,
-    /// If set, complying user agents will report Certificate Transparency failures to this URL.
-    pub report_uri: Option<String>,
+    /// If set, complying user agents will report Certificate Transparency failures to this URL. It
+    /// has to be absolute, such as `https://example.com/report`, and must not contain `,`, see
+    /// [`ExpectCt::validate`].
+    ///
+    /// With the `serde` feature it is deserialized from a string, rejecting relative URLs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::uri"))]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::uri::json_schema")
+    )]
+    pub report_uri: Option<Uri>,
 }
 
 impl Default for ExpectCt {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a value such as `max-age=86400, enforce, report-uri="https://example.com/report"`.
+/// `max-age` is required.
+impl FromStr for ExpectCt {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHeaderError::new("Expect-CT", s);
+        let mut max_age = None;
+        let mut enforce = false;
+        let mut report_uri = None;
+
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.trim().trim_matches('"');
+                    match name.trim().to_ascii_lowercase().as_str() {
+                        "max-age" => {
+                            max_age = Some(Duration::from_secs(value.parse().map_err(|_| error())?))
+                        }
+                        "report-uri" => {
+                            report_uri = Some(crate::origin::absolute_uri(value).ok_or_else(error)?)
+                        }
+                        _ => return Err(error()),
+                    }
+                }
+                None if directive.eq_ignore_ascii_case("enforce") => enforce = true,
+                None => return Err(error()),
+            }
+        }
+
+        Ok(ExpectCt {
+            max_age: max_age.ok_or_else(error)?,
+            enforce,
+            report_uri,
+        })
+    }
+}
+
+impl ExpectCt {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
         ExpectCt {
             max_age: Duration::from_secs(0),
             enforce: false,
             report_uri: None,
         }
     }
-}
 
-impl IntoHeader for ExpectCt {
-    fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("expect-ct")
+    /// Checks that `report_uri` is absolute and doesn't contain `,`, which would inject further
+    /// directives. A [`Uri`] can't contain control characters or `"`.
+    ///
+    /// ```
+    /// use http::Uri;
+    /// use tower_helmet::header::ExpectCt;
+    ///
+    /// let mut expect_ct = ExpectCt::new();
+    /// expect_ct.report_uri = Some(Uri::from_static("https://example.com/report"));
+    /// assert!(expect_ct.validate().is_ok());
+    ///
+    /// expect_ct.report_uri = Some(Uri::from_static("/report"));
+    /// assert!(expect_ct.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidReportUriError> {
+        match &self.report_uri {
+            Some(report_uri) => validate_report_uri("Expect-CT", report_uri, &[',']),
+            None => Ok(()),
+        }
     }
+}
 
-    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+impl Display for ExpectCt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut directives = vec![format!("max-age={}", self.max_age.as_secs())];
 
         if self.enforce {
@@ -44,6 +131,18 @@ impl IntoHeader for ExpectCt {
             directives.push(format!("report-uri={}", report_uri));
         }
 
-        HeaderValue::from_str(directives.join(", ").as_str())
+        write!(f, "{}", directives.join(", "))
+    }
+}
+
+impl IntoHeader for ExpectCt {
+    fn header_name(&self) -> HeaderName {
+        EXPECT_CT
+    }
+
+    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
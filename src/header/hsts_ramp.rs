@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime};
+
+use http::header::{HeaderName, InvalidHeaderValue};
+use http::HeaderValue;
+
+use crate::header::StrictTransportSecurity;
+use crate::IntoHeader;
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 24 * 60 * MINUTE;
+
+/// `HstsRamp` raises the `max-age` of `Strict-Transport-Security` in stages, following the
+/// [recommended adoption path](https://hstspreload.org/#deployment-recommendations): a short
+/// `max-age` first, which is raised once nothing broke. Every stage starts at a fixed time after
+/// `start`, usually the date HSTS was first deployed.
+///
+/// It is evaluated when it is enabled on a layer, so the stage advances with every deployment or
+/// restart. To move on based on external state instead, such as a configuration value, use
+/// [`HstsRamp::stage`]. On `wasm32-unknown-unknown`, reading the current time needs the `js`
+/// feature.
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use tower_helmet::header::HstsRamp;
+/// use tower_helmet::HelmetLayer;
+///
+/// let deployed = SystemTime::UNIX_EPOCH + Duration::from_secs(1717200000);
+/// let ramp = HstsRamp::recommended(deployed);
+///
+/// let hsts = ramp.at(deployed + Duration::from_secs(3 * 86400));
+/// assert_eq!(hsts.max_age, Duration::from_secs(86400));
+///
+/// let mut layer = HelmetLayer::with_defaults_v2();
+/// layer.enable(ramp);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HstsRamp {
+    start: SystemTime,
+    /// The time after `start` each stage begins, with its `max-age`, ordered by time.
+    stages: Vec<(Duration, Duration)>,
+    include_subdomains: bool,
+}
+
+impl HstsRamp {
+    /// Starts at `start` with `max_age`, with `includeSubDomains`. Add further stages with
+    /// [`HstsRamp::then`].
+    pub fn new(start: SystemTime, max_age: Duration) -> Self {
+        HstsRamp {
+            start,
+            stages: vec![(Duration::ZERO, max_age)],
+            include_subdomains: true,
+        }
+    }
+
+    /// The stages of the recommended adoption path, each served long enough to notice problems
+    /// before moving on:
+    ///
+    /// | after `start` | `max-age`  |
+    /// |---------------|------------|
+    /// | immediately   | 5 minutes  |
+    /// | 1 day         | 1 day      |
+    /// | 1 week        | 30 days    |
+    /// | 5 weeks       | 1 year     |
+    pub fn recommended(start: SystemTime) -> Self {
+        let mut ramp = Self::new(start, Duration::from_secs(5 * MINUTE));
+        ramp.then(Duration::from_secs(DAY), Duration::from_secs(DAY))
+            .then(Duration::from_secs(7 * DAY), Duration::from_secs(30 * DAY))
+            .then(
+                Duration::from_secs(35 * DAY),
+                Duration::from_secs(365 * DAY),
+            );
+        ramp
+    }
+
+    /// Uses `max_age` from `after` the start on.
+    pub fn then(&mut self, after: Duration, max_age: Duration) -> &mut Self {
+        self.stages.push((after, max_age));
+        self.stages.sort_by_key(|(after, _)| *after);
+        self
+    }
+
+    /// Whether to send `includeSubDomains`, which is the default.
+    pub fn include_subdomains(&mut self, include_subdomains: bool) -> &mut Self {
+        self.include_subdomains = include_subdomains;
+        self
+    }
+
+    /// The index of the stage active at `now`, `0` before the start.
+    pub fn stage_at(&self, now: SystemTime) -> usize {
+        let elapsed = now.duration_since(self.start).unwrap_or_default();
+        self.stages
+            .iter()
+            .rposition(|(after, _)| *after <= elapsed)
+            .unwrap_or_default()
+    }
+
+    /// The policy of the stage active at `now`.
+    pub fn at(&self, now: SystemTime) -> StrictTransportSecurity {
+        self.stage(self.stage_at(now))
+    }
+
+    /// The policy of the stage with the given index, the last one if it is out of range.
+    pub fn stage(&self, index: usize) -> StrictTransportSecurity {
+        let (_, max_age) = self.stages[index.min(self.stages.len() - 1)];
+        StrictTransportSecurity {
+            max_age,
+            include_subdomains: self.include_subdomains,
+            preload: false,
+        }
+    }
+}
+
+/// Sets the policy of the stage active now.
+impl IntoHeader for HstsRamp {
+    fn header_name(&self) -> HeaderName {
+        http::header::STRICT_TRANSPORT_SECURITY
+    }
+
+    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.at(crate::clock::now()).header_value()
+    }
+}
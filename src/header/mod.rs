@@ -1,11 +1,18 @@
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
 mod content_security_policy;
 mod cross_origin_embedder_policy;
 mod cross_origin_opener_policy;
 mod cross_origin_resource_policy;
 mod expect_ct;
+mod hsts_ramp;
+mod names;
 mod origin_agent_cluster;
 mod referrer_policy;
+mod set;
 mod strict_transport_security;
+#[cfg(feature = "headers")]
+mod typed;
 mod x_content_type_options;
 mod x_dns_prefetch_control;
 mod x_download_options;
@@ -13,17 +20,247 @@ mod x_frame_options;
 mod x_permitted_cross_domain_policies;
 mod x_xss_protection;
 
-pub use self::content_security_policy::ContentSecurityPolicy;
+pub use self::content_security_policy::{ContentSecurityPolicy, OwnedContentSecurityPolicy};
 pub use self::cross_origin_embedder_policy::CrossOriginEmbedderPolicy;
 pub use self::cross_origin_opener_policy::CrossOriginOpenerPolicy;
 pub use self::cross_origin_resource_policy::CrossOriginResourcePolicy;
 pub use self::expect_ct::ExpectCt;
+pub use self::hsts_ramp::HstsRamp;
+pub use self::names::{
+    CROSS_ORIGIN_EMBEDDER_POLICY, CROSS_ORIGIN_OPENER_POLICY, CROSS_ORIGIN_RESOURCE_POLICY,
+    EXPECT_CT, NEL, ORIGIN_AGENT_CLUSTER, REPORTING_ENDPOINTS, REPORT_TO, X_DOWNLOAD_OPTIONS,
+    X_PERMITTED_CROSS_DOMAIN_POLICIES,
+};
 pub use self::origin_agent_cluster::OriginAgentCluster;
 pub use self::referrer_policy::{ReferrerPolicy, ReferrerPolicyValue};
-pub use self::strict_transport_security::StrictTransportSecurity;
+pub use self::set::HeaderSet;
+pub use self::strict_transport_security::{PreloadError, StrictTransportSecurity};
 pub use self::x_content_type_options::XContentTypeOptions;
 pub use self::x_dns_prefetch_control::XDnsPrefetchControl;
 pub use self::x_download_options::XDownloadOptions;
 pub use self::x_frame_options::XFrameOptions;
 pub use self::x_permitted_cross_domain_policies::XPermittedCrossDomainPolicies;
 pub use self::x_xss_protection::XXSSProtection;
+
+pub(crate) use self::content_security_policy::parse_directives;
+
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use http::header::InvalidHeaderValue;
+use http::{HeaderName, HeaderValue, Uri};
+
+use crate::IntoHeader;
+
+/// Returned when a header value can't be parsed into its typed representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHeaderError {
+    header: &'static str,
+    value: String,
+}
+
+impl ParseHeaderError {
+    pub(crate) fn new(header: &'static str, value: &str) -> Self {
+        ParseHeaderError {
+            header,
+            value: value.to_owned(),
+        }
+    }
+}
+
+impl Display for ParseHeaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid {} value: {:?}", self.header, self.value)
+    }
+}
+
+impl std::error::Error for ParseHeaderError {}
+
+/// Implements `TryFrom<&HeaderValue>` based on the type's [`FromStr`] implementation, so values
+/// of existing responses can be parsed back into the typed API.
+macro_rules! try_from_header_value {
+    ($($ty:ty => $header:literal,)*) => {
+        $(
+            impl TryFrom<&HeaderValue> for $ty {
+                type Error = ParseHeaderError;
+
+                fn try_from(value: &HeaderValue) -> Result<Self, Self::Error> {
+                    value
+                        .to_str()
+                        .map_err(|_| {
+                            ParseHeaderError::new($header, &String::from_utf8_lossy(value.as_bytes()))
+                        })?
+                        .parse()
+                }
+            }
+        )*
+    };
+}
+
+try_from_header_value! {
+    CrossOriginEmbedderPolicy => "Cross-Origin-Embedder-Policy",
+    CrossOriginOpenerPolicy => "Cross-Origin-Opener-Policy",
+    CrossOriginResourcePolicy => "Cross-Origin-Resource-Policy",
+    ExpectCt => "Expect-CT",
+    OriginAgentCluster => "Origin-Agent-Cluster",
+    ReferrerPolicy => "Referrer-Policy",
+    StrictTransportSecurity => "Strict-Transport-Security",
+    XContentTypeOptions => "X-Content-Type-Options",
+    XDnsPrefetchControl => "X-DNS-Prefetch-Control",
+    XDownloadOptions => "X-Download-Options",
+    XFrameOptions => "X-Frame-Options",
+    XPermittedCrossDomainPolicies => "X-Permitted-Cross-Domain-Policies",
+    XXSSProtection => "X-XSS-Protection",
+}
+
+/// Returned when a header value built from dynamic input (configuration, partner origins, report
+/// URIs, ...) contains a character which could end the header, start a new directive or policy, or
+/// which is not allowed in header values at all. Rejecting these prevents response splitting and
+/// policy injection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCharacterError {
+    header: &'static str,
+    value: String,
+    index: usize,
+    character: char,
+}
+
+impl InvalidCharacterError {
+    /// The offending character.
+    pub fn character(&self) -> char {
+        self.character
+    }
+
+    /// Byte index of the offending character in the value.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Display for InvalidCharacterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid character {:?} at index {} in {} value {:?}",
+            self.character, self.index, self.header, self.value
+        )
+    }
+}
+
+impl std::error::Error for InvalidCharacterError {}
+
+/// Returned when a report URI can't be used in its header, because it is relative or contains a
+/// separator of the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidReportUriError {
+    /// The URI contains a character which would start a new directive.
+    InvalidCharacter(InvalidCharacterError),
+    /// The URI has no scheme or host, such as `/report`, which browsers can't report to.
+    Relative(String),
+}
+
+impl Display for InvalidReportUriError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidReportUriError::InvalidCharacter(error) => write!(f, "{}", error),
+            InvalidReportUriError::Relative(uri) => {
+                write!(f, "report URI {:?} is not absolute", uri)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidReportUriError {}
+
+impl From<InvalidCharacterError> for InvalidReportUriError {
+    fn from(error: InvalidCharacterError) -> Self {
+        InvalidReportUriError::InvalidCharacter(error)
+    }
+}
+
+/// Checks that `uri` is absolute and doesn't contain any of the `separators` of `header`.
+pub(crate) fn validate_report_uri(
+    header: &'static str,
+    uri: &Uri,
+    separators: &[char],
+) -> Result<(), InvalidReportUriError> {
+    let value = uri.to_string();
+    validate_value_with(header, &value, separators)?;
+    if uri.scheme().is_none() || uri.authority().is_none() {
+        return Err(InvalidReportUriError::Relative(value));
+    }
+    Ok(())
+}
+
+/// Checks that `value` only consists of visible ASCII characters and spaces, so it can be used in
+/// the given header. CR, LF and all other control characters are rejected.
+///
+/// ```
+/// use tower_helmet::header::validate_value;
+///
+/// assert!(validate_value("Report-To", "https://example.com/reports").is_ok());
+/// assert!(validate_value("Report-To", "https://example.com\r\nSet-Cookie: a=b").is_err());
+/// ```
+pub fn validate_value(header: &'static str, value: &str) -> Result<(), InvalidCharacterError> {
+    validate_value_with(header, value, &[])
+}
+
+/// Like [`validate_value`], additionally rejecting the given separators.
+pub(crate) fn validate_value_with(
+    header: &'static str,
+    value: &str,
+    separators: &[char],
+) -> Result<(), InvalidCharacterError> {
+    let invalid = value
+        .char_indices()
+        .find(|(_, c)| !(*c == ' ' || c.is_ascii_graphic()) || separators.contains(c));
+
+    match invalid {
+        Some((index, character)) => Err(InvalidCharacterError {
+            header,
+            value: value.to_owned(),
+            index,
+            character,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The error of values rejected by a `validate` method, which [`IntoHeader::header_value`] has to
+/// return as [`InvalidHeaderValue`].
+pub(crate) fn invalid_header_value() -> InvalidHeaderValue {
+    HeaderValue::from_bytes(b"\n").unwrap_err()
+}
+
+/// Parses the value of a known header into its typed representation and renders it again. Returns
+/// `None` for unknown headers and values which can't be parsed.
+pub(crate) fn normalize(name: &HeaderName, value: &HeaderValue) -> Option<HeaderValue> {
+    fn render<T: FromStr + IntoHeader>(value: &str) -> Option<HeaderValue> {
+        value.parse::<T>().ok()?.header_value().ok()
+    }
+
+    let value = value.to_str().ok()?;
+    match name.as_str() {
+        "content-security-policy" | "content-security-policy-report-only" => {
+            ContentSecurityPolicy::try_from(value)
+                .ok()?
+                .header_value()
+                .ok()
+        }
+        "cross-origin-embedder-policy" => render::<CrossOriginEmbedderPolicy>(value),
+        "cross-origin-opener-policy" => render::<CrossOriginOpenerPolicy>(value),
+        "cross-origin-resource-policy" => render::<CrossOriginResourcePolicy>(value),
+        "expect-ct" => render::<ExpectCt>(value),
+        "origin-agent-cluster" => render::<OriginAgentCluster>(value),
+        "referrer-policy" => render::<ReferrerPolicy>(value),
+        "strict-transport-security" => render::<StrictTransportSecurity>(value),
+        "x-content-type-options" => render::<XContentTypeOptions>(value),
+        "x-dns-prefetch-control" => render::<XDnsPrefetchControl>(value),
+        "x-download-options" => render::<XDownloadOptions>(value),
+        "x-frame-options" => render::<XFrameOptions>(value),
+        "x-permitted-cross-domain-policies" => render::<XPermittedCrossDomainPolicies>(value),
+        "x-xss-protection" => render::<XXSSProtection>(value),
+        _ => None,
+    }
+}
@@ -0,0 +1,35 @@
+use http::HeaderName;
+
+/// `Cross-Origin-Embedder-Policy`, see [`super::CrossOriginEmbedderPolicy`].
+pub const CROSS_ORIGIN_EMBEDDER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-embedder-policy");
+
+/// `Cross-Origin-Opener-Policy`, see [`super::CrossOriginOpenerPolicy`].
+pub const CROSS_ORIGIN_OPENER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-opener-policy");
+
+/// `Cross-Origin-Resource-Policy`, see [`super::CrossOriginResourcePolicy`].
+pub const CROSS_ORIGIN_RESOURCE_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-resource-policy");
+
+/// `Expect-CT`, see [`super::ExpectCt`].
+pub const EXPECT_CT: HeaderName = HeaderName::from_static("expect-ct");
+
+/// `NEL` (Network Error Logging), see [`crate::reporting`].
+pub const NEL: HeaderName = HeaderName::from_static("nel");
+
+/// `Origin-Agent-Cluster`, see [`super::OriginAgentCluster`].
+pub const ORIGIN_AGENT_CLUSTER: HeaderName = HeaderName::from_static("origin-agent-cluster");
+
+/// `Report-To`, the legacy reporting header, see [`crate::reporting`].
+pub const REPORT_TO: HeaderName = HeaderName::from_static("report-to");
+
+/// `Reporting-Endpoints`, see [`crate::reporting`].
+pub const REPORTING_ENDPOINTS: HeaderName = HeaderName::from_static("reporting-endpoints");
+
+/// `X-Download-Options`, see [`super::XDownloadOptions`].
+pub const X_DOWNLOAD_OPTIONS: HeaderName = HeaderName::from_static("x-download-options");
+
+/// `X-Permitted-Cross-Domain-Policies`, see [`super::XPermittedCrossDomainPolicies`].
+pub const X_PERMITTED_CROSS_DOMAIN_POLICIES: HeaderName =
+    HeaderName::from_static("x-permitted-cross-domain-policies");
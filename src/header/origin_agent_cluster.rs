@@ -1,24 +1,53 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{ParseHeaderError, ORIGIN_AGENT_CLUSTER};
 use crate::IntoHeader;
 
 /// `OriginAgentCluster` sets the `Origin-Agent-Cluster` header, which provides a mechanism to allow
 /// web applications to isolate their origins. Read more about it [in the spec](https://whatpr.org/html/6214/origin.html#origin-keyed-agent-clusters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct OriginAgentCluster;
 
+impl OriginAgentCluster {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        OriginAgentCluster
+    }
+}
+
 impl Default for OriginAgentCluster {
     fn default() -> Self {
-        OriginAgentCluster
+        Self::new()
+    }
+}
+
+impl FromStr for OriginAgentCluster {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "?1" => Ok(OriginAgentCluster),
+            _ => Err(ParseHeaderError::new("Origin-Agent-Cluster", s)),
+        }
+    }
+}
+
+impl Display for OriginAgentCluster {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "?1")
     }
 }
 
 impl IntoHeader for OriginAgentCluster {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("origin-agent-cluster")
+        ORIGIN_AGENT_CLUSTER
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str("?1")
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
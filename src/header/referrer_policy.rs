@@ -1,16 +1,21 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+};
 use crate::IntoHeader;
 
 /// `ReferrerPolicy` sets the `Referrer-Policy` header which controls what information is set in [the `Referer` header](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Referer).
 /// See ["Referer header: privacy and security concerns"](https://developer.mozilla.org/en-US/docs/Web/Security/Referer_header:_privacy_and_security_concerns) and [the header's documentation](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Referrer-Policy) on MDN for more.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ReferrerPolicy(pub Vec<ReferrerPolicyValue>);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum ReferrerPolicyValue {
     NoReferrer,
     NoReferrerWhenDowngrade,
@@ -19,6 +24,9 @@ pub enum ReferrerPolicyValue {
     SameOrigin,
     StrictOrigin,
     StrictOriginWhenCrossOrigin,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`ReferrerPolicy::validate`].
+    Custom(String),
 }
 
 impl Display for ReferrerPolicyValue {
@@ -31,25 +39,84 @@ impl Display for ReferrerPolicyValue {
             ReferrerPolicyValue::SameOrigin => "same-origin",
             ReferrerPolicyValue::StrictOrigin => "strict-origin",
             ReferrerPolicyValue::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+            ReferrerPolicyValue::Custom(value) => value.as_str(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl ReferrerPolicy {
+    /// Checks [`ReferrerPolicyValue::Custom`] values for characters which would break the header
+    /// or inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        for value in &self.0 {
+            if let ReferrerPolicyValue::Custom(value) = value {
+                validate_value_with("Referrer-Policy", value, &[','])?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for ReferrerPolicy {
     fn default() -> Self {
         ReferrerPolicy(vec![ReferrerPolicyValue::NoReferrer])
     }
 }
 
+impl FromStr for ReferrerPolicyValue {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "no-referrer" => Ok(ReferrerPolicyValue::NoReferrer),
+            "no-referrer-when-downgrade" => Ok(ReferrerPolicyValue::NoReferrerWhenDowngrade),
+            "origin" => Ok(ReferrerPolicyValue::Origin),
+            "origin-when-cross-origin" => Ok(ReferrerPolicyValue::OriginWhenCrossOrigin),
+            "same-origin" => Ok(ReferrerPolicyValue::SameOrigin),
+            "strict-origin" => Ok(ReferrerPolicyValue::StrictOrigin),
+            "strict-origin-when-cross-origin" => {
+                Ok(ReferrerPolicyValue::StrictOriginWhenCrossOrigin)
+            }
+            _ => Err(ParseHeaderError::new("Referrer-Policy", s)),
+        }
+    }
+}
+
+/// Parses the comma separated list of policies, such as `no-referrer, strict-origin`.
+impl FromStr for ReferrerPolicy {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| ParseHeaderError::new("Referrer-Policy", s))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ReferrerPolicy(values))
+    }
+}
+
+impl Display for ReferrerPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s: Vec<String> = self.0.iter().map(|v| v.to_string()).collect();
+        write!(f, "{}", s.join(","))
+    }
+}
+
 impl IntoHeader for ReferrerPolicy {
     fn header_name(&self) -> HeaderName {
         http::header::REFERRER_POLICY
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        let s: Vec<String> = self.0.iter().map(|v| v.to_string()).collect();
-        HeaderValue::from_str(s.join(",").as_str())
+        self.validate().map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -0,0 +1,380 @@
+use std::str::FromStr;
+
+use http::header::InvalidHeaderValue;
+use http::HeaderMap;
+
+use super::*;
+use crate::IntoHeaders;
+
+/// The names of the headers a [`HeaderSet`] holds.
+const NAMES: [&str; 15] = [
+    "content-security-policy",
+    "content-security-policy-report-only",
+    "cross-origin-embedder-policy",
+    "cross-origin-opener-policy",
+    "cross-origin-resource-policy",
+    "expect-ct",
+    "origin-agent-cluster",
+    "referrer-policy",
+    "strict-transport-security",
+    "x-content-type-options",
+    "x-dns-prefetch-control",
+    "x-download-options",
+    "x-frame-options",
+    "x-permitted-cross-domain-policies",
+    "x-xss-protection",
+];
+
+/// `HeaderSet` holds one optional value per known header, as a typed alternative to a
+/// [`HeaderMap`]: every header can be inspected and changed through its field, two values can't
+/// be set for the same header, and sets can be compared with `==` or
+/// [`crate::HelmetLayer::diff`]. A [`crate::HelmetLayer`] keeps its headers in a set and only
+/// renders them into a header map when it wraps a service.
+///
+/// The enforced and the report-only CSP have a field each, so a policy can be tried out in
+/// report-only mode next to the one enforced. They hold [`OwnedContentSecurityPolicy`]s, so a set
+/// doesn't borrow from anything.
+///
+/// With the `serde` feature it is (de)serialized as a map of lowercase header names to their
+/// values. With the `schemars` feature it implements `JsonSchema`, describing that map with the
+/// values of [`HeaderSet::with_defaults`] as examples, for validating configuration files and
+/// editor completion.
+///
+/// ```
+/// use tower_helmet::header::{HeaderSet, XFrameOptions};
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut set = HeaderSet::with_defaults_v2();
+/// set.x_frame_options = Some(XFrameOptions::Deny);
+/// set.cross_origin_embedder_policy = None;
+/// set.content_security_policy_report_only = Some("default-src 'none'".parse().unwrap());
+///
+/// let layer = HelmetLayer::from(set.clone());
+/// assert_eq!(layer.header_set(), &set);
+/// assert_eq!(
+///     layer.header_map().unwrap()["content-security-policy-report-only"],
+///     "default-src 'none'"
+/// );
+/// # use tower_helmet::IntoHeaders;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HeaderSet {
+    pub content_security_policy: Option<OwnedContentSecurityPolicy>,
+    pub content_security_policy_report_only: Option<OwnedContentSecurityPolicy>,
+    pub cross_origin_embedder_policy: Option<CrossOriginEmbedderPolicy>,
+    pub cross_origin_opener_policy: Option<CrossOriginOpenerPolicy>,
+    pub cross_origin_resource_policy: Option<CrossOriginResourcePolicy>,
+    pub expect_ct: Option<ExpectCt>,
+    pub origin_agent_cluster: Option<OriginAgentCluster>,
+    pub referrer_policy: Option<ReferrerPolicy>,
+    pub strict_transport_security: Option<StrictTransportSecurity>,
+    pub x_content_type_options: Option<XContentTypeOptions>,
+    pub x_dns_prefetch_control: Option<XDnsPrefetchControl>,
+    pub x_download_options: Option<XDownloadOptions>,
+    pub x_frame_options: Option<XFrameOptions>,
+    pub x_permitted_cross_domain_policies: Option<XPermittedCrossDomainPolicies>,
+    pub x_xss_protection: Option<XXSSProtection>,
+}
+
+impl HeaderSet {
+    /// The headers of [`crate::HelmetLayer::with_defaults`].
+    pub fn with_defaults() -> Self {
+        HeaderSet {
+            expect_ct: Some(ExpectCt::new()),
+            x_download_options: Some(XDownloadOptions::new()),
+            x_xss_protection: Some(XXSSProtection::new()),
+            ..Self::with_defaults_v2()
+        }
+    }
+
+    /// The headers of [`crate::HelmetLayer::with_defaults_v2`].
+    pub fn with_defaults_v2() -> Self {
+        HeaderSet {
+            content_security_policy: Some(ContentSecurityPolicy::default().into()),
+            content_security_policy_report_only: None,
+            cross_origin_embedder_policy: Some(CrossOriginEmbedderPolicy::default()),
+            cross_origin_opener_policy: Some(CrossOriginOpenerPolicy::default()),
+            cross_origin_resource_policy: Some(CrossOriginResourcePolicy::default()),
+            expect_ct: None,
+            origin_agent_cluster: Some(OriginAgentCluster::new()),
+            referrer_policy: Some(ReferrerPolicy::default()),
+            strict_transport_security: Some(StrictTransportSecurity::new()),
+            x_content_type_options: Some(XContentTypeOptions::new()),
+            x_dns_prefetch_control: Some(XDnsPrefetchControl::default()),
+            x_download_options: None,
+            x_frame_options: Some(XFrameOptions::default()),
+            x_permitted_cross_domain_policies: Some(XPermittedCrossDomainPolicies::default()),
+            x_xss_protection: None,
+        }
+    }
+
+    /// Sets `csp` as enforced or, if it is [report only](ContentSecurityPolicy::report_only), as
+    /// report-only policy.
+    pub fn set_content_security_policy(&mut self, csp: ContentSecurityPolicy<'_>) {
+        let field = if csp.report_only {
+            &mut self.content_security_policy_report_only
+        } else {
+            &mut self.content_security_policy
+        };
+        *field = Some(csp.into());
+    }
+
+    /// Parses the known headers of `headers`. Other headers are ignored, see
+    /// [`crate::HelmetLayer::from_header_map`] for keeping them.
+    pub fn from_header_map(headers: &HeaderMap) -> Result<Self, ParseHeaderError> {
+        let mut set = HeaderSet::default();
+        for (name, value) in headers {
+            let header = match NAMES.iter().find(|known| **known == name.as_str()) {
+                Some(header) => *header,
+                None => continue,
+            };
+            let value = value.to_str().map_err(|_| {
+                ParseHeaderError::new(header, &String::from_utf8_lossy(value.as_bytes()))
+            })?;
+            set.parse(header, value)?;
+        }
+
+        Ok(set)
+    }
+
+    /// Splits `headers` into the set and the headers it can't hold: unknown ones, repeated ones
+    /// and values which don't render the same once parsed, so nothing is lost or changed.
+    pub(crate) fn split(headers: HeaderMap) -> (Self, HeaderMap) {
+        let mut set = HeaderSet::default();
+        let mut others = HeaderMap::new();
+        for name in headers.keys() {
+            let mut values = headers.get_all(name).iter();
+            let value = values.next().filter(|_| values.next().is_none());
+            let parsed = match (NAMES.iter().find(|known| **known == name.as_str()), value) {
+                (Some(header), Some(value)) => {
+                    let mut single = HeaderSet::default();
+                    let parsed = matches!(
+                        value.to_str().map(|text| single.parse(header, text)),
+                        Ok(Ok(()))
+                    );
+                    let rendered = single.header_map().ok();
+                    let lossless =
+                        parsed && rendered.as_ref().and_then(|h| h.get(name)) == Some(value);
+                    if lossless {
+                        set.merge(single);
+                    }
+                    lossless
+                }
+                _ => false,
+            };
+            if !parsed {
+                for value in headers.get_all(name) {
+                    others.append(name, value.clone());
+                }
+            }
+        }
+
+        (set, others)
+    }
+
+    /// Parses `value` into the field of the header `name`, one of [`NAMES`].
+    fn parse(&mut self, name: &str, value: &str) -> Result<(), ParseHeaderError> {
+        fn parse<T: FromStr<Err = ParseHeaderError>>(
+            field: &mut Option<T>,
+            value: &str,
+        ) -> Result<(), ParseHeaderError> {
+            *field = Some(value.parse()?);
+            Ok(())
+        }
+
+        match name {
+            "content-security-policy" => parse(&mut self.content_security_policy, value),
+            "content-security-policy-report-only" => {
+                parse(&mut self.content_security_policy_report_only, value)
+            }
+            "cross-origin-embedder-policy" => parse(&mut self.cross_origin_embedder_policy, value),
+            "cross-origin-opener-policy" => parse(&mut self.cross_origin_opener_policy, value),
+            "cross-origin-resource-policy" => parse(&mut self.cross_origin_resource_policy, value),
+            "expect-ct" => parse(&mut self.expect_ct, value),
+            "origin-agent-cluster" => parse(&mut self.origin_agent_cluster, value),
+            "referrer-policy" => parse(&mut self.referrer_policy, value),
+            "strict-transport-security" => parse(&mut self.strict_transport_security, value),
+            "x-content-type-options" => parse(&mut self.x_content_type_options, value),
+            "x-dns-prefetch-control" => parse(&mut self.x_dns_prefetch_control, value),
+            "x-download-options" => parse(&mut self.x_download_options, value),
+            "x-frame-options" => parse(&mut self.x_frame_options, value),
+            "x-permitted-cross-domain-policies" => {
+                parse(&mut self.x_permitted_cross_domain_policies, value)
+            }
+            "x-xss-protection" => parse(&mut self.x_xss_protection, value),
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes the headers set in `other`.
+    fn merge(&mut self, other: HeaderSet) {
+        fn merge<T>(field: &mut Option<T>, other: Option<T>) {
+            if other.is_some() {
+                *field = other;
+            }
+        }
+
+        merge(
+            &mut self.content_security_policy,
+            other.content_security_policy,
+        );
+        merge(
+            &mut self.content_security_policy_report_only,
+            other.content_security_policy_report_only,
+        );
+        merge(
+            &mut self.cross_origin_embedder_policy,
+            other.cross_origin_embedder_policy,
+        );
+        merge(
+            &mut self.cross_origin_opener_policy,
+            other.cross_origin_opener_policy,
+        );
+        merge(
+            &mut self.cross_origin_resource_policy,
+            other.cross_origin_resource_policy,
+        );
+        merge(&mut self.expect_ct, other.expect_ct);
+        merge(&mut self.origin_agent_cluster, other.origin_agent_cluster);
+        merge(&mut self.referrer_policy, other.referrer_policy);
+        merge(
+            &mut self.strict_transport_security,
+            other.strict_transport_security,
+        );
+        merge(
+            &mut self.x_content_type_options,
+            other.x_content_type_options,
+        );
+        merge(
+            &mut self.x_dns_prefetch_control,
+            other.x_dns_prefetch_control,
+        );
+        merge(&mut self.x_download_options, other.x_download_options);
+        merge(&mut self.x_frame_options, other.x_frame_options);
+        merge(
+            &mut self.x_permitted_cross_domain_policies,
+            other.x_permitted_cross_domain_policies,
+        );
+        merge(&mut self.x_xss_protection, other.x_xss_protection);
+    }
+
+    /// The typed headers other than the CSPs, in the order of the fields.
+    fn headers(&self) -> Vec<&dyn IntoHeader> {
+        let headers: [Option<&dyn IntoHeader>; 13] = [
+            self.cross_origin_embedder_policy.as_ref().map(|h| h as _),
+            self.cross_origin_opener_policy.as_ref().map(|h| h as _),
+            self.cross_origin_resource_policy.as_ref().map(|h| h as _),
+            self.expect_ct.as_ref().map(|h| h as _),
+            self.origin_agent_cluster.as_ref().map(|h| h as _),
+            self.referrer_policy.as_ref().map(|h| h as _),
+            self.strict_transport_security.as_ref().map(|h| h as _),
+            self.x_content_type_options.as_ref().map(|h| h as _),
+            self.x_dns_prefetch_control.as_ref().map(|h| h as _),
+            self.x_download_options.as_ref().map(|h| h as _),
+            self.x_frame_options.as_ref().map(|h| h as _),
+            self.x_permitted_cross_domain_policies
+                .as_ref()
+                .map(|h| h as _),
+            self.x_xss_protection.as_ref().map(|h| h as _),
+        ];
+
+        headers.iter().flatten().copied().collect()
+    }
+}
+
+impl IntoHeaders for HeaderSet {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        if let Some(csp) = &self.content_security_policy {
+            headers.insert(http::header::CONTENT_SECURITY_POLICY, csp.header_value()?);
+        }
+        if let Some(csp) = &self.content_security_policy_report_only {
+            headers.insert(
+                http::header::CONTENT_SECURITY_POLICY_REPORT_ONLY,
+                csp.header_value()?,
+            );
+        }
+        for header in self.headers() {
+            headers.insert(header.header_name(), header.header_value()?);
+        }
+
+        Ok(headers)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HeaderSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeMap};
+
+        let headers = self.header_map().map_err(S::Error::custom)?;
+        let mut map = serializer.serialize_map(Some(headers.len()))?;
+        for (name, value) in &headers {
+            let value = value.to_str().map_err(S::Error::custom)?;
+            map.serialize_entry(name.as_str(), value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for HeaderSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use std::fmt::Formatter;
+
+        use serde::de::{Error, MapAccess, Visitor};
+
+        struct HeaderSetVisitor;
+
+        impl<'de> Visitor<'de> for HeaderSetVisitor {
+            type Value = HeaderSet;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a map of header names to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut set = HeaderSet::default();
+                while let Some((name, value)) = map.next_entry::<String, String>()? {
+                    let name = name.to_ascii_lowercase();
+                    if !NAMES.contains(&name.as_str()) {
+                        return Err(A::Error::custom(format!("unknown header {}", name)));
+                    }
+                    set.parse(&name, &value).map_err(A::Error::custom)?;
+                }
+                Ok(set)
+            }
+        }
+
+        deserializer.deserialize_map(HeaderSetVisitor)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for HeaderSet {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "HeaderSet".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        use schemars::json_schema;
+
+        let defaults = HeaderSet::with_defaults().header_map().unwrap_or_default();
+        let mut properties = json_schema!({});
+        for name in NAMES.iter() {
+            let description = format!("The value of the `{}` header.", name);
+            let mut property = json_schema!({ "type": "string", "description": description });
+            if let Some(value) = defaults.get(*name).and_then(|v| v.to_str().ok()) {
+                property.insert("examples".to_owned(), [value].as_slice().into());
+            }
+            properties.insert((*name).to_owned(), property.to_value());
+        }
+
+        json_schema!({
+            "description": "Security headers by their lowercase name.",
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false
+        })
+    }
+}
@@ -1,16 +1,30 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::Duration;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::ParseHeaderError;
 use crate::IntoHeader;
 
 /// `StrictTransportSecurity` sets the `Strict-Transport-Security` header which tells browsers to
 /// prefer HTTPS over insecure HTTP. See [the documentation on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Strict-Transport-Security) for more.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StrictTransportSecurity {
     /// `max_age` is the number of seconds browsers should remember to prefer HTTPS. It defaults to
     /// `15552000`, which is 180 days.
+    ///
+    /// With the `serde` feature it is deserialized from either a number of seconds or a
+    /// human-friendly duration such as `"180d"` or `"1y"`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::duration"))]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(schema_with = "crate::duration::json_schema")
+    )]
     pub max_age: Duration,
     /// `include_subdomains` dictates whether to include the `includeSubDomains` directive, which
     /// makes this policy extend to subdomains. It defaults to `true`.
@@ -22,30 +36,186 @@ pub struct StrictTransportSecurity {
 
 impl Default for StrictTransportSecurity {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The minimum `max-age` accepted by [hstspreload.org](https://hstspreload.org/), one year.
+const PRELOAD_MIN_MAX_AGE: Duration = Duration::from_secs(31536000);
+
+/// Reasons why a [`StrictTransportSecurity`] doesn't meet the [hstspreload.org](https://hstspreload.org/#submission-requirements)
+/// submission requirements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadError {
+    /// `max_age` is less than one year.
+    MaxAgeTooShort(Duration),
+    /// `include_subdomains` is `false`.
+    MissingIncludeSubDomains,
+    /// `preload` is `false`.
+    MissingPreload,
+}
+
+impl Display for PreloadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreloadError::MaxAgeTooShort(max_age) => write!(
+                f,
+                "max-age={} is less than the required {}",
+                max_age.as_secs(),
+                PRELOAD_MIN_MAX_AGE.as_secs()
+            ),
+            PreloadError::MissingIncludeSubDomains => {
+                write!(f, "the includeSubDomains directive is missing")
+            }
+            PreloadError::MissingPreload => write!(f, "the preload directive is missing"),
+        }
+    }
+}
+
+impl std::error::Error for PreloadError {}
+
+impl StrictTransportSecurity {
+    /// Same as [`Default::default`], usable in constants and statics.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use tower_helmet::header::StrictTransportSecurity;
+    ///
+    /// static HSTS: StrictTransportSecurity = StrictTransportSecurity {
+    ///     max_age: Duration::from_secs(63072000),
+    ///     ..StrictTransportSecurity::new()
+    /// };
+    /// ```
+    pub const fn new() -> Self {
         StrictTransportSecurity {
             max_age: Duration::from_secs(15552000),
             include_subdomains: true,
             preload: false,
         }
     }
+
+    /// Returns a policy which can be submitted to [the HSTS preload list](https://hstspreload.org/),
+    /// with `include_subdomains` and `preload` enabled. Fails if `max_age` is less than one year.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use tower_helmet::header::StrictTransportSecurity;
+    ///
+    /// let hsts = StrictTransportSecurity::preload_ready(Duration::from_secs(63072000)).unwrap();
+    /// assert!(StrictTransportSecurity::preload_ready(Duration::from_secs(86400)).is_err());
+    /// ```
+    pub fn preload_ready(max_age: Duration) -> Result<Self, PreloadError> {
+        let hsts = StrictTransportSecurity {
+            max_age,
+            include_subdomains: true,
+            preload: true,
+        };
+        hsts.check_preload()?;
+
+        Ok(hsts)
+    }
+
+    /// Returns the policy which backs out of this one: `max-age=0` tells browsers to forget the
+    /// HSTS policy on their next visit. `includeSubDomains` is kept as in the previous policy so
+    /// subdomains are cleared as well, while `preload` is dropped.
+    ///
+    /// Serve it for at least the previous `max_age` to reach every browser that has seen the old
+    /// policy. Being on the preload list is not undone by this header, request removal on
+    /// [hstspreload.org](https://hstspreload.org/removal/) as well.
+    ///
+    /// ```
+    /// use tower_helmet::audit::Strict;
+    /// use tower_helmet::header::StrictTransportSecurity;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::with_defaults();
+    /// layer.enable(StrictTransportSecurity::default().clear());
+    /// assert!(layer.enforce(&Strict::new()).is_ok());
+    /// ```
+    pub const fn clear(&self) -> Self {
+        StrictTransportSecurity {
+            max_age: Duration::ZERO,
+            include_subdomains: self.include_subdomains,
+            preload: false,
+        }
+    }
+
+    /// Checks whether this policy meets the [hstspreload.org submission requirements](https://hstspreload.org/#submission-requirements).
+    pub fn check_preload(&self) -> Result<(), PreloadError> {
+        if self.max_age < PRELOAD_MIN_MAX_AGE {
+            return Err(PreloadError::MaxAgeTooShort(self.max_age));
+        }
+        if !self.include_subdomains {
+            return Err(PreloadError::MissingIncludeSubDomains);
+        }
+        if !self.preload {
+            return Err(PreloadError::MissingPreload);
+        }
+
+        Ok(())
+    }
 }
 
-impl IntoHeader for StrictTransportSecurity {
-    fn header_name(&self) -> HeaderName {
-        http::header::STRICT_TRANSPORT_SECURITY
+/// Parses a value such as `max-age=31536000; includeSubDomains; preload`. `max-age` is required.
+impl FromStr for StrictTransportSecurity {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHeaderError::new("Strict-Transport-Security", s);
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        let mut preload = false;
+
+        for directive in s.split(';').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((name, value)) if name.trim().eq_ignore_ascii_case("max-age") => {
+                    let seconds = value
+                        .trim()
+                        .trim_matches('"')
+                        .parse()
+                        .map_err(|_| error())?;
+                    max_age = Some(Duration::from_secs(seconds));
+                }
+                None if directive.eq_ignore_ascii_case("includesubdomains") => {
+                    include_subdomains = true;
+                }
+                None if directive.eq_ignore_ascii_case("preload") => preload = true,
+                _ => return Err(error()),
+            }
+        }
+
+        Ok(StrictTransportSecurity {
+            max_age: max_age.ok_or_else(error)?,
+            include_subdomains,
+            preload,
+        })
     }
+}
 
-    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+impl Display for StrictTransportSecurity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut directives = vec![format!("max-age={}", self.max_age.as_secs())];
 
         if self.include_subdomains {
-            directives.push("includeSubdomains".to_owned());
+            directives.push("includeSubDomains".to_owned());
         }
 
         if self.preload {
             directives.push("preload".to_owned());
         }
 
-        HeaderValue::from_str(directives.join("; ").as_str())
+        write!(f, "{}", directives.join("; "))
+    }
+}
+
+impl IntoHeader for StrictTransportSecurity {
+    fn header_name(&self) -> HeaderName {
+        http::header::STRICT_TRANSPORT_SECURITY
+    }
+
+    fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
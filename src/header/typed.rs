@@ -0,0 +1,59 @@
+use headers::{Error, Header};
+use http::{HeaderName, HeaderValue};
+
+use super::*;
+
+// references to the constants would be references to temporaries
+static EXPECT_CT_NAME: HeaderName = EXPECT_CT;
+static ORIGIN_AGENT_CLUSTER_NAME: HeaderName = ORIGIN_AGENT_CLUSTER;
+static X_DOWNLOAD_OPTIONS_NAME: HeaderName = X_DOWNLOAD_OPTIONS;
+static X_PERMITTED_CROSS_DOMAIN_POLICIES_NAME: HeaderName = X_PERMITTED_CROSS_DOMAIN_POLICIES;
+static CROSS_ORIGIN_EMBEDDER_POLICY_NAME: HeaderName = CROSS_ORIGIN_EMBEDDER_POLICY;
+static CROSS_ORIGIN_OPENER_POLICY_NAME: HeaderName = CROSS_ORIGIN_OPENER_POLICY;
+static CROSS_ORIGIN_RESOURCE_POLICY_NAME: HeaderName = CROSS_ORIGIN_RESOURCE_POLICY;
+
+/// Implements [`Header`] based on the type's [`std::str::FromStr`] and [`IntoHeader`]
+/// implementations.
+macro_rules! typed_header {
+    ($($ty:ty => $name:expr,)*) => {
+        $(
+            impl Header for $ty {
+                fn name() -> &'static HeaderName {
+                    &$name
+                }
+
+                fn decode<'i, I>(values: &mut I) -> Result<Self, Error>
+                where
+                    I: Iterator<Item = &'i HeaderValue>,
+                {
+                    values
+                        .next()
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(Error::invalid)
+                }
+
+                fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+                    let value = self.header_value().expect("invalid header value");
+                    values.extend(std::iter::once(value));
+                }
+            }
+        )*
+    };
+}
+
+typed_header! {
+    CrossOriginEmbedderPolicy => CROSS_ORIGIN_EMBEDDER_POLICY_NAME,
+    CrossOriginOpenerPolicy => CROSS_ORIGIN_OPENER_POLICY_NAME,
+    CrossOriginResourcePolicy => CROSS_ORIGIN_RESOURCE_POLICY_NAME,
+    ExpectCt => EXPECT_CT_NAME,
+    OriginAgentCluster => ORIGIN_AGENT_CLUSTER_NAME,
+    ReferrerPolicy => http::header::REFERRER_POLICY,
+    StrictTransportSecurity => http::header::STRICT_TRANSPORT_SECURITY,
+    XContentTypeOptions => http::header::X_CONTENT_TYPE_OPTIONS,
+    XDnsPrefetchControl => http::header::X_DNS_PREFETCH_CONTROL,
+    XDownloadOptions => X_DOWNLOAD_OPTIONS_NAME,
+    XFrameOptions => http::header::X_FRAME_OPTIONS,
+    XPermittedCrossDomainPolicies => X_PERMITTED_CROSS_DOMAIN_POLICIES_NAME,
+    XXSSProtection => http::header::X_XSS_PROTECTION,
+}
@@ -1,16 +1,45 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::ParseHeaderError;
 use crate::IntoHeader;
 
 /// `XContentTypeOptions` sets the `X-Content-Type-Options` header to `nosniff`.
 /// This mitigates [MIME type sniffing](https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types#MIME_sniffing) which can cause security vulnerabilities.
 /// See [documentation for this header on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Content-Type-Options) for more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct XContentTypeOptions;
 
+impl XContentTypeOptions {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        XContentTypeOptions
+    }
+}
+
 impl Default for XContentTypeOptions {
     fn default() -> Self {
-        XContentTypeOptions
+        Self::new()
+    }
+}
+
+impl FromStr for XContentTypeOptions {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "nosniff" => Ok(XContentTypeOptions),
+            _ => Err(ParseHeaderError::new("X-Content-Type-Options", s)),
+        }
+    }
+}
+
+impl Display for XContentTypeOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "nosniff")
     }
 }
 
@@ -20,6 +49,6 @@ impl IntoHeader for XContentTypeOptions {
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str("nosniff")
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -1,22 +1,44 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::ParseHeaderError;
 use crate::IntoHeader;
 
 /// `XDnsPrefetchControl` sets the `X-DNS-Prefetch-Control` header to help control DNS prefetching,
 /// which can improve user privacy at the expense of performance. See [documentation on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-DNS-Prefetch-Control) for more.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct XDnsPrefetchControl(
     /// Is indictating whether to enable DNS prefetching.
     pub bool,
 );
 
+impl FromStr for XDnsPrefetchControl {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "on" => Ok(XDnsPrefetchControl(true)),
+            "off" => Ok(XDnsPrefetchControl(false)),
+            _ => Err(ParseHeaderError::new("X-DNS-Prefetch-Control", s)),
+        }
+    }
+}
+
+impl Display for XDnsPrefetchControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", if self.0 { "on" } else { "off" })
+    }
+}
+
 impl IntoHeader for XDnsPrefetchControl {
     fn header_name(&self) -> HeaderName {
         http::header::X_DNS_PREFETCH_CONTROL
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str(if self.0 { "on" } else { "off" })
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
@@ -1,25 +1,54 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{ParseHeaderError, X_DOWNLOAD_OPTIONS};
 use crate::IntoHeader;
 
 /// `XDownloadOptions` sets the `X-Download-Options` header, which is specific to Internet Explorer
 /// 8. It forces potentially-unsafe downloads to be saved, mitigating execution of HTML in your
 /// site's context. For more, see [this old post on MSDN](https://docs.microsoft.com/en-us/archive/blogs/ie/ie8-security-part-v-comprehensive-protection).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct XDownloadOptions;
 
+impl XDownloadOptions {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        XDownloadOptions
+    }
+}
+
 impl Default for XDownloadOptions {
     fn default() -> Self {
-        XDownloadOptions
+        Self::new()
+    }
+}
+
+impl FromStr for XDownloadOptions {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "noopen" => Ok(XDownloadOptions),
+            _ => Err(ParseHeaderError::new("X-Download-Options", s)),
+        }
+    }
+}
+
+impl Display for XDownloadOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "noopen")
     }
 }
 
 impl IntoHeader for XDownloadOptions {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("x-download-options")
+        X_DOWNLOAD_OPTIONS
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str("noopen")
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
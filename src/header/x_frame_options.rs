@@ -1,8 +1,12 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+};
 use crate::IntoHeader;
 
 /// `XFrameOptions` sets the `X-Frame-Options` header to help you mitigate [clickjacking attacks](https://en.wikipedia.org/wiki/Clickjacking).
@@ -10,11 +14,15 @@ use crate::IntoHeader;
 /// For more, see `helmet.contentSecurityPolicy`, as well as [the documentation on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Frame-Options).
 ///
 /// `DENY` or `SAMEORIGIN`. (A legacy directive, `ALLOW-FROM`, is not supported by this crate. [Read more here.](https://github.com/helmetjs/helmet/wiki/How-to-use-X%E2%80%93Frame%E2%80%93Options's-%60ALLOW%E2%80%93FROM%60-directive))
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum XFrameOptions {
     Deny,
     #[default]
     SameOrigin,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`XFrameOptions::validate`].
+    Custom(String),
 }
 
 impl Display for XFrameOptions {
@@ -22,18 +30,45 @@ impl Display for XFrameOptions {
         let s = match self {
             XFrameOptions::SameOrigin => "SAMEORIGIN",
             XFrameOptions::Deny => "DENY",
+            XFrameOptions::Custom(value) => value.as_str(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl XFrameOptions {
+    /// Checks a [`XFrameOptions::Custom`] value for characters which would break the header or
+    /// inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        match self {
+            XFrameOptions::Custom(value) => validate_value_with("X-Frame-Options", value, &[',']),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for XFrameOptions {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "deny" => Ok(XFrameOptions::Deny),
+            "sameorigin" => Ok(XFrameOptions::SameOrigin),
+            _ => Err(ParseHeaderError::new("X-Frame-Options", s)),
+        }
+    }
+}
+
 impl IntoHeader for XFrameOptions {
     fn header_name(&self) -> HeaderName {
         http::header::X_FRAME_OPTIONS
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
         HeaderValue::from_str(self.to_string().as_str())
     }
 }
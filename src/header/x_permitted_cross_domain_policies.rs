@@ -1,19 +1,28 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use http::header::{HeaderName, InvalidHeaderValue};
 use http::HeaderValue;
 
+use crate::header::{
+    invalid_header_value, validate_value_with, InvalidCharacterError, ParseHeaderError,
+    X_PERMITTED_CROSS_DOMAIN_POLICIES,
+};
 use crate::IntoHeader;
 
 /// `XPermittedCrossDomainPolicies` sets the `X-Permitted-Cross-Domain-Policies` header, which tells
 /// some clients (mostly Adobe products) your domain's policy for loading cross-domain content. See [the description on OWASP](https://owasp.org/www-project-secure-headers/) for more.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum XPermittedCrossDomainPolicies {
     #[default]
     None,
     MasterOnly,
     ByContentType,
     All,
+    /// Any other value, for values newer than this crate. It must not contain control characters
+    /// or `,`, see [`XPermittedCrossDomainPolicies::validate`].
+    Custom(String),
 }
 
 impl Display for XPermittedCrossDomainPolicies {
@@ -23,18 +32,52 @@ impl Display for XPermittedCrossDomainPolicies {
             XPermittedCrossDomainPolicies::MasterOnly => "master-only",
             XPermittedCrossDomainPolicies::ByContentType => "by-content-type",
             XPermittedCrossDomainPolicies::All => "all",
+            XPermittedCrossDomainPolicies::Custom(value) => value.as_str(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl XPermittedCrossDomainPolicies {
+    /// Checks a [`XPermittedCrossDomainPolicies::Custom`] value for characters which would break the header or
+    /// inject further values. Values which fail the check are also rejected by
+    /// [`IntoHeader::header_value`], but without saying why.
+    pub fn validate(&self) -> Result<(), InvalidCharacterError> {
+        match self {
+            XPermittedCrossDomainPolicies::Custom(value) => {
+                validate_value_with("X-Permitted-Cross-Domain-Policies", value, &[','])
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for XPermittedCrossDomainPolicies {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(XPermittedCrossDomainPolicies::None),
+            "master-only" => Ok(XPermittedCrossDomainPolicies::MasterOnly),
+            "by-content-type" => Ok(XPermittedCrossDomainPolicies::ByContentType),
+            "all" => Ok(XPermittedCrossDomainPolicies::All),
+            _ => Err(ParseHeaderError::new(
+                "X-Permitted-Cross-Domain-Policies",
+                s,
+            )),
+        }
+    }
+}
+
 impl IntoHeader for XPermittedCrossDomainPolicies {
     fn header_name(&self) -> HeaderName {
-        HeaderName::from_static("x-permitted-cross-domain-policies")
+        X_PERMITTED_CROSS_DOMAIN_POLICIES
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
+        self.validate().map_err(|_| invalid_header_value())?;
+
         HeaderValue::from_str(self.to_string().as_str())
     }
 }
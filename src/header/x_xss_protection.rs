@@ -1,15 +1,115 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use http::header::{HeaderName, InvalidHeaderValue};
-use http::HeaderValue;
+use http::{HeaderValue, Uri};
 
+use crate::header::{
+    invalid_header_value, validate_report_uri, InvalidReportUriError, ParseHeaderError,
+};
 use crate::IntoHeader;
 
-/// `XXSSProtection` disables browsers' buggy cross-site scripting filter by setting the
-/// `X-XSS-Protection` header to `0`. See [discussion about disabling the header here](https://github.com/helmetjs/helmet/issues/230) and [documentation on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-XSS-Protection).
-pub struct XXSSProtection;
+/// `XXSSProtection` sets the `X-XSS-Protection` header, which controls the cross-site scripting
+/// filter of old browsers. By default it disables the buggy filter by setting the header to `0`.
+/// See [discussion about disabling the header here](https://github.com/helmetjs/helmet/issues/230) and [documentation on MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-XSS-Protection).
+///
+/// The other values only exist for scanners and legacy clients which still require them.
+///
+/// ```
+/// use tower_helmet::header::XXSSProtection;
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut layer = HelmetLayer::with_defaults_v2();
+/// layer.enable(XXSSProtection::EnabledBlock);
+///
+/// let parsed: XXSSProtection = "1; report=https://example.com/xss".parse().unwrap();
+/// assert_eq!(parsed.to_string(), "1; report=https://example.com/xss");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum XXSSProtection {
+    /// `0`, disables the filter.
+    #[default]
+    Disabled,
+    /// `1`, sanitizes the page when an attack is detected.
+    Enabled,
+    /// `1; mode=block`, doesn't render the page when an attack is detected.
+    EnabledBlock,
+    /// `1; report=<uri>`, sanitizes the page and reports the attack to the URI. It has to be
+    /// absolute and must not contain `;` or `,`, see [`XXSSProtection::validate`].
+    EnabledReport(Uri),
+}
+
+impl XXSSProtection {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        XXSSProtection::Disabled
+    }
+
+    /// Checks that the URI of [`XXSSProtection::EnabledReport`] is absolute and doesn't contain
+    /// `;` or `,`, which would inject further directives. A [`Uri`] can't contain control
+    /// characters or `"`.
+    ///
+    /// ```
+    /// use http::Uri;
+    /// use tower_helmet::header::XXSSProtection;
+    ///
+    /// let report = XXSSProtection::EnabledReport(Uri::from_static("https://example.com/xss"));
+    /// assert!(report.validate().is_ok());
+    /// assert!(XXSSProtection::EnabledReport(Uri::from_static("/xss")).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidReportUriError> {
+        match self {
+            XXSSProtection::EnabledReport(report_uri) => {
+                validate_report_uri("X-XSS-Protection", report_uri, &[';', ','])
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses `0`, `1`, `1; mode=block` or `1; report=<uri>`.
+impl FromStr for XXSSProtection {
+    type Err = ParseHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHeaderError::new("X-XSS-Protection", s);
+        let mut parts = s.split(';').map(str::trim);
 
-impl Default for XXSSProtection {
-    fn default() -> Self {
-        XXSSProtection
+        let protection = match parts.next() {
+            Some("0") => XXSSProtection::Disabled,
+            Some("1") => match parts.next() {
+                None | Some("") => XXSSProtection::Enabled,
+                Some(directive) => {
+                    let (name, value) = directive.split_once('=').ok_or_else(error)?;
+                    match name.trim().to_ascii_lowercase().as_str() {
+                        "mode" if value.trim().eq_ignore_ascii_case("block") => {
+                            XXSSProtection::EnabledBlock
+                        }
+                        "report" => XXSSProtection::EnabledReport(
+                            crate::origin::absolute_uri(value).ok_or_else(error)?,
+                        ),
+                        _ => return Err(error()),
+                    }
+                }
+            },
+            _ => return Err(error()),
+        };
+
+        match parts.next() {
+            None => Ok(protection),
+            Some(_) => Err(error()),
+        }
+    }
+}
+
+impl Display for XXSSProtection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XXSSProtection::Disabled => write!(f, "0"),
+            XXSSProtection::Enabled => write!(f, "1"),
+            XXSSProtection::EnabledBlock => write!(f, "1; mode=block"),
+            XXSSProtection::EnabledReport(report_uri) => write!(f, "1; report={}", report_uri),
+        }
     }
 }
 
@@ -19,6 +119,8 @@ impl IntoHeader for XXSSProtection {
     }
 
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue> {
-        HeaderValue::from_str("0")
+        self.validate().map_err(|_| invalid_header_value())?;
+
+        HeaderValue::from_str(self.to_string().as_str())
     }
 }
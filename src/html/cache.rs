@@ -0,0 +1,55 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use super::rewrite::InlineHashes;
+
+/// Bounded cache of the inline hashes of documents. When full, the oldest entry is evicted.
+#[derive(Debug)]
+pub(crate) struct HashCache {
+    capacity: usize,
+    inner: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<Key, InlineHashes>,
+    order: VecDeque<Key>,
+}
+
+/// Documents are identified by their authority and path with their strong `ETag` or, without
+/// one, by the hash of their content.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Key {
+    ETag { document: String, etag: Vec<u8> },
+    Content([u8; 32]),
+}
+
+impl HashCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        HashCache {
+            capacity,
+            inner: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &Key) -> Option<InlineHashes> {
+        self.inner.lock().unwrap().map.get(key).cloned()
+    }
+
+    pub(crate) fn insert(&self, key: Key, hashes: InlineHashes) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.inner.lock().unwrap();
+        if entries.map.insert(key.clone(), hashes).is_none() {
+            entries.order.push_back(key);
+        }
+
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.map.remove(&oldest);
+            }
+        }
+    }
+}
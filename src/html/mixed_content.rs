@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::BytesMut;
+
+use crate::audit::{Finding, Rule, Severity};
+
+/// Maximum length of an unfinished tag held back between chunks. Longer ones are skipped.
+const MAX_PENDING: usize = 8 * 1024;
+
+/// Attributes which load a subresource, by tag.
+const ATTRIBUTES: [(&str, &[&str]); 12] = [
+    ("audio", &["src"]),
+    ("embed", &["src"]),
+    ("frame", &["src"]),
+    ("iframe", &["src"]),
+    ("img", &["src", "srcset"]),
+    ("input", &["src"]),
+    ("link", &["href"]),
+    ("object", &["data"]),
+    ("script", &["src"]),
+    ("source", &["src", "srcset"]),
+    ("track", &["src"]),
+    ("video", &["src", "poster"]),
+];
+
+/// `rel` values of `<link>` tags whose `href` is fetched as subresource.
+const LINK_RELS: [&str; 6] = [
+    "icon",
+    "manifest",
+    "modulepreload",
+    "preload",
+    "prefetch",
+    "stylesheet",
+];
+
+type FindingCallback = Arc<dyn Fn(&Finding) + Send + Sync>;
+
+/// Flags subresources of HTML documents which are loaded over plain `http://`, passed to
+/// [`super::HtmlRewriteLayer::audit_mixed_content`].
+///
+/// Browsers block or upgrade such mixed content on `https://` pages, so the findings
+/// ([`Rule::MixedContent`]) show which URLs depend on `upgrade-insecure-requests` or would break
+/// with `block-all-mixed-content`, before the CSP is tightened. Documents are not modified, the
+/// findings are passed to a callback and with the `tracing` feature logged as warning. Every URL is
+/// reported once per document, and only the first [`super::HtmlRewriteLayer::max_size`] bytes are
+/// scanned.
+///
+/// ```
+/// use tower_helmet::html::{HtmlRewriteLayer, MixedContentAudit};
+///
+/// let mut audit = MixedContentAudit::new();
+/// audit.sample(100).on_finding(|finding| eprintln!("{}", finding));
+///
+/// let mut rewrite = HtmlRewriteLayer::new();
+/// rewrite.audit_mixed_content(audit);
+/// ```
+pub struct MixedContentAudit {
+    sample: usize,
+    on_finding: Option<FindingCallback>,
+    seen: AtomicUsize,
+}
+
+impl Debug for MixedContentAudit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MixedContentAudit")
+            .field("sample", &self.sample)
+            .field("on_finding", &self.on_finding.is_some())
+            .finish()
+    }
+}
+
+impl Default for MixedContentAudit {
+    fn default() -> Self {
+        MixedContentAudit {
+            sample: 1,
+            on_finding: None,
+            seen: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl MixedContentAudit {
+    /// Creates an audit which scans every document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only scans one in `n` documents, to limit the overhead in production.
+    pub fn sample(&mut self, n: usize) -> &mut Self {
+        self.sample = n.max(1);
+        self
+    }
+
+    /// Calls `callback` for every insecure subresource.
+    pub fn on_finding<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Finding) + Send + Sync + 'static,
+    {
+        self.on_finding = Some(Arc::new(callback));
+        self
+    }
+
+    /// Whether the next document is part of the sample.
+    pub(crate) fn sampled(&self) -> bool {
+        self.seen
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.sample)
+    }
+
+    /// Scans a complete document.
+    pub(crate) fn scan(self: &Arc<Self>, html: &[u8]) {
+        let mut scanner = MixedContentScanner::new(self.clone());
+        scanner.push(html);
+        scanner.finish();
+    }
+
+    fn report(&self, finding: Finding) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(rule = %finding.rule, "{}", finding.message);
+
+        if let Some(callback) = &self.on_finding {
+            callback(&finding);
+        }
+    }
+}
+
+/// Scans a document chunk by chunk as it is streamed, only an unfinished tag at the end of a chunk
+/// is held back until its closing `>` arrives.
+#[derive(Debug)]
+pub(crate) struct MixedContentScanner {
+    audit: Arc<MixedContentAudit>,
+    pending: BytesMut,
+    reported: HashSet<String>,
+}
+
+impl MixedContentScanner {
+    pub(crate) fn new(audit: Arc<MixedContentAudit>) -> Self {
+        MixedContentScanner {
+            audit,
+            pending: BytesMut::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Scans the next chunk.
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+
+        let split = match self.pending.iter().rposition(|b| *b == b'<') {
+            Some(pos) if !self.pending[pos..].contains(&b'>') => pos,
+            _ => self.pending.len(),
+        };
+
+        let ready = self.pending.split_to(split);
+        self.scan(&ready);
+
+        if self.pending.len() > MAX_PENDING {
+            self.pending.clear();
+        }
+    }
+
+    /// Stops scanning, discarding whatever is held back.
+    pub(crate) fn abort(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Scans whatever is still held back at the end of the document.
+    pub(crate) fn finish(&mut self) {
+        let rest = self.pending.split();
+        self.scan(&rest);
+    }
+
+    fn scan(&mut self, html: &[u8]) {
+        for (tag, attribute, url) in insecure_urls(html) {
+            if self.reported.insert(url.clone()) {
+                self.audit.report(Finding::new(
+                    Rule::MixedContent,
+                    Severity::Warning,
+                    None,
+                    format!("<{} {}> loads {}", tag, attribute, url),
+                ));
+            }
+        }
+    }
+}
+
+/// Finds the `http://` URLs of the subresources of `html`, as `(tag, attribute, url)`.
+fn insecure_urls(html: &[u8]) -> Vec<(&'static str, &'static str, String)> {
+    let mut urls = Vec::new();
+    let mut rest = html;
+
+    while let Some(pos) = rest.iter().position(|b| *b == b'<') {
+        rest = &rest[pos + 1..];
+        let name_end = rest
+            .iter()
+            .position(|b| !b.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        let (tag, names) = match ATTRIBUTES
+            .iter()
+            .find(|(tag, _)| name.eq_ignore_ascii_case(tag.as_bytes()))
+        {
+            Some(entry) => *entry,
+            None => continue,
+        };
+
+        let (attributes, end) = parse_attributes(&rest[name_end..]);
+        rest = &rest[name_end + end..];
+
+        if tag == "link" {
+            let rel = attributes
+                .iter()
+                .find(|(name, _)| name == "rel")
+                .map(|(_, value)| value.to_ascii_lowercase())
+                .unwrap_or_default();
+            if !rel.split_ascii_whitespace().any(|r| LINK_RELS.contains(&r)) {
+                continue;
+            }
+        }
+
+        for (name, value) in &attributes {
+            let attribute = match names.iter().find(|n| *n == name) {
+                Some(attribute) => *attribute,
+                None => continue,
+            };
+            // `srcset` is a list of `url descriptor` candidates
+            let candidates: Vec<&str> = if attribute == "srcset" {
+                value
+                    .split(',')
+                    .filter_map(|c| c.split_ascii_whitespace().next())
+                    .collect()
+            } else {
+                vec![value.trim()]
+            };
+
+            for url in candidates {
+                if url.len() > 7 && url[..7].eq_ignore_ascii_case("http://") {
+                    urls.push((tag, attribute, url.to_owned()));
+                }
+            }
+        }
+    }
+
+    urls
+}
+
+/// Parses the attributes of a tag up to its closing `>`, returning them with lowercase names and
+/// the offset after the tag.
+pub(crate) fn parse_attributes(input: &[u8]) -> (Vec<(String, String)>, usize) {
+    let mut attributes = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while input
+            .get(i)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b'/')
+        {
+            i += 1;
+        }
+        match input.get(i) {
+            None => return (attributes, i),
+            Some(b'>') => return (attributes, i + 1),
+            Some(_) => {}
+        }
+
+        let name_start = i;
+        while input
+            .get(i)
+            .is_some_and(|b| !b.is_ascii_whitespace() && !matches!(b, b'=' | b'>' | b'/'))
+        {
+            i += 1;
+        }
+        let name = String::from_utf8_lossy(&input[name_start..i]).to_ascii_lowercase();
+
+        while input.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+        if input.get(i) != Some(&b'=') {
+            attributes.push((name, String::new()));
+            continue;
+        }
+        i += 1;
+        while input.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+
+        let value = match input.get(i) {
+            Some(quote @ (b'"' | b'\'')) => {
+                let start = i + 1;
+                let len = input[start..]
+                    .iter()
+                    .position(|b| b == quote)
+                    .unwrap_or(input.len() - start);
+                i = (start + len + 1).min(input.len());
+                &input[start..start + len]
+            }
+            _ => {
+                let start = i;
+                while input
+                    .get(i)
+                    .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'>')
+                {
+                    i += 1;
+                }
+                &input[start..i]
+            }
+        };
+        attributes.push((name, String::from_utf8_lossy(value).into_owned()));
+    }
+}
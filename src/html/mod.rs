@@ -0,0 +1,1136 @@
+//! Rewrites outgoing HTML responses, enabled with the `html` feature.
+//!
+//! [`HtmlRewriteLayer`] adds the per-request [`CspNonce`] as `nonce` attribute to the `<script>`
+//! and `<style>` tags which templates mark as trusted with [`HtmlRewriteLayer::marker`], so
+//! templates work with a nonce-based CSP without passing the nonce to each of them. Bodies are
+//! rewritten chunk by chunk as they are streamed, so large responses are not buffered.
+//!
+//! It can also compute the hashes of the marked inline `<script>` and `<style>` blocks and add them
+//! to the CSP, enabling a hash-based CSP for server-rendered pages without build-time tooling.
+//!
+//! For deployments behind intermediaries which strip large response headers, the CSP can be
+//! delivered through a `<meta>` tag as well (see [`CspDelivery`]).
+//!
+//! Before tightening the CSP, a sample of the documents can be scanned for subresources loaded over
+//! plain `http://` (see [`MixedContentAudit`]).
+//!
+//! The layer has to be added outside of the [`crate::HelmetLayer`] which generates the nonce, and
+//! inside of any compression layer, as compressed bodies are passed through untouched.
+//!
+//! # Security
+//!
+//! A nonce-based CSP only protects against injected scripts if the nonce is given to the scripts
+//! of the application alone. Adding it to every `<script>` of a response would hand it to markup
+//! injected through reflected or stored XSS as well, making the CSP useless, and so would adding
+//! the hashes of every inline block to the CSP. Therefore only tags carrying the marker attribute
+//! `data-helmet-nonce` with the secret of the layer get the nonce or have their content hashed.
+//! The secret is removed from every tag before the response is sent, so it can't be read from a
+//! page and copied into injected markup; it must not be rendered anywhere else. Only render the
+//! marker into tags of templates, never into user-controlled content.
+//!
+//! `<script` and `<style` in comments, quoted attribute values and the content of other scripts and
+//! styles is not mistaken for a tag.
+//!
+//! ```
+//! # use std::convert::Infallible;
+//! # use std::future::{ready, Ready};
+//! # use std::task::{Context, Poll};
+//! # use http::header::CONTENT_TYPE;
+//! # use http::{Request, Response};
+//! # use http_body_util::BodyExt;
+//! # use tower_helmet::html::HtmlRewriteLayer;
+//! # use tower_helmet::{CspNonce, HelmetLayer};
+//! # use tower_layer::Layer;
+//! # use tower_service::Service;
+//! #
+//! # /// Serves the body of the request as HTML.
+//! # #[derive(Clone)]
+//! # struct Page;
+//! #
+//! # impl Service<Request<String>> for Page {
+//! #     type Response = Response<String>;
+//! #     type Error = Infallible;
+//! #     type Future = Ready<Result<Response<String>, Infallible>>;
+//! #
+//! #     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+//! #         Poll::Ready(Ok(()))
+//! #     }
+//! #
+//! #     fn call(&mut self, request: Request<String>) -> Self::Future {
+//! #         let mut response = Response::new(request.into_body());
+//! #         response.headers_mut().insert(CONTENT_TYPE, "text/html".parse().unwrap());
+//! #         ready(Ok(response))
+//! #     }
+//! # }
+//! #
+//! # fn serve(rewrite: &HtmlRewriteLayer, html: String) -> (Response<()>, String) {
+//! #     let mut helmet = HelmetLayer::with_defaults();
+//! #     helmet.enable_nonce();
+//! #     let mut service = rewrite.layer(helmet.layer(Page));
+//! #     let response = futures::executor::block_on(service.call(Request::new(html))).unwrap();
+//! #     let (parts, body) = response.into_parts();
+//! #     let body = futures::executor::block_on(body.collect()).unwrap().to_bytes();
+//! #     (Response::from_parts(parts, ()), String::from_utf8(body.to_vec()).unwrap())
+//! # }
+//! #
+//! # fn render(rewrite: &HtmlRewriteLayer, html: String) -> (CspNonce, String) {
+//! #     let (response, body) = serve(rewrite, html);
+//! #     (response.extensions().get::<CspNonce>().unwrap().clone(), body)
+//! # }
+//! let mut rewrite = HtmlRewriteLayer::new();
+//! rewrite.inject_nonce(true);
+//!
+//! let html = format!(
+//!     "<script {}>app()</script><!-- <script> --><script>injected()</script>",
+//!     rewrite.marker()
+//! );
+//! let (nonce, body) = render(&rewrite, html);
+//! assert_eq!(
+//!     body,
+//!     format!(
+//!         "<script nonce=\"{}\">app()</script><!-- <script> --><script>injected()</script>",
+//!         nonce
+//!     )
+//! );
+//!
+//! // forged markers are removed but don't get the nonce
+//! let (_, body) = render(&rewrite, "<script data-helmet-nonce=guess>x()</script>".into());
+//! assert_eq!(body, "<script>x()</script>");
+//!
+//! // markers are removed from all other tags as well, without adding a nonce
+//! let html = format!("<div {0}>hi</div><br {0}/>", rewrite.marker());
+//! let (_, body) = render(&rewrite, html);
+//! assert_eq!(body, "<div>hi</div><br/>");
+//!
+//! // the same goes for hashes
+//! let mut rewrite = HtmlRewriteLayer::new();
+//! rewrite.hash_inline(true);
+//! let html = format!(
+//!     "<script {0}>a()</script><script {0}>a()</script><script>injected()</script>",
+//!     rewrite.marker()
+//! );
+//! let (response, body) = serve(&rewrite, html);
+//! let csp = response.headers()["content-security-policy"].to_str().unwrap();
+//! let hash = "'sha256-qVpDBgj7bpq5hMAcGp3AOc79J3Y1Z4HvySTwKrWDoy4='";
+//! assert_eq!(csp.matches(hash).count(), 1);
+//! assert_eq!(csp.matches("'sha256-").count(), 1);
+//! assert_eq!(body, "<script>a()</script><script>a()</script><script>injected()</script>");
+//! ```
+//!
+//! # Limits
+//!
+//! Only responses with one of the configured content types (by default `text/html`) are rewritten.
+//! Bodies larger than the configured maximum size (by default 10 MiB) are passed through without
+//! adding nonces, only markers are still removed: if the `Content-Length` is known upfront (or the
+//! body is buffered for hashing), the nonce can optionally be removed from the CSP as well,
+//! otherwise nonces are simply not added anymore once the limit is reached. Tags split across
+//! chunks are held back until they are complete, up to 8 KiB; longer ones are dropped (with the
+//! content of a `<script>` or `<style>`), like an unfinished tag at the end of the body. Responses
+//! to `HEAD` requests and responses without a body (`204`, `304`) are passed through with their
+//! `Content-Length`, only getting cached hashes.
+//!
+//! ```
+//! # use std::convert::Infallible;
+//! # use std::future::{ready, Ready};
+//! # use std::task::{Context, Poll};
+//! # use bytes::Bytes;
+//! # use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+//! # use http::{Method, Request, Response, StatusCode};
+//! # use http_body::Frame;
+//! # use http_body_util::combinators::UnsyncBoxBody;
+//! # use http_body_util::{BodyExt, StreamBody};
+//! # use tower_helmet::html::HtmlRewriteLayer;
+//! # use tower_helmet::{CspNonce, HelmetLayer};
+//! # use tower_layer::Layer;
+//! # use tower_service::Service;
+//! #
+//! # type Body = UnsyncBoxBody<Bytes, Infallible>;
+//! #
+//! # /// Serves the chunks of the request as HTML, with the status and headers of the request.
+//! # #[derive(Clone)]
+//! # struct Page;
+//! #
+//! # impl Service<Request<Vec<String>>> for Page {
+//! #     type Response = Response<Body>;
+//! #     type Error = Infallible;
+//! #     type Future = Ready<Result<Response<Body>, Infallible>>;
+//! #
+//! #     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+//! #         Poll::Ready(Ok(()))
+//! #     }
+//! #
+//! #     fn call(&mut self, request: Request<Vec<String>>) -> Self::Future {
+//! #         let (parts, chunks) = request.into_parts();
+//! #         let frames = chunks.into_iter().map(|c| Ok(Frame::data(Bytes::from(c))));
+//! #         let mut response = Response::new(StreamBody::new(futures::stream::iter(frames)).boxed_unsync());
+//! #         *response.status_mut() = parts.extensions.get().copied().unwrap_or_default();
+//! #         *response.headers_mut() = parts.headers;
+//! #         response.headers_mut().insert(CONTENT_TYPE, "text/html".parse().unwrap());
+//! #         ready(Ok(response))
+//! #     }
+//! # }
+//! #
+//! # fn serve(rewrite: &HtmlRewriteLayer, request: Request<Vec<String>>) -> (Response<()>, String) {
+//! #     let mut helmet = HelmetLayer::with_defaults();
+//! #     helmet.enable_nonce();
+//! #     let mut service = rewrite.layer(helmet.layer(Page));
+//! #     let response = futures::executor::block_on(service.call(request)).unwrap();
+//! #     let (parts, body) = response.into_parts();
+//! #     let body = futures::executor::block_on(body.collect()).unwrap().to_bytes();
+//! #     (Response::from_parts(parts, ()), String::from_utf8(body.to_vec()).unwrap())
+//! # }
+//! #
+//! # fn render(rewrite: &HtmlRewriteLayer, chunks: &[&str]) -> String {
+//! #     let chunks = chunks.iter().map(|c| c.to_string()).collect();
+//! #     let (response, body) = serve(rewrite, Request::new(chunks));
+//! #     let nonce = response.extensions().get::<CspNonce>().unwrap();
+//! #     body.replace(nonce.as_str(), "n")
+//! # }
+//! let mut rewrite = HtmlRewriteLayer::new();
+//! rewrite.inject_nonce(true).marker_secret("4ZjdPNTkKd8ftrFm");
+//!
+//! // a tag and its marker split across chunks, in uppercase
+//! let chunks = ["<p>a</p><SCR", "IPT DATA-HELMET-NONCE=\"4ZjdPNTk", "Kd8ftrFm\">b()</SCRIPT>"];
+//! assert_eq!(render(&rewrite, &chunks), "<p>a</p><SCRIPT nonce=\"n\">b()</SCRIPT>");
+//!
+//! // `<script` in comments, attribute values and scripts is no tag, even if split
+//! let chunks = [
+//!     "<!-- <script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\"> -",
+//!     "-><a title=\"<script data-helmet-nonce='4ZjdPNTkKd8ftrFm'>\">",
+//!     "<script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\" data-x=\"a>b\">",
+//!     "let s = '<style data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\">';</scr",
+//!     "ipt>",
+//! ];
+//! assert_eq!(
+//!     render(&rewrite, &chunks),
+//!     "<!-- <script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\"> -->\
+//!      <a title=\"<script data-helmet-nonce='4ZjdPNTkKd8ftrFm'>\">\
+//!      <script nonce=\"n\" data-x=\"a>b\">\
+//!      let s = '<style data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\">';</script>"
+//! );
+//!
+//! // a marked tag too long to be held back is dropped with its content, and so is an unfinished
+//! // tag at the end, as their markers can't be removed
+//! let long = "a".repeat(9000);
+//! let long = format!("<script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\" data-x='{}", long);
+//! let chunks = ["<p>a</p>", &long, "'>d()</script><p>b</p>", "<div data-helmet-nonce=\"4Zj"];
+//! assert_eq!(render(&rewrite, &chunks), "<p>a</p></script><p>b</p>");
+//!
+//! // beyond the maximum size, nonces aren't added but markers are still removed
+//! let mut small = rewrite.clone();
+//! small.max_size(16);
+//! let chunks = ["<p>0123456789</p>", "<script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\">c()</script>"];
+//! assert_eq!(render(&small, &chunks), "<p>0123456789</p><script>c()</script>");
+//!
+//! // documents sharing an `ETag` have their own cached hashes
+//! let mut cached = rewrite.clone();
+//! cached.hash_inline(true).cache_hashes(16);
+//! let csp = |path: &str, script: &str| {
+//!     let html = format!("<script data-helmet-nonce=\"4ZjdPNTkKd8ftrFm\">{}</script>", script);
+//!     let mut request = Request::new(vec![html]);
+//!     *request.uri_mut() = path.parse().unwrap();
+//!     request.headers_mut().insert("etag", "\"v1\"".parse().unwrap());
+//!     let (response, _) = serve(&cached, request);
+//!     response.headers()["content-security-policy"].to_str().unwrap().to_owned()
+//! };
+//! csp("/a", "a()");
+//! assert!(csp("/b", "b()").contains("'sha256-cf242PFc/kb7xD0Qzhsmd9vvRkc+cWFmbi+k+YvtdKc='"));
+//!
+//! // bodiless responses keep their `Content-Length`
+//! let mut request = Request::new(vec![]);
+//! *request.method_mut() = Method::HEAD;
+//! request.headers_mut().insert(CONTENT_LENGTH, 1024.into());
+//! assert_eq!(serve(&rewrite, request).0.headers()[CONTENT_LENGTH], "1024");
+//!
+//! let mut request = Request::new(vec![]);
+//! request.extensions_mut().insert(StatusCode::NOT_MODIFIED);
+//! request.headers_mut().insert(CONTENT_LENGTH, 1024.into());
+//! assert_eq!(serve(&rewrite, request).0.headers()[CONTENT_LENGTH], "1024");
+//! ```
+//!
+//! ```
+//! use tower_helmet::html::HtmlRewriteLayer;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut helmet = HelmetLayer::with_defaults();
+//! helmet.enable_nonce();
+//!
+//! let mut rewrite = HtmlRewriteLayer::new();
+//! rewrite.inject_nonce(true).marker_secret("4ZjdPNTkKd8ftrFm");
+//!
+//! // rendered by a template
+//! let html = format!("<script {}>init()</script>", rewrite.marker());
+//! assert_eq!(html, r#"<script data-helmet-nonce="4ZjdPNTkKd8ftrFm">init()</script>"#);
+//!
+//! // let app = Router::new().layer(helmet).layer(rewrite);
+//! ```
+
+mod cache;
+pub(crate) mod mixed_content;
+pub(crate) mod rewrite;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::ready;
+use http::header::{
+    CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_SECURITY_POLICY, CONTENT_TYPE, ETAG, HOST,
+};
+use http::response::Parts;
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use sha2::{Digest, Sha256};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::CspNonce;
+pub use mixed_content::MixedContentAudit;
+use mixed_content::MixedContentScanner;
+
+/// Default for [`HtmlRewriteLayer::max_size`], 10 MiB.
+const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+/// Directives which browsers ignore when the CSP is delivered through a `<meta>` tag.
+const META_UNSUPPORTED: [&str; 4] = ["frame-ancestors", "report-to", "report-uri", "sandbox"];
+
+/// How the `Content-Security-Policy` of rewritten responses is delivered.
+///
+/// A `<meta>` tag requires the document to be buffered completely (up to
+/// [`HtmlRewriteLayer::max_size`]). It is inserted right after the opening `<head>` tag, documents
+/// without one and bodies exceeding the maximum size keep the header. `frame-ancestors`,
+/// `report-to`, `report-uri` and `sandbox` are not supported in `<meta>` tags and therefore removed
+/// from it; `Content-Security-Policy-Report-Only` is always sent as header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CspDelivery {
+    /// Only as header.
+    #[default]
+    Header,
+    /// As header and additionally through a `<meta>` tag.
+    HeaderAndMeta,
+    /// Only through a `<meta>` tag, the header is removed.
+    Meta,
+}
+
+/// Layer rewriting HTML responses, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct HtmlRewriteLayer {
+    inject_nonce: bool,
+    marker_secret: Arc<[u8]>,
+    hash_inline: bool,
+    cache: Option<Arc<cache::HashCache>>,
+    delivery: CspDelivery,
+    mixed_content: Option<Arc<MixedContentAudit>>,
+    content_types: Vec<String>,
+    max_size: usize,
+    remove_nonce_on_overflow: bool,
+}
+
+impl Default for HtmlRewriteLayer {
+    fn default() -> Self {
+        HtmlRewriteLayer {
+            inject_nonce: false,
+            marker_secret: random_secret(),
+            hash_inline: false,
+            cache: None,
+            delivery: CspDelivery::default(),
+            mixed_content: None,
+            content_types: vec!["text/html".to_owned()],
+            max_size: DEFAULT_MAX_SIZE,
+            remove_nonce_on_overflow: false,
+        }
+    }
+}
+
+impl HtmlRewriteLayer {
+    /// Creates a layer which doesn't rewrite anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to add the hashes of the inline `<script>` and `<style>` blocks marked with
+    /// [`HtmlRewriteLayer::marker`] to the `script-src` and `style-src` directives of the
+    /// response's CSP. Like nonces, hashes of all inline blocks would allow injected ones as well,
+    /// see the [module docs](self#security).
+    ///
+    /// As the header has to be sent before the body, responses are buffered completely (up to
+    /// [`HtmlRewriteLayer::max_size`]) in this mode. Note that hashes make browsers ignore
+    /// `'unsafe-inline'` in the same directive.
+    pub fn hash_inline(&mut self, hash: bool) -> &mut Self {
+        self.hash_inline = hash;
+        self
+    }
+
+    /// Caches the hashes computed by [`HtmlRewriteLayer::hash_inline`] for up to `capacity`
+    /// documents, so repeated serves of the same document don't redo the work.
+    ///
+    /// Documents are identified by the authority and path of the request together with their
+    /// strong `ETag`, as different documents may share an `ETag`. If it is cached, the body
+    /// doesn't even have to be buffered anymore and is streamed instead. Documents without one are
+    /// identified by the hash of their content, which saves scanning them. Rewritten bodies
+    /// themselves are not cached: hashing doesn't modify them and the injected nonce is different
+    /// for every request.
+    pub fn cache_hashes(&mut self, capacity: usize) -> &mut Self {
+        self.cache = Some(Arc::new(cache::HashCache::new(capacity)));
+        self
+    }
+
+    /// How the CSP is delivered, see [`CspDelivery`].
+    pub fn csp_delivery(&mut self, delivery: CspDelivery) -> &mut Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Scans documents for subresources loaded over plain `http://`, see [`MixedContentAudit`].
+    pub fn audit_mixed_content(&mut self, audit: MixedContentAudit) -> &mut Self {
+        self.mixed_content = Some(Arc::new(audit));
+        self
+    }
+
+    /// Media types (without parameters, such as `text/html`) of the responses to rewrite.
+    pub fn content_types<I, T>(&mut self, content_types: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.content_types = content_types
+            .into_iter()
+            .map(|t| t.into().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Maximum size of a body in bytes which is rewritten. Larger bodies are passed through
+    /// unmodified, except for removing markers.
+    pub fn max_size(&mut self, max_size: usize) -> &mut Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Whether to remove the nonce from the CSP of responses which are passed through because of
+    /// their size, so inline scripts and styles without a nonce are treated as if nonces were not
+    /// enabled. Only possible when the `Content-Length` is known before the body is read.
+    pub fn remove_nonce_on_overflow(&mut self, remove: bool) -> &mut Self {
+        self.remove_nonce_on_overflow = remove;
+        self
+    }
+
+    /// Whether to add the request's [`CspNonce`] to the `<script>` and `<style>` tags marked with
+    /// [`HtmlRewriteLayer::marker`].
+    pub fn inject_nonce(&mut self, inject: bool) -> &mut Self {
+        self.inject_nonce = inject;
+        self
+    }
+
+    /// The marker attribute, such as `data-helmet-nonce="..."`, to render into the opening
+    /// `<script>` and `<style>` tags of templates which are trusted, see the
+    /// [module docs](self#security).
+    pub fn marker(&self) -> String {
+        format!(
+            "{}=\"{}\"",
+            String::from_utf8_lossy(rewrite::MARKER),
+            String::from_utf8_lossy(&self.marker_secret)
+        )
+    }
+
+    /// The secret of the [`HtmlRewriteLayer::marker`], for templates which are configured
+    /// separately from the layer or several instances of the application. By default, a random
+    /// secret is generated for every layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than 16 characters or contains other characters than ASCII
+    /// letters, digits, `-` and `_`.
+    pub fn marker_secret(&mut self, secret: &str) -> &mut Self {
+        assert!(
+            secret.len() >= 16
+                && secret
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            "invalid marker secret"
+        );
+        self.marker_secret = secret.as_bytes().into();
+        self
+    }
+}
+
+/// A random secret for [`HtmlRewriteLayer::marker`].
+fn random_secret() -> Arc<[u8]> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("failed to generate a random marker secret");
+    URL_SAFE_NO_PAD.encode(bytes).into_bytes().into()
+}
+
+impl<S> Layer<S> for HtmlRewriteLayer {
+    type Service = HtmlRewriteService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        HtmlRewriteService {
+            inner: service,
+            config: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HtmlRewriteService<S> {
+    inner: S,
+    config: Arc<HtmlRewriteLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for HtmlRewriteService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Body,
+{
+    type Response = Response<HtmlBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        ResponseFuture {
+            head: request.method() == Method::HEAD,
+            document: self.config.cache.as_ref().map(|_| document(&request)),
+            state: State::Inner {
+                future: self.inner.call(request),
+            },
+            config: self.config.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`HtmlRewriteService`].
+    pub struct ResponseFuture<F, B> {
+        #[pin]
+        state: State<F, B>,
+
+        config: Arc<HtmlRewriteLayer>,
+        head: bool,
+        // the authority and path of the request, identifying cached documents
+        document: Option<String>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<F, B> {
+        Inner {
+            #[pin]
+            future: F,
+        },
+        /// The body is buffered to compute the hashes of its inline blocks or inject the CSP.
+        Buffering {
+            parts: Option<Parts>,
+            body: Option<Pin<Box<B>>>,
+            buffer: BytesMut,
+            trailers: Option<HeaderMap>,
+            nonce: Option<CspNonce>,
+            audit: Option<Arc<MixedContentAudit>>,
+        },
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F, ResBody>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: Body,
+{
+    type Output = Result<Response<HtmlBody<ResBody>>, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let this = self.as_mut().project();
+            let config = this.config;
+            let head = *this.head;
+            let document = this.document.as_deref();
+
+            match this.state.project() {
+                StateProj::Inner { future } => {
+                    let res = ready!(future.poll(cx)?);
+                    if head || is_bodiless(res.status()) {
+                        // nothing to rewrite, and the `Content-Length` may describe another body
+                        let (mut parts, body) = res.into_parts();
+                        if let Some(hashes) = config.cached_by_etag(document, &parts.headers) {
+                            add_hashes(&mut parts.headers, &hashes);
+                        }
+                        let body = HtmlBody::Passthrough { body };
+                        return Poll::Ready(Ok(Response::from_parts(parts, body)));
+                    }
+                    if !config.is_rewritable(res.headers()) {
+                        return Poll::Ready(Ok(res.map(|body| HtmlBody::Passthrough { body })));
+                    }
+
+                    let nonce = res
+                        .extensions()
+                        .get::<CspNonce>()
+                        .filter(|_| config.inject_nonce)
+                        .cloned();
+                    let audit = config
+                        .mixed_content
+                        .as_ref()
+                        .filter(|audit| audit.sampled())
+                        .cloned();
+                    let (parts, body) = res.into_parts();
+
+                    if config.hash_inline || config.delivery != CspDelivery::Header {
+                        let cached = config
+                            .cached_by_etag(document, &parts.headers)
+                            .filter(|_| config.delivery == CspDelivery::Header);
+                        if let Some(hashes) = cached {
+                            let mut parts = parts;
+                            add_hashes(&mut parts.headers, &hashes);
+                            return Poll::Ready(Ok(config.stream(parts, body, nonce, audit)));
+                        }
+
+                        let state = State::Buffering {
+                            parts: Some(parts),
+                            body: Some(Box::pin(body)),
+                            buffer: BytesMut::new(),
+                            trailers: None,
+                            nonce,
+                            audit,
+                        };
+                        self.as_mut().project().state.set(state);
+                        continue;
+                    }
+
+                    return Poll::Ready(Ok(config.stream(parts, body, nonce, audit)));
+                }
+                StateProj::Buffering {
+                    parts,
+                    body,
+                    buffer,
+                    trailers,
+                    nonce,
+                    audit,
+                } => {
+                    let inner = body.as_mut().expect("polled after completion");
+                    while let Some(frame) = ready!(inner.as_mut().poll_frame(cx)) {
+                        let frame = match frame {
+                            Ok(frame) => frame,
+                            Err(error) => {
+                                // the error can only be reported through the body
+                                let mut parts = parts.take().expect("polled after completion");
+                                parts.headers.remove(CONTENT_LENGTH);
+                                let data = match config.removes_markers() {
+                                    true => {
+                                        rewrite::inject_nonce(buffer, None, &config.marker_secret)
+                                            .into()
+                                    }
+                                    false => buffer.split().freeze(),
+                                };
+                                let body = HtmlBody::Buffered {
+                                    data: Some(data),
+                                    trailers: None,
+                                    error: Some(error),
+                                };
+                                return Poll::Ready(Ok(Response::from_parts(parts, body)));
+                            }
+                        };
+
+                        match frame.into_data() {
+                            Ok(mut data) => {
+                                buffer.extend_from_slice(&data.copy_to_bytes(data.remaining()));
+                                if buffer.len() > config.max_size {
+                                    let parts = parts.take().expect("polled after completion");
+                                    if let Some(audit) = audit {
+                                        audit.scan(&buffer[..config.max_size]);
+                                    }
+                                    return Poll::Ready(Ok(config.overflow(
+                                        parts,
+                                        buffer.split().freeze(),
+                                        body.take().expect("polled after completion"),
+                                        nonce.as_ref(),
+                                    )));
+                                }
+                            }
+                            Err(frame) => {
+                                if let Ok(t) = frame.into_trailers() {
+                                    *trailers = Some(t);
+                                }
+                            }
+                        }
+                    }
+
+                    let parts = parts.take().expect("polled after completion");
+                    return Poll::Ready(Ok(config.buffered(
+                        parts,
+                        document,
+                        buffer.split().freeze(),
+                        trailers.take(),
+                        nonce.as_ref(),
+                        audit.as_ref(),
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl HtmlRewriteLayer {
+    /// Whether documents may contain markers, which are removed from them.
+    fn removes_markers(&self) -> bool {
+        self.inject_nonce || self.hash_inline
+    }
+
+    /// Only uncompressed responses of the configured content types are rewritten.
+    fn is_rewritable(&self, headers: &HeaderMap) -> bool {
+        let media_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase());
+        let is_encoded = headers
+            .get(CONTENT_ENCODING)
+            .is_some_and(|v| v.as_bytes() != b"identity");
+
+        media_type.is_some_and(|t| self.content_types.contains(&t)) && !is_encoded
+    }
+
+    /// Rewrites the body while it is streamed.
+    fn stream<B: Body>(
+        &self,
+        mut parts: Parts,
+        body: B,
+        nonce: Option<CspNonce>,
+        audit: Option<Arc<MixedContentAudit>>,
+    ) -> Response<HtmlBody<B>> {
+        let content_length = parts
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+
+        let scanner = audit.map(MixedContentScanner::new);
+        let body = match self.removes_markers() {
+            true => {
+                let too_large = content_length.is_some_and(|len| len > self.max_size);
+                if let (Some(nonce), true) = (&nonce, too_large && self.remove_nonce_on_overflow) {
+                    crate::nonce::remove(&mut parts.headers, nonce);
+                }
+                let mut injector = rewrite::NonceInjector::new(nonce, self.marker_secret.clone());
+                if too_large {
+                    // the markers are still removed, so their secret isn't revealed
+                    injector.stop();
+                }
+
+                // the rewritten body has a different length
+                parts.headers.remove(CONTENT_LENGTH);
+                HtmlBody::Rewrite {
+                    body,
+                    injector,
+                    scanner: scanner.filter(|_| !too_large),
+                    remaining: self.max_size,
+                    bypass: too_large,
+                    trailers: None,
+                    done: false,
+                }
+            }
+            false => match scanner {
+                Some(scanner) => HtmlBody::Scanned {
+                    body,
+                    scanner,
+                    remaining: self.max_size,
+                },
+                None => HtmlBody::Passthrough { body },
+            },
+        };
+
+        Response::from_parts(parts, body)
+    }
+
+    /// Rewrites a completely buffered body, adds the hashes of its inline blocks to the CSP and
+    /// delivers it as configured.
+    fn buffered<B: Body>(
+        &self,
+        mut parts: Parts,
+        document: Option<&str>,
+        data: Bytes,
+        trailers: Option<HeaderMap>,
+        nonce: Option<&CspNonce>,
+        audit: Option<&Arc<MixedContentAudit>>,
+    ) -> Response<HtmlBody<B>> {
+        let hashes = match &self.cache {
+            _ if !self.hash_inline => rewrite::InlineHashes::default(),
+            Some(cache) => {
+                let key = etag_key(document, &parts.headers)
+                    .unwrap_or_else(|| cache::Key::Content(Sha256::digest(&data).into()));
+                cache.get(&key).unwrap_or_else(|| {
+                    let hashes = rewrite::inline_hashes(&data, Some(&self.marker_secret));
+                    cache.insert(key, hashes.clone());
+                    hashes
+                })
+            }
+            None => rewrite::inline_hashes(&data, Some(&self.marker_secret)),
+        };
+        add_hashes(&mut parts.headers, &hashes);
+        if let Some(audit) = audit {
+            audit.scan(&data);
+        }
+
+        let data = match self.removes_markers() {
+            true => Bytes::from(rewrite::inject_nonce(&data, nonce, &self.marker_secret)),
+            false => data,
+        };
+        let data = match self.inject_meta(&mut parts.headers, &data) {
+            Some(html) => Bytes::from(html),
+            None => data,
+        };
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(data.len()));
+
+        Response::from_parts(
+            parts,
+            HtmlBody::Buffered {
+                data: Some(data),
+                trailers,
+                error: None,
+            },
+        )
+    }
+
+    /// Inserts the CSP into the document as `<meta>` tag, according to the configured delivery.
+    fn inject_meta(&self, headers: &mut HeaderMap, html: &[u8]) -> Option<Vec<u8>> {
+        if self.delivery == CspDelivery::Header {
+            return None;
+        }
+
+        let policy = headers.get(CONTENT_SECURITY_POLICY)?.to_str().ok()?;
+        let policy = crate::header::parse_directives(policy)
+            .into_iter()
+            .filter(|(name, _)| !META_UNSUPPORTED.contains(&name.to_ascii_lowercase().as_str()))
+            .map(|(name, values)| {
+                std::iter::once(name)
+                    .chain(values)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        let html = rewrite::inject_meta(html, &policy)?;
+        if self.delivery == CspDelivery::Meta {
+            headers.remove(CONTENT_SECURITY_POLICY);
+        }
+        Some(html)
+    }
+
+    /// The cached hashes of the `document`, if it has a strong `ETag` which is cached.
+    fn cached_by_etag(
+        &self,
+        document: Option<&str>,
+        headers: &HeaderMap,
+    ) -> Option<rewrite::InlineHashes> {
+        let cache = self.cache.as_ref()?;
+        cache.get(&etag_key(document, headers)?)
+    }
+
+    /// Passes a body which exceeded the maximum size while buffering through unmodified.
+    fn overflow<B: Body>(
+        &self,
+        mut parts: Parts,
+        prefix: Bytes,
+        body: Pin<Box<B>>,
+        nonce: Option<&CspNonce>,
+    ) -> Response<HtmlBody<B>> {
+        if let (Some(nonce), true) = (nonce, self.remove_nonce_on_overflow) {
+            crate::nonce::remove(&mut parts.headers, nonce);
+        }
+
+        // the markers are still removed, so their secret isn't revealed
+        let mut injector = self
+            .removes_markers()
+            .then(|| rewrite::NonceInjector::new(None, self.marker_secret.clone()));
+        let prefix = match &mut injector {
+            Some(injector) => {
+                parts.headers.remove(CONTENT_LENGTH);
+                injector.push(&prefix)
+            }
+            None => prefix,
+        };
+
+        let body = HtmlBody::Prefixed {
+            prefix: Some(prefix),
+            body,
+            injector,
+            trailers: None,
+            done: false,
+        };
+        Response::from_parts(parts, body)
+    }
+}
+
+/// Responses which never have a body.
+fn is_bodiless(status: StatusCode) -> bool {
+    status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+}
+
+/// The authority and path of the document requested with `request`.
+fn document<B>(request: &Request<B>) -> String {
+    let authority = match request.headers().get(HOST) {
+        Some(host) => String::from_utf8_lossy(host.as_bytes()).to_ascii_lowercase(),
+        None => request
+            .uri()
+            .authority()
+            .map(|a| a.as_str().to_ascii_lowercase())
+            .unwrap_or_default(),
+    };
+    let path = request.uri().path_and_query().map_or("/", |p| p.as_str());
+
+    format!("{}{}", authority, path)
+}
+
+/// The cache key of the `document` by its `ETag`. Different documents may have the same `ETag`,
+/// so it is only unique together with the document. Weak `ETag`s don't guarantee byte-identical
+/// bodies, so only strong ones are used.
+fn etag_key(document: Option<&str>, headers: &HeaderMap) -> Option<cache::Key> {
+    let etag = headers
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))?;
+
+    Some(cache::Key::ETag {
+        document: document?.to_owned(),
+        etag: etag.as_bytes().to_vec(),
+    })
+}
+
+fn add_hashes(headers: &mut HeaderMap, hashes: &rewrite::InlineHashes) {
+    crate::nonce::add_sources(headers, &["script-src"], &hashes.scripts);
+    crate::nonce::add_sources(headers, &["style-src"], &hashes.styles);
+}
+
+pin_project! {
+    /// Response body of [`HtmlRewriteService`].
+    #[project = HtmlBodyProj]
+    pub enum HtmlBody<B: Body> {
+        Passthrough {
+            #[pin]
+            body: B,
+        },
+        Rewrite {
+            #[pin]
+            body: B,
+            injector: rewrite::NonceInjector,
+            scanner: Option<MixedContentScanner>,
+            remaining: usize,
+            bypass: bool,
+            trailers: Option<HeaderMap>,
+            done: bool,
+        },
+        Scanned {
+            #[pin]
+            body: B,
+            scanner: MixedContentScanner,
+            remaining: usize,
+        },
+        Buffered {
+            data: Option<Bytes>,
+            trailers: Option<HeaderMap>,
+            error: Option<B::Error>,
+        },
+        Prefixed {
+            prefix: Option<Bytes>,
+            body: Pin<Box<B>>,
+            injector: Option<rewrite::NonceInjector>,
+            trailers: Option<HeaderMap>,
+            done: bool,
+        },
+    }
+}
+
+impl<B> Body for HtmlBody<B>
+where
+    B: Body,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.project() {
+            HtmlBodyProj::Passthrough { body } => poll_bytes(body, cx),
+            HtmlBodyProj::Rewrite {
+                body,
+                injector,
+                scanner,
+                remaining,
+                bypass,
+                trailers,
+                done,
+            } => {
+                let was_done = *done;
+                let poll = poll_injected(body, injector, trailers, done, cx, |data, injector| {
+                    if *bypass {
+                        return;
+                    }
+                    if data.len() > *remaining {
+                        // too large, only remove markers from here on
+                        *bypass = true;
+                        if let Some(scanner) = scanner.as_mut() {
+                            scanner.abort();
+                        }
+                        injector.stop();
+                    } else {
+                        *remaining -= data.len();
+                        if let Some(scanner) = scanner.as_mut() {
+                            scanner.push(data);
+                        }
+                    }
+                });
+                if let (false, true, Some(scanner)) = (was_done, *done, scanner) {
+                    scanner.finish();
+                }
+                poll
+            }
+            HtmlBodyProj::Scanned {
+                body,
+                scanner,
+                remaining,
+            } => {
+                let frame = ready!(poll_bytes(body, cx));
+                match &frame {
+                    Some(Ok(frame)) => {
+                        if let Some(data) = frame.data_ref() {
+                            if data.len() > *remaining {
+                                *remaining = 0;
+                                scanner.abort();
+                            } else {
+                                *remaining -= data.len();
+                                scanner.push(data);
+                            }
+                        }
+                    }
+                    Some(Err(_)) => {}
+                    None => scanner.finish(),
+                }
+                Poll::Ready(frame)
+            }
+            HtmlBodyProj::Buffered {
+                data,
+                trailers,
+                error,
+            } => {
+                if let Some(data) = data.take() {
+                    return Poll::Ready(Some(Ok(Frame::data(data))));
+                }
+                if let Some(error) = error.take() {
+                    return Poll::Ready(Some(Err(error)));
+                }
+                Poll::Ready(trailers.take().map(|t| Ok(Frame::trailers(t))))
+            }
+            HtmlBodyProj::Prefixed {
+                prefix,
+                body,
+                injector,
+                trailers,
+                done,
+            } => match (prefix.take(), injector) {
+                (Some(prefix), _) => Poll::Ready(Some(Ok(Frame::data(prefix)))),
+                (None, Some(injector)) => {
+                    poll_injected(body.as_mut(), injector, trailers, done, cx, |_, _| {})
+                }
+                (None, None) => poll_bytes(body.as_mut(), cx),
+            },
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match self {
+            HtmlBody::Passthrough { body } | HtmlBody::Scanned { body, .. } => body.is_end_stream(),
+            HtmlBody::Rewrite { done, trailers, .. } => *done && trailers.is_none(),
+            HtmlBody::Buffered {
+                data,
+                trailers,
+                error,
+            } => data.is_none() && trailers.is_none() && error.is_none(),
+            HtmlBody::Prefixed {
+                prefix,
+                body,
+                injector,
+                trailers,
+                done,
+            } => match injector {
+                Some(_) => prefix.is_none() && *done && trailers.is_none(),
+                None => prefix.is_none() && body.is_end_stream(),
+            },
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self {
+            HtmlBody::Passthrough { body } | HtmlBody::Scanned { body, .. } => body.size_hint(),
+            HtmlBody::Buffered { data, .. } => {
+                SizeHint::with_exact(data.as_ref().map_or(0, |d| d.len() as u64))
+            }
+            _ => SizeHint::default(),
+        }
+    }
+}
+
+/// Polls the rest of `body` through `injector`, passing every chunk to `inspect` before it is
+/// rewritten.
+fn poll_injected<B: Body>(
+    mut body: Pin<&mut B>,
+    injector: &mut rewrite::NonceInjector,
+    trailers: &mut Option<HeaderMap>,
+    done: &mut bool,
+    cx: &mut Context<'_>,
+    mut inspect: impl FnMut(&[u8], &mut rewrite::NonceInjector),
+) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+    if *done {
+        return Poll::Ready(trailers.take().map(|t| Ok(Frame::trailers(t))));
+    }
+
+    while let Some(frame) = ready!(body.as_mut().poll_frame(cx)?) {
+        match frame.into_data() {
+            Ok(mut data) => {
+                let data = data.copy_to_bytes(data.remaining());
+                inspect(&data, injector);
+                let out = injector.push(&data);
+                if !out.is_empty() {
+                    return Poll::Ready(Some(Ok(Frame::data(out))));
+                }
+            }
+            Err(frame) => {
+                if let Ok(t) = frame.into_trailers() {
+                    *trailers = Some(t);
+                    break;
+                }
+            }
+        }
+    }
+
+    *done = true;
+    let out = injector.finish();
+    if out.is_empty() {
+        Poll::Ready(trailers.take().map(|t| Ok(Frame::trailers(t))))
+    } else {
+        Poll::Ready(Some(Ok(Frame::data(out))))
+    }
+}
+
+/// Polls the next frame of `body`, converting its data into [`Bytes`].
+fn poll_bytes<B: Body>(
+    body: Pin<&mut B>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+    let frame = ready!(body.poll_frame(cx)?);
+    Poll::Ready(
+        frame.map(|frame| Ok(frame.map_data(|mut data| data.copy_to_bytes(data.remaining())))),
+    )
+}
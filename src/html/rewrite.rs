@@ -0,0 +1,583 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::{Buf, Bytes, BytesMut};
+use sha2::{Digest, Sha256};
+
+use crate::CspNonce;
+
+/// Maximum length of an unfinished tag held back between chunks. Longer ones are dropped, as their
+/// markers can't be removed, together with the content of a `<script>` or `<style>` element.
+const MAX_PENDING: usize = 8 * 1024;
+
+/// Tags whose inline content is governed by `script-src` and `style-src`.
+const TAGS: [&[u8]; 2] = [b"script", b"style"];
+
+/// Attribute marking `<script>` and `<style>` tags as trusted, see
+/// [`super::HtmlRewriteLayer::marker`].
+pub(crate) const MARKER: &[u8] = b"data-helmet-nonce";
+
+/// Adds a `nonce` attribute to every `<script>` and `<style>` tag of `html` which is marked with
+/// `secret` (see [`rewrite_tag`]) and removes the markers of all tags.
+pub(crate) fn inject_nonce(html: &[u8], nonce: Option<&CspNonce>, secret: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(html.len() + 64);
+    rewrite(
+        &mut Tokenizer::default(),
+        html,
+        true,
+        nonce,
+        secret,
+        &mut out,
+    );
+    out
+}
+
+/// Rewrites the tokens of `input` into `out`, returning how many bytes were consumed. Without
+/// `eof`, an unfinished token at the end is left for the next call.
+fn rewrite(
+    tokenizer: &mut Tokenizer,
+    input: &[u8],
+    eof: bool,
+    nonce: Option<&CspNonce>,
+    secret: &[u8],
+    out: &mut Vec<u8>,
+) -> usize {
+    let mut offset = 0;
+    while let Some((token, len)) = tokenizer.next(&input[offset..], eof) {
+        let bytes = &input[offset..offset + len];
+        match token {
+            Token::Open { name_end, .. } => rewrite_tag(bytes, name_end, nonce, secret, out),
+            Token::Tag { name_end } => rewrite_tag(bytes, name_end, None, secret, out),
+            Token::Other | Token::Content => out.extend_from_slice(bytes),
+            Token::Dropped => {}
+        }
+        offset += len;
+    }
+    offset
+}
+
+/// Writes the opening tag `tag` (attributes starting at `name_end`) to `out` without its
+/// markers. If it is marked with `secret`, it gets the `nonce`, unless it has one already.
+fn rewrite_tag(
+    tag: &[u8],
+    name_end: usize,
+    nonce: Option<&CspNonce>,
+    secret: &[u8],
+    out: &mut Vec<u8>,
+) {
+    let attributes = attributes(&tag[name_end..]);
+    let markers: Vec<&Attribute> = attributes
+        .iter()
+        .filter(|a| a.name.eq_ignore_ascii_case(MARKER))
+        .collect();
+    if markers.is_empty() {
+        out.extend_from_slice(tag);
+        return;
+    }
+
+    out.extend_from_slice(&tag[..name_end]);
+    let trusted = markers.iter().any(|m| constant_time_eq(m.value, secret));
+    let has_nonce = attributes
+        .iter()
+        .any(|a| a.name.eq_ignore_ascii_case(b"nonce"));
+    if let (Some(nonce), true, false) = (nonce, trusted, has_nonce) {
+        out.extend_from_slice(format!(" nonce=\"{}\"", nonce).as_bytes());
+    }
+
+    let mut offset = name_end;
+    for marker in markers {
+        out.extend_from_slice(&tag[offset..name_end + marker.start]);
+        offset = name_end + marker.end;
+    }
+    out.extend_from_slice(&tag[offset..]);
+}
+
+/// Compares the marker with the secret without revealing where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Streaming variant of [`inject_nonce`]. Chunks are rewritten as they arrive, only an unfinished
+/// tag at the end of a chunk is held back until its closing `>` arrives.
+#[derive(Debug)]
+pub(crate) struct NonceInjector {
+    nonce: Option<CspNonce>,
+    secret: Arc<[u8]>,
+    tokenizer: Tokenizer,
+    pending: BytesMut,
+}
+
+impl NonceInjector {
+    pub(crate) fn new(nonce: Option<CspNonce>, secret: Arc<[u8]>) -> Self {
+        NonceInjector {
+            nonce,
+            secret,
+            tokenizer: Tokenizer::default(),
+            pending: BytesMut::new(),
+        }
+    }
+
+    /// Rewrites the next chunk, returning everything that is ready to be sent.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.pending.extend_from_slice(chunk);
+
+        let mut out = Vec::with_capacity(self.pending.len() + 64);
+        let consumed = rewrite(
+            &mut self.tokenizer,
+            &self.pending,
+            false,
+            self.nonce.as_ref(),
+            &self.secret,
+            &mut out,
+        );
+        self.pending.advance(consumed);
+
+        if self.pending.len() > MAX_PENDING {
+            self.tokenizer.drop_tag(&self.pending);
+            self.pending.clear();
+        }
+
+        Bytes::from(out)
+    }
+
+    /// Stops adding the nonce, only removing the markers from here on.
+    pub(crate) fn stop(&mut self) {
+        self.nonce = None;
+    }
+
+    /// Returns whatever is still held back at the end of the body.
+    pub(crate) fn finish(&mut self) -> Bytes {
+        let mut out = Vec::with_capacity(self.pending.len());
+        rewrite(
+            &mut self.tokenizer,
+            &self.pending,
+            true,
+            self.nonce.as_ref(),
+            &self.secret,
+            &mut out,
+        );
+        self.pending.clear();
+        Bytes::from(out)
+    }
+}
+
+/// Where the [`Tokenizer`] is in the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Context {
+    #[default]
+    Markup,
+    /// Inside `<!-- ... -->`.
+    Comment,
+    /// Inside the content of the `<script>` or `<style>` element, which ends at its closing tag
+    /// only. The content of an element whose opening tag was dropped is dropped as well.
+    RawText { tag: &'static [u8], dropped: bool },
+    /// Inside the rest of a dropped tag, see [`Tokenizer::drop_tag`].
+    Dropped {
+        tag: Option<&'static [u8]>,
+        scan: TagScan,
+    },
+}
+
+/// A token found by the [`Tokenizer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// The opening tag of a `<script>` or `<style>` element, with its attributes starting at
+    /// `name_end`.
+    Open { tag: &'static [u8], name_end: usize },
+    /// Any other tag, with its attributes starting at `name_end`.
+    Tag { name_end: usize },
+    /// Part of the content of a `<script>` or `<style>` element.
+    Content,
+    /// Anything else, such as text and comments.
+    Other,
+    /// Part of a tag which is unfinished at the end of the document or too long to be held back,
+    /// and the content of its element. It must not be sent, as its markers can't be removed.
+    Dropped,
+}
+
+/// Splits HTML into [`Token`]s, possibly spread over several chunks.
+///
+/// Only what matters for `<script>` and `<style>` elements and the markers of all tags is
+/// tokenized: comments, quoted attribute values of all tags and the content of these elements are
+/// skipped, so `<script` in them doesn't start a tag.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tokenizer {
+    context: Context,
+}
+
+impl Tokenizer {
+    /// The next token at the start of `input` and its length. Returns `None` if `input` is empty or,
+    /// without `eof`, more input is needed to finish the token.
+    pub(crate) fn next(&mut self, input: &[u8], eof: bool) -> Option<(Token, usize)> {
+        if input.is_empty() {
+            return None;
+        }
+
+        match self.context {
+            Context::Markup => {
+                if input[0] != b'<' {
+                    let len = input.iter().position(|b| *b == b'<').unwrap_or(input.len());
+                    return Some((Token::Other, len));
+                }
+
+                if starts_with(input, b"<!--") {
+                    self.context = Context::Comment;
+                    return Some((Token::Other, 4));
+                }
+                let mut incomplete = !eof && b"<!--".starts_with(input);
+
+                for tag in TAGS {
+                    let name_end = 1 + tag.len();
+                    let name = &input[1..input.len().min(name_end)];
+                    if !name.eq_ignore_ascii_case(&tag[..name.len()]) {
+                        continue;
+                    }
+                    match input.get(name_end) {
+                        Some(b) if b.is_ascii_whitespace() || *b == b'>' || *b == b'/' => {
+                            return match tag_end(&input[name_end..]) {
+                                Some(end) => {
+                                    self.context = Context::RawText {
+                                        tag,
+                                        dropped: false,
+                                    };
+                                    Some((Token::Open { tag, name_end }, name_end + end))
+                                }
+                                None if eof => Some((Token::Dropped, input.len())),
+                                None => None,
+                            };
+                        }
+                        Some(_) => {}
+                        None => incomplete |= !eof,
+                    }
+                }
+
+                if incomplete {
+                    return None;
+                }
+
+                // any other tag, whose quoted attribute values may contain `<script`
+                let name_start = if input.get(1) == Some(&b'/') { 2 } else { 1 };
+                match input.get(name_start) {
+                    Some(b) if b.is_ascii_alphabetic() => match tag_end(&input[name_start..]) {
+                        Some(end) => {
+                            let name_len = input[name_start..]
+                                .iter()
+                                .position(|b| b.is_ascii_whitespace() || matches!(b, b'>' | b'/'))
+                                .unwrap_or(end);
+                            let name_end = name_start + name_len;
+                            Some((Token::Tag { name_end }, name_start + end))
+                        }
+                        None if eof => Some((Token::Dropped, input.len())),
+                        None => None,
+                    },
+                    None if !eof => None,
+                    _ => Some((Token::Other, 1)),
+                }
+            }
+            Context::Comment => match find(input, b"-->") {
+                Some(pos) => {
+                    self.context = Context::Markup;
+                    Some((Token::Other, pos + 3))
+                }
+                None if eof => Some((Token::Other, input.len())),
+                // the end may be split
+                None => Some((Token::Other, input.len().saturating_sub(2))).filter(|t| t.1 > 0),
+            },
+            Context::RawText { tag, dropped } => {
+                let content = if dropped {
+                    Token::Dropped
+                } else {
+                    Token::Content
+                };
+                match find_closing(input, tag) {
+                    Some(0) => {
+                        self.context = Context::Markup;
+                        self.next(input, eof)
+                    }
+                    Some(pos) => Some((content, pos)),
+                    None if eof => Some((content, input.len())),
+                    // the closing tag may be split
+                    None => Some((content, input.len().saturating_sub(tag.len() + 2)))
+                        .filter(|t| t.1 > 0),
+                }
+            }
+            Context::Dropped { tag, mut scan } => match scan.end(input) {
+                Some(end) => {
+                    self.context = match tag {
+                        Some(tag) => Context::RawText { tag, dropped: true },
+                        None => Context::Markup,
+                    };
+                    Some((Token::Dropped, end))
+                }
+                None => {
+                    self.context = Context::Dropped { tag, scan };
+                    Some((Token::Dropped, input.len()))
+                }
+            },
+        }
+    }
+
+    /// Drops the unfinished tag at the start of `input`, which is too long to be held back. The
+    /// rest of the tag in the following input is dropped as well, and the content of the element
+    /// if it is a `<script>` or `<style>`.
+    pub(crate) fn drop_tag(&mut self, input: &[u8]) {
+        let tag = TAGS.iter().copied().find(|tag| {
+            starts_with(&input[1..], tag)
+                && input
+                    .get(1 + tag.len())
+                    .is_some_and(|b| b.is_ascii_whitespace() || *b == b'>' || *b == b'/')
+        });
+        let mut scan = TagScan::default();
+        scan.end(input);
+        self.context = Context::Dropped { tag, scan };
+    }
+}
+
+fn starts_with(input: &[u8], prefix: &[u8]) -> bool {
+    input.len() >= prefix.len() && input[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn find(input: &[u8], needle: &[u8]) -> Option<usize> {
+    input.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Finds the closing `</tag` of an element's content, followed by the end of the tag name.
+fn find_closing(content: &[u8], tag: &[u8]) -> Option<usize> {
+    (0..content.len()).find(|&i| {
+        let rest = &content[i..];
+        starts_with(rest, b"</")
+            && starts_with(&rest[2..], tag)
+            && rest
+                .get(2 + tag.len())
+                .is_some_and(|b| b.is_ascii_whitespace() || *b == b'>' || *b == b'/')
+    })
+}
+
+/// The offset right after the `>` ending a tag, skipping quoted attribute values.
+fn tag_end(tag: &[u8]) -> Option<usize> {
+    TagScan::default().end(tag)
+}
+
+/// Where a scan for the end of a tag is, so it can be continued in the next chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum TagScan {
+    #[default]
+    Attributes,
+    /// Right after `=`, where a quoted value may start.
+    Value,
+    /// Inside a value quoted with the given quote.
+    Quoted(u8),
+}
+
+impl TagScan {
+    /// The offset right after the `>` ending the tag in `input`, `None` if it doesn't end there.
+    fn end(&mut self, input: &[u8]) -> Option<usize> {
+        for (i, b) in input.iter().enumerate() {
+            *self = match (*self, *b) {
+                (TagScan::Quoted(quote), b) if b == quote => TagScan::Attributes,
+                (TagScan::Quoted(quote), _) => TagScan::Quoted(quote),
+                (TagScan::Value, b) if b.is_ascii_whitespace() => TagScan::Value,
+                (TagScan::Value, quote @ (b'"' | b'\'')) => TagScan::Quoted(quote),
+                (_, b'>') => return Some(i + 1),
+                (_, b'=') => TagScan::Value,
+                _ => TagScan::Attributes,
+            };
+        }
+        None
+    }
+}
+
+/// An attribute of a tag.
+#[derive(Debug)]
+struct Attribute<'a> {
+    /// Offset of the whitespace before the attribute.
+    start: usize,
+    /// Offset right after its value.
+    end: usize,
+    name: &'a [u8],
+    /// The value without quotes, empty without one.
+    value: &'a [u8],
+}
+
+/// The attributes of a tag, `input` starting right after the tag name.
+fn attributes(input: &[u8]) -> Vec<Attribute<'_>> {
+    let mut attributes = Vec::new();
+    let mut i = 0;
+
+    loop {
+        let start = i;
+        while input
+            .get(i)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b'/')
+        {
+            i += 1;
+        }
+        if input.get(i).is_none_or(|b| *b == b'>') {
+            return attributes;
+        }
+
+        let name_start = i;
+        while input
+            .get(i)
+            .is_some_and(|b| !b.is_ascii_whitespace() && !matches!(b, b'=' | b'>' | b'/'))
+        {
+            i += 1;
+        }
+        let name = &input[name_start..i];
+
+        let mut end = i;
+        while input.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+        let mut value: &[u8] = &[];
+        if input.get(i) == Some(&b'=') {
+            i += 1;
+            while input.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+                i += 1;
+            }
+            match input.get(i) {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let len = input[i + 1..]
+                        .iter()
+                        .position(|b| b == quote)
+                        .unwrap_or(input.len() - i - 1);
+                    value = &input[i + 1..i + 1 + len];
+                    i = (i + len + 2).min(input.len());
+                }
+                _ => {
+                    let value_start = i;
+                    while input
+                        .get(i)
+                        .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'>')
+                    {
+                        i += 1;
+                    }
+                    value = &input[value_start..i];
+                }
+            }
+            end = i;
+        } else {
+            i = end;
+        }
+
+        attributes.push(Attribute {
+            start,
+            end,
+            name,
+            value,
+        });
+    }
+}
+
+/// CSP hash sources of the inline scripts and styles of a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct InlineHashes {
+    pub(crate) scripts: Vec<String>,
+    pub(crate) styles: Vec<String>,
+}
+
+/// Computes the `'sha256-...'` hash sources of the inline `<script>` and `<style>` blocks of `html`
+/// which are marked with `secret`, or of all of them without one. Scripts with a `src` attribute
+/// are external and skipped, every hash is only added once.
+pub(crate) fn inline_hashes(html: &[u8], secret: Option<&[u8]>) -> InlineHashes {
+    let mut hashes = InlineHashes::default();
+    let mut seen = HashSet::new();
+    let mut add = |tag: &'static [u8], content: &[u8]| {
+        let source = hash_source(content);
+        if seen.insert((tag, source.clone())) {
+            match tag {
+                b"script" => hashes.scripts.push(source),
+                _ => hashes.styles.push(source),
+            }
+        }
+    };
+
+    let mut tokenizer = Tokenizer::default();
+    let mut offset = 0;
+    // the element being hashed and where its content starts
+    let mut element = None;
+    while let Some((token, len)) = tokenizer.next(&html[offset..], true) {
+        match token {
+            Token::Open { tag, name_end } => {
+                let attributes = attributes(&html[offset + name_end..offset + len]);
+                let external = tag == b"script"
+                    && attributes
+                        .iter()
+                        .any(|a| a.name.eq_ignore_ascii_case(b"src"));
+                let trusted = secret.is_none_or(|secret| {
+                    attributes.iter().any(|a| {
+                        a.name.eq_ignore_ascii_case(MARKER) && constant_time_eq(a.value, secret)
+                    })
+                });
+                element = Some((tag, offset + len)).filter(|_| trusted && !external);
+            }
+            Token::Content => {}
+            Token::Tag { .. } | Token::Other | Token::Dropped => {
+                if let Some((tag, start)) = element.take() {
+                    add(tag, &html[start..offset]);
+                }
+            }
+        }
+        offset += len;
+    }
+    if let Some((tag, start)) = element {
+        add(tag, &html[start..]);
+    }
+
+    hashes
+}
+
+/// The CSP hash source of `content`, such as `'sha256-...'`.
+pub(crate) fn hash_source(content: &[u8]) -> String {
+    format!("'sha256-{}'", STANDARD.encode(Sha256::digest(content)))
+}
+
+/// Inserts `<meta http-equiv="Content-Security-Policy">` with `policy` right after the opening
+/// `<head>` tag. Returns `None` if the document has no `<head>`.
+pub(crate) fn inject_meta(html: &[u8], policy: &str) -> Option<Vec<u8>> {
+    let head_end = find_head(html)?;
+    let meta = format!(
+        "<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">",
+        escape_attribute(policy)
+    );
+
+    let mut out = Vec::with_capacity(html.len() + meta.len());
+    out.extend_from_slice(&html[..head_end]);
+    out.extend_from_slice(meta.as_bytes());
+    out.extend_from_slice(&html[head_end..]);
+    Some(out)
+}
+
+/// Finds the offset right after the opening `<head>` tag.
+fn find_head(html: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+
+    while let Some(pos) = html[offset..].iter().position(|b| *b == b'<') {
+        let start = offset + pos + 1;
+        let matches = html
+            .get(start..start + 4)
+            .is_some_and(|name| name.eq_ignore_ascii_case(b"head"));
+        let terminated = html
+            .get(start + 4)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b'>');
+
+        if matches && terminated {
+            let end = html[start..].iter().position(|b| *b == b'>')?;
+            return Some(start + end + 1);
+        }
+
+        offset = start;
+    }
+
+    None
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
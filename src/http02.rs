@@ -0,0 +1,204 @@
+//! Support for services built on version 0.2 of the `http` crate (hyper 0.14, axum 0.6, ...),
+//! enabled with the `http02` feature.
+//!
+//! [`HelmetLayer`] wraps a [`crate::HelmetLayer`] configured as usual and applies it to
+//! `http` 0.2 requests and responses, so applications in the middle of migrating to `http` 1.x
+//! can share a single configuration between both stacks. Nonces and [`crate::HeaderOverride`]s
+//! work the same, as extensions are independent of the `http` version.
+//!
+//! ```
+//! use tower_helmet::http02;
+//!
+//! let layer = http02::HelmetLayer::from(tower_helmet::HelmetLayer::with_defaults());
+//! ```
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::ready;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use http_02::{Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::conflict::ConflictStrategies;
+use crate::cookie::CookiePolicy;
+use crate::corp::ContentTypeCorp;
+use crate::cors::CorsAudit;
+use crate::redirect::RedirectAudit;
+use crate::{nonce, preset, CspNonce, HeaderOverride};
+
+/// [`crate::HelmetLayer`] for `http` 0.2 services.
+#[derive(Debug, Clone)]
+pub struct HelmetLayer(crate::HelmetLayer);
+
+impl From<crate::HelmetLayer> for HelmetLayer {
+    fn from(layer: crate::HelmetLayer) -> Self {
+        HelmetLayer(layer)
+    }
+}
+
+impl<S> Layer<S> for HelmetLayer {
+    type Service = HelmetService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        assert!(
+            self.0.fallback != crate::provider::Fallback::Fail,
+            "`Fallback::Fail` isn't supported for http 0.2 services"
+        );
+        HelmetService(self.0.layer(service))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HelmetService<S>(crate::HelmetService<S>);
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for HelmetService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let is_grpc = self.0.grpc.is_some() && preset::is_grpc(&to_http1(request.headers()));
+        let user_agent = request
+            .headers()
+            .get(http_02::header::USER_AGENT)
+            .map(http_02::HeaderValue::as_bytes);
+        let session_id = self
+            .0
+            .session_id(|| Cow::Owned(to_http1(request.headers())));
+        let (headers, adjustments, _) = crate::or_fallback(self.0.prepare(
+            is_grpc,
+            request.uri().path(),
+            user_agent,
+            session_id,
+            None,
+            None,
+        ));
+        let nonce = adjustments.nonce();
+        if let Some(nonce) = &nonce {
+            request.extensions_mut().insert(nonce.clone());
+        }
+
+        ResponseFuture {
+            future: self.0.inner.call(request),
+            headers,
+            nonce,
+            private: self.0.private_max_age(),
+            vary: self.0.vary.clone(),
+            cookies: self.0.cookies.clone(),
+            conflicts: self.0.conflicts.clone(),
+            corp: self.0.corp.clone(),
+            redirects: self.0.redirects.clone(),
+            cors: self.0.cors.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`HelmetService`].
+    #[derive(Debug)]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        future: F,
+
+        headers: HeaderMap,
+        nonce: Option<CspNonce>,
+        private: Option<Duration>,
+        vary: Arc<[HeaderName]>,
+        cookies: Option<Arc<CookiePolicy>>,
+        conflicts: Option<Arc<ConflictStrategies>>,
+        corp: Option<Arc<ContentTypeCorp>>,
+        redirects: Option<Arc<RedirectAudit>>,
+        cors: Option<Arc<CorsAudit>>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res: Response<ResBody> = {
+            let _current = nonce::enter(this.nonce.as_ref());
+            ready!(this.future.poll(cx)?)
+        };
+        let header_override = res.extensions_mut().remove::<HeaderOverride>();
+
+        let mut headers = to_http1(res.headers());
+        crate::finish(
+            &mut headers,
+            this.headers,
+            this.conflicts.as_deref(),
+            header_override,
+            this.vary,
+            this.cookies.as_deref(),
+            this.corp.as_deref(),
+            None,
+        );
+        if let (Some(redirects), Ok(status)) = (
+            this.redirects.as_deref(),
+            StatusCode::from_u16(res.status().as_u16()),
+        ) {
+            redirects.report(status, &headers);
+        }
+        if let Some(cors) = this.cors.as_deref() {
+            cors.report(&headers);
+        }
+        if let Some(nonce) = this.nonce.take() {
+            match *this.private {
+                Some(max_age) => nonce::keep_private(&mut headers, max_age),
+                #[cfg(feature = "tracing")]
+                None => nonce::warn_if_cacheable(&headers),
+                #[cfg(not(feature = "tracing"))]
+                None => {}
+            }
+            res.extensions_mut().insert(nonce);
+        }
+        *res.headers_mut() = to_http02(&headers);
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+pub(crate) fn to_http1(headers: &http_02::HeaderMap) -> HeaderMap {
+    let mut converted = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        // both versions accept the same names and values
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
+
+pub(crate) fn to_http02(headers: &HeaderMap) -> http_02::HeaderMap {
+    let mut converted = http_02::HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            http_02::HeaderName::from_bytes(name.as_str().as_bytes()),
+            http_02::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
@@ -0,0 +1,316 @@
+//! Redirects plaintext requests to HTTPS.
+//!
+//! `Strict-Transport-Security` only takes effect once a browser has reached the site over HTTPS,
+//! so the first plaintext request of a visitor has to be redirected. [`HttpsRedirectLayer`] is a
+//! companion to [`crate::HelmetLayer`] which answers every plaintext request with a redirect to
+//! the same host and path over HTTPS and passes HTTPS requests on.
+//!
+//! A server only sees whether a request is encrypted if it terminates TLS itself: by default, a
+//! request is plaintext unless its URI has the `https` scheme, so the layer belongs to the
+//! plaintext listener only. Behind a load balancer or reverse proxy terminating TLS, enable
+//! [`HttpsRedirectLayer::trust_forwarded_headers`] to read the scheme from the `Forwarded` or
+//! `X-Forwarded-Proto` header the proxy sets. Clients can send these headers themselves, so they
+//! must only be trusted if every request passes through a proxy which overwrites them.
+//!
+//! The `Host` of the request is validated like [`crate::origin::sanitize_host`] (requests with an
+//! invalid one get `400 Bad Request`), and [`HttpsRedirectLayer::host`] pins the redirect target
+//! to a single host so spoofed `Host` headers can't be used for open redirects. ACME HTTP-01
+//! challenges (`/.well-known/acme-challenge/`) have to be served over plaintext and are passed on.
+//!
+//! ```
+//! use http::StatusCode;
+//! use tower_helmet::https_redirect::HttpsRedirectLayer;
+//!
+//! let mut layer = HttpsRedirectLayer::new();
+//! layer
+//!     .trust_forwarded_headers(true)
+//!     .host("example.com")
+//!     .status(StatusCode::MOVED_PERMANENTLY);
+//!
+//! // let app = Router::new().layer(HelmetLayer::with_defaults()).layer(layer);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::{FORWARDED, HOST, LOCATION};
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode, Uri};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::origin::sanitize_host;
+
+static X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Layer redirecting plaintext requests to HTTPS, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct HttpsRedirectLayer {
+    status: StatusCode,
+    trust_forwarded_headers: bool,
+    host: Option<String>,
+    port: Option<u16>,
+    exempt: Vec<String>,
+}
+
+impl Default for HttpsRedirectLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpsRedirectLayer {
+    /// Redirects with `308 Permanent Redirect` to the host of the request on the default port,
+    /// not trusting forwarded headers.
+    pub fn new() -> Self {
+        HttpsRedirectLayer {
+            status: StatusCode::PERMANENT_REDIRECT,
+            trust_forwarded_headers: false,
+            host: None,
+            port: None,
+            exempt: vec!["/.well-known/acme-challenge/".to_owned()],
+        }
+    }
+
+    /// Status of the redirects. `308 Permanent Redirect`, the default, keeps the method and body
+    /// of the request, while `301 Moved Permanently` turns `POST` into `GET` but is understood by
+    /// very old clients as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `status` is not one of `301`, `302`, `307` and `308`.
+    pub fn status(&mut self, status: StatusCode) -> &mut Self {
+        assert!(
+            matches!(status.as_u16(), 301 | 302 | 307 | 308),
+            "{} is not a redirect status",
+            status
+        );
+        self.status = status;
+        self
+    }
+
+    /// Whether the scheme of a request is read from the `Forwarded` (`proto=`) or
+    /// `X-Forwarded-Proto` header, set by a proxy terminating TLS. The value added last, by the
+    /// nearest proxy, counts; requests without either header are passed on. Defaults to `false`.
+    pub fn trust_forwarded_headers(&mut self, trust: bool) -> &mut Self {
+        self.trust_forwarded_headers = trust;
+        self
+    }
+
+    /// Redirects to `host` instead of the `Host` of the request. Like the port of the request, a
+    /// port in `host` is replaced by [`HttpsRedirectLayer::port`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `host` is not a valid host, see [`crate::origin::sanitize_host`].
+    pub fn host(&mut self, host: &str) -> &mut Self {
+        self.host = Some(valid_host(host).expect("invalid host"));
+        self
+    }
+
+    /// Redirects to `port` instead of the default port `443`.
+    pub fn port(&mut self, port: u16) -> &mut Self {
+        self.port = Some(port).filter(|port| *port != 443);
+        self
+    }
+
+    /// Passes on plaintext requests whose path starts with `prefix`, in addition to ACME
+    /// challenges.
+    pub fn exempt(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.exempt.push(prefix.into());
+        self
+    }
+
+    /// Whether the request with `uri` and `headers` was sent over HTTPS.
+    fn is_https(&self, uri: &Uri, headers: &HeaderMap) -> bool {
+        if !self.trust_forwarded_headers {
+            return uri.scheme_str() == Some("https");
+        }
+
+        let forwarded = headers
+            .get_all(FORWARDED)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (name, value) = pair.split_once('=')?;
+                    Some(value.trim().trim_matches('"'))
+                        .filter(|_| name.trim().eq_ignore_ascii_case("proto"))
+                })
+            })
+            .next_back();
+        let proto = forwarded.or_else(|| {
+            headers
+                .get_all(&X_FORWARDED_PROTO)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .next_back()
+        });
+
+        match proto {
+            Some(proto) => proto.eq_ignore_ascii_case("https"),
+            None => true,
+        }
+    }
+
+    /// The `Location` of the redirect of the request with `uri` and `headers`, `None` if the host
+    /// is missing or invalid.
+    fn location(&self, uri: &Uri, headers: &HeaderMap) -> Option<HeaderValue> {
+        let host = match &self.host {
+            Some(host) => host.clone(),
+            None => {
+                let host = match headers.get(HOST) {
+                    Some(host) => host.to_str().ok()?,
+                    None => uri.authority()?.as_str(),
+                };
+                valid_host(host)?
+            }
+        };
+        // the port of the plaintext listener doesn't apply to HTTPS
+        let name = match host.rfind(':') {
+            Some(pos) if !host[pos..].contains(']') => &host[..pos],
+            _ => host.as_str(),
+        };
+        let port = self
+            .port
+            .map(|port| format!(":{}", port))
+            .unwrap_or_default();
+        let path = uri.path_and_query().map_or("/", |p| p.as_str());
+
+        HeaderValue::from_str(&format!("https://{}{}{}", name, port, path)).ok()
+    }
+}
+
+/// A host without wildcards, in lowercase.
+fn valid_host(host: &str) -> Option<String> {
+    sanitize_host(host).ok().filter(|host| !host.contains('*'))
+}
+
+impl<S> Layer<S> for HttpsRedirectLayer {
+    type Service = HttpsRedirectService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        HttpsRedirectService {
+            inner: service,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+/// Service answering plaintext requests with a redirect (or `400 Bad Request` for an invalid
+/// `Host`) and an empty body, without calling the inner service.
+///
+/// ```
+/// use http::header::LOCATION;
+/// use http::{Request, StatusCode};
+/// use tower_helmet::https_redirect::HttpsRedirectLayer;
+/// use tower_helmet::test_util::{call_service, CountingEcho};
+/// use tower_layer::Layer;
+///
+/// let request = |uri: &str, host: &str| {
+///     Request::builder()
+///         .uri(uri)
+///         .header("host", host)
+///         .body("page".to_owned())
+///         .unwrap()
+/// };
+/// let echo = CountingEcho::new();
+/// let mut service = HttpsRedirectLayer::new().layer(echo.clone());
+///
+/// let res = call_service(&mut service, request("/login?next=%2F", "Example.com:8080"));
+/// assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+/// assert_eq!(res.headers()[LOCATION], "https://example.com/login?next=%2F");
+/// assert_eq!(res.headers().len(), 1);
+/// assert_eq!(res.body(), "");
+///
+/// let res = call_service(&mut service, request("/", "example.com/evil"));
+/// assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+/// assert!(res.headers().is_empty());
+/// assert_eq!(echo.calls(), 0);
+///
+/// // HTTPS requests and ACME challenges are passed on
+/// call_service(&mut service, request("https://example.com/", "example.com"));
+/// let res = call_service(&mut service, request("/.well-known/acme-challenge/t", "example.com"));
+/// assert_eq!(res.body(), "page");
+/// assert_eq!(echo.calls(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpsRedirectService<S> {
+    inner: S,
+    layer: Arc<HttpsRedirectLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for HttpsRedirectService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let uri = request.uri();
+        let exempt = self
+            .layer
+            .exempt
+            .iter()
+            .any(|prefix| uri.path().starts_with(prefix.as_str()));
+        if exempt || self.layer.is_https(uri, request.headers()) {
+            return ResponseFuture::Inner {
+                future: self.inner.call(request),
+            };
+        }
+
+        let mut response = Response::new(ResBody::default());
+        match self.layer.location(uri, request.headers()) {
+            Some(location) => {
+                *response.status_mut() = self.layer.status;
+                response.headers_mut().insert(LOCATION, location);
+            }
+            None => *response.status_mut() = StatusCode::BAD_REQUEST,
+        }
+        ResponseFuture::Redirect {
+            response: Some(response),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`HttpsRedirectService`].
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Inner {
+            #[pin]
+            future: F,
+        },
+        Redirect {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F, ResBody>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Redirect { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Integration with [Leptos](https://docs.rs/leptos) server rendering, enabled with the `leptos`
+//! feature.
+//!
+//! [`provide_csp_nonce`] makes the nonce of the current request (see
+//! [`crate::HelmetLayer::enable_nonce`]) available as Leptos context, so components read it with
+//! [`use_csp_nonce`] instead of the thread-local [`CspNonce::current`], which isn't set while
+//! Leptos renders on other tasks. Call it from the `additional_context` of `leptos_axum`, which
+//! runs with the request parts in context:
+//!
+//! ```ignore
+//! let routes = generate_route_list(App);
+//! let app = Router::new()
+//!     .leptos_routes_with_context(&options, routes, tower_helmet::leptos::provide_csp_nonce, {
+//!         let options = options.clone();
+//!         move || shell(options.clone())
+//!     })
+//!     .layer(helmet);
+//!
+//! #[component]
+//! fn Analytics() -> impl IntoView {
+//!     let nonce = use_csp_nonce().map(|nonce| nonce.to_string());
+//!     view! { <script nonce=nonce>"track()"</script> }
+//! }
+//! ```
+//!
+//! The nonce is provided as Leptos' own `leptos::nonce::Nonce` as well, so the scripts Leptos
+//! generates for hydration and streaming and the `<Script/>` and `<Style/>` of `leptos_meta` carry
+//! it automatically. Don't enable the `nonce` feature of `leptos_axum`, it would generate a nonce
+//! the layer's CSP doesn't know.
+
+use ::leptos::context::{provide_context, use_context};
+use ::leptos::nonce::Nonce;
+use http::request::Parts;
+
+use crate::CspNonce;
+
+/// Provides the nonce of the current request as Leptos context, if the request passed through a
+/// [`crate::HelmetLayer`] with nonces enabled.
+///
+/// The nonce is taken from the extensions of the request parts provided by the server
+/// integration, or from [`CspNonce::current`] without them. It is provided as [`CspNonce`] and as
+/// `leptos::nonce::Nonce`, which Leptos adds to its hydration scripts.
+pub fn provide_csp_nonce() {
+    let nonce = use_context::<Parts>()
+        .and_then(|parts| parts.extensions.get::<CspNonce>().cloned())
+        .or_else(CspNonce::current);
+    if let Some(nonce) = nonce {
+        provide_context(Nonce::from_value(nonce.to_string()));
+        provide_context(nonce);
+    }
+}
+
+/// The nonce provided with [`provide_csp_nonce`], `None` on the client and without nonce.
+pub fn use_csp_nonce() -> Option<CspNonce> {
+    use_context::<CspNonce>()
+}
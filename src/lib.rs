@@ -5,7 +5,8 @@
 //!
 //! You can find a list of all available headers under the [header] module. By default (with
 //! [HelmetLayer::with_defaults]) **all of them** are enabled. Please take a good look at
-//! [ContentSecurityPolicy]. Most of the time you will need to adapt this one to your needs.
+//! [ContentSecurityPolicy](header::ContentSecurityPolicy). Most of the time you will need to adapt
+//! this one to your needs.
 //!
 //! # Examples
 //!
@@ -38,10 +39,131 @@
 //!     .enable(XFrameOptions::SameOrigin)
 //!     .enable(ExpectCt::default());
 //! ```
+//!
+//! # Features
+//!
+//! - `actix-web`: middleware applying the configuration to actix-web applications, see [actix].
+//! - `arbitrary`: [`arbitrary::Arbitrary`] implementations for every header type, generating only
+//!   configurations with valid header values, for property tests and fuzzing.
+//! - `askama`, `minijinja`, `tera`: template helpers for rendering the [`CspNonce`], see
+//!   [templates].
+//! - `axum`: extractor for [`CspNonce`], response parts for [`HeaderOverride`] and a route layer
+//!   changing the headers of single routes, see [axum].
+//! - `cli`: the `tower-helmet` binary, which prints the headers of a configuration file, audits
+//!   them and exits with a non-zero code on findings, for example in CI.
+//! - `dioxus`: access to [`CspNonce`] while rendering Dioxus fullstack applications, see [dioxus].
+//! - `headers`: [`headers::Header`] implementations for every header type except
+//!   [`header::ContentSecurityPolicy`], for use with `TypedHeader` and other typed header APIs.
+//! - `html`: rewriting of HTML responses, see [html].
+//! - `http02`: support for services built on `http` 0.2, see [http02].
+//! - `js`: generates nonces with the browser's `crypto.getRandomValues` and reads the time from
+//!   JavaScript, required on `wasm32-unknown-unknown` (for example Cloudflare Workers), where
+//!   `std::time` panics. WASI targets such as Fastly Compute work without it. Other runtimes can
+//!   register their own random source with getrandom's `custom` feature.
+//! - `json`: JSON and `vercel.json` exports, see [`HelmetLayer::to_json_report`].
+//! - `leptos`: [`CspNonce`] as Leptos context for server rendering, see [leptos].
+//! - `preload`: checks whether the HSTS configuration and a live domain qualify for the HSTS
+//!   preload list and queries its status, see [preload].
+//! - `rocket`: fairing applying the configuration to Rocket applications, see [rocket].
+//! - `scan`: fetches a live URL and grades the headers it actually sends, see [scan].
+//! - `schemars`: JSON Schema of the serialized header configuration, see
+//!   [`header::HeaderSet`].
+//! - `serde`: (de)serialization of the header configuration.
+//! - `session-nonce`: nonces which stay the same for a session, see [session_nonce].
+//! - `starter`: proposes an initial CSP by scanning the built assets of a site, see [starter].
+//! - `sri`: integrity values and CSP hashes of static assets, see [sri].
+//! - `test-util`: an echo service, request helpers, [`assert_helmet_headers!`] and an assertion
+//!   layer for testing the configuration in a few lines, see [test_util].
+//! - `tower-http`: stacking with tower-http's CORS, timeout and body limit layers and conversion of
+//!   single headers into its `SetResponseHeaderLayer`, see [tower_http].
+//! - `tracing`: logs the effective header set when the layer is first used.
+//! - `warp`: applies the configuration to warp filters, see [warp].
+#[cfg(feature = "actix-web")]
+pub mod actix;
+pub mod audit;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod browser_baseline;
+mod builder;
+pub mod canary;
+mod clock;
+mod compat;
+pub mod compliance;
+pub mod conflict;
+pub mod cookie;
+pub mod corp;
+pub mod cors;
+pub mod critical;
+pub mod csrf;
+pub mod diff;
+#[cfg(feature = "dioxus")]
+pub mod dioxus;
+#[cfg(feature = "serde")]
+mod duration;
+pub mod explain;
+pub mod export;
+pub mod fetch_metadata;
+pub mod fingerprint;
+pub mod frame_ancestors;
+pub mod grade;
 pub mod header;
+#[cfg(feature = "html")]
+pub mod html;
+#[cfg(feature = "http02")]
+pub mod http02;
+pub mod https_redirect;
+#[cfg(feature = "leptos")]
+pub mod leptos;
+mod nonce;
+pub mod origin;
+mod overrides;
+#[cfg(feature = "preload")]
+pub mod preload;
+pub mod preset;
+pub mod provider;
+pub mod redirect;
+pub mod reporting;
+#[cfg(feature = "rocket")]
+pub mod rocket;
+pub mod rollout;
+pub mod router;
+#[cfg(feature = "scan")]
+pub mod scan;
+pub mod security_txt;
+#[cfg(feature = "session-nonce")]
+pub mod session_nonce;
+pub mod shadow;
+#[cfg(feature = "sri")]
+pub mod sri;
+#[cfg(feature = "starter")]
+pub mod starter;
+#[cfg(any(feature = "askama", feature = "minijinja", feature = "tera"))]
+pub mod templates;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "tower-http")]
+pub mod tower_http;
+#[cfg(feature = "serde")]
+mod uri;
+pub mod user_agent;
+mod vary;
+pub mod violations;
+#[cfg(feature = "warp")]
+pub mod warp;
 
+pub use crate::browser_baseline::BrowserBaseline;
+pub use crate::builder::{CspDecided, CspUndecided, HelmetBuilder};
+pub use crate::compat::HelmetJsVersion;
+pub use crate::nonce::CspNonce;
+pub use crate::overrides::HeaderOverride;
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::pin::Pin;
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::ready;
@@ -51,77 +173,824 @@ use pin_project_lite::pin_project;
 use tower_layer::Layer;
 use tower_service::Service;
 
-use crate::header::{
-    ContentSecurityPolicy, CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy,
-    CrossOriginResourcePolicy, ExpectCt, OriginAgentCluster, ReferrerPolicy,
-    StrictTransportSecurity, XContentTypeOptions, XDnsPrefetchControl, XDownloadOptions,
-    XFrameOptions, XPermittedCrossDomainPolicies, XXSSProtection,
-};
+use crate::header::{HeaderSet, XDownloadOptions, XXSSProtection};
 
 pub trait IntoHeader {
     fn header_name(&self) -> HeaderName;
     fn header_value(&self) -> Result<HeaderValue, InvalidHeaderValue>;
 }
 
+/// `IntoHeaders` is implemented by everything that can be passed to [`HelmetLayer::enable`]. Every
+/// [`IntoHeader`] sets a single header, while presets (see the [preset] module) set several at once.
+pub trait IntoHeaders {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue>;
+}
+
+impl<T: IntoHeader> IntoHeaders for T {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(self.header_name(), self.header_value()?);
+        Ok(headers)
+    }
+}
+
 /// HelmetLayer
 #[derive(Debug, Clone)]
 pub struct HelmetLayer {
-    headers: HeaderMap,
+    set: HeaderSet,
+    /// The headers `set` can't hold, see [`HelmetLayer::other_headers`].
+    others: HeaderMap,
+    /// The CSP directives a nonce is added to, if enabled.
+    nonce: Option<&'static [&'static str]>,
+    /// The request header the nonce is forwarded in, if any.
+    forward_nonce: Option<HeaderName>,
+    /// Headers used instead of `headers` for gRPC requests.
+    grpc: Option<HeaderMap>,
+    routes: Option<Arc<router::PolicyRouter>>,
+    provider: Option<Arc<dyn provider::PolicyProvider>>,
+    fallback: provider::Fallback,
+    explain: Option<Arc<explain::Explain>>,
+    frame_ancestors: Option<frame_ancestors::FrameAncestorsHandle>,
+    rollout: Option<Arc<rollout::Rollout>>,
+    shadow: Option<Arc<shadow::Shadow>>,
+    fingerprints: Option<Arc<fingerprint::Fingerprints>>,
+    conflicts: Option<Arc<conflict::ConflictStrategies>>,
+    cookies: Option<Arc<cookie::CookiePolicy>>,
+    corp: Option<Arc<corp::ContentTypeCorp>>,
+    redirects: Option<Arc<redirect::RedirectAudit>>,
+    cors: Option<Arc<cors::CorsAudit>>,
+    user_agent: Option<Arc<user_agent::UserAgentAdjustments>>,
+    #[cfg(feature = "session-nonce")]
+    session_nonce: Option<Arc<session_nonce::SessionNonce>>,
+    /// Whether the effective configuration was already logged. Shared between clones, so it is
+    /// only logged once.
+    #[cfg(feature = "tracing")]
+    logged: Arc<AtomicBool>,
 }
 
 impl HelmetLayer {
     /// Helmet without any headers added in by default. See [`enable`] for enabling headers.
+    ///
+    /// The layer holds a [`HeaderMap`], so it can't be built in a constant. The header types and
+    /// presets have `const fn new()` constructors instead, so the configuration can live in
+    /// statics and only the layer is built at startup.
+    ///
+    /// ```
+    /// use tower_helmet::header::{StrictTransportSecurity, XContentTypeOptions};
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// static HSTS: StrictTransportSecurity = StrictTransportSecurity::new();
+    /// static NOSNIFF: XContentTypeOptions = XContentTypeOptions::new();
+    ///
+    /// let mut layer = HelmetLayer::blank();
+    /// layer.enable(HSTS).enable(NOSNIFF);
+    /// ```
     pub fn blank() -> Self {
         Self {
-            headers: HeaderMap::new(),
+            set: HeaderSet::default(),
+            others: HeaderMap::new(),
+            nonce: None,
+            forward_nonce: None,
+            grpc: None,
+            routes: None,
+            provider: None,
+            fallback: provider::Fallback::Configured,
+            explain: None,
+            frame_ancestors: None,
+            rollout: None,
+            shadow: None,
+            fingerprints: None,
+            conflicts: None,
+            cookies: None,
+            corp: None,
+            redirects: None,
+            cors: None,
+            user_agent: None,
+            #[cfg(feature = "session-nonce")]
+            session_nonce: None,
+            #[cfg(feature = "tracing")]
+            logged: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Helmet with most of the headers already added with the base configuration.
-    #[allow(clippy::default_constructed_unit_structs)]
-    pub fn with_defaults() -> Self {
+    /// Starts a [`HelmetBuilder`], which has a method for every header.
+    pub fn builder() -> HelmetBuilder {
+        HelmetBuilder::default()
+    }
+
+    /// Starts a [`HelmetBuilder`] which can only be built once the CSP is decided on, either by
+    /// configuring one or by calling [`HelmetBuilder::no_content_security_policy`], so a layer
+    /// never ships the generic default policy or none at all by accident.
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    ///
+    /// use tower_helmet::header::{ContentSecurityPolicy, StrictTransportSecurity};
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let csp = ContentSecurityPolicy::try_from("default-src 'self'; frame-ancestors 'none'").unwrap();
+    ///
+    /// let layer = HelmetLayer::explicit_builder()
+    ///     .hsts(StrictTransportSecurity::default())
+    ///     .content_security_policy(csp)
+    ///     .nonce()
+    ///     .build();
+    ///
+    /// let api = HelmetLayer::explicit_builder()
+    ///     .content_type_options()
+    ///     .hsts(StrictTransportSecurity::default())
+    ///     .no_content_security_policy()
+    ///     .build();
+    /// ```
+    ///
+    /// Without a decision, the builder has no `build` method:
+    ///
+    /// ```compile_fail
+    /// use tower_helmet::header::StrictTransportSecurity;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let layer = HelmetLayer::explicit_builder()
+    ///     .hsts(StrictTransportSecurity::default())
+    ///     .build();
+    /// ```
+    pub fn explicit_builder() -> HelmetBuilder<CspUndecided> {
+        HelmetBuilder::default()
+    }
+
+    /// Helmet with the headers of an existing [`HeaderMap`], for example the ones a legacy proxy
+    /// currently sends. Known security headers are parsed into their typed representation and
+    /// normalized where possible, every other header is carried over as is.
+    ///
+    /// ```
+    /// use http::{HeaderMap, HeaderValue};
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-frame-options", HeaderValue::from_static("deny"));
+    /// headers.insert("x-content-type-options", HeaderValue::from_static("NOSNIFF"));
+    /// headers.insert(
+    ///     "content-security-policy",
+    ///     HeaderValue::from_static("img-src *;  default-src 'self'"),
+    /// );
+    /// headers.insert("x-powered-by", HeaderValue::from_static("legacy"));
+    ///
+    /// let layer = HelmetLayer::from_header_map(&headers);
+    /// assert_eq!(
+    ///     layer.to_string(),
+    ///     "content-security-policy: default-src 'self'; img-src *\n\
+    ///      x-content-type-options: nosniff\n\
+    ///      x-frame-options: DENY\n\
+    ///      x-powered-by: legacy\n"
+    /// );
+    /// ```
+    pub fn from_header_map(headers: &HeaderMap) -> Self {
+        let mut normalized = HeaderMap::new();
+        for (name, value) in headers {
+            match header::normalize(name, value) {
+                Some(value) => {
+                    normalized.insert(name, value);
+                }
+                None => {
+                    normalized.append(name, value.clone());
+                }
+            }
+        }
+
         let mut layer = Self::blank();
+        layer.store(normalized);
         layer
-            .enable(ContentSecurityPolicy::default())
-            .enable(CrossOriginEmbedderPolicy::default())
-            .enable(CrossOriginOpenerPolicy::default())
-            .enable(CrossOriginResourcePolicy::default())
-            .enable(ExpectCt::default())
-            .enable(OriginAgentCluster::default())
-            .enable(ReferrerPolicy::default())
-            .enable(StrictTransportSecurity::default())
-            .enable(XContentTypeOptions::default())
-            .enable(XDnsPrefetchControl::default())
-            .enable(XDownloadOptions::default())
-            .enable(XFrameOptions::default())
-            .enable(XPermittedCrossDomainPolicies::default())
-            .enable(XXSSProtection::default());
+    }
 
-        layer
+    /// Helmet with most of the headers already added with the base configuration. Kept for
+    /// compatibility, new services should prefer [`HelmetLayer::with_defaults_v2`].
+    pub fn with_defaults() -> Self {
+        Self::from(HeaderSet::with_defaults())
+    }
+
+    /// Second version of the default headers. Compared to [`HelmetLayer::with_defaults`], the
+    /// deprecated `Expect-CT` header, which browsers ignore by now, is not sent anymore. The legacy
+    /// `X-XSS-Protection` and `X-Download-Options` headers are left out as well and can be opted
+    /// into with [`HelmetLayer::enable_legacy`].
+    pub fn with_defaults_v2() -> Self {
+        Self::from(HeaderSet::with_defaults_v2())
+    }
+
+    /// Enables the headers which only old browsers (such as Internet Explorer) understand:
+    /// `X-XSS-Protection` and `X-Download-Options`.
+    pub fn enable_legacy(&mut self) -> &mut Self {
+        self.enable(XXSSProtection::new())
+            .enable(XDownloadOptions::new())
+    }
+
+    pub fn enable(&mut self, h: impl IntoHeaders) -> &mut Self {
+        self.extend(h.header_map().unwrap());
+        self
+    }
+
+    /// Like [`HelmetLayer::enable`], but returns an error instead of panicking if a header value
+    /// is invalid, for headers built from dynamic input. Use the `validate` methods of the header
+    /// types (such as [`header::ContentSecurityPolicy::validate`]) to find out why.
+    ///
+    /// With the `tracing` feature, suspicious `Strict-Transport-Security` values (such as a
+    /// `max-age` below one day or `preload` without `includeSubDomains`, see [audit]) are logged as
+    /// warning by both methods.
+    pub fn try_enable(&mut self, h: impl IntoHeaders) -> Result<&mut Self, InvalidHeaderValue> {
+        self.extend(h.header_map()?);
+        Ok(self)
+    }
+
+    fn extend(&mut self, headers: HeaderMap) {
+        #[cfg(feature = "tracing")]
+        if headers.contains_key(http::header::STRICT_TRANSPORT_SECURITY) {
+            for finding in audit::audit(&headers)
+                .iter()
+                .filter(|f| audit::is_hsts_sanity(f.rule))
+            {
+                tracing::warn!(rule = %finding.rule, "{}", finding.message);
+            }
+        }
+
+        self.update(|current| current.extend(headers));
+    }
+
+    /// Renders the configured headers. The set was rendered when its headers were enabled, so it
+    /// can't fail.
+    pub(crate) fn headers(&self) -> HeaderMap {
+        let mut headers = self
+            .set
+            .header_map()
+            .expect("header set rendered when enabled");
+        headers.extend(self.others.clone());
+        headers
+    }
+
+    /// Changes the rendered headers with `f`, keeping the result.
+    pub(crate) fn update(&mut self, f: impl FnOnce(&mut HeaderMap)) {
+        let mut headers = self.headers();
+        f(&mut headers);
+        self.store(headers);
+    }
+
+    fn store(&mut self, headers: HeaderMap) {
+        let (set, others) = HeaderSet::split(headers);
+        self.set = set;
+        self.others = others;
+    }
+
+    /// Generates a [`CspNonce`] for every request and adds it to the `script-src` directive of the
+    /// CSP. The nonce is available in the extensions of both the request and the response.
+    ///
+    /// Responses with a nonce must not be cached, with the `tracing` feature cacheable ones are
+    /// logged as warning, see [`audit::audit_nonce_caching`].
+    pub fn enable_nonce(&mut self) -> &mut Self {
+        self.enable_nonce_for(&["script-src"])
+    }
+
+    /// Like [`HelmetLayer::enable_nonce`], but adds the nonce to the given CSP directives, such as
+    /// `script-src` and `style-src` for nonce-based styles, or additionally `script-src-elem` if
+    /// the policy sets it. Directives the policy doesn't set are added with the sources of their
+    /// fallback (`script-src` for `script-src-elem`, otherwise `default-src`), if that is set.
+    ///
+    /// Browsers ignore `'unsafe-inline'` in directives with a nonce, so adding it to a `style-src`
+    /// which relies on `'unsafe-inline'` (like the default one) blocks inline styles without nonce.
+    ///
+    /// ```
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::with_defaults_v2();
+    /// layer.enable_nonce_for(&["script-src", "style-src"]);
+    /// ```
+    pub fn enable_nonce_for(&mut self, directives: &'static [&'static str]) -> &mut Self {
+        self.nonce = Some(directives);
+        self
+    }
+
+    /// Derives the nonce from the session of the request instead of generating one per request,
+    /// for pages which are cached and hydrated later, see the [session_nonce] module for the
+    /// trade-offs. Enables nonces for `script-src` unless they are already enabled.
+    ///
+    /// Responses with a nonce are kept out of shared caches and are stored by private caches for
+    /// at most the rotation period of the nonce.
+    #[cfg(feature = "session-nonce")]
+    pub fn session_nonce(&mut self, session_nonce: session_nonce::SessionNonce) -> &mut Self {
+        self.nonce.get_or_insert(&["script-src"]);
+        self.session_nonce = Some(Arc::new(session_nonce));
+        self
+    }
+
+    /// Adds the nonce of every request as the request header `name`, for reverse proxies in front
+    /// of a server-side rendering backend: the backend renders the nonce into its inline scripts,
+    /// while nonce generation and the CSP stay in the proxy. A header of that name sent by the
+    /// client is always removed, so it can't choose the nonce. Only the layer for `http` 1.x
+    /// services forwards the nonce.
+    ///
+    /// Nonces have to be [enabled](HelmetLayer::enable_nonce) as well.
+    ///
+    /// ```
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::with_defaults_v2();
+    /// layer.enable_nonce().forward_nonce("x-csp-nonce");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is invalid.
+    pub fn forward_nonce(&mut self, name: &str) -> &mut Self {
+        self.forward_nonce = Some(HeaderName::from_bytes(name.as_bytes()).unwrap());
+        self
+    }
+
+    /// Sends the headers of `preset` instead of the configured ones for gRPC and gRPC-web requests
+    /// (see [`preset::is_grpc`]), as the defaults for browser documents are mostly wrong for that
+    /// traffic. No nonce is generated for them.
+    ///
+    /// As the headers then depend on the `Content-Type` of the request, it is added to the `Vary`
+    /// header of every response, so shared caches don't mix them up.
+    pub fn grpc(&mut self, preset: preset::Grpc) -> &mut Self {
+        self.grpc = Some(preset.header_map().unwrap());
+        self
     }
 
-    pub fn enable(&mut self, h: impl IntoHeader) -> &mut Self {
-        self.headers
-            .insert(h.header_name(), h.header_value().unwrap());
+    /// Sends the headers of the route matching the request path instead of the configured ones,
+    /// see the [router] module. Nonces and the other features of the layer apply to them as well.
+    ///
+    /// The exports, audits and [`Display`] of the layer only cover its own headers.
+    pub fn routes(&mut self, router: router::PolicyRouter) -> &mut Self {
+        self.routes = Some(Arc::new(router));
         self
     }
 
+    /// Loads the headers of every request from `provider`, sending the configured ones (or those
+    /// of the matching [route](HelmetLayer::routes)) if it returns `None`, see the [provider]
+    /// module. gRPC requests get the [`HelmetLayer::grpc`] preset without asking the provider.
+    ///
+    /// Only the layer for `http` 1.x services consults the provider; the [http02] layer and the
+    /// adapters for other frameworks send the configured headers.
+    pub fn policy_provider(&mut self, provider: impl provider::PolicyProvider) -> &mut Self {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// What to send if the headers of a request can't be computed, because the
+    /// [policy provider](HelmetLayer::policy_provider) failed or no nonce could be generated.
+    /// Defaults to [`provider::Fallback::Configured`].
+    ///
+    /// ```
+    /// use tower_helmet::provider::Fallback;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::with_defaults_v2();
+    /// layer.enable_nonce().fallback(Fallback::Nothing);
+    /// ```
+    ///
+    /// Failing needs a response body to fail with, so the layer panics when wrapping a service
+    /// with [`provider::Fallback::Fail`], use a [`provider::FailClosedLayer`] instead.
+    pub fn fallback(&mut self, fallback: provider::Fallback) -> &mut Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Explains how the headers of the requests selected by `explain` were decided, see the
+    /// [explain] module. Only the layer for `http` 1.x services explains its decisions.
+    pub fn explain(&mut self, explain: explain::Explain) -> &mut Self {
+        self.explain = Some(Arc::new(explain));
+        self
+    }
+
+    /// The request headers the sent headers depend on, which are added to the `Vary` header of
+    /// every response: `Content-Type` if a [`HelmetLayer::grpc`] preset is configured and
+    /// `User-Agent` if the headers are [adjusted](HelmetLayer::adjust_for_user_agents) for it.
+    fn vary(&self) -> Arc<[HeaderName]> {
+        let mut vary = Vec::new();
+        if self.grpc.is_some() {
+            vary.push(http::header::CONTENT_TYPE);
+        }
+        if self.user_agent.is_some() {
+            vary.push(http::header::USER_AGENT);
+        }
+        vary.into()
+    }
+
+    /// Rewrites the `Set-Cookie` headers of all responses to carry the attributes of `policy`, see
+    /// the [cookie] module.
+    pub fn harden_cookies(&mut self, policy: cookie::CookiePolicy) -> &mut Self {
+        self.cookies = Some(Arc::new(policy));
+        self
+    }
+
+    /// Chooses per header whether the layer's values replace, are merged with or give way to the
+    /// values the response already has, for example from a proxied backend, see the [conflict]
+    /// module. By default they replace them.
+    pub fn conflicts(&mut self, conflicts: conflict::ConflictStrategies) -> &mut Self {
+        self.conflicts = Some(Arc::new(conflicts));
+        self
+    }
+
+    /// Sets `Cross-Origin-Resource-Policy` by the `Content-Type` of each response, overriding the
+    /// configured value for matching responses, see the [corp] module.
+    pub fn corp_by_content_type(&mut self, corp: corp::ContentTypeCorp) -> &mut Self {
+        self.corp = Some(Arc::new(corp));
+        self
+    }
+
+    /// Flags responses redirecting to origins outside the allowlist of `audit`, see the [redirect]
+    /// module.
+    pub fn audit_redirects(&mut self, audit: redirect::RedirectAudit) -> &mut Self {
+        self.redirects = Some(Arc::new(audit));
+        self
+    }
+
+    /// Flags responses whose CORS headers are wide-open or conflict with their
+    /// `Cross-Origin-Resource-Policy`, see the [cors] module.
+    pub fn audit_cors(&mut self, audit: cors::CorsAudit) -> &mut Self {
+        self.cors = Some(Arc::new(audit));
+        self
+    }
+
+    /// Sets the `frame-ancestors` directive of the CSP to the partners of `handle`, which can be
+    /// changed while the application is running, see the [frame_ancestors] module.
+    pub fn frame_ancestors(&mut self, handle: frame_ancestors::FrameAncestorsHandle) -> &mut Self {
+        self.frame_ancestors = Some(handle);
+        self
+    }
+
+    /// Sends the headers of `rollout` for requests whose feature flag is enabled, see the
+    /// [rollout] module. Only the layer for `http` 1.x services consults the flags.
+    pub fn rollout(&mut self, rollout: rollout::Rollout) -> &mut Self {
+        self.rollout = Some(Arc::new(rollout));
+        self
+    }
+
+    /// Adds the [fingerprint](fingerprint::Fingerprint) of the headers chosen for every response
+    /// to the response, see the [fingerprint] module. Only the layer for `http` 1.x services
+    /// fingerprints responses.
+    pub fn fingerprints(&mut self, fingerprints: fingerprint::Fingerprints) -> &mut Self {
+        self.fingerprints = Some(Arc::new(fingerprints));
+        self
+    }
+
+    /// Compares the headers of sampled requests with the candidate headers of `shadow` without
+    /// sending them, see the [shadow] module. Only the layer for `http` 1.x services compares
+    /// them.
+    pub fn shadow(&mut self, shadow: shadow::Shadow) -> &mut Self {
+        self.shadow = Some(Arc::new(shadow));
+        self
+    }
+
+    /// Adjusts the headers of requests from some browsers, based on their `User-Agent`, see the
+    /// [user_agent] module. The `User-Agent` is added to the `Vary` header of every response.
+    pub fn adjust_for_user_agents(
+        &mut self,
+        adjustments: user_agent::UserAgentAdjustments,
+    ) -> &mut Self {
+        self.user_agent = Some(Arc::new(adjustments));
+        self
+    }
+
+    /// Sets `Reporting-Endpoints`, `Report-To` and `NEL` from `config` and points the `report-to`
+    /// of the configured CSP, COOP and COEP to its groups, see the [reporting] module. Call it
+    /// after these headers have been enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` is invalid, see [`reporting::ReportingConfig::validate`].
+    pub fn reporting(&mut self, config: reporting::ReportingConfig) -> &mut Self {
+        if let Err(error) = config.validate() {
+            panic!("{}", error);
+        }
+        self.update(|headers| config.apply(headers));
+        self
+    }
+
+    /// Tailors the currently configured headers to the given [`BrowserBaseline`], adding or
+    /// removing legacy headers. Call it after all headers have been enabled.
+    pub fn target(&mut self, baseline: BrowserBaseline) -> &mut Self {
+        self.update(|headers| baseline.apply(headers));
+        self
+    }
+
+    /// Estimates the [Mozilla Observatory](https://observatory.mozilla.org/) score of the
+    /// configured headers, together with the findings which cost or gained points.
+    ///
+    /// ```
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let grade = HelmetLayer::with_defaults().grade();
+    /// assert_eq!(grade.letter(), "A+");
+    /// ```
+    pub fn grade(&self) -> grade::Grade {
+        grade::grade(&self.headers())
+    }
+
+    /// Reports which browser-related requirements of PCI DSS the configured headers satisfy, see
+    /// [`compliance::pci_dss`].
+    pub fn pci_dss_checklist(&self) -> compliance::Checklist {
+        compliance::pci_dss(&self.headers())
+    }
+
+    /// Reports which OWASP ASVS controls the configured headers cover, see
+    /// [`compliance::asvs`].
+    pub fn asvs_checklist(&self) -> compliance::Checklist {
+        compliance::asvs(&self.headers())
+    }
+
+    /// The [fingerprint](fingerprint::Fingerprint) of the configured headers, which changes
+    /// whenever they do.
+    pub fn fingerprint(&self) -> fingerprint::Fingerprint {
+        fingerprint::Fingerprint::of(&self.headers())
+    }
+
+    /// Lints the configured headers for common misconfigurations, such as a weak HSTS `max-age` or
+    /// a missing CSP. See the [audit] module for more.
+    pub fn audit(&self) -> Vec<audit::Finding> {
+        audit::audit(&self.headers())
+    }
+
+    /// Checks whether the configured `Strict-Transport-Security` header meets the requirements of
+    /// the HSTS preload list, see the [preload] module.
+    #[cfg(feature = "preload")]
+    pub fn check_preload(&self) -> Result<header::StrictTransportSecurity, preload::Problem> {
+        preload::check(&self.headers())
+    }
+
+    /// Checks the configured headers against `strict`, for example at startup, failing with the
+    /// findings which violate it.
+    ///
+    /// ```
+    /// use tower_helmet::audit::Strict;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// HelmetLayer::with_defaults()
+    ///     .enforce(&Strict::new())
+    ///     .expect("the headers meet the minimum standard");
+    /// ```
+    pub fn enforce(&self, strict: &audit::Strict) -> Result<(), audit::StrictError> {
+        strict.check(&self.headers())
+    }
+
+    /// Like [`HelmetLayer::audit`], additionally checking that the configured headers enable
+    /// cross-origin isolation, for pages which need `crossOriginIsolated` (see
+    /// [`audit::audit_cross_origin_isolated`]).
+    pub fn audit_cross_origin_isolated(&self) -> Vec<audit::Finding> {
+        let mut findings = audit::audit(&self.headers());
+        findings.extend(audit::audit_cross_origin_isolated(&self.headers()));
+        findings
+    }
+
+    /// Checks that the configured headers fit into `budget`, see [`audit::audit_header_size`].
+    /// Pages with a nonce get a slightly longer CSP, and the application adds headers of its own,
+    /// so the budget should leave some room.
+    ///
+    /// ```
+    /// use tower_helmet::audit::HeaderBudget;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// assert!(HelmetLayer::with_defaults()
+    ///     .audit_header_size(&HeaderBudget::default())
+    ///     .is_empty());
+    /// ```
+    pub fn audit_header_size(&self, budget: &audit::HeaderBudget) -> Vec<audit::Finding> {
+        audit::audit_header_size(&self.headers(), budget)
+    }
+
+    /// Builds a structured JSON document of all configured headers, with the directives of
+    /// `Content-Security-Policy`, `Strict-Transport-Security`, `Expect-CT` and `Referrer-Policy`
+    /// parsed into their values. Requires the `json` feature.
+    ///
+    /// ```text
+    /// {
+    ///   "headers": [
+    ///     {
+    ///       "name": "content-security-policy",
+    ///       "value": "base-uri 'self'; default-src 'self'; ...",
+    ///       "directives": { "base-uri": ["'self'"], "default-src": ["'self'"], ... }
+    ///     },
+    ///     ...
+    ///   ]
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json_report(&self) -> serde_json::Value {
+        export::json_report(&self.headers())
+    }
+
+    /// Renders the configured headers as nginx `add_header` directives, for moving enforcement
+    /// to the edge.
+    ///
+    /// ```
+    /// use tower_helmet::header::XFrameOptions;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::blank();
+    /// layer.enable(XFrameOptions::Deny);
+    /// assert_eq!(layer.to_nginx(), "add_header x-frame-options \"DENY\" always;\n");
+    /// ```
+    pub fn to_nginx(&self) -> String {
+        export::nginx(&self.headers())
+    }
+
+    /// Renders the configured headers as a Caddyfile `header` block, for moving enforcement to
+    /// the edge.
+    ///
+    /// ```
+    /// use tower_helmet::header::XFrameOptions;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::blank();
+    /// layer.enable(XFrameOptions::Deny);
+    /// assert_eq!(layer.to_caddy(), "header {\n\tx-frame-options \"DENY\"\n}\n");
+    /// ```
+    pub fn to_caddy(&self) -> String {
+        export::caddy(&self.headers())
+    }
+
+    /// Renders the configured headers for snapshot testing, for example with `insta`, so changes
+    /// show up as readable diffs.
+    ///
+    /// Headers are sorted by name, one per line. CSP directives are sorted and put on their own
+    /// lines, with nonces redacted, so [`export::snapshot`] also renders the headers of responses
+    /// with a [`CspNonce`] stably.
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    ///
+    /// use tower_helmet::header::{ContentSecurityPolicy, XFrameOptions};
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let csp = ContentSecurityPolicy::try_from("script-src 'self'; default-src 'none'").unwrap();
+    /// let mut layer = HelmetLayer::blank();
+    /// layer.enable(XFrameOptions::Deny).enable(csp);
+    ///
+    /// assert_eq!(
+    ///     layer.to_snapshot(),
+    ///     "content-security-policy:\n  default-src 'none'\n  script-src 'self'\nx-frame-options: DENY\n"
+    /// );
+    /// ```
+    pub fn to_snapshot(&self) -> String {
+        export::snapshot(&self.headers())
+    }
+
+    /// Renders the configured headers as a Netlify `_headers` file, applying them to `path` (such
+    /// as `/*`).
+    ///
+    /// ```
+    /// use tower_helmet::header::XFrameOptions;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut layer = HelmetLayer::blank();
+    /// layer.enable(XFrameOptions::Deny);
+    /// assert_eq!(layer.to_netlify("/*"), "/*\n  x-frame-options: DENY\n");
+    /// ```
+    pub fn to_netlify(&self, path: &str) -> String {
+        export::netlify(&self.headers(), path)
+    }
+
+    /// Renders the configured headers as a `vercel.json` document, applying them to the routes
+    /// matching `source` (such as `/(.*)`). Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn to_vercel(&self, source: &str) -> String {
+        export::vercel(&self.headers(), source)
+    }
+
+    /// Compares the configured headers against a [`diff::Baseline`] or another layer, returning
+    /// the added, removed and changed headers, including directive-level CSP differences.
+    pub fn diff(&self, baseline: &impl IntoHeaders) -> diff::Diff {
+        diff::Diff::new(&self.headers(), &baseline.header_map().unwrap())
+    }
+
+    /// The configured headers as [`HeaderSet`], for inspecting them field by field. Headers it
+    /// can't hold are kept in [`HelmetLayer::other_headers`].
+    pub fn header_set(&self) -> &HeaderSet {
+        &self.set
+    }
+
+    /// The configured headers [`HeaderSet`] has no field for, like `Permissions-Policy`, together
+    /// with known headers which are set more than once or whose value doesn't render the same once
+    /// parsed, so they are sent exactly as enabled.
+    ///
+    /// ```
+    /// use http::{HeaderMap, HeaderValue};
+    /// use tower_helmet::header::XFrameOptions;
+    /// use tower_helmet::HelmetLayer;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-frame-options", HeaderValue::from_static("DENY"));
+    /// headers.insert("permissions-policy", HeaderValue::from_static("camera=()"));
+    ///
+    /// let layer = HelmetLayer::from_header_map(&headers);
+    /// assert_eq!(layer.header_set().x_frame_options, Some(XFrameOptions::Deny));
+    /// assert_eq!(layer.other_headers()["permissions-policy"], "camera=()");
+    /// ```
+    pub fn other_headers(&self) -> &HeaderMap {
+        &self.others
+    }
+
     pub fn remove<K>(&mut self, key: K) -> &mut Self
     where
         K: AsHeaderName,
     {
-        self.headers.remove(key);
+        self.update(|headers| {
+            headers.remove(key);
+        });
         self
     }
 }
 
+impl From<HeaderSet> for HelmetLayer {
+    fn from(set: HeaderSet) -> Self {
+        let mut layer = Self::blank();
+        layer.enable(set);
+        layer
+    }
+}
+
+/// Enabling a layer in another one copies all of its headers.
+impl IntoHeaders for HelmetLayer {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        Ok(self.headers())
+    }
+}
+
+/// Prints every configured header as `name: value` on its own line, sorted by name.
+///
+/// ```
+/// use tower_helmet::header::XFrameOptions;
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut layer = HelmetLayer::blank();
+/// layer.enable(XFrameOptions::Deny);
+/// assert_eq!(layer.to_string(), "x-frame-options: DENY\n");
+/// ```
+impl Display for HelmetLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in export::sorted(&self.headers()) {
+            writeln!(f, "{}: {}", name, String::from_utf8_lossy(value.as_bytes()))?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<S> Layer<S> for HelmetLayer {
     type Service = HelmetService<S>;
 
     fn layer(&self, service: S) -> Self::Service {
+        assert!(
+            self.fallback != provider::Fallback::Fail,
+            "`Fallback::Fail` needs a response body to fail with, wrap the service with a \
+             `provider::FailClosedLayer` instead"
+        );
+        self.service(service)
+    }
+}
+
+impl HelmetLayer {
+    /// Wraps `service` without checking the [fallback](HelmetLayer::fallback).
+    pub(crate) fn service<S>(&self, service: S) -> HelmetService<S> {
+        let headers = self.headers();
+        #[cfg(feature = "tracing")]
+        if !self.logged.swap(true, Ordering::Relaxed) {
+            tracing::info!(
+                headers = headers.len(),
+                fingerprint = %self.fingerprint(),
+                "tower-helmet security headers configured"
+            );
+            for (name, value) in &headers {
+                tracing::debug!(header = %name, value = ?value, "tower-helmet header");
+            }
+            // reports to undefined groups are silently dropped by browsers
+            for finding in audit::audit(&headers)
+                .iter()
+                .filter(|f| f.rule == audit::Rule::UndefinedReportingGroup)
+            {
+                tracing::warn!(rule = %finding.rule, "{}", finding.message);
+            }
+        }
+
         HelmetService {
             inner: service,
-            headers: self.headers.clone(),
+            headers,
+            nonce: self.nonce,
+            forward_nonce: self.forward_nonce.clone(),
+            grpc: self.grpc.clone(),
+            routes: self.routes.clone(),
+            provider: self.provider.clone(),
+            fallback: self.fallback,
+            explain: self.explain.clone(),
+            frame_ancestors: self.frame_ancestors.clone(),
+            rollout: self.rollout.clone(),
+            shadow: self.shadow.clone(),
+            fingerprints: self.fingerprints.clone(),
+            user_agent: self.user_agent.clone(),
+            #[cfg(feature = "session-nonce")]
+            session_nonce: self.session_nonce.clone(),
+            vary: self.vary(),
+            conflicts: self.conflicts.clone(),
+            cookies: self.cookies.clone(),
+            corp: self.corp.clone(),
+            redirects: self.redirects.clone(),
+            cors: self.cors.clone(),
         }
     }
 }
@@ -130,6 +999,27 @@ impl<S> Layer<S> for HelmetLayer {
 pub struct HelmetService<S> {
     inner: S,
     headers: HeaderMap,
+    nonce: Option<&'static [&'static str]>,
+    forward_nonce: Option<HeaderName>,
+    grpc: Option<HeaderMap>,
+    routes: Option<Arc<router::PolicyRouter>>,
+    provider: Option<Arc<dyn provider::PolicyProvider>>,
+    fallback: provider::Fallback,
+    explain: Option<Arc<explain::Explain>>,
+    frame_ancestors: Option<frame_ancestors::FrameAncestorsHandle>,
+    rollout: Option<Arc<rollout::Rollout>>,
+    shadow: Option<Arc<shadow::Shadow>>,
+    fingerprints: Option<Arc<fingerprint::Fingerprints>>,
+    user_agent: Option<Arc<user_agent::UserAgentAdjustments>>,
+    #[cfg(feature = "session-nonce")]
+    session_nonce: Option<Arc<session_nonce::SessionNonce>>,
+    /// Request headers the sent headers depend on, see [`HelmetLayer::vary`].
+    vary: Arc<[HeaderName]>,
+    conflicts: Option<Arc<conflict::ConflictStrategies>>,
+    cookies: Option<Arc<cookie::CookiePolicy>>,
+    corp: Option<Arc<corp::ContentTypeCorp>>,
+    redirects: Option<Arc<redirect::RedirectAudit>>,
+    cors: Option<Arc<cors::CorsAudit>>,
 }
 
 impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for HelmetService<S>
@@ -144,22 +1034,341 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let is_grpc = preset::is_grpc(request.headers());
+        let mut explanation = self
+            .explain
+            .as_ref()
+            .filter(|explain| explain.selects(request.headers()))
+            .map(|_| explain::Explanation::new(request.uri().path()));
+        let rollout = match &self.rollout {
+            Some(rollout) if !(is_grpc && self.grpc.is_some()) => {
+                let (parts, body) = request.into_parts();
+                let enabled = rollout.enabled(&parts);
+                request = Request::from_parts(parts, body);
+                enabled
+            }
+            _ => None,
+        };
+        let prepared = self.prepare(
+            is_grpc,
+            request.uri().path(),
+            request
+                .headers()
+                .get(http::header::USER_AGENT)
+                .map(HeaderValue::as_bytes),
+            self.session_id(|| Cow::Borrowed(request.headers())),
+            rollout,
+            explanation.as_mut(),
+        );
+        // the provider isn't asked if the fallback already replaced the headers
+        let degraded = prepared.is_err();
+        if let (true, Some(explanation)) = (degraded, &mut explanation) {
+            explanation.source = explain::Source::Fallback;
+        }
+        let (headers, adjustments, failed) = or_fallback(prepared);
+        let nonce = adjustments.nonce();
+        if let Some(name) = &self.forward_nonce {
+            // the client must not choose the nonce the upstream renders
+            request.headers_mut().remove(name);
+        }
+        if let Some(nonce) = &nonce {
+            request.extensions_mut().insert(nonce.clone());
+            if let Some(name) = &self.forward_nonce {
+                let value = HeaderValue::from_str(&nonce.to_string()).unwrap();
+                request.headers_mut().insert(name.clone(), value);
+            }
+        }
+        let shadow = match &self.shadow {
+            Some(shadow) if !(degraded || is_grpc && self.grpc.is_some()) => {
+                shadow.sample(request.uri().path(), &adjustments)
+            }
+            _ => None,
+        };
+
+        let provided = match &self.provider {
+            Some(provider) if !(degraded || is_grpc && self.grpc.is_some()) => {
+                let (parts, body) = request.into_parts();
+                let pending = provider::Pending::new(provider.as_ref(), &parts, adjustments);
+                request = Request::from_parts(parts, body);
+                Some(pending)
+            }
+            _ => None,
+        };
+
         ResponseFuture {
-            future: self.inner.call(request),
-            headers: self.headers.clone(),
+            // the request is answered without the inner service if it fails anyway
+            future: if failed {
+                None
+            } else {
+                Some(self.inner.call(request))
+            },
+            headers,
+            provided,
+            fallback: self.fallback,
+            failed,
+            shadow,
+            explanation,
+            fingerprints: self.fingerprints.clone(),
+            nonce,
+            private: self.private_max_age(),
+            vary: self.vary.clone(),
+            conflicts: self.conflicts.clone(),
+            cookies: self.cookies.clone(),
+            corp: self.corp.clone(),
+            redirects: self.redirects.clone(),
+            cors: self.cors.clone(),
         }
     }
 }
 
+impl<S> HelmetService<S> {
+    /// The headers to send for a request, those of the route matching its `path` if any, adjusted
+    /// for its `User-Agent` and with the nonce added if enabled, derived from its session if it
+    /// has one and nonces are [stable per session](HelmetLayer::session_nonce).
+    /// Returns them with the adjustments made, which are applied to the headers of a
+    /// [`provider::PolicyProvider`] as well, or the [fallback](HelmetLayer::fallback) if no nonce
+    /// could be generated and it is [`provider::Fallback::Nothing`] or
+    /// [`provider::Fallback::Fail`].
+    fn prepare(
+        &self,
+        is_grpc: bool,
+        path: &str,
+        user_agent: Option<&[u8]>,
+        session_id: Option<Vec<u8>>,
+        rollout: Option<HeaderMap>,
+        mut explanation: Option<&mut explain::Explanation>,
+    ) -> Result<(HeaderMap, provider::Adjustments), provider::Fallback> {
+        if let Some(grpc) = self.grpc.as_ref().filter(|_| is_grpc) {
+            if let Some(explanation) = explanation {
+                explanation.source = explain::Source::Grpc;
+            }
+            return Ok((grpc.clone(), provider::Adjustments::default()));
+        }
+
+        let route = self.routes.as_ref().and_then(|routes| routes.headers(path));
+        if let (Some(_), Some(explanation)) = (route, explanation.as_deref_mut()) {
+            explanation.source = explain::Source::Route;
+        }
+        let mut headers = route.unwrap_or(&self.headers).clone();
+        let mut nonces = Vec::new();
+        if self.nonce.is_some() {
+            #[cfg(feature = "session-nonce")]
+            if let (Some(session_nonce), Some(session_id)) = (&self.session_nonce, &session_id) {
+                let (nonce, previous) = session_nonce.nonces(session_id, clock::now());
+                nonces.extend([nonce, previous]);
+            }
+            if nonces.is_empty() {
+                match CspNonce::try_generate() {
+                    Ok(nonce) => nonces.push(nonce),
+                    Err(error) => {
+                        self.report_failure(&error, "failed to generate a nonce");
+                        if self.fallback != provider::Fallback::Configured {
+                            return Err(self.fallback);
+                        }
+                    }
+                }
+            }
+        }
+        #[cfg(not(feature = "session-nonce"))]
+        let _ = session_id;
+
+        let adjustments = provider::Adjustments {
+            rollout,
+            user_agent: self
+                .user_agent
+                .clone()
+                .map(|adjustments| (adjustments, user_agent.map(<[u8]>::to_vec))),
+            frame_ancestors: self
+                .frame_ancestors
+                .as_ref()
+                .map(|handle| (handle.clone(), handle.snapshot())),
+            directives: self.nonce.unwrap_or_default(),
+            nonces,
+        };
+        adjustments.apply(&mut headers, explanation);
+
+        Ok((headers, adjustments))
+    }
+
+    /// Logs that the headers of a request couldn't be computed, with the `tracing` feature.
+    fn report_failure(&self, error: &dyn std::fmt::Display, message: &str) {
+        report_failure(error, message, self.fallback);
+    }
+
+    /// The id of the request's session if nonces are stable per session, with the request headers
+    /// only built if needed.
+    fn session_id<'a>(&self, headers: impl FnOnce() -> Cow<'a, HeaderMap>) -> Option<Vec<u8>> {
+        #[cfg(feature = "session-nonce")]
+        if let Some(session_nonce) = &self.session_nonce {
+            return session_nonce.session_id(&headers());
+        }
+        #[cfg(not(feature = "session-nonce"))]
+        let _ = headers;
+        None
+    }
+
+    /// How long private caches may store responses with a nonce if nonces are stable per session,
+    /// in which case they are kept out of shared caches.
+    fn private_max_age(&self) -> Option<std::time::Duration> {
+        #[cfg(feature = "session-nonce")]
+        if let Some(session_nonce) = &self.session_nonce {
+            return Some(session_nonce.rotation());
+        }
+        None
+    }
+
+    /// Applies the `headers` prepared for a request to its response like [`ResponseFuture`], for
+    /// adapters of frameworks whose responses aren't `http` 1.x ones.
+    #[cfg(any(feature = "actix-web", feature = "rocket", feature = "warp"))]
+    pub(crate) fn respond(
+        &self,
+        status: http::StatusCode,
+        response_headers: &mut HeaderMap,
+        headers: &HeaderMap,
+        nonce: Option<&CspNonce>,
+        header_override: Option<HeaderOverride>,
+    ) {
+        finish(
+            response_headers,
+            headers,
+            self.conflicts.as_deref(),
+            header_override,
+            &self.vary,
+            self.cookies.as_deref(),
+            self.corp.as_deref(),
+            None,
+        );
+        if nonce.is_some() {
+            match self.private_max_age() {
+                Some(max_age) => nonce::keep_private(response_headers, max_age),
+                #[cfg(feature = "tracing")]
+                None => nonce::warn_if_cacheable(response_headers),
+                #[cfg(not(feature = "tracing"))]
+                None => {}
+            }
+        }
+        if let Some(redirects) = self.redirects.as_deref() {
+            redirects.report(status, response_headers);
+        }
+        if let Some(cors) = self.cors.as_deref() {
+            cors.report(response_headers);
+        }
+    }
+}
+
+/// The prepared headers, or none if the [fallback](HelmetLayer::fallback) replaced them, and
+/// whether the request has to fail.
+fn or_fallback(
+    prepared: Result<(HeaderMap, provider::Adjustments), provider::Fallback>,
+) -> (HeaderMap, provider::Adjustments, bool) {
+    match prepared {
+        Ok((headers, adjustments)) => (headers, adjustments, false),
+        Err(fallback) => (
+            HeaderMap::new(),
+            provider::Adjustments::default(),
+            fallback == provider::Fallback::Fail,
+        ),
+    }
+}
+
+/// Logs that the headers of a request couldn't be computed and `fallback` is used instead, with
+/// the `tracing` feature.
+fn report_failure(error: &dyn std::fmt::Display, message: &str, fallback: provider::Fallback) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(%error, ?fallback, "{}", message);
+    #[cfg(not(feature = "tracing"))]
+    let _ = (error, message, fallback);
+}
+
+/// Sets the layer's headers on a response, resolving conflicts with its values by `conflicts`,
+/// with the CORP for its content type if configured and applying its [`HeaderOverride`] last, adds
+/// the request headers they depend on to `Vary` and hardens its cookies.
+#[allow(clippy::too_many_arguments)]
+fn finish(
+    response_headers: &mut HeaderMap,
+    headers: &HeaderMap,
+    conflicts: Option<&conflict::ConflictStrategies>,
+    header_override: Option<HeaderOverride>,
+    vary: &[HeaderName],
+    cookies: Option<&cookie::CookiePolicy>,
+    corp: Option<&corp::ContentTypeCorp>,
+    mut explanation: Option<&mut explain::Explanation>,
+) {
+    use explain::{Explanation, Step};
+
+    Explanation::trace(
+        explanation.as_deref_mut(),
+        Step::Response,
+        response_headers,
+        |response_headers| match conflicts {
+            Some(conflicts) => conflicts.apply(response_headers, headers),
+            None => {
+                for (name, value) in headers {
+                    response_headers.insert(name, value.clone());
+                }
+            }
+        },
+    );
+    if let Some(corp) = corp {
+        Explanation::trace(
+            explanation.as_deref_mut(),
+            Step::ContentType,
+            response_headers,
+            |response_headers| corp.apply(response_headers),
+        );
+    }
+    if let Some(header_override) = header_override {
+        Explanation::trace(
+            explanation.as_deref_mut(),
+            Step::Override,
+            response_headers,
+            |response_headers| header_override.apply(response_headers),
+        );
+    }
+    Explanation::trace(
+        explanation.as_deref_mut(),
+        Step::Vary,
+        response_headers,
+        |response_headers| vary::merge(response_headers, vary),
+    );
+    if let Some(cookies) = cookies {
+        Explanation::trace(
+            explanation,
+            Step::Cookies,
+            response_headers,
+            |response_headers| cookies.apply(response_headers),
+        );
+    }
+}
+
 pin_project! {
     /// Response future for [`HelmetService`].
     #[derive(Debug)]
     pub struct ResponseFuture<F> {
         #[pin]
-        future: F,
+        future: Option<F>,
 
         headers: HeaderMap,
+        // the headers being loaded by the policy provider, replacing `headers` unless `None`
+        provided: Option<provider::Pending>,
+        fallback: provider::Fallback,
+        // whether the response is replaced with a `500 Internal Server Error`
+        failed: bool,
+        // the shadow headers of a sampled request, compared once the provider is done
+        shadow: Option<shadow::Pending>,
+        // how the headers were decided, if the request is explained
+        explanation: Option<explain::Explanation>,
+        fingerprints: Option<Arc<fingerprint::Fingerprints>>,
+        nonce: Option<CspNonce>,
+        // the longest responses with a session nonce may be stored by private caches
+        private: Option<std::time::Duration>,
+        vary: Arc<[HeaderName]>,
+        conflicts: Option<Arc<conflict::ConflictStrategies>>,
+        cookies: Option<Arc<cookie::CookiePolicy>>,
+        corp: Option<Arc<corp::ContentTypeCorp>>,
+        redirects: Option<Arc<redirect::RedirectAudit>>,
+        cors: Option<Arc<cors::CorsAudit>>,
     }
 }
 
@@ -170,12 +1379,105 @@ where
     type Output = F::Output;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // only services built by a `FailClosedLayer` fail requests
+        self.poll_response(cx, || {
+            unreachable!("`Fallback::Fail` without `FailClosedLayer`")
+        })
+    }
+}
+
+impl<F, ResBody, E> ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    /// Polls the response, answering with the response of `fail` if the request failed.
+    pub(crate) fn poll_response(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        fail: impl FnOnce() -> Response<ResBody>,
+    ) -> Poll<Result<Response<ResBody>, E>> {
         let this = self.project();
-        let mut res: Response<ResBody> = ready!(this.future.poll(cx)?);
-        let headers = res.headers_mut();
+        if let Some(provided) = this.provided {
+            match ready!(provided.poll(cx)) {
+                Ok(Some(headers)) => {
+                    if let Some(explanation) = this.explanation {
+                        explanation.source = explain::Source::Provider;
+                        explanation.record(explain::Step::Provider, this.headers, &headers);
+                    }
+                    *this.headers = headers;
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    report_failure(&error, "the policy provider failed", *this.fallback);
+                    match this.fallback {
+                        provider::Fallback::Configured => {}
+                        provider::Fallback::Nothing => {
+                            if let Some(explanation) = this.explanation {
+                                explanation.source = explain::Source::Fallback;
+                                explanation.record(
+                                    explain::Step::Fallback,
+                                    this.headers,
+                                    &HeaderMap::new(),
+                                );
+                            }
+                            this.headers.clear();
+                        }
+                        provider::Fallback::Fail => *this.failed = true,
+                    }
+                }
+            }
+            *this.provided = None;
+        }
+        if let Some(shadow) = this.shadow.take() {
+            shadow.compare(this.headers);
+        }
+        let mut res = match this.future.as_pin_mut() {
+            Some(future) if !*this.failed => {
+                let _current = nonce::enter(this.nonce.as_ref());
+                ready!(future.poll(cx)?)
+            }
+            // the future of the inner service is dropped without being polled
+            _ => fail(),
+        };
+        let header_override = res.extensions_mut().remove::<HeaderOverride>();
+        if let Some(fingerprints) = this.fingerprints.as_deref() {
+            fingerprints.apply(this.headers, &mut res);
+        }
 
-        for (name, value) in this.headers {
-            headers.insert(name, value.clone());
+        finish(
+            res.headers_mut(),
+            this.headers,
+            this.conflicts.as_deref(),
+            header_override,
+            this.vary,
+            this.cookies.as_deref(),
+            this.corp.as_deref(),
+            this.explanation.as_mut(),
+        );
+        if let Some(nonce) = this.nonce.take() {
+            match *this.private {
+                Some(max_age) => explain::Explanation::trace(
+                    this.explanation.as_mut(),
+                    explain::Step::Private,
+                    res.headers_mut(),
+                    |headers| nonce::keep_private(headers, max_age),
+                ),
+                #[cfg(feature = "tracing")]
+                None => nonce::warn_if_cacheable(res.headers()),
+                #[cfg(not(feature = "tracing"))]
+                None => {}
+            }
+            res.extensions_mut().insert(nonce);
+        }
+        if let Some(redirects) = this.redirects.as_deref() {
+            redirects.report(res.status(), res.headers());
+        }
+        if let Some(cors) = this.cors.as_deref() {
+            cors.report(res.headers());
+        }
+        if let Some(explanation) = this.explanation.take() {
+            explanation.log();
+            res.extensions_mut().insert(explanation);
         }
 
         Poll::Ready(Ok(res))
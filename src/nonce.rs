@@ -0,0 +1,224 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY};
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::parse_directives;
+
+/// A per-request nonce for [CSP nonce sources](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/script-src#unsafe_inline_script).
+///
+/// If nonces are enabled with [`crate::HelmetLayer::enable_nonce`], a fresh nonce is generated for
+/// every request, added as `'nonce-<value>'` to the CSP and stored in the extensions of both the
+/// request and the response, so handlers and templates can render it into `<script nonce="...">`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CspNonce(Arc<str>);
+
+impl CspNonce {
+    /// Generates a new nonce from 16 random bytes, encoded as base64.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system has no random numbers available.
+    pub fn generate() -> Self {
+        Self::try_generate().expect("failed to generate a random nonce")
+    }
+
+    /// Like [`CspNonce::generate`], failing if no random numbers are available.
+    pub(crate) fn try_generate() -> Result<Self, getrandom::Error> {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes)?;
+
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// A nonce from the given bytes, encoded as base64.
+    #[cfg_attr(not(feature = "session-nonce"), allow(dead_code))]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        CspNonce(STANDARD.encode(bytes).into())
+    }
+
+    /// The nonce of the request currently being handled by this thread.
+    ///
+    /// It is set while the inner service's future is polled, so it is available in handlers and
+    /// templates rendered by them, but not in tasks spawned from there.
+    pub fn current() -> Option<CspNonce> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for CspNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<CspNonce>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous [`CspNonce::current`] when dropped.
+pub(crate) struct CurrentGuard(Option<CspNonce>);
+
+/// Makes `nonce` the [`CspNonce::current`] until the returned guard is dropped.
+pub(crate) fn enter(nonce: Option<&CspNonce>) -> Option<CurrentGuard> {
+    let nonce = nonce?.clone();
+    Some(CurrentGuard(
+        CURRENT.with(|current| current.replace(Some(nonce))),
+    ))
+}
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Logs a warning if a response with a nonce is cacheable, see
+/// [`crate::audit::audit_nonce_caching`].
+#[cfg(feature = "tracing")]
+pub(crate) fn warn_if_cacheable(headers: &HeaderMap) {
+    if let Some(finding) = crate::audit::audit_nonce_caching(headers) {
+        tracing::warn!(rule = %finding.rule, "{}", finding.message);
+    }
+}
+
+/// Keeps a response with a nonce which is stable per session out of shared caches and caps how
+/// long private caches store it at `max_age`, the rotation period of the nonce. Without a
+/// `max-age`, it is set to `max_age`, so private caches don't store it longer heuristically.
+pub(crate) fn keep_private(headers: &mut HeaderMap, max_age: Duration) {
+    let directives: Vec<String> = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|d| d.trim().to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    if directives.iter().any(|d| d == "no-store") {
+        return;
+    }
+
+    let mut kept = vec!["private".to_owned()];
+    for directive in directives {
+        if let Some(value) = directive.strip_prefix("max-age=") {
+            let value = value.trim_matches('"').parse().unwrap_or(0);
+            kept.push(format!("max-age={}", max_age.as_secs().min(value)));
+        } else if directive != "public"
+            && directive != "private"
+            && !directive.starts_with("s-maxage=")
+        {
+            kept.push(directive);
+        }
+    }
+    if !kept.iter().any(|d| d.starts_with("max-age=")) {
+        kept.push(format!("max-age={}", max_age.as_secs()));
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&kept.join(", ")) {
+        headers.insert(CACHE_CONTROL, value);
+    }
+}
+
+/// Adds the nonce to the given directives of the CSP headers in `headers`.
+pub(crate) fn apply(headers: &mut HeaderMap, nonce: &CspNonce, directives: &[&str]) {
+    let source = format!("'nonce-{}'", nonce);
+    add_sources(headers, directives, &[source]);
+}
+
+/// Adds the given sources to the given directives of the CSP headers in `headers`.
+pub(crate) fn add_sources(headers: &mut HeaderMap, directives: &[&str], sources: &[String]) {
+    update(headers, |policy| with_sources(policy, directives, sources));
+}
+
+/// Removes the nonce from the CSP headers in `headers` again.
+#[cfg_attr(not(feature = "html"), allow(dead_code))]
+pub(crate) fn remove(headers: &mut HeaderMap, nonce: &CspNonce) {
+    let source = format!("'nonce-{}'", nonce);
+    update(headers, |policy| {
+        let directives: Vec<(&str, Vec<&str>)> = parse_directives(policy)
+            .into_iter()
+            .map(|(name, sources)| {
+                let sources = sources.into_iter().filter(|s| *s != source).collect();
+                (name, sources)
+            })
+            .collect();
+        render(&directives)
+    });
+}
+
+/// Replaces the CSP headers in `headers` with the result of `f`.
+pub(crate) fn update(headers: &mut HeaderMap, f: impl Fn(&str) -> String) {
+    for name in [CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY] {
+        let value = match headers.get(&name).and_then(|v| v.to_str().ok()) {
+            Some(value) => f(value),
+            None => continue,
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+fn with_sources(policy: &str, targets: &[&str], new_sources: &[String]) -> String {
+    let mut directives: Vec<(&str, Vec<&str>)> = parse_directives(policy);
+
+    for target in targets {
+        if !directives.iter().any(|(name, _)| name == target) {
+            // a new directive replaces its fallback, so it starts out with the fallback's sources
+            let sources = fallbacks(target)
+                .iter()
+                .find_map(|fallback| directives.iter().find(|(name, _)| name == fallback))
+                .map(|(_, sources)| sources.clone());
+            match sources {
+                Some(sources) => directives.push((target, sources)),
+                None => continue,
+            }
+        }
+
+        let (_, sources) = directives
+            .iter_mut()
+            .find(|(name, _)| name == target)
+            .unwrap();
+        for source in new_sources {
+            if !sources.contains(&source.as_str()) {
+                sources.push(source);
+            }
+        }
+    }
+
+    render(&directives)
+}
+
+/// The directives browsers fall back to if `directive` isn't set, in order.
+pub(crate) fn fallbacks(directive: &str) -> &'static [&'static str] {
+    match directive {
+        "script-src-elem" | "script-src-attr" => &["script-src", "default-src"],
+        "style-src-elem" | "style-src-attr" => &["style-src", "default-src"],
+        _ => &["default-src"],
+    }
+}
+
+pub(crate) fn render(directives: &[(&str, Vec<&str>)]) -> String {
+    directives
+        .iter()
+        .map(|(name, sources)| {
+            if sources.is_empty() {
+                name.to_string()
+            } else {
+                format!("{} {}", name, sources.join(" "))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
+}
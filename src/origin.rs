@@ -0,0 +1,331 @@
+//! Validation and normalization of origins and hosts from untrusted configuration.
+//!
+//! Origins allowed to frame a page, partner CDNs or CORS origins often come from a database, for
+//! example one entry per tenant. Put into a CSP as is, a value such as
+//! `https://partner.example; script-src *` adds a directive, and one with a space or quote adds
+//! sources. [`sanitize_origin`] and [`sanitize_host`] only accept a single well-formed origin or
+//! host source and return it normalized (lowercase, without trailing slash), so it can be used
+//! in CSP directives such as `frame-ancestors`, in CORS allowlists and in the other allowlists of
+//! this crate.
+//!
+//! ```
+//! use tower_helmet::origin::{sanitize_host, sanitize_origin};
+//!
+//! assert_eq!(sanitize_origin(" HTTPS://Partner.Example/ ").unwrap(), "https://partner.example");
+//! assert_eq!(sanitize_host("*.cdn.example:443").unwrap(), "*.cdn.example:443");
+//!
+//! assert!(sanitize_origin("https://partner.example; script-src *").is_err());
+//! assert!(sanitize_origin("https://partner.example/path").is_err());
+//! assert!(sanitize_host("cdn.example 'unsafe-inline'").is_err());
+//! ```
+//!
+//! # Origin sets
+//!
+//! The same origins often appear in several places: a CDN in `script-src`, `style-src` and
+//! `font-src`, a partner in `frame-ancestors` and the allowlist of a [`crate::csrf`] guard.
+//! [`OriginSets`] defines named groups of them once, validated like above. CSP directives
+//! reference a set as `@name`, which [`OriginSets::resolve`] replaces with its origins, while
+//! [`OriginSets::origins`] lists them for the allowlists of this crate and
+//! [`crate::frame_ancestors::FrameAncestorsHandle::add_set`] allows them to frame the
+//! application. When an origin changes, only its set has to be updated.
+//!
+//! ```
+//! use std::convert::TryFrom;
+//!
+//! use tower_helmet::header::ContentSecurityPolicy;
+//! use tower_helmet::origin::OriginSets;
+//! use tower_helmet::redirect::RedirectAudit;
+//!
+//! let mut sets = OriginSets::new();
+//! sets.define("cdn", ["https://cdn.example", "https://static.example"])
+//!     .unwrap()
+//!     .define("login", ["https://login.example"])
+//!     .unwrap();
+//!
+//! let csp = ContentSecurityPolicy::try_from("script-src 'self' @cdn; style-src @cdn").unwrap();
+//! assert_eq!(
+//!     sets.resolve(&csp).unwrap().directives["style-src"],
+//!     ["https://cdn.example", "https://static.example"]
+//! );
+//!
+//! let audit = RedirectAudit::new(sets.origins(&["login"]).unwrap());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use http::Uri;
+
+use crate::header::{validate_value_with, ContentSecurityPolicy, InvalidCharacterError};
+
+/// Characters which end a source or directive in a CSP, or an entry of a list header.
+const SEPARATORS: [char; 5] = [' ', ';', ',', '\'', '"'];
+
+/// Returned by [`sanitize_origin`] and [`sanitize_host`] for values which are not a single
+/// well-formed origin or host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidOriginError {
+    /// The value contains whitespace, a separator such as `;` or `,`, a quote or a control
+    /// character.
+    InvalidCharacter(InvalidCharacterError),
+    /// The value is not of the form `scheme://host[:port]` (or `host[:port]` for hosts).
+    Malformed(String),
+}
+
+impl Display for InvalidOriginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidOriginError::InvalidCharacter(error) => write!(f, "{}", error),
+            InvalidOriginError::Malformed(value) => write!(f, "malformed origin {:?}", value),
+        }
+    }
+}
+
+impl std::error::Error for InvalidOriginError {}
+
+impl From<InvalidCharacterError> for InvalidOriginError {
+    fn from(error: InvalidCharacterError) -> Self {
+        InvalidOriginError::InvalidCharacter(error)
+    }
+}
+
+/// Checks that `origin` is a single origin such as `https://example.com:8443`, returning it with
+/// lowercase scheme and host and without trailing slash. The host may start with a `*.` wildcard,
+/// as CSP allows. Paths, queries, fragments and userinfo are rejected.
+pub fn sanitize_origin(origin: &str) -> Result<String, InvalidOriginError> {
+    let origin = origin.trim();
+    validate_value_with("origin", origin, &SEPARATORS)?;
+    let malformed = || InvalidOriginError::Malformed(origin.to_owned());
+
+    let (scheme, rest) = origin.split_once("://").ok_or_else(malformed)?;
+    let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !is_scheme {
+        return Err(malformed());
+    }
+
+    let host = rest.strip_suffix('/').unwrap_or(rest);
+    let host = sanitize_host(host).map_err(|_| malformed())?;
+
+    Ok(format!("{}://{}", scheme.to_ascii_lowercase(), host))
+}
+
+/// Checks that `host` is a single host source such as `cdn.example.com`, `*.example.com:443` or
+/// `[::1]:8080`, returning it in lowercase. The port may be `*`, as CSP allows.
+pub fn sanitize_host(host: &str) -> Result<String, InvalidOriginError> {
+    let host = host.trim();
+    validate_value_with("host", host, &SEPARATORS)?;
+    let malformed = || InvalidOriginError::Malformed(host.to_owned());
+
+    let (name, port) = match host.rfind(':') {
+        Some(pos) if !host[pos..].contains(']') => (&host[..pos], Some(&host[pos + 1..])),
+        _ => (host, None),
+    };
+
+    let valid_port = |port: &str| {
+        port == "*"
+            || (!port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit()))
+    };
+    if !port.is_none_or(valid_port) {
+        return Err(malformed());
+    }
+
+    let valid_name = match name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+        Some(ip) => {
+            !ip.is_empty()
+                && ip
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.')
+        }
+        None => {
+            let name = name.strip_prefix("*.").unwrap_or(name);
+            !name.is_empty()
+                && name.split('.').all(|label| {
+                    !label.is_empty()
+                        && label.len() <= 63
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+        }
+    };
+    if !valid_name {
+        return Err(malformed());
+    }
+
+    Ok(host.to_ascii_lowercase())
+}
+
+/// Returned by [`OriginSets`] for invalid definitions and references to undefined sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginSetError {
+    /// A set name is empty or contains characters other than ASCII letters, digits, `-` and `_`.
+    InvalidName(String),
+    /// A member of a set is neither a single origin nor a host source.
+    InvalidOrigin(InvalidOriginError),
+    /// A referenced set is not defined.
+    UnknownSet(String),
+}
+
+impl Display for OriginSetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OriginSetError::InvalidName(name) => write!(f, "invalid origin set name {:?}", name),
+            OriginSetError::InvalidOrigin(error) => write!(f, "{}", error),
+            OriginSetError::UnknownSet(name) => write!(f, "origin set {:?} is not defined", name),
+        }
+    }
+}
+
+impl std::error::Error for OriginSetError {}
+
+impl From<InvalidOriginError> for OriginSetError {
+    fn from(error: InvalidOriginError) -> Self {
+        OriginSetError::InvalidOrigin(error)
+    }
+}
+
+/// Named groups of origins, see the [module docs](self#origin-sets).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginSets {
+    sets: BTreeMap<String, Vec<String>>,
+}
+
+impl OriginSets {
+    /// Defines no sets yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines the set `name`, replacing an earlier definition. Every member has to be a single
+    /// origin such as `https://cdn.example` (see [`sanitize_origin`]) or a host source such as
+    /// `*.cdn.example` (see [`sanitize_host`]); the latter are only useful in CSP directives, as
+    /// the allowlists of this crate compare whole origins.
+    pub fn define<I, T>(&mut self, name: &str, origins: I) -> Result<&mut Self, OriginSetError>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(OriginSetError::InvalidName(name.to_owned()));
+        }
+
+        let mut members: Vec<String> = Vec::new();
+        for origin in origins {
+            let origin = origin.as_ref();
+            let origin = sanitize_origin(origin).or_else(|error| {
+                if origin.contains("://") {
+                    Err(error)
+                } else {
+                    sanitize_host(origin)
+                }
+            })?;
+            if !members.contains(&origin) {
+                members.push(origin);
+            }
+        }
+        self.sets.insert(name.to_owned(), members);
+        Ok(self)
+    }
+
+    /// The members of the set `name`, `None` if it isn't defined.
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.sets.get(name).map(Vec::as_slice)
+    }
+
+    /// The members of the sets `names`, in order and without duplicates, for example for a
+    /// [`crate::redirect::RedirectAudit`] or a [`crate::csrf`] guard.
+    pub fn origins(&self, names: &[&str]) -> Result<Vec<String>, OriginSetError> {
+        let mut origins: Vec<String> = Vec::new();
+        for name in names {
+            let members = self
+                .get(name)
+                .ok_or_else(|| OriginSetError::UnknownSet((*name).to_owned()))?;
+            for member in members {
+                if !origins.contains(member) {
+                    origins.push(member.clone());
+                }
+            }
+        }
+        Ok(origins)
+    }
+
+    /// Replaces the references to sets (`@name`) in the directives of `csp` with their members,
+    /// skipping sources the directive already has.
+    pub fn resolve<'a>(
+        &'a self,
+        csp: &ContentSecurityPolicy<'a>,
+    ) -> Result<ContentSecurityPolicy<'a>, OriginSetError> {
+        let mut resolved = csp.clone();
+        for sources in resolved.directives.values_mut() {
+            let mut expanded: Vec<&'a str> = Vec::with_capacity(sources.len());
+            for source in sources.iter() {
+                let members: Vec<&'a str> = match source.strip_prefix('@') {
+                    Some(name) => self
+                        .get(name)
+                        .ok_or_else(|| OriginSetError::UnknownSet(name.to_owned()))?
+                        .iter()
+                        .map(String::as_str)
+                        .collect(),
+                    None => vec![*source],
+                };
+                for member in members {
+                    if !expanded.contains(&member) {
+                        expanded.push(member);
+                    }
+                }
+            }
+            *sources = expanded;
+        }
+        Ok(resolved)
+    }
+}
+
+/// Origins are compared case-insensitively and without trailing slash.
+pub(crate) fn normalize(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+/// Drops the default port of `http` (`:80`) and `https` (`:443`) from a normalized origin, as
+/// browsers leave it out of `Origin`.
+pub(crate) fn without_default_port(origin: String) -> String {
+    let default_port = if origin.starts_with("https://") {
+        ":443"
+    } else if origin.starts_with("http://") {
+        ":80"
+    } else {
+        return origin;
+    };
+
+    match origin.strip_suffix(default_port) {
+        Some(stripped) => stripped.to_owned(),
+        None => origin,
+    }
+}
+
+/// The origin of an absolute URL, such as `https://example.com:8443` for
+/// `https://example.com:8443/path?query`.
+pub(crate) fn origin_of(url: &str) -> Option<String> {
+    let uri: Uri = url.trim().parse().ok()?;
+    let scheme = uri.scheme_str()?;
+    let authority = uri.authority()?;
+    // userinfo is not part of the origin
+    let host = authority.as_str().rsplit('@').next()?;
+
+    Some(normalize(&format!("{}://{}", scheme, host)))
+}
+
+/// Parses an absolute URL, with scheme and host, such as a report endpoint.
+pub(crate) fn absolute_uri(url: &str) -> Option<Uri> {
+    let uri: Uri = url.trim().parse().ok()?;
+    uri.scheme()?;
+    uri.authority()?;
+
+    Some(uri)
+}
@@ -0,0 +1,75 @@
+use http::header::AsHeaderName;
+use http::{HeaderMap, HeaderName};
+
+use crate::IntoHeaders;
+
+/// Per-response changes to the headers set by [`crate::HelmetLayer`].
+///
+/// Insert it into the extensions of a response (with the `axum` feature it can also be returned
+/// from handlers as response parts) to relax or tighten the headers of a single route. Overrides
+/// always take precedence over the layer's configuration.
+///
+/// ```
+/// use http::Response;
+/// use tower_helmet::header::{CrossOriginResourcePolicy, XFrameOptions};
+/// use tower_helmet::HeaderOverride;
+///
+/// let mut res = Response::new(());
+/// res.extensions_mut().insert(
+///     HeaderOverride::new()
+///         .enable(CrossOriginResourcePolicy::CrossOrigin)
+///         .remove(http::header::X_FRAME_OPTIONS),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderOverride {
+    headers: HeaderMap,
+    removed: Vec<HeaderName>,
+}
+
+impl HeaderOverride {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the given headers, replacing the layer's values.
+    pub fn enable(mut self, h: impl IntoHeaders) -> Self {
+        let headers = h.header_map().unwrap();
+        self.removed.retain(|name| !headers.contains_key(name));
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Doesn't send the given header, even if the layer is configured to.
+    pub fn remove<K>(mut self, key: K) -> Self
+    where
+        K: AsHeaderName + Into<HeaderName>,
+    {
+        let name = key.into();
+        self.headers.remove(&name);
+        self.removed.push(name);
+        self
+    }
+
+    /// Combines two overrides, `later` taking precedence for the headers both of them change.
+    #[cfg(feature = "axum")]
+    pub(crate) fn then(mut self, later: HeaderOverride) -> Self {
+        self.removed
+            .retain(|name| !later.headers.contains_key(name));
+        for name in later.removed {
+            self.headers.remove(&name);
+            if !self.removed.contains(&name) {
+                self.removed.push(name);
+            }
+        }
+        self.headers.extend(later.headers);
+        self
+    }
+
+    pub(crate) fn apply(self, headers: &mut HeaderMap) {
+        for name in &self.removed {
+            headers.remove(name);
+        }
+        headers.extend(self.headers);
+    }
+}
@@ -0,0 +1,207 @@
+//! Checks whether a domain qualifies for the HSTS preload list, enabled with the `preload`
+//! feature.
+//!
+//! Submissions to [hstspreload.org](https://hstspreload.org/) are rejected unless the domain meets
+//! the [submission requirements](https://hstspreload.org/#submission-requirements), and removal
+//! from the list takes months, so it is worth checking before submitting. [`check`] validates the
+//! `Strict-Transport-Security` header of a configuration, see
+//! [`crate::HelmetLayer::check_preload`]. [`check_domain`] checks the live domain: the header sent
+//! on its HTTPS site and the redirect of its HTTP site to HTTPS. [`status`] asks hstspreload.org
+//! whether the domain is already on the list or pending.
+//!
+//! hstspreload.org checks a few more things, such as that subdomains serve HTTPS and that the
+//! domain is not a subdomain itself, so a domain passing these checks is not guaranteed to be
+//! accepted.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use tower_helmet::header::StrictTransportSecurity;
+//! use tower_helmet::HelmetLayer;
+//!
+//! # async fn run() -> Result<(), reqwest::Error> {
+//! let hsts = StrictTransportSecurity::preload_ready(Duration::from_secs(63072000)).unwrap();
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.enable(hsts);
+//! assert!(layer.check_preload().is_ok());
+//!
+//! let report = tower_helmet::preload::check_domain("example.com").await?;
+//! println!("{}", report);
+//! println!("{:?}", tower_helmet::preload::status("example.com").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::header::{LOCATION, STRICT_TRANSPORT_SECURITY};
+use http::HeaderMap;
+
+use crate::header::{PreloadError, StrictTransportSecurity};
+
+/// The status API of hstspreload.org.
+const STATUS_API: &str = "https://hstspreload.org/api/v2/status";
+
+/// A requirement of the HSTS preload list which isn't met.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// No `Strict-Transport-Security` header is sent.
+    MissingHeader,
+    /// The `Strict-Transport-Security` header can't be parsed.
+    InvalidHeader(String),
+    /// The policy doesn't meet the requirements, see [`StrictTransportSecurity::check_preload`].
+    Policy(PreloadError),
+    /// The HTTP site doesn't redirect to HTTPS on the same host, but to the contained location, if
+    /// any.
+    NoHttpsRedirect(Option<String>),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::MissingHeader => write!(f, "no Strict-Transport-Security header is sent"),
+            Problem::InvalidHeader(value) => {
+                write!(
+                    f,
+                    "the Strict-Transport-Security header {:?} is invalid",
+                    value
+                )
+            }
+            Problem::Policy(error) => write!(f, "{}", error),
+            Problem::NoHttpsRedirect(Some(location)) => write!(
+                f,
+                "HTTP redirects to {} instead of HTTPS on the same host",
+                location
+            ),
+            Problem::NoHttpsRedirect(None) => write!(f, "HTTP doesn't redirect to HTTPS"),
+        }
+    }
+}
+
+impl std::error::Error for Problem {}
+
+/// Checks the `Strict-Transport-Security` header in `headers` against the preload requirements,
+/// returning the parsed policy if it meets them.
+pub fn check(headers: &HeaderMap) -> Result<StrictTransportSecurity, Problem> {
+    let value = headers
+        .get(STRICT_TRANSPORT_SECURITY)
+        .ok_or(Problem::MissingHeader)?;
+    let hsts: StrictTransportSecurity = value
+        .to_str()
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| Problem::InvalidHeader(String::from_utf8_lossy(value.as_bytes()).into()))?;
+    hsts.check_preload().map_err(Problem::Policy)?;
+
+    Ok(hsts)
+}
+
+/// The result of checking a live domain, see [`check_domain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub domain: String,
+    /// The requirements which aren't met, empty if the domain qualifies.
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    /// Whether the domain meets all checked requirements.
+    pub fn is_eligible(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_eligible() {
+            return writeln!(f, "{}: eligible for preloading", self.domain);
+        }
+        writeln!(f, "{}: not eligible for preloading", self.domain)?;
+        for problem in &self.problems {
+            writeln!(f, "- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches `https://<domain>/` and `http://<domain>/`, without following redirects, and checks
+/// the HSTS header of the first and that the second redirects to HTTPS on the same host.
+pub async fn check_domain(domain: &str) -> Result<Report, reqwest::Error> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let mut problems = Vec::new();
+
+    let https = client.get(format!("https://{}/", domain)).send().await?;
+    if let Err(problem) = check(https.headers()) {
+        problems.push(problem);
+    }
+
+    let http = client.get(format!("http://{}/", domain)).send().await?;
+    let location = http
+        .headers()
+        .get(LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let same_host = format!("https://{}", domain);
+    let redirects = http.status().is_redirection()
+        && location.as_deref().is_some_and(|location| {
+            location
+                .strip_prefix(&same_host)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        });
+    if !redirects {
+        problems.push(Problem::NoHttpsRedirect(location));
+    }
+
+    Ok(Report {
+        domain: domain.to_owned(),
+        problems,
+    })
+}
+
+/// The status of a domain on the preload list, see [`status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// Not on the list and not submitted.
+    Unknown,
+    /// Submitted and waiting to be added.
+    Pending,
+    Preloaded,
+    Rejected,
+    /// Waiting to be removed.
+    PendingRemoval,
+    Removed,
+    /// Any other status, for statuses newer than this crate, or the response if it can't be read.
+    Other(String),
+}
+
+impl Status {
+    fn parse(body: &str) -> Self {
+        let value: Option<serde_json::Value> = serde_json::from_str(body).ok();
+        match value.as_ref().and_then(|v| v["status"].as_str()) {
+            Some("unknown") => Status::Unknown,
+            Some("pending") => Status::Pending,
+            Some("preloaded") => Status::Preloaded,
+            Some("rejected") => Status::Rejected,
+            Some("pending-removal") => Status::PendingRemoval,
+            Some("removed") => Status::Removed,
+            Some(other) => Status::Other(other.to_owned()),
+            None => Status::Other(body.to_owned()),
+        }
+    }
+}
+
+/// Asks the hstspreload.org API for the status of `domain`.
+pub async fn status(domain: &str) -> Result<Status, reqwest::Error> {
+    let body = reqwest::Client::new()
+        .get(STATUS_API)
+        .query(&[("domain", domain)])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(Status::parse(&body))
+}
@@ -0,0 +1,68 @@
+use http::header::InvalidHeaderValue;
+use http::HeaderMap;
+
+use crate::header::{ContentSecurityPolicy, CrossOriginResourcePolicy, XFrameOptions};
+use crate::{IntoHeader, IntoHeaders};
+
+/// `EmbeddedDocuments` is meant for endpoints that intentionally serve PDFs or other objects which
+/// are displayed inline through `<object>`, `<embed>` or `<iframe>`. The default CSP sets
+/// `object-src 'none'`, which breaks the browser's built-in viewers, so this preset relaxes
+/// exactly the policies involved:
+///
+/// ```text
+/// Content-Security-Policy: <default directives>; object-src 'self'; frame-ancestors 'self'
+/// Cross-Origin-Resource-Policy: same-origin
+/// X-Frame-Options: SAMEORIGIN
+/// ```
+///
+/// ```
+/// use tower_helmet::preset::EmbeddedDocuments;
+/// use tower_helmet::HelmetLayer;
+///
+/// let layer = HelmetLayer::with_defaults().enable(EmbeddedDocuments::default());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmbeddedDocuments<'a> {
+    /// Sources allowed to be loaded by `<object>` and `<embed>`. Defaults to `'self'`.
+    pub object_src: Vec<&'a str>,
+    /// Origins allowed to embed the documents. Defaults to `'self'`. If it only contains `'self'`,
+    /// `X-Frame-Options` is set to `SAMEORIGIN`. Otherwise this preset doesn't set it, as it can't
+    /// express a list of origins (remove it from the layer if it was enabled before).
+    pub frame_ancestors: Vec<&'a str>,
+    /// `cross_origin_resource_policy` defaults to [`CrossOriginResourcePolicy::SameOrigin`]. Set it
+    /// to [`CrossOriginResourcePolicy::CrossOrigin`] if other sites should embed the documents.
+    pub cross_origin_resource_policy: CrossOriginResourcePolicy,
+}
+
+impl<'a> Default for EmbeddedDocuments<'a> {
+    fn default() -> Self {
+        EmbeddedDocuments {
+            object_src: vec!["'self'"],
+            frame_ancestors: vec!["'self'"],
+            cross_origin_resource_policy: CrossOriginResourcePolicy::SameOrigin,
+        }
+    }
+}
+
+impl<'a> IntoHeaders for EmbeddedDocuments<'a> {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut csp = ContentSecurityPolicy::default();
+        csp.directives.insert("object-src", self.object_src.clone());
+        csp.directives
+            .insert("frame-ancestors", self.frame_ancestors.clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(csp.header_name(), csp.header_value()?);
+        headers.insert(
+            self.cross_origin_resource_policy.header_name(),
+            self.cross_origin_resource_policy.header_value()?,
+        );
+
+        if self.frame_ancestors == ["'self'"] {
+            let xfo = XFrameOptions::SameOrigin;
+            headers.insert(xfo.header_name(), xfo.header_value()?);
+        }
+
+        Ok(headers)
+    }
+}
@@ -0,0 +1,88 @@
+use http::header::{InvalidHeaderValue, CONTENT_TYPE};
+use http::HeaderMap;
+
+use crate::header::{CrossOriginResourcePolicy, StrictTransportSecurity, XContentTypeOptions};
+use crate::{IntoHeader, IntoHeaders};
+
+/// `Grpc` is meant for gRPC and gRPC-web traffic, for example of tonic servers called by browser
+/// applications. Most of the default headers only make sense for documents, so this preset only
+/// enables the ones that matter for API responses:
+///
+/// ```text
+/// Strict-Transport-Security: max-age=15552000; includeSubDomains
+/// X-Content-Type-Options: nosniff
+/// Cross-Origin-Resource-Policy: cross-origin
+/// ```
+///
+/// gRPC-web is usually called from another origin, so `Cross-Origin-Resource-Policy` defaults to
+/// `cross-origin`, otherwise pages with `Cross-Origin-Embedder-Policy: require-corp` could not read
+/// the responses. CSP, `X-Frame-Options` and the cross-origin isolation headers are left out, as
+/// the responses are never rendered.
+///
+/// It is used for gRPC requests by [`crate::HelmetLayer::grpc`], or can be enabled directly on a
+/// layer wrapping only the gRPC service:
+///
+/// ```
+/// use tower_helmet::preset::Grpc;
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut layer = HelmetLayer::with_defaults();
+/// layer.grpc(Grpc::default());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grpc {
+    /// `cross_origin_resource_policy` controls who may read the responses. It defaults to
+    /// [`CrossOriginResourcePolicy::CrossOrigin`].
+    pub cross_origin_resource_policy: CrossOriginResourcePolicy,
+    pub strict_transport_security: StrictTransportSecurity,
+}
+
+impl Grpc {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        Grpc {
+            cross_origin_resource_policy: CrossOriginResourcePolicy::CrossOrigin,
+            strict_transport_security: StrictTransportSecurity::new(),
+        }
+    }
+}
+
+impl Default for Grpc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoHeaders for Grpc {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            self.strict_transport_security.header_name(),
+            self.strict_transport_security.header_value()?,
+        );
+        headers.insert(
+            XContentTypeOptions.header_name(),
+            XContentTypeOptions.header_value()?,
+        );
+        headers.insert(
+            self.cross_origin_resource_policy.header_name(),
+            self.cross_origin_resource_policy.header_value()?,
+        );
+
+        Ok(headers)
+    }
+}
+
+/// Whether the request (or response) headers belong to gRPC or gRPC-web traffic, based on the
+/// `application/grpc` family of content types (`application/grpc+proto`, `application/grpc-web`,
+/// `application/grpc-web-text`, ...).
+pub fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim_start().to_ascii_lowercase())
+        .is_some_and(|v| {
+            v.strip_prefix("application/grpc")
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['+', '-', ';']))
+        })
+}
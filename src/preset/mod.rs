@@ -0,0 +1,11 @@
+mod embedded_documents;
+mod grpc;
+mod healthcare_portal;
+mod payment_pages;
+mod user_uploads;
+
+pub use self::embedded_documents::EmbeddedDocuments;
+pub use self::grpc::{is_grpc, Grpc};
+pub use self::healthcare_portal::HealthcarePortal;
+pub use self::payment_pages::PaymentPages;
+pub use self::user_uploads::UserUploads;
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use http::header::{InvalidHeaderValue, CACHE_CONTROL};
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::{
+    ContentSecurityPolicy, ReferrerPolicy, ReferrerPolicyValue, StrictTransportSecurity,
+    XContentTypeOptions, XFrameOptions,
+};
+use crate::{IntoHeader, IntoHeaders};
+
+/// `PaymentPages` is meant for pages handling cardholder data, tuned to the browser-related
+/// requirements of PCI DSS 4.0:
+///
+/// ```text
+/// Content-Security-Policy: <default directives>; script-src 'self' <script_src>;
+///     frame-ancestors 'none'; report-uri <report_uri>
+/// Strict-Transport-Security: max-age=31536000; includeSubDomains
+/// X-Frame-Options: DENY
+/// X-Content-Type-Options: nosniff
+/// Referrer-Policy: no-referrer
+/// Cache-Control: no-store
+/// ```
+///
+/// The `script-src` allowlist is the inventory of authorized payment page scripts (requirement
+/// 6.4.3), and the violation reports are a way to get alerted about unauthorized changes
+/// (requirement 11.6.1). [`crate::compliance::pci_dss`] reports which requirements a
+/// configuration covers.
+///
+/// ```
+/// use tower_helmet::preset::PaymentPages;
+/// use tower_helmet::HelmetLayer;
+///
+/// let mut layer = HelmetLayer::with_defaults_v2();
+/// layer.enable(PaymentPages {
+///     script_src: vec!["https://js.psp.example"],
+///     report_uri: Some("https://csp.example.com/report"),
+/// });
+///
+/// assert!(layer.pci_dss_checklist().is_satisfied());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct PaymentPages<'a> {
+    /// Scripts authorized in addition to `'self'`, such as the payment service provider's SDK.
+    pub script_src: Vec<&'a str>,
+    /// Where violations of the CSP are reported to. Without it, requirement 11.6.1 isn't covered.
+    pub report_uri: Option<&'a str>,
+}
+
+impl<'a> IntoHeaders for PaymentPages<'a> {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut csp = ContentSecurityPolicy::default();
+        let mut script_src = vec!["'self'"];
+        script_src.extend(&self.script_src);
+        csp.directives.insert("script-src", script_src);
+        csp.directives.insert("frame-ancestors", vec!["'none'"]);
+        if let Some(report_uri) = self.report_uri {
+            csp.directives.insert("report-uri", vec![report_uri]);
+        }
+
+        let hsts = StrictTransportSecurity {
+            max_age: Duration::from_secs(31536000),
+            ..StrictTransportSecurity::new()
+        };
+        let referrer_policy = ReferrerPolicy(vec![ReferrerPolicyValue::NoReferrer]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(csp.header_name(), csp.header_value()?);
+        headers.insert(hsts.header_name(), hsts.header_value()?);
+        headers.insert(
+            XFrameOptions::Deny.header_name(),
+            XFrameOptions::Deny.header_value()?,
+        );
+        headers.insert(
+            XContentTypeOptions.header_name(),
+            XContentTypeOptions.header_value()?,
+        );
+        headers.insert(
+            referrer_policy.header_name(),
+            referrer_policy.header_value()?,
+        );
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+        Ok(headers)
+    }
+}
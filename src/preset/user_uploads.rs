@@ -0,0 +1,72 @@
+use http::header::{InvalidHeaderValue, CONTENT_DISPOSITION};
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::{ContentSecurityPolicy, CrossOriginResourcePolicy, XContentTypeOptions};
+use crate::{IntoHeader, IntoHeaders};
+
+/// `UserUploads` is meant for routes serving untrusted, user-uploaded files. Serving those safely
+/// needs several headers together, so this preset enables all of them at once:
+///
+/// ```text
+/// Content-Disposition: attachment
+/// X-Content-Type-Options: nosniff
+/// Content-Security-Policy: default-src 'none'; sandbox
+/// Cross-Origin-Resource-Policy: same-origin
+/// ```
+///
+/// `Content-Disposition: attachment` makes browsers download the file instead of rendering it,
+/// while the sandboxing CSP makes sure that content which is rendered anyway can't run scripts or
+/// load anything.
+///
+/// ```
+/// use tower_helmet::preset::UserUploads;
+/// use tower_helmet::HelmetLayer;
+///
+/// let layer = HelmetLayer::blank().enable(UserUploads::default());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserUploads {
+    /// `cross_origin_resource_policy` controls who may embed the uploaded files. It defaults to
+    /// [`CrossOriginResourcePolicy::SameOrigin`].
+    pub cross_origin_resource_policy: CrossOriginResourcePolicy,
+}
+
+impl UserUploads {
+    /// Same as [`Default::default`], usable in constants and statics.
+    pub const fn new() -> Self {
+        UserUploads {
+            cross_origin_resource_policy: CrossOriginResourcePolicy::SameOrigin,
+        }
+    }
+}
+
+impl Default for UserUploads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoHeaders for UserUploads {
+    fn header_map(&self) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut csp = ContentSecurityPolicy {
+            use_defaults: false,
+            ..Default::default()
+        };
+        csp.directives.insert("default-src", vec!["'none'"]);
+        csp.directives.insert("sandbox", vec![]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_DISPOSITION, HeaderValue::from_static("attachment"));
+        headers.insert(
+            XContentTypeOptions.header_name(),
+            XContentTypeOptions.header_value()?,
+        );
+        headers.insert(csp.header_name(), csp.header_value()?);
+        headers.insert(
+            self.cross_origin_resource_policy.header_name(),
+            self.cross_origin_resource_policy.header_value()?,
+        );
+
+        Ok(headers)
+    }
+}
@@ -0,0 +1,521 @@
+//! Headers loaded per request, for example from the database of a multi-tenant application.
+//!
+//! Embedding the policy of every tenant in the static configuration doesn't scale once tenants
+//! can configure their own CSP sources or `frame-ancestors`. A [`PolicyProvider`] passed to
+//! [`crate::HelmetLayer::policy_provider`] is asked for the headers of every request instead. It
+//! gets the request parts, so it can identify the tenant by the `Host`, a path prefix or an
+//! extension added by an earlier layer, and returns a future resolving to the complete header set
+//! to send, or `None` for the layer's own headers. Nonces and the `User-Agent` adjustments of the
+//! layer are applied to the provided headers as well.
+//!
+//! Providers can fail, for example if the database is unreachable. What is sent then is configured
+//! with [`crate::HelmetLayer::fallback`], see [`Fallback`], or failed with a [`FailClosedLayer`].
+//!
+//! The inner service is called right away, but its future is only polled once the provider's
+//! future has resolved, so slow lookups delay every response. A [`ProviderCache`] keeps the
+//! loaded headers per tenant for a while, so most requests don't wait for a lookup.
+//!
+//! ```
+//! use http::request::Parts;
+//! use tower_helmet::preset::EmbeddedDocuments;
+//! use tower_helmet::provider::BoxError;
+//! use tower_helmet::{HelmetLayer, IntoHeaders};
+//!
+//! async fn tenant_headers(host: Option<String>) -> Result<Option<http::HeaderMap>, BoxError> {
+//!     let ancestors = match host.as_deref() {
+//!         Some("acme.example.com") => vec!["https://portal.acme.example"],
+//!         _ => return Ok(None),
+//!     };
+//!     let mut layer = HelmetLayer::with_defaults_v2();
+//!     layer.enable(EmbeddedDocuments {
+//!         frame_ancestors: ancestors,
+//!         ..Default::default()
+//!     });
+//!     Ok(Some(layer.header_map()?))
+//! }
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.policy_provider(|parts: &Parts| {
+//!     let host = parts
+//!         .headers
+//!         .get(http::header::HOST)
+//!         .and_then(|v| v.to_str().ok())
+//!         .map(str::to_owned);
+//!     tenant_headers(host)
+//! });
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::future::{ready, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use http::request::Parts;
+use http::{HeaderMap, Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::clock::Instant;
+use crate::explain::{Explanation, Step};
+use crate::frame_ancestors::FrameAncestorsHandle;
+use crate::user_agent::UserAgentAdjustments;
+use crate::{nonce, CspNonce};
+
+/// The error of a failed [`PolicyProvider`].
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The headers loaded by a [`PolicyProvider`].
+pub type ProvidedHeaders = BoxFuture<'static, Result<Option<HeaderMap>, BoxError>>;
+
+/// Loads the headers of a request, see the [module docs](self).
+///
+/// It is implemented for closures taking the request parts and returning a future.
+pub trait PolicyProvider: Send + Sync + 'static {
+    /// The headers to send in response to the request with `parts`, `None` for the headers
+    /// configured in the layer.
+    fn headers(&self, parts: &Parts) -> ProvidedHeaders;
+}
+
+impl<F, Fut, E> PolicyProvider for F
+where
+    F: Fn(&Parts) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<HeaderMap>, E>> + Send + 'static,
+    E: Into<BoxError>,
+{
+    fn headers(&self, parts: &Parts) -> ProvidedHeaders {
+        let future = self(parts);
+        Box::pin(async move { future.await.map_err(Into::into) })
+    }
+}
+
+/// What to send if the headers of a request can't be computed, because a [`PolicyProvider`]
+/// failed or no nonce could be generated. The failure is logged as error with the `tracing`
+/// feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Fallback {
+    /// The headers configured in the layer (or of the matching route), without nonce if none
+    /// could be generated.
+    #[default]
+    Configured,
+    /// No headers of the layer at all.
+    Nothing,
+    /// An empty `500 Internal Server Error` instead of the response of the inner service.
+    ///
+    /// Only a [`FailClosedLayer`] and the adapters for other frameworks can fail requests.
+    Fail,
+}
+
+/// Wraps services like its [`crate::HelmetLayer`], but answers requests whose headers can't be
+/// computed with an empty `500 Internal Server Error`, see [`Fallback::Fail`].
+///
+/// The response body of the inner service must implement [`Default`] to build that response.
+/// If no nonce could be generated, the inner service isn't called at all. If the provider fails,
+/// the inner service has already been called, but its future is dropped without being polled.
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use std::sync::atomic::{AtomicBool, Ordering};
+/// # use std::sync::Arc;
+/// # use std::task::{Context, Poll};
+/// # use futures::future::BoxFuture;
+/// use http::request::Parts;
+/// use http::{HeaderMap, Request, Response, StatusCode};
+/// use tower_helmet::provider::FailClosedLayer;
+/// use tower_helmet::HelmetLayer;
+/// use tower_layer::Layer;
+/// use tower_service::Service;
+///
+/// # #[derive(Clone)]
+/// # struct Handler(Arc<AtomicBool>);
+/// # impl Service<Request<()>> for Handler {
+/// #     type Response = Response<String>;
+/// #     type Error = Infallible;
+/// #     type Future = BoxFuture<'static, Result<Response<String>, Infallible>>;
+/// #     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+/// #         Poll::Ready(Ok(()))
+/// #     }
+/// #     fn call(&mut self, _: Request<()>) -> Self::Future {
+/// #         let handled = self.0.clone();
+/// #         Box::pin(async move {
+/// #             handled.store(true, Ordering::SeqCst);
+/// #             Ok(Response::new("secret".to_owned()))
+/// #         })
+/// #     }
+/// # }
+/// let mut layer = HelmetLayer::with_defaults_v2();
+/// layer.policy_provider(|_: &Parts| async { Err::<Option<HeaderMap>, _>("database unreachable") });
+/// let handled = Arc::new(AtomicBool::new(false));
+/// let mut service = FailClosedLayer::from(layer).layer(Handler(handled.clone()));
+///
+/// let res = futures::executor::block_on(service.call(Request::new(()))).unwrap();
+/// assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+/// assert_eq!(res.body(), "");
+/// assert!(!handled.load(Ordering::SeqCst));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FailClosedLayer(crate::HelmetLayer);
+
+impl From<crate::HelmetLayer> for FailClosedLayer {
+    fn from(mut layer: crate::HelmetLayer) -> Self {
+        layer.fallback(Fallback::Fail);
+        FailClosedLayer(layer)
+    }
+}
+
+impl<S> Layer<S> for FailClosedLayer {
+    type Service = FailClosedService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        FailClosedService(self.0.service(service))
+    }
+}
+
+/// Service of a [`FailClosedLayer`].
+#[derive(Debug, Clone)]
+pub struct FailClosedService<S>(crate::HelmetService<S>);
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for FailClosedService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FailClosedFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        FailClosedFuture {
+            future: self.0.call(request),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`FailClosedService`].
+    #[derive(Debug)]
+    pub struct FailClosedFuture<F> {
+        #[pin]
+        future: crate::ResponseFuture<F>,
+    }
+}
+
+impl<F, ResBody, E> Future for FailClosedFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: Default,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll_response(cx, || {
+            let mut res = Response::new(ResBody::default());
+            *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            res
+        })
+    }
+}
+
+impl Debug for dyn PolicyProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PolicyProvider")
+    }
+}
+
+type CacheKey = Arc<dyn Fn(&Parts) -> Option<String> + Send + Sync>;
+
+/// Caches the headers of a [`PolicyProvider`] per tenant, with a time to live and a maximum
+/// number of tenants, dropping the least recently used one when full.
+///
+/// Clones share the cached headers, so a clone kept by the application can invalidate them once
+/// a tenant changes its policy.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use http::request::Parts;
+/// use tower_helmet::provider::ProviderCache;
+/// use tower_helmet::HelmetLayer;
+///
+/// # async fn load_policy(
+/// #     _: Option<String>,
+/// # ) -> Result<Option<http::HeaderMap>, tower_helmet::provider::BoxError> {
+/// #     Ok(None)
+/// # }
+/// let cache = ProviderCache::new(Duration::from_secs(300), 10_000);
+///
+/// let mut layer = HelmetLayer::with_defaults_v2();
+/// layer.policy_provider(cache.wrap(|parts: &Parts| {
+///     let host = parts.uri.host().map(str::to_owned);
+///     load_policy(host)
+/// }));
+///
+/// // after acme.example.com changed its policy
+/// cache.invalidate("acme.example.com");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProviderCache {
+    entries: Arc<Mutex<Entries>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    /// Incremented on every use, the most recent use of an entry is its `used`.
+    clock: u64,
+    /// Incremented on every invalidation, headers loaded before are not cached.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    headers: Option<HeaderMap>,
+    expires: Instant,
+    used: u64,
+}
+
+impl ProviderCache {
+    /// Keeps the headers of up to `capacity` tenants for `ttl` each.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        ProviderCache {
+            entries: Arc::new(Mutex::new(Entries::default())),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Caches the headers of `provider`, by default per `Host` of the request.
+    pub fn wrap<P: PolicyProvider>(&self, provider: P) -> CachedProvider<P> {
+        CachedProvider {
+            provider,
+            cache: self.clone(),
+            key: Arc::new(|parts: &Parts| {
+                let host = parts
+                    .headers
+                    .get(http::header::HOST)
+                    .and_then(|v| v.to_str().ok())
+                    .or_else(|| parts.uri.host())?;
+                Some(host.to_ascii_lowercase())
+            }),
+        }
+    }
+
+    /// Drops the cached headers of the tenant with `key`.
+    pub fn invalidate(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.entries.remove(key);
+        entries.generation += 1;
+    }
+
+    /// Drops the cached headers of all tenants.
+    pub fn invalidate_all(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.entries.clear();
+        entries.generation += 1;
+    }
+
+    /// The cached headers of `key` unless they expired, and the current generation.
+    fn get(&self, key: &str, now: Instant) -> (Option<Option<HeaderMap>>, u64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let generation = entries.generation;
+
+        match entries.entries.get_mut(key) {
+            Some(entry) if entry.expires > now => {
+                entry.used = clock;
+                (Some(entry.headers.clone()), generation)
+            }
+            Some(_) => {
+                entries.entries.remove(key);
+                (None, generation)
+            }
+            None => (None, generation),
+        }
+    }
+
+    /// Caches the headers loaded for `key`, unless the cache was invalidated since `generation`.
+    fn insert(&self, key: String, headers: Option<HeaderMap>, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation || self.capacity == 0 {
+            return;
+        }
+        if entries.entries.len() >= self.capacity && !entries.entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.entries.retain(|_, entry| entry.expires > now);
+        }
+        if entries.entries.len() >= self.capacity && !entries.entries.contains_key(&key) {
+            let least_recent = entries
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recent) = least_recent {
+                entries.entries.remove(&least_recent);
+            }
+        }
+
+        entries.clock += 1;
+        let entry = Entry {
+            headers,
+            expires: Instant::now() + self.ttl,
+            used: entries.clock,
+        };
+        entries.entries.insert(key, entry);
+    }
+}
+
+/// A [`PolicyProvider`] cached by a [`ProviderCache`].
+#[derive(Clone)]
+pub struct CachedProvider<P> {
+    provider: P,
+    cache: ProviderCache,
+    key: CacheKey,
+}
+
+impl<P> Debug for CachedProvider<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedProvider")
+            .field("cache", &self.cache)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> CachedProvider<P> {
+    /// Identifies the tenant of a request by `key` instead of its `Host`. Requests for which it
+    /// returns `None` are not cached.
+    pub fn key<F>(&mut self, key: F) -> &mut Self
+    where
+        F: Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+}
+
+/// Failures are not cached, so the next request tries again.
+impl<P: PolicyProvider> PolicyProvider for CachedProvider<P> {
+    fn headers(&self, parts: &Parts) -> ProvidedHeaders {
+        let key = match (self.key)(parts) {
+            Some(key) => key,
+            None => return self.provider.headers(parts),
+        };
+        let (cached, generation) = self.cache.get(&key, Instant::now());
+        if let Some(headers) = cached {
+            return Box::pin(ready(Ok(headers)));
+        }
+
+        let future = self.provider.headers(parts);
+        let cache = self.cache.clone();
+        Box::pin(async move {
+            let headers = future.await?;
+            cache.insert(key, headers.clone(), generation);
+            Ok(headers)
+        })
+    }
+}
+
+/// The changes the layer makes to the headers of a request, applied to the configured headers
+/// right away and to the provided ones once they are loaded.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Adjustments {
+    /// The headers of the feature flags enabled for the request.
+    pub(crate) rollout: Option<HeaderMap>,
+    pub(crate) user_agent: Option<(Arc<UserAgentAdjustments>, Option<Vec<u8>>)>,
+    /// The partners allowed to frame the response when the request arrived.
+    pub(crate) frame_ancestors: Option<(FrameAncestorsHandle, Arc<[String]>)>,
+    /// The CSP directives the nonces are added to.
+    pub(crate) directives: &'static [&'static str],
+    /// The nonce of the request first, followed by the previous session nonce if any.
+    pub(crate) nonces: Vec<CspNonce>,
+}
+
+impl Adjustments {
+    /// Applies the adjustments to `headers`, recording them if the request is explained.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap, mut explanation: Option<&mut Explanation>) {
+        if let Some(rollout) = &self.rollout {
+            Explanation::trace(
+                explanation.as_deref_mut(),
+                Step::Rollout,
+                headers,
+                |headers| headers.extend(rollout.clone()),
+            );
+        }
+        if let Some((adjustments, user_agent)) = &self.user_agent {
+            Explanation::trace(
+                explanation.as_deref_mut(),
+                Step::UserAgent,
+                headers,
+                |headers| adjustments.apply(user_agent.as_deref(), headers),
+            );
+        }
+        if let Some((handle, origins)) = &self.frame_ancestors {
+            Explanation::trace(
+                explanation.as_deref_mut(),
+                Step::FrameAncestors,
+                headers,
+                |headers| handle.apply(headers, origins),
+            );
+        }
+        Explanation::trace(explanation, Step::Nonce, headers, |headers| {
+            for nonce in &self.nonces {
+                nonce::apply(headers, nonce, self.directives);
+            }
+        });
+    }
+
+    /// The nonce of the request.
+    pub(crate) fn nonce(&self) -> Option<CspNonce> {
+        self.nonces.first().cloned()
+    }
+}
+
+/// Headers being loaded by a [`PolicyProvider`].
+pub(crate) struct Pending {
+    future: ProvidedHeaders,
+    adjustments: Adjustments,
+}
+
+impl Debug for Pending {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pending")
+            .field("adjustments", &self.adjustments)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pending {
+    pub(crate) fn new(
+        provider: &dyn PolicyProvider,
+        parts: &Parts,
+        adjustments: Adjustments,
+    ) -> Self {
+        Pending {
+            future: provider.headers(parts),
+            adjustments,
+        }
+    }
+
+    /// The provided headers with the adjustments applied, once they are loaded.
+    pub(crate) fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, BoxError>> {
+        self.future.as_mut().poll(cx).map_ok(|headers| {
+            headers.map(|mut headers| {
+                self.adjustments.apply(&mut headers, None);
+                headers
+            })
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! Detection of open redirects in the response path.
+//!
+//! An open redirect lets attackers bounce users from a trusted URL to any site, undermining
+//! policies such as `frame-ancestors` or the CSRF checks of this crate. A [`RedirectAudit`] passed
+//! to [`crate::HelmetLayer::audit_redirects`] flags every `3xx` response whose `Location` points
+//! to an origin outside the allowlist. Responses are not modified: the [`Finding`]s
+//! ([`Rule::OffsiteRedirect`](crate::audit::Rule::OffsiteRedirect)) are passed to a callback, for
+//! example to record metrics, and with the `tracing` feature logged as warning.
+//!
+//! ```
+//! use tower_helmet::redirect::RedirectAudit;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut audit = RedirectAudit::new(["https://example.com", "https://accounts.example.com"]);
+//! audit.on_finding(|finding| eprintln!("{}", finding));
+//!
+//! let mut layer = HelmetLayer::with_defaults();
+//! layer.audit_redirects(audit);
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use http::header::LOCATION;
+use http::{HeaderMap, StatusCode};
+
+use crate::audit::{Finding, Rule, Severity};
+use crate::origin::{normalize, origin_of};
+
+type FindingCallback = Arc<dyn Fn(&Finding) + Send + Sync>;
+
+/// Flags redirects to origins outside an allowlist. Relative locations always stay on the
+/// current origin and are allowed.
+#[derive(Clone)]
+pub struct RedirectAudit {
+    allowed_origins: Vec<String>,
+    on_finding: Option<FindingCallback>,
+}
+
+impl Debug for RedirectAudit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedirectAudit")
+            .field("allowed_origins", &self.allowed_origins)
+            .field("on_finding", &self.on_finding.is_some())
+            .finish()
+    }
+}
+
+impl RedirectAudit {
+    /// Allows redirects to the given origins, such as `https://example.com`.
+    pub fn new<I, T>(allowed_origins: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        RedirectAudit {
+            allowed_origins: allowed_origins
+                .into_iter()
+                .map(|origin| normalize(&origin.into()))
+                .collect(),
+            on_finding: None,
+        }
+    }
+
+    /// Calls `callback` for every offsite redirect.
+    pub fn on_finding<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Finding) + Send + Sync + 'static,
+    {
+        self.on_finding = Some(Arc::new(callback));
+        self
+    }
+
+    /// Checks a response, returning a finding if it redirects offsite.
+    pub fn check(&self, status: StatusCode, headers: &HeaderMap) -> Option<Finding> {
+        if !status.is_redirection() {
+            return None;
+        }
+
+        let location = headers.get(LOCATION)?.to_str().ok()?.trim();
+        // browsers treat backslashes like slashes, so `/\evil.com` is scheme-relative as well
+        let url = location.replace('\\', "/");
+        let origins = if url.starts_with("//") {
+            vec![
+                origin_of(&format!("https:{}", url)),
+                origin_of(&format!("http:{}", url)),
+            ]
+        } else if has_scheme(&url) {
+            vec![origin_of(&url)]
+        } else {
+            return None;
+        };
+
+        let allowed = origins
+            .iter()
+            .flatten()
+            .any(|origin| self.allowed_origins.contains(origin));
+        if allowed {
+            return None;
+        }
+
+        Some(Finding::new(
+            Rule::OffsiteRedirect,
+            Severity::Warning,
+            Some(LOCATION),
+            format!("{} redirects to {}", status.as_u16(), location),
+        ))
+    }
+
+    /// Checks a response and reports a finding.
+    pub(crate) fn report(&self, status: StatusCode, headers: &HeaderMap) {
+        let finding = match self.check(status, headers) {
+            Some(finding) => finding,
+            None => return,
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(rule = %finding.rule, "{}", finding.message);
+
+        if let Some(callback) = &self.on_finding {
+            callback(&finding);
+        }
+    }
+}
+
+/// Whether a URL is absolute, starting with a scheme such as `https:`.
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
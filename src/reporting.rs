@@ -0,0 +1,243 @@
+//! A single configuration for all reporting headers.
+//!
+//! Reports of CSP, COOP, COEP and Network Error Logging are sent to named groups, which have to be
+//! defined in `Reporting-Endpoints` (or `Report-To` for NEL and older browsers). Configured one by
+//! one, it is easy to reference a group which is defined nowhere, and browsers silently drop the
+//! reports. A [`ReportingConfig`] holds the endpoints and which group each policy reports to, and
+//! [`crate::HelmetLayer::reporting`] derives all of these headers and references from it.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use tower_helmet::header::CrossOriginOpenerPolicy;
+//! use tower_helmet::reporting::ReportingConfig;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut reporting = ReportingConfig::new(Duration::from_secs(86400));
+//! reporting
+//!     .endpoint("csp", "https://reports.example.com/csp")
+//!     .endpoint("default", "https://reports.example.com/default")
+//!     .csp("csp")
+//!     .coop("default")
+//!     .nel("default");
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.enable(CrossOriginOpenerPolicy::SameOrigin);
+//! layer.reporting(reporting);
+//!
+//! assert!(layer.audit().is_empty());
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use http::{HeaderMap, HeaderValue};
+
+use crate::header::{parse_directives, validate_value_with, NEL, REPORTING_ENDPOINTS, REPORT_TO};
+use crate::origin::absolute_uri;
+
+/// Characters which would break the endpoint lists or the JSON of `Report-To`.
+const URL_SEPARATORS: [char; 5] = [' ', ',', ';', '"', '\\'];
+
+/// Returned by [`ReportingConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportingError {
+    /// A group name is empty or contains characters other than ASCII letters, digits, `-` and
+    /// `_`.
+    InvalidGroup(String),
+    /// An endpoint is not an absolute URL or contains characters which would break the headers.
+    InvalidEndpoint(String),
+    /// A policy reports to a group without endpoint.
+    UnknownGroup(String),
+}
+
+impl Display for ReportingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportingError::InvalidGroup(group) => write!(f, "invalid reporting group {:?}", group),
+            ReportingError::InvalidEndpoint(url) => {
+                write!(f, "invalid reporting endpoint {:?}", url)
+            }
+            ReportingError::UnknownGroup(group) => {
+                write!(f, "reporting group {:?} has no endpoint", group)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReportingError {}
+
+/// Endpoints and the groups policies report to, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportingConfig {
+    /// Group names with their endpoint URL.
+    endpoints: Vec<(String, String)>,
+    /// How long browsers remember the `Report-To` and `NEL` configuration.
+    max_age: Duration,
+    csp: Option<String>,
+    coop: Option<String>,
+    coep: Option<String>,
+    nel: Option<String>,
+}
+
+impl ReportingConfig {
+    /// Creates a configuration without endpoints. `max_age` is how long browsers remember the
+    /// `Report-To` and `NEL` configuration.
+    pub fn new(max_age: Duration) -> Self {
+        ReportingConfig {
+            endpoints: Vec::new(),
+            max_age,
+            csp: None,
+            coop: None,
+            coep: None,
+            nel: None,
+        }
+    }
+
+    /// Defines the group `group`, sending its reports to the absolute URL `url`. Defining a group
+    /// again replaces its URL.
+    pub fn endpoint(&mut self, group: impl Into<String>, url: impl Into<String>) -> &mut Self {
+        let group = group.into();
+        self.endpoints.retain(|(name, _)| *name != group);
+        self.endpoints.push((group, url.into()));
+        self
+    }
+
+    /// Reports violations of the CSP (enforced and report-only) to `group`.
+    pub fn csp(&mut self, group: impl Into<String>) -> &mut Self {
+        self.csp = Some(group.into());
+        self
+    }
+
+    /// Reports violations of `Cross-Origin-Opener-Policy` to `group`.
+    pub fn coop(&mut self, group: impl Into<String>) -> &mut Self {
+        self.coop = Some(group.into());
+        self
+    }
+
+    /// Reports violations of `Cross-Origin-Embedder-Policy` to `group`.
+    pub fn coep(&mut self, group: impl Into<String>) -> &mut Self {
+        self.coep = Some(group.into());
+        self
+    }
+
+    /// Enables Network Error Logging, reporting failed requests to `group`.
+    pub fn nel(&mut self, group: impl Into<String>) -> &mut Self {
+        self.nel = Some(group.into());
+        self
+    }
+
+    /// Checks that the group names and endpoints are valid and that every referenced group has
+    /// an endpoint.
+    pub fn validate(&self) -> Result<(), ReportingError> {
+        for (group, url) in &self.endpoints {
+            let valid_group = !group.is_empty()
+                && group
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_group {
+                return Err(ReportingError::InvalidGroup(group.clone()));
+            }
+            if validate_value_with("Reporting-Endpoints", url, &URL_SEPARATORS).is_err()
+                || absolute_uri(url).is_none()
+            {
+                return Err(ReportingError::InvalidEndpoint(url.clone()));
+            }
+        }
+
+        let references = [&self.csp, &self.coop, &self.coep, &self.nel];
+        for group in references.iter().filter_map(|group| group.as_ref()) {
+            if !self.endpoints.iter().any(|(name, _)| name == group) {
+                return Err(ReportingError::UnknownGroup(group.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn url(&self, group: &str) -> &str {
+        self.endpoints
+            .iter()
+            .find(|(name, _)| name == group)
+            .map(|(_, url)| url.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Sets the reporting headers and adds the references to the policies in `headers`, after
+    /// [`ReportingConfig::validate`] passed.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        // group names and endpoints are validated, so all values are valid header values
+        let value = |value: String| HeaderValue::from_str(&value).unwrap();
+
+        if !self.endpoints.is_empty() {
+            let endpoints: Vec<String> = self
+                .endpoints
+                .iter()
+                .map(|(group, url)| format!("{}=\"{}\"", group, url))
+                .collect();
+            headers.insert(REPORTING_ENDPOINTS, value(endpoints.join(", ")));
+
+            let groups: Vec<String> = self
+                .endpoints
+                .iter()
+                .map(|(group, url)| {
+                    format!(
+                        "{{\"group\":\"{}\",\"max_age\":{},\"endpoints\":[{{\"url\":\"{}\"}}]}}",
+                        group,
+                        self.max_age.as_secs(),
+                        url
+                    )
+                })
+                .collect();
+            headers.insert(REPORT_TO, value(groups.join(", ")));
+        }
+
+        if let Some(group) = &self.nel {
+            headers.insert(
+                NEL,
+                value(format!(
+                    "{{\"report_to\":\"{}\",\"max_age\":{}}}",
+                    group,
+                    self.max_age.as_secs()
+                )),
+            );
+        }
+
+        if let Some(group) = &self.csp {
+            // report-uri for browsers which don't support report-to yet
+            let url = self.url(group);
+            crate::nonce::update(headers, |policy| {
+                let mut directives: Vec<(&str, Vec<&str>)> = parse_directives(policy)
+                    .into_iter()
+                    .filter(|(name, _)| *name != "report-to" && *name != "report-uri")
+                    .collect();
+                directives.push(("report-to", vec![group]));
+                directives.push(("report-uri", vec![url]));
+                crate::nonce::render(&directives)
+            });
+        }
+
+        let report_to_parameter = [
+            ("cross-origin-embedder-policy", &self.coep),
+            ("cross-origin-embedder-policy-report-only", &self.coep),
+            ("cross-origin-opener-policy", &self.coop),
+            ("cross-origin-opener-policy-report-only", &self.coop),
+        ];
+        for (name, group) in report_to_parameter {
+            let (group, current) = match (group, headers.get(name).map(|v| v.to_str())) {
+                (Some(group), Some(Ok(current))) => (group, current),
+                _ => continue,
+            };
+
+            let mut params: Vec<&str> = current
+                .split(';')
+                .map(str::trim)
+                .filter(|param| !param.starts_with("report-to"))
+                .collect();
+            let report_to = format!("report-to=\"{}\"", group);
+            params.push(&report_to);
+            let updated = value(params.join("; "));
+            headers.insert(name, updated);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Integration with [Rocket](https://rocket.rs), enabled with the `rocket` feature.
+//!
+//! [`Helmet`] is a fairing applying a [`crate::HelmetLayer`] configured as usual to all responses,
+//! so Rocket applications share the configuration of tower services. With nonces enabled,
+//! [`CspNonce`] is a request guard for the nonce of the current request.
+//!
+//! ```
+//! use tower_helmet::rocket::Helmet;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let rocket = rocket::build().attach(Helmet::from(HelmetLayer::with_defaults()));
+//! ```
+//!
+//! Rocket's default `Shield` fairing sets some of the same headers; attach `Shield::new()` without
+//! policies so they are only configured here.
+//!
+//! Rocket responses have no extensions, so [`crate::HeaderOverride`]s can't be used, and
+//! [`CspNonce::current`] isn't set while handlers run.
+//!
+//! Fairings can't stop requests from being handled, so with [`crate::provider::Fallback::Fail`]
+//! the handler still runs when the headers can't be computed; only its response is replaced by
+//! `500 Internal Server Error`.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+
+use crate::{preset, CspNonce};
+
+/// Fairing applying a [`crate::HelmetLayer`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Helmet(Arc<crate::HelmetService<()>>);
+
+impl From<crate::HelmetLayer> for Helmet {
+    fn from(layer: crate::HelmetLayer) -> Self {
+        Helmet(Arc::new(layer.service(())))
+    }
+}
+
+/// The headers prepared for a request, in its local cache.
+struct Prepared {
+    headers: HeaderMap,
+    nonce: Option<CspNonce>,
+    /// Whether the response is replaced by `500 Internal Server Error`.
+    failed: bool,
+}
+
+#[rocket::async_trait]
+impl Fairing for Helmet {
+    fn info(&self) -> Info {
+        Info {
+            name: "tower-helmet",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let request_headers = to_http1(request.headers().iter().map(|h| (h.name, h.value)));
+        let (headers, adjustments, failed) = crate::or_fallback(
+            self.0.prepare(
+                preset::is_grpc(&request_headers),
+                request.uri().path().as_str(),
+                request_headers
+                    .get(http::header::USER_AGENT)
+                    .map(HeaderValue::as_bytes),
+                self.0.session_id(|| Cow::Borrowed(&request_headers)),
+                None,
+                None,
+            ),
+        );
+        let nonce = adjustments.nonce();
+        request.local_cache(|| Prepared {
+            headers,
+            nonce,
+            failed,
+        });
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let prepared = request.local_cache(|| Prepared {
+            headers: HeaderMap::new(),
+            nonce: None,
+            failed: false,
+        });
+
+        if prepared.failed {
+            *response = Response::new();
+            response.set_status(Status::InternalServerError);
+        }
+
+        let mut headers = to_http1(response.headers().iter().map(|h| (h.name, h.value)));
+        let status = StatusCode::from_u16(response.status().code).unwrap_or(StatusCode::OK);
+        self.0.respond(
+            status,
+            &mut headers,
+            &prepared.headers,
+            prepared.nonce.as_ref(),
+            None,
+        );
+
+        let names: Vec<String> = response
+            .headers()
+            .iter()
+            .map(|h| h.name().to_string())
+            .collect();
+        for name in names {
+            response.remove_header(&name);
+        }
+        for (name, value) in &headers {
+            if let Ok(value) = value.to_str() {
+                response.adjoin_raw_header(name.as_str().to_owned(), value.to_owned());
+            }
+        }
+    }
+}
+
+/// Request guard for the nonce of the current request, failing with `500 Internal Server Error`
+/// if the [`Helmet`] fairing isn't attached or nonces aren't enabled.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CspNonce {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let prepared = request.local_cache(|| Prepared {
+            headers: HeaderMap::new(),
+            nonce: None,
+            failed: false,
+        });
+        match &prepared.nonce {
+            Some(nonce) => Outcome::Success(nonce.clone()),
+            None => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+fn to_http1<N, V>(headers: impl Iterator<Item = (N, V)>) -> HeaderMap
+where
+    N: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut converted = HeaderMap::new();
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_ref().as_bytes()),
+            HeaderValue::from_str(value.as_ref()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
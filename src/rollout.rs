@@ -0,0 +1,106 @@
+//! Gradual enablement of newer or stricter headers through feature flags.
+//!
+//! Headers such as `Cross-Origin-Embedder-Policy` or a tightened CSP break pages which load
+//! resources the policy doesn't allow, so they are best rolled out to a growing share of users.
+//! A [`Rollout`] passed to [`crate::HelmetLayer::rollout`] maps feature flags to headers and asks
+//! a [`FeatureFlags`] client, such as one of LaunchDarkly or Unleash, for every request whether
+//! the flag is enabled. The headers of enabled flags are added to (or replace) the configured ones
+//! before the nonce and the `User-Agent` adjustments are applied, so percentages and targeting
+//! are changed in the flag service without deploying.
+//!
+//! Only the layer for `http` 1.x services consults the flags, and not for gRPC requests which get
+//! the [`crate::HelmetLayer::grpc`] preset.
+//!
+//! ```
+//! use http::request::Parts;
+//! use tower_helmet::header::CrossOriginEmbedderPolicy;
+//! use tower_helmet::rollout::Rollout;
+//! use tower_helmet::HelmetLayer;
+//!
+//! # fn is_enabled(flag: &str, user: Option<&str>) -> bool { false }
+//! let flags = |flag: &str, parts: &Parts| {
+//!     let user = parts.headers.get("x-user-id").and_then(|v| v.to_str().ok());
+//!     is_enabled(flag, user)
+//! };
+//!
+//! let mut rollout = Rollout::new(flags);
+//! rollout.header("security-headers-coep", CrossOriginEmbedderPolicy::RequireCorp);
+//!
+//! let mut layer = HelmetLayer::with_defaults();
+//! layer.rollout(rollout);
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use http::request::Parts;
+use http::HeaderMap;
+
+use crate::IntoHeaders;
+
+/// Decides per request whether a feature flag is enabled, see the [module docs](self).
+///
+/// It is implemented for closures taking the flag and the request parts. Flags are evaluated on
+/// the request path, so clients should evaluate them locally instead of asking a remote service.
+pub trait FeatureFlags: Send + Sync + 'static {
+    /// Whether `flag` is enabled for the request with `parts`.
+    fn is_enabled(&self, flag: &str, parts: &Parts) -> bool;
+}
+
+impl<F> FeatureFlags for F
+where
+    F: Fn(&str, &Parts) -> bool + Send + Sync + 'static,
+{
+    fn is_enabled(&self, flag: &str, parts: &Parts) -> bool {
+        self(flag, parts)
+    }
+}
+
+/// Headers sent only for requests whose feature flag is enabled, see the [module docs](self).
+#[derive(Clone)]
+pub struct Rollout {
+    flags: Arc<dyn FeatureFlags>,
+    headers: Vec<(String, HeaderMap)>,
+}
+
+impl Debug for Rollout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let flags: Vec<&str> = self.headers.iter().map(|(flag, _)| flag.as_str()).collect();
+        f.debug_struct("Rollout").field("flags", &flags).finish()
+    }
+}
+
+impl Rollout {
+    /// Asks `flags` whether the flags of the headers are enabled.
+    pub fn new(flags: impl FeatureFlags) -> Self {
+        Rollout {
+            flags: Arc::new(flags),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Sends `headers` for requests with `flag` enabled. Flags are checked in the order they were
+    /// added, so a later one replaces the headers of an earlier one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header value is invalid, like [`crate::HelmetLayer::enable`].
+    pub fn header(&mut self, flag: impl Into<String>, headers: impl IntoHeaders) -> &mut Self {
+        self.headers
+            .push((flag.into(), headers.header_map().unwrap()));
+        self
+    }
+
+    /// The headers of the flags enabled for the request with `parts`, `None` if there are none.
+    pub(crate) fn enabled(&self, parts: &Parts) -> Option<HeaderMap> {
+        let mut enabled: Option<HeaderMap> = None;
+        for (flag, headers) in &self.headers {
+            if self.flags.is_enabled(flag, parts) {
+                enabled
+                    .get_or_insert_with(HeaderMap::new)
+                    .extend(headers.clone());
+            }
+        }
+        enabled
+    }
+}
@@ -0,0 +1,98 @@
+//! Different header sets for different parts of a service.
+//!
+//! A single policy for a whole service is often a compromise: the application wants a strict CSP,
+//! static assets may be loaded cross-origin and an embeddable widget must allow framing. Instead
+//! of weakening the headers everywhere, a [`PolicyRouter`] maps paths to complete header sets and
+//! is added to the layer with [`crate::HelmetLayer::routes`]. The layer's own headers are sent for
+//! paths no route matches.
+//!
+//! ```
+//! use tower_helmet::header::{CrossOriginResourcePolicy, XFrameOptions};
+//! use tower_helmet::preset::EmbeddedDocuments;
+//! use tower_helmet::router::PolicyRouter;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut assets = HelmetLayer::with_defaults_v2();
+//! assets.enable(CrossOriginResourcePolicy::CrossOrigin);
+//!
+//! let mut embed = HelmetLayer::with_defaults_v2();
+//! embed.enable(EmbeddedDocuments {
+//!     frame_ancestors: vec!["https://partner.example"],
+//!     ..Default::default()
+//! });
+//! embed.remove(http::header::X_FRAME_OPTIONS);
+//!
+//! let mut router = PolicyRouter::new();
+//! router
+//!     .route("/assets", assets)
+//!     .route("/embed", embed)
+//!     .route("/tenants/*/embed", XFrameOptions::SameOrigin);
+//!
+//! let headers = router.headers("/tenants/acme/embed/widget").unwrap();
+//! assert_eq!(headers.len(), 1);
+//! assert!(router.headers("/assets/app.js").is_some());
+//! assert!(router.headers("/assets-old/app.js").is_none());
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.routes(router);
+//! ```
+
+use http::HeaderMap;
+
+use crate::IntoHeaders;
+
+/// Maps path patterns to header sets, see the [module docs](self).
+///
+/// A pattern such as `/assets` matches the path itself and every path below it (`/assets/app.js`,
+/// but not `/assets-old`). A `*` segment matches any single segment, so `/tenants/*/embed` matches
+/// `/tenants/acme/embed/widget`. If several patterns match, the one with the most segments is
+/// used, and of those the one added first.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyRouter {
+    routes: Vec<(Vec<String>, HeaderMap)>,
+}
+
+impl PolicyRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends exactly the headers of `headers` (for example another [`crate::HelmetLayer`] or a
+    /// preset) for requests whose path matches `pattern`, instead of the layer's headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header value is invalid, like [`crate::HelmetLayer::enable`].
+    pub fn route(&mut self, pattern: &str, headers: impl IntoHeaders) -> &mut Self {
+        self.routes.push((
+            segments(pattern).map(str::to_owned).collect(),
+            headers.header_map().unwrap(),
+        ));
+        self
+    }
+
+    /// The headers of the route matching `path`, if any.
+    pub fn headers(&self, path: &str) -> Option<&HeaderMap> {
+        let path: Vec<&str> = segments(path).collect();
+
+        let mut matched: Option<&(Vec<String>, HeaderMap)> = None;
+        for route in &self.routes {
+            let (pattern, _) = route;
+            let matches = pattern.len() <= path.len()
+                && pattern
+                    .iter()
+                    .zip(&path)
+                    .all(|(pattern, segment)| pattern == "*" || pattern == segment);
+            if matches && matched.is_none_or(|(m, _)| pattern.len() > m.len()) {
+                matched = Some(route);
+            }
+        }
+
+        matched.map(|(_, headers)| headers)
+    }
+}
+
+/// The non-empty segments of a path, so trailing and repeated slashes don't matter.
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
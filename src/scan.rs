@@ -0,0 +1,82 @@
+//! Grades the headers a live URL actually sends, enabled with the `scan` feature.
+//!
+//! CDNs and proxies in front of a service can add, drop or rewrite headers, so what is configured
+//! is not necessarily what reaches browsers. [`scan`] fetches a URL (following redirects) and
+//! produces the same [`audit`](crate::audit) findings and [`grade`](crate::grade) as
+//! [`crate::HelmetLayer::audit`] and [`crate::HelmetLayer::grade`].
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), reqwest::Error> {
+//! let report = tower_helmet::scan::scan("https://example.com").await?;
+//! println!("{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use http::{HeaderMap, StatusCode};
+
+use crate::audit::{self, Finding};
+use crate::canary::Canary;
+use crate::grade::{self, Grade};
+use crate::HelmetLayer;
+
+/// The result of scanning a URL.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The final URL, after following redirects.
+    pub url: String,
+    pub status: StatusCode,
+    /// All response headers, not only the security related ones.
+    pub headers: HeaderMap,
+    pub findings: Vec<Finding>,
+    pub grade: Grade,
+}
+
+impl Report {
+    /// Builds the report of an already received response.
+    pub fn new(url: impl Into<String>, status: StatusCode, headers: HeaderMap) -> Self {
+        Report {
+            url: url.into(),
+            status,
+            findings: audit::audit(&headers),
+            grade: grade::grade(&headers),
+            headers,
+        }
+    }
+
+    /// Reports headers of `layer` which were stripped or changed in transit, see
+    /// [`Canary::check`].
+    pub fn stripped(&self, canary: &Canary, layer: &HelmetLayer) -> Vec<Finding> {
+        canary.check(layer, &self.headers)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} ({})", self.url, self.status)?;
+        writeln!(f, "grade: {}", self.grade)?;
+        for finding in &self.findings {
+            writeln!(f, "{}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches `url` with a default client and reports on its response headers.
+pub async fn scan(url: &str) -> Result<Report, reqwest::Error> {
+    scan_with(&reqwest::Client::new(), url).await
+}
+
+/// Like [`scan`], using the given client, for example to configure timeouts, proxies or the
+/// redirect policy.
+pub async fn scan_with(client: &reqwest::Client, url: &str) -> Result<Report, reqwest::Error> {
+    let response = client.get(url).send().await?;
+
+    Ok(Report::new(
+        response.url().as_str(),
+        response.status(),
+        response.headers().clone(),
+    ))
+}
@@ -0,0 +1,249 @@
+//! Serves an [RFC 9116](https://www.rfc-editor.org/rfc/rfc9116) `security.txt`, which tells
+//! security researchers how to report vulnerabilities.
+//!
+//! [`SecurityTxtLayer`] answers `GET` and `HEAD` requests to `/.well-known/security.txt` (and the
+//! legacy `/security.txt`) itself and passes everything else on to the inner service. The file is
+//! served as `text/plain; charset=utf-8` and cached for at most a day, but never past its expiry.
+//!
+//! ```
+//! use std::time::{Duration, SystemTime};
+//!
+//! use tower_helmet::security_txt::{SecurityTxt, SecurityTxtLayer};
+//!
+//! let expires = SystemTime::now() + Duration::from_secs(365 * 24 * 60 * 60);
+//! let mut security_txt = SecurityTxt::new("mailto:security@example.com", expires);
+//! security_txt.preferred_languages.push("en".to_owned());
+//!
+//! let layer = SecurityTxtLayer::new(security_txt);
+//! // let app = Router::new().layer(layer);
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderValue, Method, Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Paths the file is served at, the first one being the one RFC 9116 requires.
+const PATHS: [&str; 2] = ["/.well-known/security.txt", "/security.txt"];
+
+/// Longest time the file is cached for.
+const MAX_CACHE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The fields of a `security.txt`. Every value of the vectors is rendered as its own line.
+#[derive(Debug, Clone)]
+pub struct SecurityTxt {
+    /// Where to report vulnerabilities to, such as `mailto:security@example.com` or an
+    /// `https://` URL. At least one is required.
+    pub contact: Vec<String>,
+    /// When the file should be considered stale. Required, and should be less than a year in the
+    /// future.
+    pub expires: SystemTime,
+    /// URLs of keys to use for encrypted reports.
+    pub encryption: Vec<String>,
+    /// URLs of pages recognizing researchers.
+    pub acknowledgments: Vec<String>,
+    /// Language tags such as `en`, rendered as a single comma separated line.
+    pub preferred_languages: Vec<String>,
+    /// URLs the file is canonically served at.
+    pub canonical: Vec<String>,
+    /// URLs of the vulnerability disclosure policy.
+    pub policy: Vec<String>,
+    /// URLs of security related job openings.
+    pub hiring: Vec<String>,
+}
+
+impl SecurityTxt {
+    pub fn new(contact: impl Into<String>, expires: SystemTime) -> Self {
+        SecurityTxt {
+            contact: vec![contact.into()],
+            expires,
+            encryption: Vec::new(),
+            acknowledgments: Vec::new(),
+            preferred_languages: Vec::new(),
+            canonical: Vec::new(),
+            policy: Vec::new(),
+            hiring: Vec::new(),
+        }
+    }
+}
+
+impl Display for SecurityTxt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("Contact", &self.contact),
+            ("Encryption", &self.encryption),
+            ("Acknowledgments", &self.acknowledgments),
+            ("Canonical", &self.canonical),
+            ("Policy", &self.policy),
+            ("Hiring", &self.hiring),
+        ];
+
+        for (name, values) in fields {
+            for value in values {
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+        if !self.preferred_languages.is_empty() {
+            writeln!(
+                f,
+                "Preferred-Languages: {}",
+                self.preferred_languages.join(", ")
+            )?;
+        }
+        writeln!(f, "Expires: {}", format_timestamp(self.expires))
+    }
+}
+
+/// Formats a time as RFC 3339 timestamp in UTC, such as `2025-01-31T12:00:00Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Layer serving a [`SecurityTxt`].
+#[derive(Debug, Clone)]
+pub struct SecurityTxtLayer {
+    content: Arc<str>,
+    expires: SystemTime,
+}
+
+impl SecurityTxtLayer {
+    pub fn new(security_txt: SecurityTxt) -> Self {
+        SecurityTxtLayer {
+            content: security_txt.to_string().into(),
+            expires: security_txt.expires,
+        }
+    }
+
+    fn response<B: From<String>>(&self, method: &Method) -> Response<B> {
+        let body = if method == Method::HEAD {
+            String::new()
+        } else {
+            self.content.to_string()
+        };
+        let max_age = self
+            .expires
+            .duration_since(crate::clock::now())
+            .unwrap_or_default()
+            .min(MAX_CACHE_AGE);
+
+        let mut response = Response::new(B::from(body));
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        response.headers_mut().insert(
+            CACHE_CONTROL,
+            HeaderValue::from_str(&format!("max-age={}", max_age.as_secs())).unwrap(),
+        );
+        response
+    }
+}
+
+impl<S> Layer<S> for SecurityTxtLayer {
+    type Service = SecurityTxtService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SecurityTxtService {
+            inner: service,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityTxtService<S> {
+    inner: S,
+    layer: SecurityTxtLayer,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for SecurityTxtService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: From<String>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let method = request.method();
+        if (method == Method::GET || method == Method::HEAD)
+            && PATHS.contains(&request.uri().path())
+        {
+            return ResponseFuture::Ready {
+                response: Some(self.layer.response(method)),
+            };
+        }
+
+        ResponseFuture::Inner {
+            future: self.inner.call(request),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`SecurityTxtService`].
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Inner {
+            #[pin]
+            future: F,
+        },
+        Ready {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F, ResBody>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Ready { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
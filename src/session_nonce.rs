@@ -0,0 +1,174 @@
+//! Nonces which stay the same for a whole session, enabled with the `session-nonce` feature.
+//!
+//! A [`crate::CspNonce`] is generated for every request, so HTML which is cached and hydrated
+//! later, as single-page applications do, carries a nonce the current CSP no longer allows. With
+//! [`crate::HelmetLayer::session_nonce`], the nonce is derived from the session of the request
+//! instead: an HMAC of the session id, keyed with a server secret, so every instance of the
+//! application derives the same nonce without storing it. Requests without a session still get a
+//! fresh nonce.
+//!
+//! This weakens the nonce: anyone who learns it, for example from a cached page or by injecting
+//! markup which leaks attributes, can use it for the rest of its lifetime instead of a single
+//! response. The trade-offs are therefore enforced by the configuration:
+//!
+//! - the nonce rotates after a fixed period of at most a day, and the CSP allows the current and
+//!   the previous nonce, so cached HTML keeps working for at least one period;
+//! - responses with a nonce are kept out of shared caches (`public` and `s-maxage` are replaced by
+//!   `private`) and their `max-age` is capped at (or, without one, set to) the rotation period;
+//! - the secret has to be at least 32 bytes long.
+//!
+//! The session is identified by a [`SessionId`], such as the [`SessionCookie`] set by session
+//! middleware like tower-sessions, whose cookie is called `id` by default. Session ids should be
+//! rotated on login, which rotates the nonce as well.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use http::header::{CACHE_CONTROL, COOKIE};
+//! use http::Request;
+//! use tower_helmet::session_nonce::{SessionCookie, SessionNonce};
+//! use tower_helmet::test_util::call;
+//! use tower_helmet::HelmetLayer;
+//!
+//! # let secret = [7u8; 32];
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.session_nonce(SessionNonce::new(
+//!     secret,
+//!     SessionCookie("id"),
+//!     Duration::from_secs(3600),
+//! ));
+//!
+//! // responses with a session nonce are only cached privately, for at most the rotation period
+//! let mut request = Request::new(String::new());
+//! request.headers_mut().insert(COOKIE, "id=1234".parse().unwrap());
+//! let response = call(&layer, request);
+//! assert_eq!(response.headers()[CACHE_CONTROL], "private, max-age=3600");
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use hmac::{Hmac, Mac};
+use http::header::COOKIE;
+use http::HeaderMap;
+use sha2::Sha256;
+
+use crate::CspNonce;
+
+/// The longest a session nonce may stay the same.
+pub const MAX_ROTATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The shortest secret accepted by [`SessionNonce::new`], in bytes.
+pub const MIN_SECRET_LEN: usize = 32;
+
+/// Identifies the session a request belongs to, see the [module docs](self).
+///
+/// It is implemented for closures taking the request headers.
+pub trait SessionId: Send + Sync + 'static {
+    /// The id of the session of the request with `headers`, `None` if it has none.
+    fn session_id(&self, headers: &HeaderMap) -> Option<Vec<u8>>;
+}
+
+impl<F> SessionId for F
+where
+    F: Fn(&HeaderMap) -> Option<Vec<u8>> + Send + Sync + 'static,
+{
+    fn session_id(&self, headers: &HeaderMap) -> Option<Vec<u8>> {
+        self(headers)
+    }
+}
+
+/// Identifies the session by the value of the cookie with the given name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionCookie(pub &'static str);
+
+impl SessionId for SessionCookie {
+    fn session_id(&self, headers: &HeaderMap) -> Option<Vec<u8>> {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, value)| *name == self.0 && !value.is_empty())
+            .map(|(_, value)| value.as_bytes().to_vec())
+    }
+}
+
+/// Derives nonces from the session of a request, see the [module docs](self).
+#[derive(Clone)]
+pub struct SessionNonce {
+    secret: Arc<[u8]>,
+    session: Arc<dyn SessionId>,
+    rotation: Duration,
+}
+
+impl Debug for SessionNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionNonce")
+            .field("rotation", &self.rotation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionNonce {
+    /// Derives the nonces from the sessions identified by `session`, keyed with `secret`, which
+    /// has to be the same on all instances of the application. The nonce rotates every
+    /// `rotation`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than [`MIN_SECRET_LEN`] or `rotation` is zero or longer than
+    /// [`MAX_ROTATION`].
+    pub fn new(secret: impl Into<Vec<u8>>, session: impl SessionId, rotation: Duration) -> Self {
+        let secret = secret.into();
+        assert!(
+            secret.len() >= MIN_SECRET_LEN,
+            "the session nonce secret must be at least {} bytes long",
+            MIN_SECRET_LEN
+        );
+        assert!(
+            rotation >= Duration::from_secs(1) && rotation <= MAX_ROTATION,
+            "the session nonce must rotate after one second to one day"
+        );
+
+        SessionNonce {
+            secret: secret.into(),
+            session: Arc::new(session),
+            rotation,
+        }
+    }
+
+    /// How long the nonce of a session stays the same.
+    pub fn rotation(&self) -> Duration {
+        self.rotation
+    }
+
+    /// The id of the session of the request with `headers`.
+    pub(crate) fn session_id(&self, headers: &HeaderMap) -> Option<Vec<u8>> {
+        self.session.session_id(headers)
+    }
+
+    /// The nonce of `session_id` at `now` and the one of the previous period, which the CSP
+    /// still allows for pages cached before the rotation.
+    pub fn nonces(&self, session_id: &[u8], now: SystemTime) -> (CspNonce, CspNonce) {
+        let elapsed = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let period = elapsed.as_secs() / self.rotation.as_secs();
+        (
+            self.derive(session_id, period),
+            self.derive(session_id, period.saturating_sub(1)),
+        )
+    }
+
+    fn derive(&self, session_id: &[u8], period: u64) -> CspNonce {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("any key length");
+        // the length prefix keeps session ids from running into the period
+        mac.update(&(session_id.len() as u64).to_be_bytes());
+        mac.update(session_id);
+        mac.update(&period.to_be_bytes());
+        CspNonce::from_bytes(&mac.finalize().into_bytes()[..16])
+    }
+}
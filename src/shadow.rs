@@ -0,0 +1,150 @@
+//! Evaluating a rewritten policy without sending it.
+//!
+//! Replacing the headers of a large application at once risks breaking pages nobody thought of.
+//! A [`Shadow`] passed to [`crate::HelmetLayer::shadow`] holds the candidate headers: the active
+//! ones are still sent, but for sampled requests the layer also computes what the shadow headers
+//! would have been, with the same nonce and `User-Agent` adjustments, and compares them with the
+//! headers sent. Differences are passed to a callback as [`Diff`] and with the `tracing` feature
+//! logged at the `info` level; responses are never affected.
+//!
+//! The shadow headers stand in for whatever the request got, the layer's headers, those of a
+//! [route](crate::HelmetLayer::routes) or of the
+//! [policy provider](crate::HelmetLayer::policy_provider). Only the layer for `http` 1.x services
+//! compares them, and not for gRPC requests which get the [`crate::HelmetLayer::grpc`] preset.
+//!
+//! ```
+//! use std::convert::TryFrom;
+//!
+//! use tower_helmet::header::ContentSecurityPolicy;
+//! use tower_helmet::shadow::Shadow;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut rewrite = HelmetLayer::with_defaults_v2();
+//! rewrite.enable(ContentSecurityPolicy::try_from("default-src 'self'; object-src 'none'").unwrap());
+//!
+//! let mut shadow = Shadow::new(rewrite);
+//! shadow
+//!     .every(100)
+//!     .on_diff(|path, diff| eprintln!("{} would change:\n{}", path, diff));
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.shadow(shadow);
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use http::HeaderMap;
+
+use crate::diff::Diff;
+use crate::provider::Adjustments;
+use crate::IntoHeaders;
+
+type DiffCallback = Arc<dyn Fn(&str, &Diff) + Send + Sync>;
+
+/// Candidate headers compared with the active ones, see the [module docs](self).
+#[derive(Clone)]
+pub struct Shadow {
+    headers: HeaderMap,
+    every: u64,
+    requests: Arc<AtomicU64>,
+    on_diff: Option<DiffCallback>,
+}
+
+impl Debug for Shadow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shadow")
+            .field("headers", &self.headers)
+            .field("every", &self.every)
+            .field("on_diff", &self.on_diff.is_some())
+            .finish()
+    }
+}
+
+impl Shadow {
+    /// Compares `headers` (for example another [`crate::HelmetLayer`]) with the active headers of
+    /// every request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header value is invalid, like [`crate::HelmetLayer::enable`].
+    pub fn new(headers: impl IntoHeaders) -> Self {
+        Shadow {
+            headers: headers.header_map().unwrap(),
+            every: 1,
+            requests: Arc::new(AtomicU64::new(0)),
+            on_diff: None,
+        }
+    }
+
+    /// Only compares every `n`-th request, counted over all clones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn every(&mut self, n: u64) -> &mut Self {
+        assert!(n > 0, "at least every first request has to be compared");
+        self.every = n;
+        self
+    }
+
+    /// Calls `callback` with the path and the differences of every compared request whose shadow
+    /// headers differ from the active ones. Added headers are only in the shadow headers, removed
+    /// ones only in the active headers.
+    pub fn on_diff<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&str, &Diff) + Send + Sync + 'static,
+    {
+        self.on_diff = Some(Arc::new(callback));
+        self
+    }
+
+    /// The shadow headers of a request with `adjustments`, if it is sampled.
+    pub(crate) fn sample(
+        self: &Arc<Self>,
+        path: &str,
+        adjustments: &Adjustments,
+    ) -> Option<Pending> {
+        let sampled = self
+            .requests
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.every);
+        if !sampled {
+            return None;
+        }
+
+        let mut headers = self.headers.clone();
+        adjustments.apply(&mut headers, None);
+        Some(Pending {
+            shadow: self.clone(),
+            path: path.to_owned(),
+            headers,
+        })
+    }
+}
+
+/// The shadow headers of a sampled request, compared once the active ones are known.
+#[derive(Debug)]
+pub(crate) struct Pending {
+    shadow: Arc<Shadow>,
+    path: String,
+    headers: HeaderMap,
+}
+
+impl Pending {
+    /// Compares the shadow headers with the `active` ones and reports the differences.
+    pub(crate) fn compare(self, active: &HeaderMap) {
+        let diff = Diff::new(&self.headers, active);
+        if diff.is_empty() {
+            return;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(path = %self.path, "tower-helmet shadow headers differ:\n{}", diff);
+
+        if let Some(callback) = &self.shadow.on_diff {
+            callback(&self.path, &diff);
+        }
+    }
+}
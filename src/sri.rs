@@ -0,0 +1,252 @@
+//! Subresource Integrity and CSP hashes of static assets, enabled with the `sri` feature.
+//!
+//! [`AssetManifest`] walks an asset directory (such as the output of a bundler), computes the
+//! `integrity` value of every script and stylesheet and adds the matching hash sources to a
+//! [`ContentSecurityPolicy`]. Browsers allow external scripts and styles whose `integrity`
+//! attribute matches one of the hashes, so a hash-based CSP for bundled JS/CSS doesn't have to be
+//! maintained by hand.
+//!
+//! The manifest is meant to be generated at build time: its [`Display`](std::fmt::Display) output
+//! can be written to a file in a build script and read back with [`str::parse`].
+//!
+//! ```no_run
+//! use tower_helmet::header::ContentSecurityPolicy;
+//! use tower_helmet::sri::AssetManifest;
+//!
+//! // build.rs
+//! let manifest = AssetManifest::from_dir("dist").unwrap();
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{}/assets.sri", out_dir), manifest.to_string()).unwrap();
+//!
+//! // main.rs
+//! // let manifest: AssetManifest = include_str!(concat!(env!("OUT_DIR"), "/assets.sri")).parse().unwrap();
+//! let mut csp = ContentSecurityPolicy::default();
+//! manifest.extend_policy(&mut csp);
+//!
+//! let integrity = manifest.get("js/app.js").map(|asset| asset.integrity());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha384};
+
+use crate::header::ContentSecurityPolicy;
+
+/// Kind of an asset, which determines the CSP directive its hash is added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum AssetKind {
+    /// `.js` and `.mjs` files, added to `script-src`.
+    Script,
+    /// `.css` files, added to `style-src`.
+    Style,
+}
+
+impl AssetKind {
+    /// The kind of the asset at `path`, based on its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "js" | "mjs" => Some(AssetKind::Script),
+            "css" => Some(AssetKind::Style),
+            _ => None,
+        }
+    }
+
+    fn directive(self) -> &'static str {
+        match self {
+            AssetKind::Script => "script-src",
+            AssetKind::Style => "style-src",
+        }
+    }
+}
+
+/// A hashed script or stylesheet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Asset {
+    kind: AssetKind,
+    integrity: String,
+    source: String,
+}
+
+impl Asset {
+    /// Hashes the content of an asset.
+    pub fn new(kind: AssetKind, content: &[u8]) -> Self {
+        Self::from_integrity(
+            kind,
+            format!("sha384-{}", STANDARD.encode(Sha384::digest(content))),
+        )
+    }
+
+    fn from_integrity(kind: AssetKind, integrity: String) -> Self {
+        Asset {
+            kind,
+            source: format!("'{}'", integrity),
+            integrity,
+        }
+    }
+
+    pub fn kind(&self) -> AssetKind {
+        self.kind
+    }
+
+    /// The value for the `integrity` attribute, such as `sha384-...`.
+    pub fn integrity(&self) -> &str {
+        &self.integrity
+    }
+
+    /// The CSP hash source, such as `'sha384-...'`.
+    pub fn csp_source(&self) -> &str {
+        &self.source
+    }
+}
+
+/// Hashes of the scripts and stylesheets of an asset directory, keyed by their path relative to it
+/// (with `/` as separator).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct AssetManifest {
+    assets: BTreeMap<String, Asset>,
+}
+
+impl AssetManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hashes every script and stylesheet in `dir` and its subdirectories.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut manifest = Self::new();
+        manifest.walk(dir.as_ref(), "")?;
+        Ok(manifest)
+    }
+
+    fn walk(&mut self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = format!("{}{}", prefix, name);
+
+            if entry.file_type()?.is_dir() {
+                self.walk(&entry.path(), &format!("{}/", path))?;
+            } else if let Some(kind) = AssetKind::from_path(&name) {
+                let content = std::fs::read(entry.path())?;
+                self.insert(path, Asset::new(kind, &content));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn insert<P: Into<String>>(&mut self, path: P, asset: Asset) -> &mut Self {
+        self.assets.insert(path.into(), asset);
+        self
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Asset> {
+        self.assets.get(path)
+    }
+
+    /// All assets, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Asset)> {
+        self.assets
+            .iter()
+            .map(|(path, asset)| (path.as_str(), asset))
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Adds the hash sources of all assets to the `script-src` and `style-src` directives of `csp`.
+    /// Directives which are not set yet start out with the sources of `default-src`.
+    pub fn extend_policy<'a>(&'a self, csp: &mut ContentSecurityPolicy<'a>) {
+        for (_, asset) in self.iter() {
+            let directive = asset.kind.directive();
+            if !csp.directives.contains_key(directive) {
+                let sources = inherited_sources(csp, directive);
+                csp.directives.insert(directive, sources);
+            }
+
+            let sources = csp.directives.get_mut(directive).unwrap();
+            if !sources.contains(&asset.csp_source()) {
+                sources.push(asset.csp_source());
+            }
+        }
+    }
+}
+
+/// The sources a directive falls back to if it is not set explicitly.
+fn inherited_sources<'a>(csp: &ContentSecurityPolicy<'a>, directive: &str) -> Vec<&'a str> {
+    let defaults = ContentSecurityPolicy::default_directives();
+
+    if csp.use_defaults {
+        if let Some(sources) = defaults.get(directive) {
+            return sources.clone();
+        }
+    }
+
+    match csp.directives.get("default-src") {
+        Some(sources) => sources.clone(),
+        None if csp.use_defaults => defaults["default-src"].clone(),
+        None => Vec::new(),
+    }
+}
+
+/// One `<integrity> <path>` line per asset.
+impl fmt::Display for AssetManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, asset) in self.iter() {
+            writeln!(f, "{} {}", asset.integrity, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing an [`AssetManifest`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseManifestError {
+    line: usize,
+}
+
+impl fmt::Display for ParseManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid asset manifest entry on line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseManifestError {}
+
+impl FromStr for AssetManifest {
+    type Err = ParseManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut manifest = Self::new();
+
+        for (i, line) in s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let error = ParseManifestError { line: i + 1 };
+            let (integrity, path) = line.trim().split_once(' ').ok_or_else(|| error.clone())?;
+            let kind = AssetKind::from_path(path).ok_or_else(|| error.clone())?;
+            if !integrity.starts_with("sha") {
+                return Err(error);
+            }
+
+            manifest.insert(path, Asset::from_integrity(kind, integrity.to_owned()));
+        }
+
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,376 @@
+//! Proposes an initial CSP for an existing site, enabled with the `starter` feature.
+//!
+//! Writing a CSP for a site which never had one means finding everything its pages load.
+//! [`StarterPolicy::from_dir`] walks the built site (HTML, JS and CSS files) and collects it: the
+//! origins of external scripts, styles, images, fonts, media, frames and form targets, the hashes
+//! of inline `<script>` and `<style>` blocks, and the origins scripts connect to.
+//! [`StarterPolicy::policy`] turns them into a [`ContentSecurityPolicy`] which allows all of it.
+//!
+//! The result is a starting point, not a finished policy: origins found in scripts are a best
+//! guess, and pages built at runtime aren't covered. Deploy it report-only first and tighten it
+//! from the reports. What a CSP can't allow by hash, such as inline event handlers, `style`
+//! attributes, `javascript:` URLs and `eval`, is listed in [`StarterPolicy::notes`], to be
+//! refactored before the policy is enforced.
+//!
+//! ```
+//! use tower_helmet::starter::StarterPolicy;
+//!
+//! let mut starter = StarterPolicy::new();
+//! starter.scan_html(
+//!     r#"<script src="https://cdn.example/app.js"></script>
+//!     <img src="https://images.example/logo.png" onclick="zoom()">"#,
+//! );
+//!
+//! let mut csp = starter.policy();
+//! csp.report_only = true;
+//! assert_eq!(csp.directives["script-src"], ["'self'", "https://cdn.example"]);
+//! assert_eq!(csp.directives["img-src"], ["'self'", "https://images.example"]);
+//! assert_eq!(starter.notes().count(), 1);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+
+use crate::header::ContentSecurityPolicy;
+use crate::html::mixed_content::parse_attributes;
+use crate::html::rewrite::inline_hashes;
+use crate::origin::origin_of;
+
+/// Font files referenced from CSS, loaded under `font-src` instead of `img-src`.
+const FONT_EXTENSIONS: [&str; 5] = [".eot", ".otf", ".ttf", ".woff", ".woff2"];
+
+/// Origins of XML namespaces and similar identifiers, which appear in scripts but are never
+/// fetched.
+const IDENTIFIER_ORIGINS: [&str; 2] = ["http://www.w3.org", "https://www.w3.org"];
+
+/// Sources collected from a site, see the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StarterPolicy {
+    sources: BTreeMap<&'static str, BTreeSet<String>>,
+    notes: BTreeSet<String>,
+    /// The file being scanned by [`StarterPolicy::from_dir`], to prefix its notes.
+    file: Option<String>,
+}
+
+impl StarterPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scans every HTML (`.html`, `.htm`), JS (`.js`, `.mjs`) and CSS (`.css`) file in `dir` and
+    /// its subdirectories.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut starter = Self::new();
+        starter.walk(dir.as_ref(), "")?;
+        starter.file = None;
+        Ok(starter)
+    }
+
+    fn walk(&mut self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = format!("{}{}", prefix, name);
+
+            if entry.file_type()?.is_dir() {
+                self.walk(&entry.path(), &format!("{}/", path))?;
+                continue;
+            }
+
+            let extension = name
+                .rsplit('.')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            if !matches!(extension.as_str(), "html" | "htm" | "js" | "mjs" | "css") {
+                continue;
+            }
+            let content = std::fs::read(entry.path())?;
+            let content = String::from_utf8_lossy(&content);
+            self.file = Some(path);
+            match extension.as_str() {
+                "html" | "htm" => self.scan_html(&content),
+                "css" => self.scan_css(&content),
+                _ => self.scan_js(&content),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Collects the subresources, inline blocks and inline styles of an HTML document.
+    ///
+    /// The hashes of all inline blocks are allowed, so only documents without user content, such
+    /// as the files of the built site, must be scanned.
+    pub fn scan_html(&mut self, html: &str) -> &mut Self {
+        let hashes = inline_hashes(html.as_bytes(), None);
+        self.extend("script-src", hashes.scripts);
+        self.extend("style-src", hashes.styles);
+
+        let mut rest = html;
+        while let Some(pos) = rest.find('<') {
+            rest = &rest[pos + 1..];
+            let name_end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let tag = rest[..name_end].to_ascii_lowercase();
+            if tag.is_empty() {
+                continue;
+            }
+            let (attributes, end) = parse_attributes(&rest.as_bytes()[name_end..]);
+            rest = rest.get(name_end + end..).unwrap_or_default();
+            self.tag(&tag, &attributes);
+
+            // the content of scripts and styles is covered by the hashes
+            if tag == "script" || tag == "style" {
+                let closing = format!("</{}", tag);
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&closing)
+                    .unwrap_or(rest.len());
+                if tag == "style" {
+                    self.scan_css(&rest[..end]);
+                }
+                rest = &rest[end..];
+            }
+        }
+
+        self
+    }
+
+    fn tag(&mut self, tag: &str, attributes: &[(String, String)]) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        for (name, value) in attributes {
+            if name.starts_with("on") && name.len() > 2 {
+                self.note(format!("inline event handler {} on <{}>", name, tag));
+            } else if name == "style" && !value.trim().is_empty() {
+                self.note(format!("style attribute on <{}>", tag));
+            } else if value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("javascript:")
+            {
+                self.note(format!("javascript: URL in {} of <{}>", name, tag));
+            }
+        }
+
+        let (directive, urls): (&'static str, Vec<&str>) = match tag {
+            "script" => ("script-src", attribute("src").into_iter().collect()),
+            "img" => ("img-src", image_urls(attribute("src"), attribute("srcset"))),
+            "source" if attribute("srcset").is_some() => {
+                ("img-src", image_urls(None, attribute("srcset")))
+            }
+            "audio" | "source" | "track" => ("media-src", attribute("src").into_iter().collect()),
+            "video" => {
+                self.add("img-src", attribute("poster"));
+                ("media-src", attribute("src").into_iter().collect())
+            }
+            "iframe" | "frame" => ("frame-src", attribute("src").into_iter().collect()),
+            "form" => ("form-action", attribute("action").into_iter().collect()),
+            "button" | "input" => ("form-action", attribute("formaction").into_iter().collect()),
+            "object" | "embed" => {
+                self.note(format!("<{}> is blocked by object-src 'none'", tag));
+                return;
+            }
+            "link" => {
+                let rel = attribute("rel").unwrap_or_default().to_ascii_lowercase();
+                let rels: Vec<&str> = rel.split_ascii_whitespace().collect();
+                let directive = if rels.contains(&"stylesheet") {
+                    "style-src"
+                } else if rels.contains(&"modulepreload") {
+                    "script-src"
+                } else if rels.contains(&"manifest") {
+                    "manifest-src"
+                } else if rels.iter().any(|rel| rel.ends_with("icon")) {
+                    "img-src"
+                } else if rels.contains(&"preload") {
+                    match attribute("as").unwrap_or_default() {
+                        "script" => "script-src",
+                        "style" => "style-src",
+                        "font" => "font-src",
+                        "image" => "img-src",
+                        "fetch" => "connect-src",
+                        _ => return,
+                    }
+                } else {
+                    return;
+                };
+                (directive, attribute("href").into_iter().collect())
+            }
+            _ => return,
+        };
+
+        for url in urls {
+            self.add(directive, Some(url));
+        }
+    }
+
+    /// Collects the images, fonts and imported stylesheets of a stylesheet.
+    pub fn scan_css(&mut self, css: &str) -> &mut Self {
+        let mut rest = css;
+        while let Some(pos) = rest.find("url(") {
+            rest = &rest[pos + 4..];
+            let end = rest.find(')').unwrap_or(rest.len());
+            let url = rest[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+            let path = url.split(['?', '#']).next().unwrap_or_default();
+            let directive = if FONT_EXTENSIONS
+                .iter()
+                .any(|extension| path.to_ascii_lowercase().ends_with(extension))
+            {
+                "font-src"
+            } else {
+                "img-src"
+            };
+            self.add(directive, Some(url));
+            rest = &rest[end..];
+        }
+
+        for line in css.split(';') {
+            if let Some(import) = line.trim().strip_prefix("@import") {
+                let url = import.trim().trim_start_matches("url(");
+                let url = url.split(['"', '\'', ')', ' ']).find(|s| !s.is_empty());
+                self.add("style-src", url);
+            }
+        }
+
+        self
+    }
+
+    /// Collects the origins a script connects to, from absolute URLs in its string literals, and
+    /// notes dynamic code evaluation.
+    pub fn scan_js(&mut self, js: &str) -> &mut Self {
+        for quote in ['"', '\'', '`'] {
+            for scheme in ["https://", "http://", "wss://", "ws://"] {
+                let prefix = format!("{}{}", quote, scheme);
+                for (pos, _) in js.match_indices(&prefix) {
+                    let url = &js[pos + 1..];
+                    let end = url
+                        .find(|c: char| c == quote || c.is_whitespace())
+                        .unwrap_or(url.len());
+                    if let Some(origin) = origin_of(&url[..end]) {
+                        if !IDENTIFIER_ORIGINS.contains(&origin.as_str()) {
+                            self.extend("connect-src", [origin]);
+                        }
+                    }
+                }
+            }
+        }
+
+        if js.contains("eval(") || js.contains("new Function(") {
+            self.note(
+                "dynamic code evaluation (eval or new Function) needs 'unsafe-eval'".to_owned(),
+            );
+        }
+
+        self
+    }
+
+    /// The sources collected for `directive`, sorted.
+    pub fn sources(&self, directive: &str) -> impl Iterator<Item = &str> {
+        self.sources
+            .get(directive)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// What the policy can't allow without `'unsafe-inline'` or `'unsafe-eval'`, prefixed with
+    /// the file it was found in when scanning a directory.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.notes.iter().map(String::as_str)
+    }
+
+    /// A policy allowing everything collected: `'self'` and the collected sources for every
+    /// directive with sources, `default-src 'self'`, and `base-uri`, `form-action` and
+    /// `frame-ancestors` limited to `'self'` and `object-src` to `'none'`.
+    pub fn policy(&self) -> ContentSecurityPolicy<'_> {
+        let mut csp = ContentSecurityPolicy {
+            use_defaults: false,
+            ..Default::default()
+        };
+        csp.directives.insert("default-src", vec!["'self'"]);
+        csp.directives.insert("base-uri", vec!["'self'"]);
+        csp.directives.insert("form-action", vec!["'self'"]);
+        csp.directives.insert("frame-ancestors", vec!["'self'"]);
+        csp.directives.insert("object-src", vec!["'none'"]);
+
+        for (directive, sources) in &self.sources {
+            let list = csp
+                .directives
+                .entry(directive)
+                .or_insert_with(|| vec!["'self'"]);
+            for source in sources {
+                if !list.contains(&source.as_str()) {
+                    list.push(source);
+                }
+            }
+        }
+
+        csp
+    }
+
+    fn add(&mut self, directive: &'static str, url: Option<&str>) {
+        if let Some(source) = url.and_then(source) {
+            self.extend(directive, [source]);
+        }
+    }
+
+    fn extend(&mut self, directive: &'static str, sources: impl IntoIterator<Item = String>) {
+        for source in sources {
+            self.sources.entry(directive).or_default().insert(source);
+        }
+    }
+
+    fn note(&mut self, note: String) {
+        let note = match &self.file {
+            Some(file) => format!("{}: {}", file, note),
+            None => note,
+        };
+        self.notes.insert(note);
+    }
+}
+
+/// The URLs of `src` and the candidates of `srcset`.
+fn image_urls<'a>(src: Option<&'a str>, srcset: Option<&'a str>) -> Vec<&'a str> {
+    let candidates = srcset
+        .into_iter()
+        .flat_map(|srcset| srcset.split(','))
+        .filter_map(|candidate| candidate.split_ascii_whitespace().next());
+    src.into_iter().chain(candidates).collect()
+}
+
+/// The CSP source allowing `url`: its origin if it is absolute, its host if it is
+/// protocol-relative, the scheme for `data:` and `blob:` URLs and `'self'` if it is relative.
+fn source(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with('#') {
+        return None;
+    }
+    if let Some(host) = url.strip_prefix("//") {
+        let host = host.split(['/', '?', '#']).next()?;
+        return Some(host.to_ascii_lowercase()).filter(|host| !host.is_empty());
+    }
+
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase())
+        .filter(|scheme| {
+            scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        });
+    match scheme.as_deref() {
+        None => Some("'self'".to_owned()),
+        Some("http" | "https" | "ws" | "wss") => origin_of(url),
+        Some(scheme @ ("data" | "blob")) => Some(format!("{}:", scheme)),
+        // javascript: URLs are noted, mailto: and others aren't fetched
+        Some(_) => None,
+    }
+}
@@ -0,0 +1,48 @@
+//! Helpers for rendering the current request's [`CspNonce`] from templates, so inline scripts can
+//! be written as `<script nonce="{{ csp_nonce() }}">` without passing the nonce around manually.
+//!
+//! They all read [`CspNonce::current`] and render an empty string outside of a request handled by
+//! a [`crate::HelmetLayer`] with nonces enabled.
+//!
+//! - `tera`: register [`tera_csp_nonce`] with `tera.register_function("csp_nonce", tera_csp_nonce)`.
+//! - `minijinja`: register [`minijinja_csp_nonce`] with
+//!   `env.add_function("csp_nonce", minijinja_csp_nonce)`.
+//! - `askama`: re-export [`askama_csp_nonce`] in the `filters` module next to your templates and
+//!   use it as `{{ ""|csp_nonce }}`.
+
+use crate::CspNonce;
+
+fn current() -> String {
+    CspNonce::current()
+        .map(|nonce| nonce.to_string())
+        .unwrap_or_default()
+}
+
+/// Tera function returning the current nonce.
+#[cfg(feature = "tera")]
+pub fn tera_csp_nonce(
+    _args: &std::collections::HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    Ok(tera::Value::String(current()))
+}
+
+/// minijinja function returning the current nonce.
+#[cfg(feature = "minijinja")]
+pub fn minijinja_csp_nonce() -> Result<String, minijinja::Error> {
+    Ok(current())
+}
+
+/// Askama filter rendering the current nonce. The filtered value is ignored.
+///
+/// ```ignore
+/// mod filters {
+///     pub use tower_helmet::templates::askama_csp_nonce as csp_nonce;
+/// }
+/// ```
+#[cfg(feature = "askama")]
+pub fn askama_csp_nonce<T: std::fmt::Display>(
+    _value: T,
+    _values: &dyn askama::Values,
+) -> askama::Result<String> {
+    Ok(current())
+}
@@ -0,0 +1,338 @@
+//! Helpers for testing a security configuration, enabled with the `test-util` feature.
+//!
+//! [`Echo`] is a minimal inner service, [`call`] runs a request through a layer wrapping it and
+//! [`headers`] returns the response headers of a plain `GET /`. [`CountingEcho`] and
+//! [`call_service`] check whether a layer answers a request itself.
+//! [`assert_helmet_headers!`](crate::assert_helmet_headers) checks them in one line, [`snapshot`]
+//! renders them for snapshot tests. Futures are driven with a local executor, so no async runtime
+//! is needed. [`AssertHelmetLayer`] wraps a whole application in integration tests and fails on
+//! any response which doesn't carry the configured headers.
+//!
+//! ```
+//! use tower_helmet::header::XFrameOptions;
+//! use tower_helmet::{assert_helmet_headers, HelmetLayer};
+//!
+//! let mut layer = HelmetLayer::blank();
+//! layer.enable(XFrameOptions::Deny);
+//!
+//! assert_helmet_headers!(layer, "x-frame-options" => "DENY");
+//! ```
+
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::ready;
+use http::{HeaderMap, Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::audit::{audit, Finding, Rule, Severity};
+use crate::{critical, preset, HelmetLayer};
+
+/// Service answering every request with `200 OK`, echoing its body and `Content-Type`.
+///
+/// Set a `Content-Type` such as `text/html` on the request to test layers which only handle some
+/// responses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Echo;
+
+impl<B> Service<Request<B>> for Echo {
+    type Response = Response<B>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        let mut response = Response::new(body);
+        if let Some(content_type) = parts.headers.get(http::header::CONTENT_TYPE) {
+            response
+                .headers_mut()
+                .insert(http::header::CONTENT_TYPE, content_type.clone());
+        }
+
+        ready(Ok(response))
+    }
+}
+
+/// [`Echo`] counting the requests which reach it, shared with its clones.
+///
+/// ```
+/// use http::Request;
+/// use tower_helmet::test_util::{call_service, CountingEcho};
+///
+/// let echo = CountingEcho::new();
+/// let response = call_service(&mut echo.clone(), Request::new("body".to_owned()));
+/// assert_eq!(response.body(), "body");
+/// assert_eq!(echo.calls(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CountingEcho {
+    calls: Arc<AtomicUsize>,
+}
+
+impl CountingEcho {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many requests reached this service or one of its clones.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl<B> Service<Request<B>> for CountingEcho {
+    type Response = Response<B>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Echo.call(request)
+    }
+}
+
+/// Runs `request` through `layer` wrapping [`Echo`] and returns the response.
+///
+/// # Panics
+///
+/// Panics if the service returns an error.
+pub fn call<L, B, ResBody>(layer: &L, request: Request<B>) -> Response<ResBody>
+where
+    L: Layer<Echo>,
+    L::Service: Service<Request<B>, Response = Response<ResBody>>,
+    <L::Service as Service<Request<B>>>::Error: Debug,
+{
+    call_service(&mut layer.layer(Echo), request)
+}
+
+/// Runs `request` through `service`, such as a layer wrapping [`CountingEcho`], and returns the
+/// response.
+///
+/// # Panics
+///
+/// Panics if the service returns an error.
+pub fn call_service<S, B, ResBody>(service: &mut S, request: Request<B>) -> Response<ResBody>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    S::Error: Debug,
+{
+    futures::executor::block_on(async {
+        futures::future::poll_fn(|cx| service.poll_ready(cx))
+            .await
+            .expect("service is not ready");
+        service.call(request).await.expect("service failed")
+    })
+}
+
+/// The response headers `layer` produces for a `GET /` without any headers.
+pub fn headers<L, ResBody>(layer: &L) -> HeaderMap
+where
+    L: Layer<Echo>,
+    L::Service: Service<Request<String>, Response = Response<ResBody>>,
+    <L::Service as Service<Request<String>>>::Error: Debug,
+{
+    let request = Request::new(String::new());
+    call(layer, request).headers().clone()
+}
+
+/// The response headers `layer` produces for a `GET /`, rendered with [`crate::export::snapshot`]
+/// for snapshot testing, for example `insta::assert_snapshot!(snapshot(&layer))`.
+pub fn snapshot<L, ResBody>(layer: &L) -> String
+where
+    L: Layer<Echo>,
+    L::Service: Service<Request<String>, Response = Response<ResBody>>,
+    <L::Service as Service<Request<String>>>::Error: Debug,
+{
+    crate::export::snapshot(&headers(layer))
+}
+
+/// Layer failing the test on any response without the security headers of a [`HelmetLayer`].
+///
+/// It is added as outermost layer of the application under test, so it sees the responses as
+/// they leave it, and panics if a header configured in the [`HelmetLayer`] is missing or has a
+/// different value (ignoring per-request nonces and hashes, like
+/// [`crate::critical::CriticalHeadersLayer`]), or if the response is weaker than the
+/// configuration by [`audit`]: a finding of at least [`Severity::Warning`] which the configured
+/// headers don't have already, such as a CSP with `'unsafe-inline'` set by a handler.
+///
+/// The panic unwinds through the caller of the service, so call it directly, for example with
+/// [`call`] or `ServiceExt::oneshot`; panics in tasks spawned by a server don't fail the test.
+///
+/// ```
+/// use tower_helmet::test_util::{call, AssertHelmetLayer};
+/// use tower_helmet::HelmetLayer;
+///
+/// let helmet = HelmetLayer::with_defaults();
+/// let layers = tower_layer::Stack::new(helmet.clone(), AssertHelmetLayer::new(&helmet));
+///
+/// let response = call(&layers, http::Request::new(String::new()));
+/// assert!(response.headers().contains_key("content-security-policy"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AssertHelmetLayer {
+    expected: HeaderMap,
+    /// The expected headers of gRPC responses, see [`HelmetLayer::grpc`].
+    grpc: Option<HeaderMap>,
+    /// The rules the configured headers already violate, which are not reported again.
+    baseline: Vec<Rule>,
+    exempt_paths: Vec<String>,
+}
+
+impl AssertHelmetLayer {
+    /// Expects every response to carry the headers configured in `helmet`.
+    pub fn new(helmet: &HelmetLayer) -> Self {
+        let baseline = audit(&helmet.headers())
+            .into_iter()
+            .chain(helmet.grpc.iter().flat_map(audit))
+            .map(|finding| finding.rule)
+            .collect();
+
+        AssertHelmetLayer {
+            expected: helmet.headers(),
+            grpc: helmet.grpc.clone(),
+            baseline,
+            exempt_paths: Vec::new(),
+        }
+    }
+
+    /// Doesn't check responses to exactly this path, for example because its handler overrides
+    /// headers on purpose.
+    pub fn exempt_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.exempt_paths.push(path.into());
+        self
+    }
+
+    /// Checks the headers of a response, returning a finding for every missing, changed or
+    /// weakened header.
+    pub fn check(&self, is_grpc: bool, headers: &HeaderMap) -> Vec<Finding> {
+        let expected = match &self.grpc {
+            Some(grpc) if is_grpc => grpc,
+            _ => &self.expected,
+        };
+
+        let mut findings = critical::check(expected, headers);
+        for finding in audit(headers) {
+            if finding.severity >= Severity::Warning
+                && !self.baseline.contains(&finding.rule)
+                && !findings.iter().any(|f| f.header == finding.header)
+            {
+                findings.push(finding);
+            }
+        }
+        findings
+    }
+}
+
+impl<S> Layer<S> for AssertHelmetLayer {
+    type Service = AssertHelmetService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        AssertHelmetService {
+            inner: service,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssertHelmetService<S> {
+    inner: S,
+    layer: Arc<AssertHelmetLayer>,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for AssertHelmetService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let path = request.uri().path();
+        let request_line = Some(format!("{} {}", request.method(), path))
+            .filter(|_| !self.layer.exempt_paths.iter().any(|p| p == path));
+        ResponseFuture {
+            is_grpc: preset::is_grpc(request.headers()),
+            future: self.inner.call(request),
+            request_line,
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`AssertHelmetService`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        future: F,
+
+        is_grpc: bool,
+        // method and path of the request, `None` if it is exempted
+        request_line: Option<String>,
+        layer: Arc<AssertHelmetLayer>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.future.poll(cx)?);
+
+        if let Some(request_line) = this.request_line {
+            let findings = this.layer.check(*this.is_grpc, res.headers());
+            if !findings.is_empty() {
+                let findings: Vec<String> = findings.iter().map(ToString::to_string).collect();
+                panic!(
+                    "the response to {} doesn't carry the expected security headers:\n{}",
+                    request_line,
+                    findings.join("\n")
+                );
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+/// Asserts that a layer sets the given headers for a `GET /`, see [`test_util`](crate::test_util).
+///
+/// Every value is compared as string; other headers may be set as well.
+#[macro_export]
+macro_rules! assert_helmet_headers {
+    ($layer:expr, $($name:expr => $value:expr),+ $(,)?) => {{
+        let headers = $crate::test_util::headers(&$layer);
+        $(
+            assert_eq!(
+                headers.get($name).map(|v| v.to_str().expect("header value is not a string")),
+                Some($value),
+                "unexpected value of {}",
+                $name,
+            );
+        )+
+    }};
+}
@@ -0,0 +1,161 @@
+//! Integration with [tower-http](https://docs.rs/tower-http), enabled with the `tower-http`
+//! feature.
+//!
+//! [`SecurityLayers`] stacks [`HelmetLayer`] with tower-http's CORS, timeout and request body limit
+//! layers in the recommended order:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use tower_helmet::tower_http::SecurityLayers;
+//! use tower_helmet::HelmetLayer;
+//! use tower_http::cors::CorsLayer;
+//!
+//! let layers = SecurityLayers::new(HelmetLayer::with_defaults())
+//!     .timeout(Duration::from_secs(30))
+//!     .cors(CorsLayer::permissive())
+//!     .request_body_limit(1024 * 1024);
+//!
+//! // let app = Router::new().layer(layers);
+//! ```
+//!
+//! The typed headers can also be converted into [`SetResponseHeaderLayer`]s, which is useful for
+//! services which are already built from tower-http primitives and only need a few headers, one
+//! layer per header:
+//!
+//! ```
+//! use tower_helmet::header::{StrictTransportSecurity, XFrameOptions};
+//! use tower_helmet::tower_http::{if_not_present, overriding};
+//!
+//! let hsts = overriding(&StrictTransportSecurity::default()).unwrap();
+//! let frame_options = if_not_present(&XFrameOptions::Deny).unwrap();
+//!
+//! // let app = Router::new().layer(hsts).layer(frame_options);
+//! ```
+
+use std::time::Duration;
+
+use http::header::InvalidHeaderValue;
+use http::{HeaderValue, StatusCode};
+use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::timeout::TimeoutLayer;
+use tower_layer::{Identity, Layer};
+
+use crate::{HelmetLayer, HelmetService, IntoHeader};
+
+/// `SecurityLayers` composes [`HelmetLayer`] with related tower-http layers. Regardless of the
+/// order the builder methods are called in, the layers are always stacked like this (outermost
+/// first):
+///
+/// 1. [`HelmetLayer`], so every response gets the security headers, including CORS preflights and
+///    the error responses of the inner layers.
+/// 2. [`RequestBodyLimitLayer`], responding with `413 Payload Too Large`. Requests announcing a
+///    too large `Content-Length` are rejected right away.
+/// 3. [`CorsLayer`]. It can't wrap the body limit, as it needs a response body it can create empty
+///    for preflights, so requests rejected by their `Content-Length` don't get CORS headers.
+/// 4. [`TimeoutLayer`], responding with `408 Request Timeout`. CORS headers are added to these
+///    responses, so browsers can read them.
+///
+/// Layers which are not configured are left out. Each one can only be set once, which is checked at
+/// compile time.
+#[derive(Debug, Clone)]
+pub struct SecurityLayers<C = Identity, T = Identity, B = Identity> {
+    helmet: HelmetLayer,
+    cors: C,
+    timeout: T,
+    body_limit: B,
+}
+
+impl SecurityLayers {
+    pub fn new(helmet: HelmetLayer) -> Self {
+        SecurityLayers {
+            helmet,
+            cors: Identity::new(),
+            timeout: Identity::new(),
+            body_limit: Identity::new(),
+        }
+    }
+}
+
+impl<T, B> SecurityLayers<Identity, T, B> {
+    pub fn cors(self, cors: CorsLayer) -> SecurityLayers<CorsLayer, T, B> {
+        SecurityLayers {
+            helmet: self.helmet,
+            cors,
+            timeout: self.timeout,
+            body_limit: self.body_limit,
+        }
+    }
+}
+
+impl<C, B> SecurityLayers<C, Identity, B> {
+    /// Fails requests which take longer than `timeout` with `408 Request Timeout`.
+    pub fn timeout(self, timeout: Duration) -> SecurityLayers<C, TimeoutLayer, B> {
+        SecurityLayers {
+            helmet: self.helmet,
+            cors: self.cors,
+            timeout: TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, timeout),
+            body_limit: self.body_limit,
+        }
+    }
+}
+
+impl<C, T> SecurityLayers<C, T, Identity> {
+    /// Rejects request bodies larger than `limit` bytes with `413 Payload Too Large`.
+    pub fn request_body_limit(self, limit: usize) -> SecurityLayers<C, T, RequestBodyLimitLayer> {
+        SecurityLayers {
+            helmet: self.helmet,
+            cors: self.cors,
+            timeout: self.timeout,
+            body_limit: RequestBodyLimitLayer::new(limit),
+        }
+    }
+}
+
+impl<S, C, T, B> Layer<S> for SecurityLayers<C, T, B>
+where
+    T: Layer<S>,
+    C: Layer<T::Service>,
+    B: Layer<C::Service>,
+{
+    type Service = HelmetService<B::Service>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        let service = self.timeout.layer(service);
+        let service = self.cors.layer(service);
+        let service = self.body_limit.layer(service);
+        self.helmet.layer(service)
+    }
+}
+
+/// Sets the header, replacing any value set by the inner service.
+pub fn overriding(
+    header: &impl IntoHeader,
+) -> Result<SetResponseHeaderLayer<HeaderValue>, InvalidHeaderValue> {
+    Ok(SetResponseHeaderLayer::overriding(
+        header.header_name(),
+        header.header_value()?,
+    ))
+}
+
+/// Sets the header, keeping any value set by the inner service.
+pub fn if_not_present(
+    header: &impl IntoHeader,
+) -> Result<SetResponseHeaderLayer<HeaderValue>, InvalidHeaderValue> {
+    Ok(SetResponseHeaderLayer::if_not_present(
+        header.header_name(),
+        header.header_value()?,
+    ))
+}
+
+/// Appends the header to any values set by the inner service.
+pub fn appending(
+    header: &impl IntoHeader,
+) -> Result<SetResponseHeaderLayer<HeaderValue>, InvalidHeaderValue> {
+    Ok(SetResponseHeaderLayer::appending(
+        header.header_name(),
+        header.header_value()?,
+    ))
+}
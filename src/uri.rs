@@ -0,0 +1,30 @@
+//! (De)serializes optional report URLs as strings, rejecting relative ones.
+
+use http::Uri;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(uri: &Option<Uri>, serializer: S) -> Result<S::Ok, S::Error> {
+    match uri {
+        Some(uri) => serializer.serialize_some(&uri.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Uri>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(url) => crate::origin::absolute_uri(&url)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid absolute URL {:?}", url))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(feature = "schemars")]
+pub fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({
+        "description": "An absolute URL.",
+        "type": ["string", "null"],
+        "format": "uri"
+    })
+}
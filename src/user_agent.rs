@@ -0,0 +1,154 @@
+//! Per-request adjustments of the headers for known-problematic browsers.
+//!
+//! [`crate::HelmetLayer::target`] tailors the headers to the oldest supported browsers once, for
+//! everyone. Fleets which still have to support a few old browsers can instead adjust the headers
+//! only for requests from them, based on the `User-Agent`: [`UserAgentAdjustments`] is a list of
+//! rules, each a check of the `User-Agent` and a change of the headers, passed to
+//! [`crate::HelmetLayer::adjust_for_user_agents`].
+//!
+//! As the headers then depend on the `User-Agent`, it is added to the `Vary` header of every
+//! response, which makes shared caches store a copy per `User-Agent`. User agents can be spoofed,
+//! so the adjustments should only relax headers for compatibility, never protect anything.
+//!
+//! ```
+//! use tower_helmet::user_agent::UserAgentAdjustments;
+//! use tower_helmet::HelmetLayer;
+//!
+//! let mut layer = HelmetLayer::with_defaults_v2();
+//! layer.adjust_for_user_agents(UserAgentAdjustments::recommended());
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use http::header::X_XSS_PROTECTION;
+use http::HeaderMap;
+
+use crate::header::{CROSS_ORIGIN_EMBEDDER_POLICY, X_DOWNLOAD_OPTIONS};
+use crate::BrowserBaseline;
+
+type Matcher = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+type Adjustment = Arc<dyn Fn(&mut HeaderMap) + Send + Sync>;
+
+/// Rules adjusting the headers for requests from some browsers, see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct UserAgentAdjustments {
+    rules: Vec<(Matcher, Adjustment)>,
+}
+
+impl Debug for UserAgentAdjustments {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserAgentAdjustments")
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl UserAgentAdjustments {
+    /// Creates an empty list of rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Omits `Cross-Origin-Embedder-Policy` for Safari before 15.2, the first version supporting
+    /// it, and sends the legacy headers only to Internet Explorer.
+    pub fn recommended() -> Self {
+        let mut adjustments = Self::new();
+        adjustments
+            .omit_coep_for_safari_before(15, 2)
+            .legacy_headers_for_internet_explorer();
+        adjustments
+    }
+
+    /// Omits `Cross-Origin-Embedder-Policy` for Safari versions before `major.minor`, for fleets
+    /// where these versions break with it.
+    pub fn omit_coep_for_safari_before(&mut self, major: u32, minor: u32) -> &mut Self {
+        self.rule(
+            move |user_agent| safari_version(user_agent).is_some_and(|v| v < (major, minor)),
+            |headers| {
+                headers.remove(CROSS_ORIGIN_EMBEDDER_POLICY);
+            },
+        )
+    }
+
+    /// Sends `X-XSS-Protection` and `X-Download-Options` only to Internet Explorer, which is the
+    /// only browser understanding them, adding them if they are not configured, see
+    /// [`BrowserBaseline::Legacy`].
+    pub fn legacy_headers_for_internet_explorer(&mut self) -> &mut Self {
+        self.rule(is_internet_explorer, |headers| {
+            BrowserBaseline::Legacy.apply(headers)
+        })
+        .rule(
+            |user_agent| !is_internet_explorer(user_agent),
+            |headers| {
+                headers.remove(X_XSS_PROTECTION);
+                headers.remove(X_DOWNLOAD_OPTIONS);
+            },
+        )
+    }
+
+    /// Adds a rule: `adjust` is called with the headers of every request whose `User-Agent`
+    /// `matches`. Rules are applied in the order they were added.
+    ///
+    /// ```
+    /// use tower_helmet::user_agent::UserAgentAdjustments;
+    ///
+    /// let mut adjustments = UserAgentAdjustments::new();
+    /// adjustments.rule(
+    ///     |user_agent| user_agent.contains("LegacyKiosk/"),
+    ///     |headers| {
+    ///         headers.remove("cross-origin-opener-policy");
+    ///     },
+    /// );
+    /// ```
+    pub fn rule<M, A>(&mut self, matches: M, adjust: A) -> &mut Self
+    where
+        M: Fn(&str) -> bool + Send + Sync + 'static,
+        A: Fn(&mut HeaderMap) + Send + Sync + 'static,
+    {
+        self.rules.push((Arc::new(matches), Arc::new(adjust)));
+        self
+    }
+
+    /// Applies the matching rules to the headers of a request with the given `User-Agent`.
+    /// Requests without one are treated like an empty one.
+    pub(crate) fn apply(&self, user_agent: Option<&[u8]>, headers: &mut HeaderMap) {
+        let user_agent = user_agent
+            .and_then(|ua| std::str::from_utf8(ua).ok())
+            .unwrap_or_default();
+        for (matches, adjust) in &self.rules {
+            if matches(user_agent) {
+                adjust(headers);
+            }
+        }
+    }
+}
+
+fn is_internet_explorer(user_agent: &str) -> bool {
+    user_agent.contains("Trident/") || user_agent.contains("MSIE ")
+}
+
+/// The `major.minor` version of Safari, `None` for other browsers, including the ones on iOS
+/// which identify as Safari as well.
+fn safari_version(user_agent: &str) -> Option<(u32, u32)> {
+    let others = [
+        "Chrome/",
+        "Chromium/",
+        "CriOS/",
+        "FxiOS/",
+        "EdgiOS/",
+        "Edg/",
+        "OPR/",
+    ];
+    if !user_agent.contains("Safari/") || others.iter().any(|o| user_agent.contains(o)) {
+        return None;
+    }
+
+    let version = user_agent.split("Version/").nth(1)?;
+    let version = version.split_whitespace().next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().and_then(|m| m.parse().ok()).unwrap_or(0);
+
+    Some((major, minor))
+}
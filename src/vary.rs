@@ -0,0 +1,41 @@
+use http::header::{HeaderName, VARY};
+use http::{HeaderMap, HeaderValue};
+
+/// Adds `members` to the `Vary` header of a response, so shared caches don't serve headers chosen
+/// for one request to another which differs in these request headers. Members the response
+/// already lists are kept and not repeated, `Vary: *` is left alone.
+pub(crate) fn merge(headers: &mut HeaderMap, members: &[HeaderName]) {
+    let mut listed: Vec<&str> = Vec::new();
+    let mut readable = true;
+    for value in headers.get_all(VARY) {
+        match value.to_str() {
+            Ok(value) => listed.extend(value.split(',').map(str::trim).filter(|m| !m.is_empty())),
+            Err(_) => readable = false,
+        }
+    }
+    if listed.contains(&"*") {
+        return;
+    }
+
+    let missing: Vec<&str> = members
+        .iter()
+        .map(HeaderName::as_str)
+        .filter(|member| !listed.iter().any(|l| l.eq_ignore_ascii_case(member)))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    // header names and members split from valid values are valid values as well
+    let value = if readable {
+        listed.extend(missing);
+        HeaderValue::from_str(&listed.join(", ")).unwrap()
+    } else {
+        HeaderValue::from_str(&missing.join(", ")).unwrap()
+    };
+    if readable {
+        headers.insert(VARY, value);
+    } else {
+        headers.append(VARY, value);
+    }
+}
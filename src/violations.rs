@@ -0,0 +1,348 @@
+//! Collecting CSP violation reports and turning them into policy changes.
+//!
+//! A report-only CSP is only useful if its reports lead to edits of the policy. A [`ReportStore`]
+//! keeps the most recent violations in memory, recorded by the application's report endpoint
+//! (with the `json` feature, [`Violation::parse_reports`] reads the bodies browsers send).
+//! [`ReportStore::analyze`] compares them with the policy and proposes concrete changes:
+//!
+//! - [`Suggestion::AddSource`]: an origin (or `data:`/`blob:`) blocked often enough to be a
+//!   legitimate dependency, to be added to the directive;
+//! - [`Suggestion::NonceOrHash`] and [`Suggestion::UnsafeEval`]: inline code and `eval`, which
+//!   can't be allowed by an origin;
+//! - [`Suggestion::Tighten`]: a directive without any violation over the whole window, whose
+//!   report-only sources are sufficient, so it can be enforced and permissive fallbacks removed.
+//!
+//! Reports can be sent by anyone, so an origin is only suggested once it was reported a minimum
+//! number of times, and every suggestion should be reviewed before the policy is changed.
+//!
+//! ```
+//! use std::time::{Duration, SystemTime};
+//!
+//! use tower_helmet::header::ContentSecurityPolicy;
+//! use tower_helmet::violations::{ReportStore, Suggestion, Violation};
+//!
+//! let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1717200000);
+//! let store = ReportStore::new(start, 10_000);
+//! for _ in 0..5 {
+//!     store.record(Violation::new("script-src-elem", "https://cdn.example/lib.js", start));
+//! }
+//!
+//! let csp = ContentSecurityPolicy::default();
+//! let suggestions = store.analyze(&csp, Duration::from_secs(7 * 86400), start);
+//! assert_eq!(
+//!     suggestions[0],
+//!     Suggestion::AddSource {
+//!         directive: "script-src".to_owned(),
+//!         source: "https://cdn.example".to_owned(),
+//!         reports: 5,
+//!     }
+//! );
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::header::{parse_directives, ContentSecurityPolicy};
+use crate::nonce::fallbacks;
+use crate::origin::origin_of;
+
+/// How often an origin has to be reported before [`ReportStore::analyze`] suggests it, unless
+/// configured with [`ReportStore::min_reports`].
+pub const DEFAULT_MIN_REPORTS: usize = 3;
+
+/// A single CSP violation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Violation {
+    /// The directive which blocked the resource, such as `script-src-elem`.
+    pub directive: String,
+    /// The blocked URL, or `inline`, `eval`, `data` or `blob` as reported by browsers.
+    pub blocked: String,
+    /// When the report was received.
+    pub received: SystemTime,
+}
+
+impl Violation {
+    pub fn new(
+        directive: impl Into<String>,
+        blocked: impl Into<String>,
+        received: SystemTime,
+    ) -> Self {
+        Violation {
+            directive: directive.into(),
+            blocked: blocked.into(),
+            received,
+        }
+    }
+
+    /// Reads the violations of a report body, either a legacy `application/csp-report` object or
+    /// a Reporting API (`application/reports+json`) list, of which only the `csp-violation`
+    /// reports are used. Malformed bodies and reports are skipped.
+    #[cfg(feature = "json")]
+    pub fn parse_reports(body: &[u8], received: SystemTime) -> Vec<Violation> {
+        use serde_json::Value;
+
+        let field = |report: &Value, names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| report.get(*name)?.as_str())
+                .map(str::to_owned)
+        };
+        let violation = |report: &Value, directive: &[&str], blocked: &[&str]| {
+            Some(Violation::new(
+                field(report, directive)?,
+                field(report, blocked).unwrap_or_default(),
+                received,
+            ))
+        };
+
+        match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Object(legacy)) => legacy
+                .get("csp-report")
+                .and_then(|report| {
+                    violation(
+                        report,
+                        &["effective-directive", "violated-directive"],
+                        &["blocked-uri"],
+                    )
+                })
+                .into_iter()
+                .collect(),
+            Ok(Value::Array(reports)) => reports
+                .iter()
+                .filter(|report| {
+                    report.get("type").and_then(Value::as_str) == Some("csp-violation")
+                })
+                .filter_map(|report| {
+                    violation(
+                        report.get("body")?,
+                        &["effectiveDirective"],
+                        &["blockedURL"],
+                    )
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The source which would allow the blocked resource, `None` for inline code, `eval` and
+    /// resources of browser extensions.
+    fn source(&self) -> Option<String> {
+        match self.blocked.trim() {
+            "data" | "data:" => Some("data:".to_owned()),
+            "blob" | "blob:" => Some("blob:".to_owned()),
+            blocked => {
+                let origin = origin_of(blocked)?;
+                let web = ["http://", "https://", "ws://", "wss://"]
+                    .iter()
+                    .any(|scheme| origin.starts_with(scheme));
+                Some(origin).filter(|_| web)
+            }
+        }
+    }
+}
+
+/// A change of the policy proposed by [`ReportStore::analyze`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Suggestion {
+    /// `source` was blocked by `directive` in `reports` reports.
+    AddSource {
+        directive: String,
+        source: String,
+        reports: usize,
+    },
+    /// Inline code was blocked by `directive` in `reports` reports. Add a nonce or hash to the
+    /// code instead of `'unsafe-inline'`.
+    NonceOrHash { directive: String, reports: usize },
+    /// `eval` or a similar function was blocked by `directive` in `reports` reports. Allowing it
+    /// needs `'unsafe-eval'`, so the code should rather be changed.
+    UnsafeEval { directive: String, reports: usize },
+    /// `directive` received no violation over the whole window.
+    Tighten { directive: String },
+}
+
+impl Display for Suggestion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Suggestion::AddSource {
+                directive,
+                source,
+                reports,
+            } => write!(f, "add {} to {} ({} reports)", source, directive, reports),
+            Suggestion::NonceOrHash { directive, reports } => write!(
+                f,
+                "add a nonce or hash to the inline code blocked by {} ({} reports)",
+                directive, reports
+            ),
+            Suggestion::UnsafeEval { directive, reports } => write!(
+                f,
+                "remove the eval blocked by {} or allow 'unsafe-eval' ({} reports)",
+                directive, reports
+            ),
+            Suggestion::Tighten { directive } => write!(
+                f,
+                "{} had no violations, it can be enforced and tightened",
+                directive
+            ),
+        }
+    }
+}
+
+/// The most recent violations, shared between clones, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct ReportStore {
+    inner: Arc<Mutex<Inner>>,
+    capacity: usize,
+    min_reports: usize,
+}
+
+#[derive(Debug)]
+struct Inner {
+    violations: VecDeque<Violation>,
+    /// Since when reports are recorded, or when the oldest dropped one was received.
+    since: SystemTime,
+}
+
+impl ReportStore {
+    /// Creates a store recording reports from `since`, usually the time the application started,
+    /// keeping the `capacity` most recent violations.
+    pub fn new(since: SystemTime, capacity: usize) -> Self {
+        ReportStore {
+            inner: Arc::new(Mutex::new(Inner {
+                violations: VecDeque::new(),
+                since,
+            })),
+            capacity,
+            min_reports: DEFAULT_MIN_REPORTS,
+        }
+    }
+
+    /// How often an origin has to be reported before it is suggested, [`DEFAULT_MIN_REPORTS`] by
+    /// default.
+    pub fn min_reports(&mut self, min_reports: usize) -> &mut Self {
+        self.min_reports = min_reports;
+        self
+    }
+
+    /// Records a violation, dropping the oldest one if the store is full.
+    pub fn record(&self, violation: Violation) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.violations.len() >= self.capacity {
+            if let Some(dropped) = inner.violations.pop_front() {
+                // the window is only complete from the dropped report on
+                inner.since = inner.since.max(dropped.received);
+            }
+        }
+        if self.capacity > 0 {
+            inner.violations.push_back(violation);
+        }
+    }
+
+    /// All recorded violations, oldest first.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.inner
+            .lock()
+            .unwrap()
+            .violations
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Proposes changes of `csp` from the violations received in the `window` before `now`,
+    /// most reported first. Sources are suggested for the directive which blocked them, which is
+    /// the fallback of the reported one (such as `script-src` for `script-src-elem`) if the
+    /// policy doesn't set the reported one. [`Suggestion::Tighten`] is only proposed once the
+    /// store has recorded the whole window.
+    pub fn analyze(
+        &self,
+        csp: &ContentSecurityPolicy<'_>,
+        window: Duration,
+        now: SystemTime,
+    ) -> Vec<Suggestion> {
+        let policy = csp.to_string();
+        let directives = parse_directives(&policy);
+        let start = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+        let inner = self.inner.lock().unwrap();
+
+        let mut sources: BTreeMap<(&str, String), usize> = BTreeMap::new();
+        let mut inline: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut eval: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut violated: Vec<&str> = Vec::new();
+        for violation in inner.violations.iter().filter(|v| v.received >= start) {
+            let reported = violation.directive.as_str();
+            let (directive, allowed) = match governing(&directives, reported) {
+                Some((name, sources)) => (*name, sources.as_slice()),
+                None => (reported, &[][..]),
+            };
+            violated.push(directive);
+
+            match violation.blocked.trim() {
+                "inline" => *inline.entry(directive).or_default() += 1,
+                "eval" | "wasm-eval" => *eval.entry(directive).or_default() += 1,
+                _ => {
+                    let source = violation
+                        .source()
+                        .filter(|s| !allowed.contains(&s.as_str()));
+                    if let Some(source) = source {
+                        *sources.entry((directive, source)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let mut suggestions: Vec<(usize, Suggestion)> = Vec::new();
+        for ((directive, source), reports) in sources {
+            if reports >= self.min_reports {
+                let directive = directive.to_owned();
+                suggestions.push((
+                    reports,
+                    Suggestion::AddSource {
+                        directive,
+                        source,
+                        reports,
+                    },
+                ));
+            }
+        }
+        for (directive, reports) in inline {
+            let directive = directive.to_owned();
+            suggestions.push((reports, Suggestion::NonceOrHash { directive, reports }));
+        }
+        for (directive, reports) in eval {
+            let directive = directive.to_owned();
+            suggestions.push((reports, Suggestion::UnsafeEval { directive, reports }));
+        }
+        // most reported first, stable so ties keep the order of the maps
+        suggestions.sort_by_key(|(reports, _)| Reverse(*reports));
+        let mut suggestions: Vec<Suggestion> = suggestions.into_iter().map(|(_, s)| s).collect();
+
+        if inner.since <= start {
+            let mut quiet: Vec<&str> = directives
+                .iter()
+                .filter(|(name, sources)| !sources.is_empty() && !violated.contains(name))
+                .map(|(name, _)| *name)
+                .collect();
+            quiet.sort_unstable();
+            for directive in quiet {
+                let directive = directive.to_owned();
+                suggestions.push(Suggestion::Tighten { directive });
+            }
+        }
+
+        suggestions
+    }
+}
+
+/// The directive of the policy which applies to violations of `reported`: the directive itself
+/// if it is set, otherwise its first fallback which is.
+fn governing<'p, 'a>(
+    directives: &'p [(&'a str, Vec<&'a str>)],
+    reported: &str,
+) -> Option<&'p (&'a str, Vec<&'a str>)> {
+    std::iter::once(reported)
+        .chain(fallbacks(reported).iter().copied())
+        .find_map(|name| directives.iter().find(|(directive, _)| *directive == name))
+}
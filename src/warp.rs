@@ -0,0 +1,104 @@
+//! Integration with [warp](https://docs.rs/warp), enabled with the `warp` feature.
+//!
+//! [`Helmet::wrap`] applies a [`crate::HelmetLayer`] configured as usual to the replies of a warp
+//! filter, so applications started with `warp::serve` share the configuration of tower services.
+//! It is used directly or with `warp::wrap_fn`:
+//!
+//! ```
+//! use tower_helmet::warp::Helmet;
+//! use tower_helmet::HelmetLayer;
+//! use warp::Filter;
+//!
+//! let helmet = Helmet::new(HelmetLayer::with_defaults());
+//! let routes = warp::path("hello").map(|| "Hello, World!");
+//! let routes = routes.with(warp::wrap_fn(|filter| helmet.wrap(filter)));
+//! ```
+//!
+//! Rejections are turned into replies after the wrapped filter, so the headers are only set on
+//! them if the filter handles its rejections with `recover` itself.
+//!
+//! Filters can't add request extensions, so [`crate::CspNonce`]s can't be passed to the handlers.
+//! With nonces, serve the filter as tower service with `warp::service` wrapped in
+//! [`crate::http02::HelmetLayer`] instead, and extract the nonce with
+//! `warp::ext::get::<CspNonce>()`.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use http::StatusCode;
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use crate::http02::{to_http02, to_http1};
+use crate::{preset, HeaderOverride};
+
+/// Applies a [`crate::HelmetLayer`] to warp filters, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Helmet(Arc<crate::HelmetService<()>>);
+
+impl Helmet {
+    /// Applies the headers configured in `layer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if nonces are enabled on `layer`, as they can't be passed to the handlers.
+    pub fn new(layer: crate::HelmetLayer) -> Self {
+        assert!(
+            layer.nonce.is_none(),
+            "warp filters can't receive nonces, use warp::service with the http02 layer instead"
+        );
+        Helmet(Arc::new(layer.service(())))
+    }
+
+    /// Sets the headers on the replies of `filter`.
+    pub fn wrap<F, R>(
+        &self,
+        filter: F,
+    ) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone + Send + Sync + 'static
+    where
+        F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+        R: Reply,
+    {
+        let helmet = self.0.clone();
+        let prepare = warp::path::full().and(warp::header::headers_cloned()).map(
+            move |path: FullPath, request_headers: warp::http::HeaderMap| {
+                let request_headers = to_http1(&request_headers);
+                // without nonces and provider, computing the headers can't fail
+                let (headers, _, _) = crate::or_fallback(
+                    helmet.prepare(
+                        preset::is_grpc(&request_headers),
+                        path.as_str(),
+                        request_headers
+                            .get(http::header::USER_AGENT)
+                            .map(http::HeaderValue::as_bytes),
+                        helmet.session_id(|| Cow::Borrowed(&request_headers)),
+                        None,
+                        None,
+                    ),
+                );
+                headers
+            },
+        );
+
+        let helmet = self.0.clone();
+        prepare
+            .and(filter)
+            .map(move |headers: http::HeaderMap, reply: R| {
+                let mut res = reply.into_response();
+                let header_override = res.extensions_mut().remove::<HeaderOverride>();
+
+                let mut response_headers = to_http1(res.headers());
+                let status = StatusCode::from_u16(res.status().as_u16()).unwrap_or(StatusCode::OK);
+                helmet.respond(
+                    status,
+                    &mut response_headers,
+                    &headers,
+                    None,
+                    header_override,
+                );
+                *res.headers_mut() = to_http02(&response_headers);
+                res
+            })
+    }
+}